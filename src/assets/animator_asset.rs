@@ -0,0 +1,239 @@
+use std::{collections::HashMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::managers::{assets::get_full_asset_path, debugger::error};
+
+/// Name of the `from` state of transitions that can start in any state.
+pub const ANY_STATE: &str = "any";
+
+/// Animation state machine definition, can be loaded from a toml or json file.
+/// See docs/animation.md for the format.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimatorAsset {
+    /// first state if not set
+    #[serde(default)]
+    pub default_state: Option<String>,
+    #[serde(default)]
+    pub parameters: HashMap<String, AnimatorParameterDefinition>,
+    pub states: Vec<AnimatorState>,
+    #[serde(default)]
+    pub transitions: Vec<AnimatorTransition>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnimatorParameterDefinition {
+    Float {
+        #[serde(default)]
+        default: f32,
+    },
+    Bool {
+        #[serde(default)]
+        default: bool,
+    },
+    /// Set from code, reset by the transition that used it.
+    Trigger,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimatorState {
+    pub name: String,
+    /// either `clip` or `blend_space` has to be set
+    #[serde(default)]
+    pub clip: Option<String>,
+    #[serde(default)]
+    pub blend_space: Option<BlendSpace>,
+    #[serde(default = "default_looping")]
+    pub looping: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum BlendSpace {
+    /// Clips placed on a line, the two around the parameter value are mixed.
+    #[serde(rename = "1d")]
+    OneDimensional {
+        parameter: String,
+        clips: Vec<BlendSpaceClip<f32>>,
+    },
+    /// Clips placed on a plane, mixed with gradient band interpolation.
+    #[serde(rename = "2d")]
+    TwoDimensional {
+        parameters: [String; 2],
+        clips: Vec<BlendSpaceClip<[f32; 2]>>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlendSpaceClip<P> {
+    pub clip: String,
+    pub position: P,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimatorTransition {
+    /// state name or "any"
+    pub from: String,
+    pub to: String,
+    /// cross-fade time in seconds
+    #[serde(default = "default_transition_duration")]
+    pub duration: f32,
+    /// all of them have to be true
+    #[serde(default)]
+    pub conditions: Vec<AnimatorCondition>,
+    /// 0 - 1 of the `from` state's clip that has to be played first.
+    /// Transitions without conditions use 1, so they happen when the clip ends.
+    #[serde(default)]
+    pub exit_time: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimatorCondition {
+    pub parameter: String,
+    #[serde(default)]
+    pub op: ConditionOp,
+    /// not needed for triggers, bools are compared to true if it's not set
+    #[serde(default)]
+    pub value: Option<ConditionValue>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ConditionOp {
+    #[serde(rename = ">")]
+    Greater,
+    #[serde(rename = "<")]
+    Less,
+    #[serde(rename = ">=")]
+    GreaterOrEqual,
+    #[serde(rename = "<=")]
+    LessOrEqual,
+    #[default]
+    #[serde(rename = "==")]
+    Equal,
+    #[serde(rename = "!=")]
+    NotEqual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ConditionValue {
+    Bool(bool),
+    Float(f32),
+}
+
+fn default_looping() -> bool {
+    true
+}
+
+fn default_transition_duration() -> f32 {
+    0.2
+}
+
+impl AnimatorAsset {
+    /// Format is picked by the extension, anything that isn't .json is read as toml.
+    pub fn from_file(path: &str) -> Result<AnimatorAsset, AnimatorAssetError> {
+        let full_path = get_full_asset_path(path);
+        let source = match std::fs::read_to_string(&full_path) {
+            Ok(source) => source,
+            Err(err) => {
+                error(&format!("animator asset loading error!\npath: {}\nerr: {}", full_path, err));
+                return Err(AnimatorAssetError::ReadError);
+            }
+        };
+
+        let asset: Result<AnimatorAsset, String> = match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+            Some("json") => serde_json::from_str(&source).map_err(|err| err.to_string()),
+            _ => toml::from_str(&source).map_err(|err| err.to_string()),
+        };
+        let asset = match asset {
+            Ok(asset) => asset,
+            Err(err) => {
+                error(&format!("animator asset parsing error!\npath: {}\nerr: {}", full_path, err));
+                return Err(AnimatorAssetError::ParseError);
+            }
+        };
+
+        if let Err(err) = asset.validate() {
+            error(&format!("animator asset error!\npath: {}\nerr: {}", full_path, err));
+            return Err(AnimatorAssetError::InvalidDefinition);
+        }
+
+        Ok(asset)
+    }
+
+    pub fn state_index(&self, name: &str) -> Option<usize> {
+        self.states.iter().position(|state| state.name == name)
+    }
+
+    pub fn default_state_index(&self) -> usize {
+        match &self.default_state {
+            Some(name) => self.state_index(name).unwrap_or(0),
+            None => 0,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.states.is_empty() {
+            return Err("no states".into());
+        }
+        if let Some(name) = &self.default_state {
+            if self.state_index(name).is_none() {
+                return Err(format!("default state '{}' doesn't exist", name));
+            }
+        }
+
+        for state in &self.states {
+            if state.clip.is_some() == state.blend_space.is_some() {
+                return Err(format!("state '{}' needs either a clip or a blend space", state.name));
+            }
+            let parameters: Vec<&String> = match &state.blend_space {
+                Some(BlendSpace::OneDimensional { parameter, clips }) => {
+                    if clips.is_empty() {
+                        return Err(format!("blend space of state '{}' has no clips", state.name));
+                    }
+                    vec![parameter]
+                }
+                Some(BlendSpace::TwoDimensional { parameters, clips }) => {
+                    if clips.is_empty() {
+                        return Err(format!("blend space of state '{}' has no clips", state.name));
+                    }
+                    parameters.iter().collect()
+                }
+                None => vec![],
+            };
+            for parameter in parameters {
+                match self.parameters.get(parameter) {
+                    Some(AnimatorParameterDefinition::Float { .. }) => (),
+                    _ => return Err(format!("blend space of state '{}' needs float parameter '{}'", state.name, parameter)),
+                }
+            }
+        }
+
+        for transition in &self.transitions {
+            if transition.from != ANY_STATE && self.state_index(&transition.from).is_none() {
+                return Err(format!("transition from unknown state '{}'", transition.from));
+            }
+            if self.state_index(&transition.to).is_none() {
+                return Err(format!("transition to unknown state '{}'", transition.to));
+            }
+            for condition in &transition.conditions {
+                if !self.parameters.contains_key(&condition.parameter) {
+                    return Err(format!(
+                        "transition '{}' -> '{}' uses unknown parameter '{}'",
+                        transition.from, transition.to, condition.parameter
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum AnimatorAssetError {
+    ReadError,
+    ParseError,
+    InvalidDefinition,
+}
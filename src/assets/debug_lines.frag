@@ -0,0 +1,9 @@
+#version 330
+
+in vec4 vertexColor;
+
+out vec4 color;
+
+void main() {
+    color = vertexColor;
+}
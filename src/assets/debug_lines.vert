@@ -0,0 +1,14 @@
+#version 330
+
+in vec3 position;
+in vec4 color;
+
+uniform mat4 view;
+uniform mat4 proj;
+
+out vec4 vertexColor;
+
+void main() {
+    vertexColor = color;
+    gl_Position = proj * view * vec4(position, 1.0);
+}
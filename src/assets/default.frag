@@ -0,0 +1,23 @@
+#version 330
+
+in vec3 v_position;
+in vec3 v_normal;
+in vec4 v_tangent;
+in vec2 v_tex_coords;
+in vec2 v_tex_coords_1;
+in vec4 v_color;
+in float v_view_depth;
+
+uniform float lodFade;
+
+#include "fog.glsl"
+#include "shadows.glsl"
+#include "lights.glsl"
+#include "pbr.glsl"
+
+out vec4 color;
+
+void main() {
+    lodDither(lodFade);
+    color = shadeMaterial(v_position, v_normal, v_tangent, v_tex_coords, v_tex_coords_1, v_color, v_view_depth);
+}
@@ -0,0 +1,45 @@
+#version 330
+
+in vec3 position;
+in vec3 normal;
+in vec2 tex_coords;
+in vec2 tex_coords_1;
+in vec4 color;
+in vec4 tangent;
+in vec4 joints;
+in vec4 weights;
+
+uniform mat4 mvp;
+uniform mat4 model;
+uniform mat4 view;
+
+#include "skinning.glsl"
+#include "morph.glsl"
+
+out vec3 v_position;
+out vec3 v_normal;
+out vec4 v_tangent;
+out vec2 v_tex_coords;
+out vec2 v_tex_coords_1;
+out vec4 v_color;
+out float v_view_depth;
+
+void main() {
+    vec3 morphedPosition = position;
+    vec3 morphedNormal = normal;
+    applyMorphTargets(morphedPosition, morphedNormal);
+
+    mat4 skin = skinMatrix(joints, weights);
+    vec4 localPosition = skin * vec4(morphedPosition, 1.0);
+    vec4 worldPosition = model * localPosition;
+    mat3 world = mat3(model * skin);
+
+    v_position = worldPosition.xyz;
+    v_normal = transpose(inverse(world)) * morphedNormal;
+    v_tangent = vec4(world * tangent.xyz, tangent.w);
+    v_tex_coords = tex_coords;
+    v_tex_coords_1 = tex_coords_1;
+    v_color = color;
+    v_view_depth = -(view * worldPosition).z;
+    gl_Position = mvp * localPosition;
+}
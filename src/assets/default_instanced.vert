@@ -0,0 +1,53 @@
+#version 330
+
+in vec3 position;
+in vec3 normal;
+in vec2 tex_coords;
+in vec2 tex_coords_1;
+in vec4 color;
+in vec4 tangent;
+in vec4 joints;
+in vec4 weights;
+
+// per instance
+in mat4 model;
+in float lod_fade;
+in vec4 tint;
+
+uniform mat4 view;
+uniform mat4 proj;
+// mesh's node transform, instances' model matrices don't have it
+uniform mat4 mesh;
+
+#include "skinning.glsl"
+#include "morph.glsl"
+
+out vec3 v_position;
+out vec3 v_normal;
+out vec4 v_tangent;
+out vec2 v_tex_coords;
+out vec2 v_tex_coords_1;
+out vec4 v_color;
+out float v_view_depth;
+out float v_lod_fade;
+
+void main() {
+    vec3 morphedPosition = position;
+    vec3 morphedNormal = normal;
+    applyMorphTargets(morphedPosition, morphedNormal);
+
+    mat4 transform = model * mesh * skinMatrix(joints, weights);
+    vec4 worldPosition = transform * vec4(morphedPosition, 1.0);
+    vec4 viewPosition = view * worldPosition;
+    mat3 world = mat3(transform);
+
+    v_position = worldPosition.xyz;
+    v_normal = transpose(inverse(world)) * morphedNormal;
+    v_tangent = vec4(world * tangent.xyz, tangent.w);
+    v_tex_coords = tex_coords;
+    v_tex_coords_1 = tex_coords_1;
+    v_color = color * tint;
+    v_view_depth = -viewPosition.z;
+    v_lod_fade = lod_fade;
+    gl_Position = proj * viewPosition;
+}
@@ -0,0 +1,18 @@
+// fog uniforms are set by sky::set_fog, see docs/shaders.md
+uniform int fogMode;
+uniform vec3 fogColor;
+uniform float fogStart;
+uniform float fogEnd;
+uniform float fogDensity;
+
+// distance - from the camera to the fragment
+vec3 applyFog(vec3 color, float distance) {
+    float fog = 1.0;
+    if (fogMode == 1) {
+        fog = clamp((fogEnd - distance) / max(fogEnd - fogStart, 0.0001), 0.0, 1.0);
+    } else if (fogMode == 2) {
+        fog = exp(-fogDensity * distance);
+    }
+
+    return mix(fogColor, color, fog);
+}
@@ -0,0 +1,95 @@
+// point and spot lights, see docs/shaders.md
+layout(std140) uniform lights {
+    ivec4 lights_count;
+    vec4 point_position_range[8];
+    vec4 point_color_intensity[8];
+    ivec4 point_shadow_index[8];
+    vec4 spot_position_range[8];
+    vec4 spot_color_intensity[8];
+    vec4 spot_direction[8];
+    vec4 spot_cone[8];
+    ivec4 spot_shadow_index[8];
+};
+uniform samplerCube pointShadowMap0;
+uniform samplerCube pointShadowMap1;
+uniform sampler2D spotShadowMap0;
+uniform sampler2D spotShadowMap1;
+uniform mat4 spotShadowViewProj0;
+uniform mat4 spotShadowViewProj1;
+uniform float lightShadowNear;
+
+// smooth falloff that reaches 0 at the light's range
+float lightFalloff(float distance, float range) {
+    float ratio = distance / range;
+    float window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return window * window / (distance * distance + 1.0);
+}
+
+// shadow maps of lights are perspective depth from lightShadowNear to the light's range
+float linearLightDepth(float depth, float range) {
+    float ndc = depth * 2.0 - 1.0;
+    return 2.0 * lightShadowNear * range / (range + lightShadowNear - ndc * (range - lightShadowNear));
+}
+
+float pointLightLit(int shadowIndex, vec3 toFragment, float range, float bias) {
+    if (shadowIndex < 0) {
+        return 1.0;
+    }
+
+    // cube faces are 90 degrees perspective, so view depth is the largest axis
+    vec3 axes = abs(toFragment);
+    float viewDepth = max(axes.x, max(axes.y, axes.z));
+    float depth = shadowIndex == 0 ? texture(pointShadowMap0, toFragment).r : texture(pointShadowMap1, toFragment).r;
+    return viewDepth - bias > linearLightDepth(depth, range) ? 0.0 : 1.0;
+}
+
+float spotLightLit(int shadowIndex, vec3 position, float range, float bias) {
+    if (shadowIndex < 0) {
+        return 1.0;
+    }
+
+    vec4 shadowPosition = (shadowIndex == 0 ? spotShadowViewProj0 : spotShadowViewProj1) * vec4(position, 1.0);
+    vec3 coords = shadowPosition.xyz / shadowPosition.w * 0.5 + 0.5;
+    if (shadowPosition.w <= 0.0 || any(lessThan(coords, vec3(0.0))) || any(greaterThan(coords, vec3(1.0)))) {
+        return 1.0;
+    }
+
+    float depth = shadowIndex == 0 ? texture(spotShadowMap0, coords.xy).r : texture(spotShadowMap1, coords.xy).r;
+    return shadowPosition.w - bias > linearLightDepth(depth, range) ? 0.0 : 1.0;
+}
+
+// colour * intensity * falloff * shadow of point light i, toLight is the normalized direction to it
+vec3 pointLightRadiance(int i, vec3 position, vec3 normal, out vec3 toLight) {
+    vec3 offset = point_position_range[i].xyz - position;
+    float distance = length(offset);
+    float range = point_position_range[i].w;
+    toLight = offset / max(distance, 0.0001);
+    if (distance >= range) {
+        return vec3(0.0);
+    }
+
+    float bias = 0.02 + 0.03 * distance * (1.0 - max(dot(normal, toLight), 0.0));
+    float lit = pointLightLit(point_shadow_index[i].x, -offset, range, bias);
+    return point_color_intensity[i].rgb * point_color_intensity[i].a * lightFalloff(distance, range) * lit;
+}
+
+vec3 spotLightRadiance(int i, vec3 position, vec3 normal, out vec3 toLight) {
+    vec3 offset = spot_position_range[i].xyz - position;
+    float distance = length(offset);
+    float range = spot_position_range[i].w;
+    toLight = offset / max(distance, 0.0001);
+    if (distance >= range) {
+        return vec3(0.0);
+    }
+
+    // x - cos of the inner angle, y - cos of the outer one
+    vec2 cone = spot_cone[i].xy;
+    float coneFade = clamp((dot(-toLight, spot_direction[i].xyz) - cone.y) / max(cone.x - cone.y, 0.0001), 0.0, 1.0);
+    if (coneFade <= 0.0) {
+        return vec3(0.0);
+    }
+
+    float bias = 0.02 + 0.03 * distance * (1.0 - max(dot(normal, toLight), 0.0));
+    float lit = spotLightLit(spot_shadow_index[i].x, position, range, bias);
+    return spot_color_intensity[i].rgb * spot_color_intensity[i].a * lightFalloff(distance, range) * coneFade * lit;
+}
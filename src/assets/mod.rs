@@ -1,3 +1,4 @@
+pub mod animator_asset;
 pub mod asset;
 pub mod model_asset;
 pub mod shader_asset;
@@ -1,27 +1,263 @@
 use std::path::Path;
-use crate::managers::{
-    assets::{self, get_full_asset_path},
-    debugger::{self, error, warn},
-    render::Vertex,
+use crate::{
+    assets::texture_asset::{self, TextureAsset},
+    managers::{
+        assets::{self, get_full_asset_path},
+        debugger::{error, warn},
+        render::Vertex,
+    },
+    math_utils::Aabb,
 };
 use data_url::DataUrl;
-use glam::Mat4;
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
 use gltf::Gltf;
-use splines::{Key, Spline};
+use serde::Deserialize;
 
 #[derive(Debug, Clone)]
 pub struct Object {
     pub vertices: Vec<Vertex>,
-    pub indices: Vec<u16>,
+    pub indices: Vec<u32>,
     pub transform: [[f32; 4]; 4],
     pub node_index: usize,
+    pub material: Material,
+    /// bounds of vertices positions and morph targets at weight 1
+    pub bind_pose_bounding_box: Aabb,
+    /// bind_pose_bounding_box, skinned meshes have it scaled by the asset's skinned bounds scale
+    pub bounding_box: Aabb,
+    /// from `_LOD<n>` suffix of node or mesh name, None for meshes drawn at every LOD
+    pub lod: Option<usize>,
+    /// blend shapes, weights are on the node (`NodeTransform::morph_weights`)
+    pub morph_targets: Vec<MorphTarget>,
+}
+
+/// Per vertex offsets that are added to the mesh, multiplied by the target's weight.
+#[derive(Debug, Clone)]
+pub struct MorphTarget {
+    /// from the mesh's `targetNames` extras (Blender shape key names), index as a string if there are none
+    pub name: String,
+    pub positions: Vec<[f32; 3]>,
+    /// zeros if the target doesn't move normals
+    pub normals: Vec<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+struct MeshExtras {
+    #[serde(default, rename = "targetNames")]
+    target_names: Vec<String>,
+}
+
+/// Animations can move skinned vertices out of their bind pose bounds,
+/// use ModelAsset::set_skinned_bounds_scale if the model's animations go further.
+pub const DEFAULT_SKINNED_BOUNDING_BOX_SCALE: f32 = 1.5;
+
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub name: String,
+    /// false for gltf primitives without a material and obj meshes without `usemtl`
+    pub from_gltf: bool,
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<MaterialTexture>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub metallic_roughness_texture: Option<MaterialTexture>,
+    pub normal_texture: Option<MaterialTexture>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<MaterialTexture>,
+    pub occlusion_strength: f32,
+    pub emissive_factor: [f32; 3],
+    pub emissive_texture: Option<MaterialTexture>,
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MaterialTexture {
+    /// index in ModelAsset's textures list
+    pub texture_index: usize,
+    pub tex_coord: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    Mask,
+    Blend,
+}
+
+impl AlphaMode {
+    /// value that is passed to shaders as `alphaMode` uniform
+    pub fn as_shader_int(&self) -> i32 {
+        match self {
+            AlphaMode::Opaque => 0,
+            AlphaMode::Mask => 1,
+            AlphaMode::Blend => 2,
+        }
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            name: String::new(),
+            from_gltf: false,
+            base_color_factor: [1.0, 1.0, 1.0, 1.0],
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive_factor: [0.0, 0.0, 0.0],
+            emissive_texture: None,
+            alpha_mode: AlphaMode::Opaque,
+            alpha_cutoff: 0.5,
+            double_sided: false,
+        }
+    }
+}
+
+impl Material {
+    fn from_gltf(material: &gltf::Material) -> Material {
+        if material.index().is_none() {
+            return Material::default();
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let texture = |info: Option<gltf::texture::Info>| {
+            info.map(|info| MaterialTexture {
+                texture_index: info.texture().source().index(),
+                tex_coord: info.tex_coord(),
+            })
+        };
+
+        let alpha_mode = match material.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => AlphaMode::Mask,
+            gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        };
+
+        Material {
+            name: material.name().unwrap_or("").into(),
+            from_gltf: true,
+            base_color_factor: pbr.base_color_factor(),
+            base_color_texture: texture(pbr.base_color_texture()),
+            metallic_factor: pbr.metallic_factor(),
+            roughness_factor: pbr.roughness_factor(),
+            metallic_roughness_texture: texture(pbr.metallic_roughness_texture()),
+            normal_texture: material.normal_texture().map(|normal| MaterialTexture {
+                texture_index: normal.texture().source().index(),
+                tex_coord: normal.tex_coord(),
+            }),
+            normal_scale: material.normal_texture().map_or(1.0, |normal| normal.scale()),
+            occlusion_texture: material.occlusion_texture().map(|occlusion| MaterialTexture {
+                texture_index: occlusion.texture().source().index(),
+                tex_coord: occlusion.tex_coord(),
+            }),
+            occlusion_strength: material
+                .occlusion_texture()
+                .map_or(1.0, |occlusion| occlusion.strength()),
+            emissive_factor: material.emissive_factor(),
+            emissive_texture: texture(material.emissive_texture()),
+            alpha_mode,
+            alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+            double_sided: material.double_sided(),
+        }
+    }
+
+    /// Kd, d (or Tr), Ke and their maps, bump or norm maps as normal maps.
+    /// Ns is turned into roughness, PBR extension values (Pr, Pm, map_Pr) are used when they're there.
+    fn from_mtl(
+        material: &tobj::Material,
+        obj_path: &str,
+        textures: &mut Vec<Option<TextureAsset>>,
+        texture_paths: &mut Vec<String>,
+    ) -> Material {
+        let param = |name: &str| material.unknown_param.get(name).map(|value| value.trim());
+        let param_float = |name: &str| param(name).and_then(|value| value.parse::<f32>().ok());
+        let mut texture = |texture: Option<&str>| {
+            let texture_path = mtl_texture_path(texture?)?;
+            let texture_index = match texture_paths.iter().position(|path| *path == texture_path) {
+                Some(index) => index,
+                None => {
+                    let texture = read_uri(obj_path, &texture_path)
+                        .ok()
+                        .and_then(|bytes| TextureAsset::from_bytes(&bytes, &texture_path).ok());
+                    if texture.is_none() {
+                        warn(&format!(
+                            "mesh asset loading warning\npath: {}\nwarning: failed to load texture {}",
+                            obj_path, texture_path
+                        ));
+                    }
+                    texture_paths.push(texture_path);
+                    textures.push(texture);
+                    textures.len() - 1
+                }
+            };
+            Some(MaterialTexture { texture_index, tex_coord: 0 })
+        };
+
+        let alpha = match (material.dissolve, param_float("Tr")) {
+            (Some(dissolve), _) => dissolve,
+            (None, Some(transparency)) => 1.0 - transparency,
+            (None, None) => 1.0,
+        };
+        let diffuse = material.diffuse.unwrap_or([1.0; 3]);
+        // usual Blinn-Phong exponent to roughness conversion
+        let roughness = match material.shininess {
+            Some(shininess) => (2.0 / (shininess.max(0.0) + 2.0)).sqrt(),
+            None => 1.0,
+        };
+        let normal_texture = match material.normal_texture.as_deref() {
+            Some(normal_texture) => texture(Some(normal_texture)),
+            None => texture(param("norm")),
+        };
+        let dissolve_texture = material.dissolve_texture.is_some();
+
+        Material {
+            name: material.name.clone(),
+            from_gltf: true,
+            base_color_factor: [diffuse[0], diffuse[1], diffuse[2], alpha],
+            base_color_texture: texture(material.diffuse_texture.as_deref()),
+            metallic_factor: param_float("Pm").unwrap_or(0.0),
+            roughness_factor: param_float("Pr").unwrap_or(roughness),
+            metallic_roughness_texture: None,
+            normal_texture,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive_factor: material.emissive.unwrap_or([0.0; 3]),
+            emissive_texture: texture(param("map_Ke")),
+            alpha_mode: match alpha < 1.0 || dissolve_texture {
+                true => AlphaMode::Blend,
+                false => AlphaMode::Opaque,
+            },
+            alpha_cutoff: 0.5,
+            double_sided: false,
+        }
+    }
+
+    pub fn textures(&self) -> [Option<MaterialTexture>; 5] {
+        [
+            self.base_color_texture,
+            self.metallic_roughness_texture,
+            self.normal_texture,
+            self.occlusion_texture,
+            self.emissive_texture,
+        ]
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Node {
+    pub name: String,
     pub transform: [[f32; 4]; 4],
     pub node_index: usize,
     pub children_id: Vec<usize>,
+    /// default morph target weights of the node's mesh, empty if it has no morph targets
+    pub morph_weights: Vec<f32>,
 }
 
 #[derive(Debug, Clone)]
@@ -39,6 +275,9 @@ pub struct ModelAsset {
     pub nodes: Vec<Node>,
     pub root_nodes: Vec<Node>,
     pub animations: Vec<Animation>,
+    /// gltf images, index = image index in the gltf file. None if the image failed to load.
+    /// For obj files these are the mtl textures in order of first use
+    pub textures: Vec<Option<TextureAsset>>,
     pub joints_mats: [[[f32; 4]; 4]; 128],
     pub joints_inverse_bind_mats: [[[f32; 4]; 4]; 128],
 }
@@ -48,15 +287,36 @@ pub struct Animation {
     pub name: String,
     pub channels: Vec<AnimationChannel>,
     pub duration: f32,
+    /// sorted by time
+    pub events: Vec<AnimationEventKey>,
+}
+
+/// Named point in an animation, e.g. a footstep.
+/// Read from the `events` list of the gltf animation's extras: `{"events": [{"time": 0.4, "name": "footstep"}]}`
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnimationEventKey {
+    pub time: f32,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+struct AnimationExtras {
+    #[serde(default)]
+    events: Vec<AnimationEventKey>,
 }
 
 #[derive(Debug, Clone)]
 pub struct AnimationChannel {
     pub channel_type: AnimationChannelType,
     pub node_index: usize,
-    pub x_axis_spline: Spline<f32, f32>,
-    pub y_axis_spline: Spline<f32, f32>,
-    pub z_axis_spline: Spline<f32, f32>,
+    pub interpolation: ChannelInterpolation,
+    pub timestamps: Vec<f32>,
+    /// xyz for translation and scale, xyzw quaternions for rotation, empty for morph target weights.
+    /// Cubic spline channels store (in tangent, value, out tangent) for every timestamp.
+    pub keyframes: Vec<Vec4>,
+    /// morph target weights channels only, `morph_targets_count` weights for every keyframe
+    pub morph_weights: Vec<f32>,
+    pub morph_targets_count: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -64,13 +324,149 @@ pub enum AnimationChannelType {
     Translation,
     Rotation,
     Scale,
+    MorphTargetWeights,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChannelInterpolation {
+    Step,
+    Linear,
+    CubicSpline,
+}
+
+impl Animation {
+    pub fn add_event(&mut self, time: f32, name: &str) {
+        let index = self.events.partition_point(|event| event.time <= time);
+        self.events.insert(index, AnimationEventKey { time, name: name.into() });
+    }
+}
+
+impl AnimationChannel {
+    pub fn sample_vec3(&self, time: f32) -> Option<Vec3> {
+        self.sample(time, false).map(|value| value.truncate())
+    }
+
+    /// Rotation channels only.
+    pub fn sample_quat(&self, time: f32) -> Option<Quat> {
+        self.sample(time, true).map(Quat::from_vec4)
+    }
+
+    /// Morph target weights channels only, one weight per target.
+    pub fn sample_weights(&self, time: f32) -> Option<Vec<f32>> {
+        let count = self.morph_targets_count;
+        let keys_count = self.timestamps.len();
+        if keys_count == 0 || count == 0 {
+            return None;
+        }
+
+        let value = |key: usize| {
+            let start = match self.interpolation {
+                ChannelInterpolation::CubicSpline => (key * 3 + 1) * count,
+                _ => key * count,
+            };
+            self.morph_weights.get(start..start + count)
+        };
+        let next_key = self.timestamps.partition_point(|timestamp| *timestamp <= time);
+        if next_key == 0 {
+            return value(0).map(|weights| weights.to_vec());
+        }
+        if next_key == keys_count {
+            return value(keys_count - 1).map(|weights| weights.to_vec());
+        }
+
+        let key = next_key - 1;
+        let delta = self.timestamps[next_key] - self.timestamps[key];
+        let t = match delta > 0.0 {
+            true => (time - self.timestamps[key]) / delta,
+            false => 0.0,
+        };
+
+        let from = value(key)?;
+        let to = value(next_key)?;
+        match self.interpolation {
+            ChannelInterpolation::Step => Some(from.to_vec()),
+            ChannelInterpolation::Linear => Some(from.iter().zip(to).map(|(from, to)| from + (to - from) * t).collect()),
+            ChannelInterpolation::CubicSpline => {
+                let out_tangents = self.morph_weights.get((key * 3 + 2) * count..(key * 3 + 3) * count)?;
+                let in_tangents = self.morph_weights.get(next_key * 3 * count..(next_key * 3 + 1) * count)?;
+                let t2 = t * t;
+                let t3 = t2 * t;
+                Some(
+                    (0..count)
+                        .map(|i| {
+                            from[i] * (2.0 * t3 - 3.0 * t2 + 1.0)
+                                + out_tangents[i] * delta * (t3 - 2.0 * t2 + t)
+                                + to[i] * (-2.0 * t3 + 3.0 * t2)
+                                + in_tangents[i] * delta * (t3 - t2)
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+
+    /// Clamped to the first and the last keyframe.
+    fn sample(&self, time: f32, is_rotation: bool) -> Option<Vec4> {
+        let keys_count = self.timestamps.len();
+        if keys_count == 0 {
+            return None;
+        }
+
+        let value = |key: usize| match self.interpolation {
+            ChannelInterpolation::CubicSpline => self.keyframes.get(key * 3 + 1).copied(),
+            _ => self.keyframes.get(key).copied(),
+        };
+        let next_key = self.timestamps.partition_point(|timestamp| *timestamp <= time);
+        if next_key == 0 {
+            return value(0);
+        }
+        if next_key == keys_count {
+            return value(keys_count - 1);
+        }
+
+        let key = next_key - 1;
+        let delta = self.timestamps[next_key] - self.timestamps[key];
+        let t = match delta > 0.0 {
+            true => (time - self.timestamps[key]) / delta,
+            false => 0.0,
+        };
+
+        match self.interpolation {
+            ChannelInterpolation::Step => value(key),
+            ChannelInterpolation::Linear => {
+                let (from, to) = (value(key)?, value(next_key)?);
+                match is_rotation {
+                    true => Some(Quat::from_vec4(from).slerp(Quat::from_vec4(to), t).into()),
+                    false => Some(from.lerp(to, t)),
+                }
+            }
+            ChannelInterpolation::CubicSpline => {
+                // hermite spline from the gltf spec, tangents are scaled by the keyframe delta
+                let from = value(key)?;
+                let out_tangent = *self.keyframes.get(key * 3 + 2)?;
+                let to = value(next_key)?;
+                let in_tangent = *self.keyframes.get(next_key * 3)?;
+                let t2 = t * t;
+                let t3 = t2 * t;
+                let result = from * (2.0 * t3 - 3.0 * t2 + 1.0)
+                    + out_tangent * delta * (t3 - 2.0 * t2 + t)
+                    + to * (-2.0 * t3 + 3.0 * t2)
+                    + in_tangent * delta * (t3 - t2);
+                // opposite tangents can cancel out, a zero quaternion isn't a rotation
+                match is_rotation {
+                    true => Some(result.try_normalize().unwrap_or(from)),
+                    false => Some(result),
+                }
+            }
+        }
+    }
 }
 
 impl ModelAsset {
     pub fn from_gltf(path: &str) -> Result<ModelAsset, ModelAssetError> {
         let full_path = assets::get_full_asset_path(path);
         let gltf_result = Gltf::open(&full_path);
-        let gltf: Gltf;
+        let mut gltf: Gltf;
         match gltf_result {
             Ok(result) => gltf = result,
             Err(err) => {
@@ -82,55 +478,52 @@ impl ModelAsset {
             }
         }
 
+        // binary chunk of .glb files
+        let mut blob = gltf.blob.take();
         let mut buffer_data: Vec<Vec<u8>> = Vec::new();
         for buffer in gltf.buffers() {
-            match buffer.source() {
-                gltf::buffer::Source::Bin => {
-                    error(&format!("mesh asset loading error!\nasset path: {}\nerror: .glb loading is not supported", &full_path));
-                    return Err(ModelAssetError::GlbError);
-                }
-                gltf::buffer::Source::Uri(uri) => {
-                    let url: DataUrl;
-                    match DataUrl::process(uri) {
-                        Ok(url_result) => {
-                            url = url_result;
-                            match url.decode_to_vec() {
-                                Ok(res) => {
-                                    buffer_data.push(res.0);
-                                }
-                                Err(_) => {
-                                    // The base64 was malformed!
-                                    error(&format!(
-                                            "got an error when creating mesh asset\nasset path: {}\nerror: can't decode a buffer: bad base64",
-                                            &full_path));
-                                    return Err(ModelAssetError::BufferDecodingError);
-                                }
-                            }
-                        }
-                        Err(err) => match err {
-                            data_url::DataUrlError::NotADataUrl => {
-                                let bin_path = get_full_asset_path(
-                                    &Path::new(path)
-                                        .with_extension("bin")
-                                        .into_os_string()
-                                        .into_string()
-                                        .unwrap(),
-                                );
-                                match std::fs::read(bin_path) {
-                                    Ok(bin) => buffer_data.push(bin),
-                                    Err(err) => {
-                                        debugger::error(&format!(
-                                                "model asset loading error\nfailed to read bin file\nerr: {}",
-                                                err));
-                                        return Err(ModelAssetError::FailedToReadBin);
-                                    }
-                                };
-                            }
-                            _ => (),
-                        },
-                    };
+            let data = match buffer.source() {
+                gltf::buffer::Source::Bin => match blob.take() {
+                    Some(blob) => blob,
+                    None => {
+                        error(&format!("mesh asset loading error!\nasset path: {}\nerror: glb file has no binary chunk", &full_path));
+                        return Err(ModelAssetError::GlbError);
+                    }
+                },
+                gltf::buffer::Source::Uri(uri) => read_uri(path, uri)?,
+            };
+            if data.len() < buffer.length() {
+                error(&format!(
+                    "mesh asset loading error!\nasset path: {}\nerror: buffer {} has {} bytes, {} expected",
+                    &full_path, buffer.index(), data.len(), buffer.length()));
+                return Err(ModelAssetError::BufferDecodingError);
+            }
+            buffer_data.push(data);
+        }
+
+        let mut textures: Vec<Option<TextureAsset>> = Vec::new();
+        for image in gltf.images() {
+            let image_name = format!("{} image {}", path, image.index());
+            let texture = match image.source() {
+                gltf::image::Source::Uri { uri, mime_type: _ } => match read_uri(path, uri) {
+                    Ok(bytes) => TextureAsset::from_bytes(&bytes, &image_name).ok(),
+                    Err(_) => None,
+                },
+                gltf::image::Source::View { view, mime_type: _ } => {
+                    let buffer = &buffer_data[view.buffer().index()];
+                    match buffer.get(view.offset()..view.offset() + view.length()) {
+                        Some(bytes) => TextureAsset::from_bytes(bytes, &image_name).ok(),
+                        None => None,
+                    }
                 }
+            };
+            if texture.is_none() {
+                warn(&format!(
+                    "mesh asset loading warning\npath: {}\nwarning: failed to load image {}",
+                    &full_path, image.index()
+                ));
             }
+            textures.push(texture);
         }
 
         let mut objects: Vec<Object> = Vec::new();
@@ -152,9 +545,11 @@ impl ModelAsset {
                 }
 
                 root_nodes.push(Node {
+                    name: node.name().unwrap_or("").to_string(),
                     transform: node.transform().matrix(),
                     node_index: node.index(),
                     children_id,
+                    morph_weights: node_morph_weights(&node),
                 });
             }
         }
@@ -165,7 +560,6 @@ impl ModelAsset {
             let mut channels: Vec<AnimationChannel> = Vec::new();
             anim.channels().for_each(|channel| {
                 let mut keyframe_timestamps: Vec<f32> = vec![];
-                let mut keyframes: Vec<Vec<f32>> = vec![];
 
                 let reader = channel.reader(|buffer| Some(&buffer_data[buffer.index()]));
                 if let Some(inputs) = reader.read_inputs() {
@@ -191,94 +585,64 @@ impl ModelAsset {
                     None => ()
                 }
 
+                let interpolation = match channel.sampler().interpolation() {
+                    gltf::animation::Interpolation::Step => ChannelInterpolation::Step,
+                    gltf::animation::Interpolation::Linear => ChannelInterpolation::Linear,
+                    gltf::animation::Interpolation::CubicSpline => ChannelInterpolation::CubicSpline,
+                };
+
                 if let Some(outputs) = reader.read_outputs() {
-                    match outputs {
-                        gltf::animation::util::ReadOutputs::Translations(translation) => {
-                            let mut x_axis_keys: Vec<Key<f32, f32>> = vec![];
-                            let mut y_axis_keys: Vec<Key<f32, f32>> = vec![];
-                            let mut z_axis_keys: Vec<Key<f32, f32>> = vec![];
-
-                            let mut current_keyframe_id = 0;
-                            translation.for_each(|tr| {
-                                let vector: Vec<f32> = tr.into();
-                                keyframes.push(vector);
-
-                                x_axis_keys.push(Key::new(keyframe_timestamps[current_keyframe_id], tr[0], splines::Interpolation::Linear));
-                                y_axis_keys.push(Key::new(keyframe_timestamps[current_keyframe_id], tr[1], splines::Interpolation::Linear));
-                                z_axis_keys.push(Key::new(keyframe_timestamps[current_keyframe_id], tr[2], splines::Interpolation::Linear));
-                                current_keyframe_id += 1;
-                            });
-
-                            let x_axis_spline = Spline::from_vec(x_axis_keys);
-                            let y_axis_spline = Spline::from_vec(y_axis_keys);
-                            let z_axis_spline = Spline::from_vec(z_axis_keys);
-
-                            channels.push(AnimationChannel {
-                                channel_type: AnimationChannelType::Translation,
-                                node_index: channel.target().node().index(),
-                                x_axis_spline,
-                                y_axis_spline,
-                                z_axis_spline,
-                            });
-                        },
-                        gltf::animation::util::ReadOutputs::Rotations(rotation) => {
-                            let mut x_axis_keys: Vec<Key<f32, f32>> = vec![];
-                            let mut y_axis_keys: Vec<Key<f32, f32>> = vec![];
-                            let mut z_axis_keys: Vec<Key<f32, f32>> = vec![];
-
-                            let rot_iter = rotation.into_f32();
-                            let mut current_keyframe_id = 0;
-                            rot_iter.for_each(|rot| {
-                                keyframes.push(rot.into());
-
-                                x_axis_keys.push(Key::new(keyframe_timestamps[current_keyframe_id], rot[0], splines::Interpolation::Linear));
-                                y_axis_keys.push(Key::new(keyframe_timestamps[current_keyframe_id], rot[1], splines::Interpolation::Linear));
-                                z_axis_keys.push(Key::new(keyframe_timestamps[current_keyframe_id], rot[2], splines::Interpolation::Linear));
-                                current_keyframe_id += 1;
-                            });
-
-                            let x_axis_spline = Spline::from_vec(x_axis_keys);
-                            let y_axis_spline = Spline::from_vec(y_axis_keys);
-                            let z_axis_spline = Spline::from_vec(z_axis_keys);
-
-                            channels.push(AnimationChannel {
-                                channel_type: AnimationChannelType::Rotation,
-                                node_index: channel.target().node().index(),
-                                x_axis_spline,
-                                y_axis_spline,
-                                z_axis_spline,
-                            });
+                    let mut morph_weights = vec![];
+                    let (channel_type, keyframes): (AnimationChannelType, Vec<Vec4>) = match outputs {
+                        gltf::animation::util::ReadOutputs::Translations(translation) => (
+                            AnimationChannelType::Translation,
+                            translation.map(|tr| Vec3::from(tr).extend(0.0)).collect(),
+                        ),
+                        gltf::animation::util::ReadOutputs::Rotations(rotation) => (
+                            AnimationChannelType::Rotation,
+                            rotation.into_f32().map(Vec4::from).collect(),
+                        ),
+                        gltf::animation::util::ReadOutputs::Scales(scale) => (
+                            AnimationChannelType::Scale,
+                            scale.map(|sc| Vec3::from(sc).extend(0.0)).collect(),
+                        ),
+                        gltf::animation::util::ReadOutputs::MorphTargetWeights(weights) => {
+                            morph_weights = weights.into_f32().collect();
+                            (AnimationChannelType::MorphTargetWeights, vec![])
                         }
-                        gltf::animation::util::ReadOutputs::Scales(scale) => {
-                            let mut x_axis_keys: Vec<Key<f32, f32>> = vec![];
-                            let mut y_axis_keys: Vec<Key<f32, f32>> = vec![];
-                            let mut z_axis_keys: Vec<Key<f32, f32>> = vec![];
-
-                            let mut current_keyframe_id = 0;
-                            scale.for_each(|sc| {
-                                let vector: Vec<f32> = sc.into();
-                                keyframes.push(vector);
-
-                                x_axis_keys.push(Key::new(keyframe_timestamps[current_keyframe_id], sc[0], splines::Interpolation::Linear));
-                                y_axis_keys.push(Key::new(keyframe_timestamps[current_keyframe_id], sc[1], splines::Interpolation::Linear));
-                                z_axis_keys.push(Key::new(keyframe_timestamps[current_keyframe_id], sc[2], splines::Interpolation::Linear));
-                                current_keyframe_id += 1;
-                            });
-
-                            let x_axis_spline = Spline::from_vec(x_axis_keys);
-                            let y_axis_spline = Spline::from_vec(y_axis_keys);
-                            let z_axis_spline = Spline::from_vec(z_axis_keys);
-
-                            channels.push(AnimationChannel {
-                                channel_type: AnimationChannelType::Scale,
-                                node_index: channel.target().node().index(),
-                                x_axis_spline,
-                                y_axis_spline,
-                                z_axis_spline,
-                            });
+                    };
+
+                    let keyframes_per_timestamp = match interpolation {
+                        ChannelInterpolation::CubicSpline => 3,
+                        _ => 1,
+                    };
+                    let morph_targets_count = match keyframe_timestamps.len() {
+                        0 => 0,
+                        keys => morph_weights.len() / (keys * keyframes_per_timestamp),
+                    };
+                    let keyframes_count = match channel_type {
+                        AnimationChannelType::MorphTargetWeights => match morph_targets_count {
+                            0 => 0,
+                            count => morph_weights.len() / count,
                         },
-                        gltf::animation::util::ReadOutputs::MorphTargetWeights(_) => (),
+                        _ => keyframes.len(),
+                    };
+                    if keyframes_count != keyframe_timestamps.len() * keyframes_per_timestamp {
+                        error(&format!(
+                            "mesh asset loading error\npath: {}\nerror: animation channel has {} keyframes for {} timestamps, skipping it",
+                            &full_path, keyframes_count, keyframe_timestamps.len()));
+                        return;
                     }
+
+                    channels.push(AnimationChannel {
+                        channel_type,
+                        node_index: channel.target().node().index(),
+                        interpolation,
+                        timestamps: keyframe_timestamps,
+                        keyframes,
+                        morph_weights,
+                        morph_targets_count,
+                    });
                 };
             });
 
@@ -286,10 +650,21 @@ impl ModelAsset {
                 Some(name) => name.to_string(),
                 None => "".to_string(),
             };
+            let mut events = vec![];
+            if let Some(extras) = anim.extras() {
+                match serde_json::from_str::<AnimationExtras>(extras.get()) {
+                    Ok(extras) => events = extras.events,
+                    Err(err) => warn(&format!(
+                        "mesh asset loading warning\npath: {}\nfailed to read events of animation '{}': {}", &full_path, animation_name, err)),
+                }
+            }
+            events.sort_by(|a: &AnimationEventKey, b| a.time.total_cmp(&b.time));
+
             animations.push(Animation {
                 name: animation_name,
                 channels,
                 duration: animation_duration,
+                events,
             });
         }
 
@@ -322,6 +697,7 @@ impl ModelAsset {
             path: path.into(),
             objects,
             animations,
+            textures,
             joints: joints.clone(),
             nodes,
             root_nodes,
@@ -330,6 +706,233 @@ impl ModelAsset {
         })
     }
 
+    /// Picks the loader by extension, `.obj` files are loaded with `from_obj`, everything else with `from_gltf`.
+    pub fn from_file(path: &str) -> Result<ModelAsset, ModelAssetError> {
+        match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("obj") => ModelAsset::from_obj(path),
+            _ => ModelAsset::from_gltf(path),
+        }
+    }
+
+    /// Wavefront obj with its mtl materials. Every object or group gets its own node,
+    /// and is split into one mesh per material.
+    pub fn from_obj(path: &str) -> Result<ModelAsset, ModelAssetError> {
+        let full_path = assets::get_full_asset_path(path);
+        let (models, materials) = match tobj::load_obj(&full_path, &tobj::GPU_LOAD_OPTIONS) {
+            Ok(result) => result,
+            Err(err) => {
+                error(&format!(
+                    "mesh asset loading error!\nasset path: {}\nerror: {}",
+                    &full_path, err
+                ));
+                return Err(ModelAssetError::LoadError);
+            }
+        };
+
+        Ok(ModelAsset::from_obj_models(path, &full_path, models, materials))
+    }
+
+    fn from_obj_models(path: &str, full_path: &str, models: Vec<tobj::Model>, materials: Result<Vec<tobj::Material>, tobj::LoadError>) -> ModelAsset {
+        let obj_materials = match materials {
+            Ok(materials) => materials,
+            Err(err) => {
+                warn(&format!(
+                    "mesh asset loading warning\npath: {}\nwarning: failed to load mtl materials: {}",
+                    full_path, err
+                ));
+                vec![]
+            }
+        };
+
+        let mut textures: Vec<Option<TextureAsset>> = Vec::new();
+        let mut texture_paths: Vec<String> = Vec::new();
+        let materials: Vec<Material> = obj_materials
+            .iter()
+            .map(|material| Material::from_mtl(material, path, &mut textures, &mut texture_paths))
+            .collect();
+
+        let mut objects: Vec<Object> = Vec::new();
+        let mut nodes: Vec<Node> = Vec::new();
+        for model in models {
+            let mesh = &model.mesh;
+            let vertices_count = mesh.positions.len() / 3;
+            let has_normals = mesh.normals.len() == mesh.positions.len();
+            let has_tex_coords = mesh.texcoords.len() / 2 == vertices_count;
+            let has_colors = mesh.vertex_color.len() == mesh.positions.len();
+
+            let mut vertices: Vec<Vertex> = (0..vertices_count)
+                .map(|i| Vertex {
+                    position: [mesh.positions[i * 3], mesh.positions[i * 3 + 1], mesh.positions[i * 3 + 2]],
+                    normal: match has_normals {
+                        true => [mesh.normals[i * 3], mesh.normals[i * 3 + 1], mesh.normals[i * 3 + 2]],
+                        false => [0.0; 3],
+                    },
+                    // obj textures start at the bottom, gltf ones at the top
+                    tex_coords: match has_tex_coords {
+                        true => [mesh.texcoords[i * 2], 1.0 - mesh.texcoords[i * 2 + 1]],
+                        false => [0.0; 2],
+                    },
+                    joints: [0.0; 4],
+                    weights: [0.0; 4],
+                    color: match has_colors {
+                        true => [mesh.vertex_color[i * 3], mesh.vertex_color[i * 3 + 1], mesh.vertex_color[i * 3 + 2], 1.0],
+                        false => [1.0; 4],
+                    },
+                    tangent: [0.0; 4],
+                    tex_coords_1: [0.0; 2],
+                })
+                .collect();
+            let indices = mesh.indices.clone();
+
+            if !has_normals {
+                generate_normals(&mut vertices, &indices);
+            }
+            if has_tex_coords && !generate_tangents(&mut vertices, &indices) {
+                warn(&format!(
+                    "mesh asset loading warning\npath: {}\nwarning: failed to generate tangents of '{}'",
+                    full_path, model.name
+                ));
+            }
+
+            // obj objects have one mesh per material, so meshes of one object share its node
+            let node_index = match nodes.iter().find(|node| node.name == model.name) {
+                Some(node) => node.node_index,
+                None => {
+                    nodes.push(Node {
+                        name: model.name.clone(),
+                        transform: Mat4::IDENTITY.to_cols_array_2d(),
+                        node_index: nodes.len(),
+                        children_id: vec![],
+                        morph_weights: vec![],
+                    });
+                    nodes.len() - 1
+                }
+            };
+
+            let bounding_box = Aabb::from_points(vertices.iter().map(|vertex| Vec3::from(vertex.position)));
+            objects.push(Object {
+                bind_pose_bounding_box: bounding_box,
+                bounding_box,
+                vertices,
+                indices,
+                transform: Mat4::IDENTITY.to_cols_array_2d(),
+                node_index,
+                material: match mesh.material_id.and_then(|id| materials.get(id)) {
+                    Some(material) => material.clone(),
+                    None => Material::default(),
+                },
+                lod: lod_from_name(&model.name),
+                morph_targets: vec![],
+            });
+        }
+
+        if objects.is_empty() {
+            warn("warning when creating model asset.\n0 mesh data found");
+        }
+
+        ModelAsset {
+            path: path.into(),
+            objects,
+            joints: vec![],
+            root_nodes: nodes.clone(),
+            nodes,
+            animations: vec![],
+            textures,
+            joints_mats: joints_vec_to_array(vec![]),
+            joints_inverse_bind_mats: joints_vec_to_inverse_mat_array(vec![]),
+        }
+    }
+
+    /// Grid mesh from a grayscale heightmap, black is 0 and white is `height`, centered like `Terrain`.
+    /// Pixel columns go along x, rows along z. `step` is pixels per grid cell, 1 uses every pixel.
+    pub fn from_heightmap(path: &str, size: Vec2, height: f32, step: usize) -> Result<ModelAsset, ModelAssetError> {
+        let (heights, depth, width) = match texture_asset::load_heightmap(path) {
+            Ok(heightmap) => heightmap,
+            Err(_) => return Err(ModelAssetError::LoadError),
+        };
+
+        Ok(ModelAsset::from_heights(path, &heights, depth, width, size, height, step))
+    }
+
+    /// `from_heightmap` for heights that are already loaded, `depth` rows of `width` 0 - 1 heights.
+    fn from_heights(path: &str, heights: &[f32], depth: usize, width: usize, size: Vec2, height: f32, step: usize) -> ModelAsset {
+        let step = step.max(1);
+        let lines = |last: usize| {
+            let mut lines: Vec<usize> = (0..last).step_by(step).collect();
+            lines.push(last);
+            lines
+        };
+        let (columns, rows) = (lines(width - 1), lines(depth - 1));
+
+        let mut vertices = Vec::with_capacity(columns.len() * rows.len());
+        for &row in &rows {
+            for &column in &columns {
+                let pixel_height = heights[row * width + column];
+                let uv = Vec2::new(column as f32 / (width - 1) as f32, row as f32 / (depth - 1) as f32);
+                vertices.push(Vertex {
+                    position: [
+                        uv.x * size.x - size.x / 2.0,
+                        pixel_height * height,
+                        -(uv.y * size.y - size.y / 2.0),
+                    ],
+                    normal: [0.0; 3],
+                    tex_coords: uv.to_array(),
+                    joints: [0.0; 4],
+                    weights: [0.0; 4],
+                    color: [1.0; 4],
+                    tangent: [0.0; 4],
+                    tex_coords_1: uv.to_array(),
+                });
+            }
+        }
+
+        let row_width = columns.len() as u32;
+        let mut indices: Vec<u32> = Vec::with_capacity((columns.len() - 1) * (rows.len() - 1) * 6);
+        for row in 0..rows.len() as u32 - 1 {
+            for column in 0..row_width - 1 {
+                let p00 = row * row_width + column;
+                let p01 = p00 + 1;
+                let p10 = p00 + row_width;
+                let p11 = p10 + 1;
+                indices.extend_from_slice(&[p00, p11, p10, p00, p01, p11]);
+            }
+        }
+
+        generate_normals(&mut vertices, &indices);
+        generate_tangents(&mut vertices, &indices);
+
+        let node = Node {
+            name: "heightmap".into(),
+            transform: Mat4::IDENTITY.to_cols_array_2d(),
+            node_index: 0,
+            children_id: vec![],
+            morph_weights: vec![],
+        };
+
+        let bounding_box = Aabb::from_points(vertices.iter().map(|vertex| Vec3::from(vertex.position)));
+        ModelAsset {
+            path: path.into(),
+            objects: vec![Object {
+                bind_pose_bounding_box: bounding_box,
+                bounding_box,
+                vertices,
+                indices,
+                transform: node.transform,
+                node_index: 0,
+                material: Material::default(),
+                lod: None,
+                morph_targets: vec![],
+            }],
+            joints: vec![],
+            nodes: vec![node.clone()],
+            root_nodes: vec![node],
+            animations: vec![],
+            textures: vec![],
+            joints_mats: joints_vec_to_array(vec![]),
+            joints_inverse_bind_mats: joints_vec_to_inverse_mat_array(vec![]),
+        }
+    }
+
     pub fn get_animations_list(&self) -> Option<&Vec<Animation>> {
         match self.animations.is_empty() {
             true => None,
@@ -361,6 +964,124 @@ impl ModelAsset {
         false
     }
 
+    /// Names of all objects' morph targets, without duplicates.
+    pub fn morph_target_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = vec![];
+        for target in self.objects.iter().flat_map(|object| &object.morph_targets) {
+            if !names.contains(&target.name.as_str()) {
+                names.push(&target.name);
+            }
+        }
+
+        names
+    }
+
+    pub fn has_materials(&self) -> bool {
+        self.objects.iter().any(|object| object.material.from_gltf)
+    }
+
+    /// Union of all objects' bounding boxes moved by their nodes' transforms,
+    /// that's where the meshes are for an instance with an identity model matrix.
+    pub fn nodes_bounding_box(&self) -> Aabb {
+        let mut boxes = self
+            .objects
+            .iter()
+            .map(|object| object.bounding_box.transformed(&Mat4::from_cols_array_2d(&object.transform)));
+        match boxes.next() {
+            Some(first) => boxes.fold(first, |aabb, object_box| aabb.union(&object_box)),
+            None => Aabb::from_points(std::iter::empty()),
+        }
+    }
+
+    /// Scales skinned meshes' bind pose bounds by `scale` for culling, 1 keeps the bind pose bounds.
+    pub fn set_skinned_bounds_scale(&mut self, scale: f32) {
+        for object in &mut self.objects {
+            if object.vertices.iter().any(|vertex| vertex.weights != [0.0; 4]) {
+                object.bounding_box = object.bind_pose_bounding_box.scaled(scale.max(1.0));
+            }
+        }
+    }
+
+    /// Number of LODs, 1 if the model has no `_LOD<n>` meshes.
+    pub fn lods_count(&self) -> usize {
+        self.objects
+            .iter()
+            .filter_map(|object| object.lod)
+            .max()
+            .map_or(1, |lod| lod + 1)
+    }
+
+    /// Merges separate assets into one, asset i becomes LOD i.
+    /// Skeleton and animations are taken from the first asset.
+    pub fn from_lod_assets(lods: Vec<ModelAsset>) -> Result<ModelAsset, ModelAssetError> {
+        let mut lods = lods.into_iter();
+        let mut merged = match lods.next() {
+            Some(first) => first,
+            None => {
+                error("model asset error
+from_lod_assets(): no assets");
+                return Err(ModelAssetError::LoadError);
+            }
+        };
+        for object in &mut merged.objects {
+            object.lod = Some(0);
+        }
+
+        for (lod, mut asset) in lods.enumerate() {
+            // nodes and images of every asset start from 0, so they're moved after the merged ones
+            let node_offset = merged.nodes.iter().map(|node| node.node_index + 1).max().unwrap_or(0);
+            let texture_offset = merged.textures.len();
+
+            if !asset.joints.is_empty() {
+                warn(&format!(
+                    "model asset warning
+LOD {} ({}) has joints, only the first asset's skeleton is used",
+                    lod + 1,
+                    asset.path
+                ));
+            }
+
+            for node in asset.nodes.iter_mut().chain(asset.root_nodes.iter_mut()) {
+                node.node_index += node_offset;
+                node.children_id.iter_mut().for_each(|child| *child += node_offset);
+            }
+            for object in &mut asset.objects {
+                object.node_index += node_offset;
+                object.lod = Some(lod + 1);
+                for texture in [
+                    &mut object.material.base_color_texture,
+                    &mut object.material.metallic_roughness_texture,
+                    &mut object.material.normal_texture,
+                    &mut object.material.occlusion_texture,
+                    &mut object.material.emissive_texture,
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    texture.texture_index += texture_offset;
+                }
+            }
+
+            merged.nodes.append(&mut asset.nodes);
+            merged.root_nodes.append(&mut asset.root_nodes);
+            merged.objects.append(&mut asset.objects);
+            merged.textures.append(&mut asset.textures);
+        }
+
+        Ok(merged)
+    }
+
+    /// Returns false if there's no such animation.
+    pub fn add_animation_event(&mut self, anim_name: &str, time: f32, event_name: &str) -> bool {
+        match self.animations.iter_mut().find(|anim| anim.name == anim_name) {
+            Some(animation) => {
+                animation.add_event(time, event_name);
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn find_animation(&self, anim_name: &str) -> Option<Animation> {
         for anim in &self.animations {
             if anim.name == anim_name {
@@ -372,6 +1093,61 @@ impl ModelAsset {
     }
 }
 
+/// Parses `_LOD<n>` suffix, case insensitive.
+fn lod_from_name(name: &str) -> Option<usize> {
+    let name = name.to_lowercase();
+    let suffix_start = name.rfind("_lod")?;
+    name[suffix_start + 4..].parse().ok()
+}
+
+struct TangentsGeometry<'a> {
+    vertices: &'a mut [Vertex],
+    indices: &'a [u32],
+}
+
+impl TangentsGeometry<'_> {
+    fn vertex(&self, face: usize, vert: usize) -> &Vertex {
+        &self.vertices[self.indices[face * 3 + vert] as usize]
+    }
+}
+
+impl mikktspace::Geometry for TangentsGeometry<'_> {
+    fn num_faces(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).position
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).normal
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.vertex(face, vert).tex_coords
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        // vertices are shared between faces, so the last face wins like in most glTF exporters
+        let index = self.indices[face * 3 + vert] as usize;
+        self.vertices[index].tangent = tangent;
+    }
+}
+
+/// MikkTSpace tangents for a triangle list, vertices need normals and texture coords.
+pub(crate) fn generate_tangents(vertices: &mut [Vertex], indices: &[u32]) -> bool {
+    if !indices.len().is_multiple_of(3) || indices.iter().any(|index| *index as usize >= vertices.len()) {
+        return false;
+    }
+
+    mikktspace::generate_tangents(&mut TangentsGeometry { vertices, indices })
+}
+
 fn joints_vec_to_array(joints_vec: Vec<Joint>) -> [[[f32; 4]; 4]; 128] {
     let identity_mat: [[f32; 4]; 4] = [
         [1.0, 0.0, 0.0, 0.0],
@@ -473,6 +1249,9 @@ fn add_object_and_children(
                             tex_coords: Default::default(),
                             joints: Default::default(),
                             weights: Default::default(),
+                            color: [1.0; 4],
+                            tangent: Default::default(),
+                            tex_coords_1: Default::default(),
                         })
                     });
                 } else {
@@ -510,16 +1289,44 @@ fn add_object_and_children(
                     ));
                 }
 
-                let mut indices = Vec::new();
-                if let Some(indices_raw) = reader.read_indices() {
-                    let u32_indices = indices_raw.into_u32().collect::<Vec<u32>>();
-                    u32_indices.iter().for_each(|ind| {
-                        indices.push(*ind as u16);
-                    });
-                } else {
+                if let Some(tex_coord_attribute) = reader.read_tex_coords(1).map(|v| v.into_f32()) {
+                    tex_coord_attribute
+                        .zip(vertices.iter_mut())
+                        .for_each(|(tex_coord, vertex)| vertex.tex_coords_1 = tex_coord);
+                }
+
+                if let Some(color_attribute) = reader.read_colors(0).map(|v| v.into_rgba_f32()) {
+                    color_attribute
+                        .zip(vertices.iter_mut())
+                        .for_each(|(color, vertex)| vertex.color = color);
+                }
+
+                // non indexed primitives are drawn in vertices order
+                let indices: Vec<u32> = match reader.read_indices() {
+                    Some(indices_raw) => indices_raw.into_u32().collect(),
+                    None => (0..vertices.len() as u32).collect(),
+                };
+
+                let has_tangents = match reader.read_tangents() {
+                    Some(tangent_attribute) => {
+                        tangent_attribute
+                            .zip(vertices.iter_mut())
+                            .for_each(|(tangent, vertex)| vertex.tangent = tangent);
+                        true
+                    }
+                    None => false,
+                };
+                let has_normals = reader.read_normals().is_some();
+                let has_tex_coords = reader.read_tex_coords(0).is_some();
+                if !has_tangents
+                    && has_normals
+                    && has_tex_coords
+                    && (primitive.mode() != gltf::mesh::Mode::Triangles
+                        || !generate_tangents(&mut vertices, &indices))
+                {
                     warn(&format!(
-                        "mesh asset loading warning\npath: {}\nwarning: no texture coords",
-                        &full_path
+                        "mesh asset loading warning\npath: {}\nwarning: failed to generate tangents",
+                        full_path
                     ));
                 }
 
@@ -545,11 +1352,57 @@ fn add_object_and_children(
                     });
                 }
 
+                let material = Material::from_gltf(&primitive.material());
+                if material
+                    .textures()
+                    .iter()
+                    .flatten()
+                    .any(|texture| texture.tex_coord > 1)
+                {
+                    warn(&format!(
+                        "mesh asset loading warning\npath: {}\nwarning: material '{}' uses a texture coords set other than 0 or 1, it's not supported",
+                        full_path, material.name
+                    ));
+                }
+
+                let target_names = mesh_target_names(&mesh, full_path);
+                let mut morph_targets = vec![];
+                for (target_index, (positions, normals, _)) in reader.read_morph_targets().enumerate() {
+                    let mut target = MorphTarget {
+                        name: target_names.get(target_index).cloned().unwrap_or(target_index.to_string()),
+                        positions: vec![[0.0; 3]; vertices.len()],
+                        normals: vec![[0.0; 3]; vertices.len()],
+                    };
+                    if let Some(positions) = positions {
+                        positions.zip(target.positions.iter_mut()).for_each(|(delta, position)| *position = delta);
+                    }
+                    if let Some(normals) = normals {
+                        normals.zip(target.normals.iter_mut()).for_each(|(delta, normal)| *normal = delta);
+                    }
+                    morph_targets.push(target);
+                }
+
+                // every target at weight 1 has to fit in, weights above 1 can still go out of the box
+                let bind_pose_bounding_box = Aabb::from_points(vertices.iter().map(|vertex| Vec3::from(vertex.position)).chain(
+                    morph_targets.iter().flat_map(|target| {
+                        vertices.iter().zip(&target.positions).map(|(vertex, delta)| Vec3::from(vertex.position) + Vec3::from(*delta))
+                    }),
+                ));
+                let bounding_box = match vertices.iter().any(|vertex| vertex.weights != [0.0; 4]) {
+                    true => bind_pose_bounding_box.scaled(DEFAULT_SKINNED_BOUNDING_BOX_SCALE),
+                    false => bind_pose_bounding_box,
+                };
+
                 objects.push(Object {
                     vertices,
                     indices,
                     transform: global_transform_mat_cols,
                     node_index,
+                    material,
+                    bind_pose_bounding_box,
+                    bounding_box,
+                    lod: node.name().and_then(lod_from_name).or(mesh.name().and_then(lod_from_name)),
+                    morph_targets,
                 });
             });
         }
@@ -562,9 +1415,11 @@ fn add_object_and_children(
     }
 
     nodes.push(Node {
+        name: node.name().unwrap_or("").to_string(),
         transform: global_transform_mat_cols,
         node_index: node.index(),
         children_id,
+        morph_weights: node_morph_weights(node),
     });
 
     for child in node.children() {
@@ -579,11 +1434,344 @@ fn add_object_and_children(
     }
 }
 
+/// Texture file of an mtl map statement, options like `-bm 0.5` before it are skipped.
+fn mtl_texture_path(statement: &str) -> Option<String> {
+    // exporters on windows write backslashes
+    let statement = statement.trim().replace('\\', "/");
+    match statement.starts_with('-') {
+        true => statement.split_whitespace().last().map(|path| path.to_string()),
+        false if statement.is_empty() => None,
+        false => Some(statement.to_string()),
+    }
+}
+
+/// Smooth normals from the faces around every vertex, weighted by face area.
+fn generate_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut normals = vec![Vec3::ZERO; vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+        if a >= vertices.len() || b >= vertices.len() || c >= vertices.len() {
+            continue;
+        }
+        let position = |index: usize| Vec3::from(vertices[index].position);
+        let normal = (position(b) - position(a)).cross(position(c) - position(a));
+        normals[a] += normal;
+        normals[b] += normal;
+        normals[c] += normal;
+    }
+
+    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+        vertex.normal = normal.normalize_or_zero().to_array();
+    }
+}
+
+/// Data uris are decoded, other uris are files relative to the gltf file.
+/// Buffers that aren't found fall back to the gltf file name with .bin extension.
+fn read_uri(gltf_path: &str, uri: &str) -> Result<Vec<u8>, ModelAssetError> {
+    if uri.starts_with("data:") {
+        let decoded = DataUrl::process(uri).ok().and_then(|url| url.decode_to_vec().ok());
+        return match decoded {
+            Some((data, _)) => Ok(data),
+            None => {
+                error(&format!("mesh asset loading error!\nasset path: {}\nerror: can't decode a data uri: bad base64", gltf_path));
+                Err(ModelAssetError::BufferDecodingError)
+            }
+        };
+    }
+
+    let relative_path = match Path::new(gltf_path).parent() {
+        Some(parent) => parent.join(decode_uri_path(uri)),
+        None => Path::new(&decode_uri_path(uri)).to_path_buf(),
+    };
+    match std::fs::read(get_full_asset_path(&relative_path.to_string_lossy())) {
+        Ok(data) => Ok(data),
+        Err(err) => {
+            let bin_path = get_full_asset_path(&Path::new(gltf_path).with_extension("bin").to_string_lossy());
+            if uri.ends_with(".bin") {
+                if let Ok(data) = std::fs::read(&bin_path) {
+                    return Ok(data);
+                }
+            }
+            error(&format!(
+                "model asset loading error\nasset path: {}\nfailed to read {}\nerr: {}",
+                gltf_path, relative_path.to_string_lossy(), err));
+            Err(ModelAssetError::FailedToReadUri)
+        }
+    }
+}
+
+/// Uris are percent encoded, e.g. spaces are `%20`.
+fn decode_uri_path(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok()).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Node's weights override the mesh's weights, missing weights are 0.
+fn node_morph_weights(node: &gltf::Node) -> Vec<f32> {
+    let mesh = match node.mesh() {
+        Some(mesh) => mesh,
+        None => return vec![],
+    };
+    let targets_count = mesh
+        .primitives()
+        .map(|primitive| primitive.morph_targets().len())
+        .max()
+        .unwrap_or(0);
+
+    let mut weights = node.weights().or(mesh.weights()).unwrap_or(&[]).to_vec();
+    weights.resize(targets_count, 0.0);
+    weights
+}
+
+fn mesh_target_names(mesh: &gltf::Mesh, full_path: &str) -> Vec<String> {
+    match mesh.extras() {
+        Some(extras) => match serde_json::from_str::<MeshExtras>(extras.get()) {
+            Ok(extras) => extras.target_names,
+            Err(err) => {
+                warn(&format!(
+                    "mesh asset loading warning\npath: {}\nfailed to read morph target names of mesh '{}': {}",
+                    full_path, mesh.name().unwrap_or(""), err));
+                vec![]
+            }
+        },
+        None => vec![],
+    }
+}
+
 #[derive(Debug)]
 pub enum ModelAssetError {
     LoadError,
     //SparseKeyframesError,
     BufferDecodingError,
     GlbError,
-    FailedToReadBin, //ChannelCurveBuildingError,
+    FailedToReadUri, //ChannelCurveBuildingError,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(interpolation: ChannelInterpolation, timestamps: Vec<f32>, keyframes: Vec<Vec4>) -> AnimationChannel {
+        AnimationChannel {
+            channel_type: AnimationChannelType::Translation,
+            node_index: 0,
+            interpolation,
+            timestamps,
+            keyframes,
+            morph_weights: vec![],
+            morph_targets_count: 0,
+        }
+    }
+
+    fn weights_channel(interpolation: ChannelInterpolation, timestamps: Vec<f32>, morph_weights: Vec<f32>) -> AnimationChannel {
+        AnimationChannel {
+            channel_type: AnimationChannelType::MorphTargetWeights,
+            node_index: 0,
+            interpolation,
+            timestamps,
+            keyframes: vec![],
+            morph_weights,
+            morph_targets_count: 2,
+        }
+    }
+
+    /// Cubic spline keys with zero tangents.
+    fn cubic_keyframes(values: &[Vec4]) -> Vec<Vec4> {
+        values.iter().flat_map(|value| [Vec4::ZERO, *value, Vec4::ZERO]).collect()
+    }
+
+    const FROM: Vec4 = Vec4::new(0.0, 0.0, 0.0, 0.0);
+    const TO: Vec4 = Vec4::new(10.0, 0.0, 0.0, 0.0);
+
+    #[test]
+    fn step_keeps_previous_key() {
+        let channel = channel(ChannelInterpolation::Step, vec![0.0, 1.0], vec![FROM, TO]);
+        assert_eq!(channel.sample_vec3(0.5), Some(Vec3::ZERO));
+        assert_eq!(channel.sample_vec3(1.0), Some(Vec3::new(10.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn linear_interpolates_between_keys() {
+        let channel = channel(ChannelInterpolation::Linear, vec![0.0, 1.0], vec![FROM, TO]);
+        assert_eq!(channel.sample_vec3(0.25), Some(Vec3::new(2.5, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn cubic_spline_with_flat_tangents_passes_midpoint() {
+        let channel = channel(ChannelInterpolation::CubicSpline, vec![0.0, 2.0], cubic_keyframes(&[FROM, TO]));
+        let value = channel.sample_vec3(1.0).unwrap();
+        assert!((value.x - 5.0).abs() < 1e-5);
+        assert_eq!(channel.sample_vec3(2.0), Some(Vec3::new(10.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn cubic_spline_uses_scaled_tangents() {
+        // out tangent of 1 unit per second over a 2 seconds segment
+        let keyframes = vec![Vec4::ZERO, FROM, Vec4::X, Vec4::ZERO, FROM, Vec4::ZERO];
+        let channel = channel(ChannelInterpolation::CubicSpline, vec![0.0, 2.0], keyframes);
+        // h10(0.5) = 0.125, times delta 2
+        let value = channel.sample_vec3(1.0).unwrap();
+        assert!((value.x - 0.25).abs() < 1e-5);
+    }
+
+    #[test]
+    fn clamps_outside_of_keys() {
+        for interpolation in [ChannelInterpolation::Step, ChannelInterpolation::Linear, ChannelInterpolation::CubicSpline] {
+            let keyframes = match interpolation {
+                ChannelInterpolation::CubicSpline => cubic_keyframes(&[FROM, TO]),
+                _ => vec![FROM, TO],
+            };
+            let channel = channel(interpolation, vec![1.0, 2.0], keyframes);
+            assert_eq!(channel.sample_vec3(-1.0), Some(Vec3::ZERO), "{:?}", interpolation);
+            assert_eq!(channel.sample_vec3(5.0), Some(Vec3::new(10.0, 0.0, 0.0)), "{:?}", interpolation);
+        }
+    }
+
+    #[test]
+    fn zero_keyframe_delta_jumps_to_the_later_key() {
+        let keyframes = vec![FROM, Vec4::X, Vec4::X * 5.0, Vec4::X * 6.0];
+        let channel = channel(ChannelInterpolation::Linear, vec![0.0, 1.0, 1.0, 2.0], keyframes);
+        let value = channel.sample_vec3(1.0).unwrap();
+        assert!(value.is_finite());
+        assert_eq!(value, Vec3::new(5.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn empty_channel_has_no_value() {
+        let channel = channel(ChannelInterpolation::Linear, vec![], vec![]);
+        assert_eq!(channel.sample_vec3(0.0), None);
+    }
+
+    #[test]
+    fn linear_rotation_stays_normalized() {
+        let from = Quat::IDENTITY;
+        let to = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2);
+        let mut channel = channel(ChannelInterpolation::Linear, vec![0.0, 1.0], vec![from.into(), to.into()]);
+        channel.channel_type = AnimationChannelType::Rotation;
+        let rotation = channel.sample_quat(0.5).unwrap();
+        assert!(rotation.is_normalized());
+        assert!(rotation.abs_diff_eq(Quat::from_rotation_y(std::f32::consts::FRAC_PI_4), 1e-5));
+    }
+
+    #[test]
+    fn cubic_rotation_that_cancels_out_falls_back_to_previous_key() {
+        // q and -q are the same rotation, the hermite midpoint of them is a zero quaternion
+        let from = Quat::from_rotation_x(0.5);
+        let to = -from;
+        let mut channel = channel(ChannelInterpolation::CubicSpline, vec![0.0, 1.0], cubic_keyframes(&[from.into(), to.into()]));
+        channel.channel_type = AnimationChannelType::Rotation;
+        let rotation = channel.sample_quat(0.5).unwrap();
+        assert!(rotation.is_normalized());
+        assert!(rotation.abs_diff_eq(from, 1e-5));
+    }
+
+    #[test]
+    fn weights_interpolate_per_target() {
+        let channel = weights_channel(ChannelInterpolation::Linear, vec![0.0, 1.0], vec![0.0, 1.0, 1.0, 0.0]);
+        assert_eq!(channel.sample_weights(0.5), Some(vec![0.5, 0.5]));
+        assert_eq!(channel.sample_weights(-1.0), Some(vec![0.0, 1.0]));
+        assert_eq!(channel.sample_weights(2.0), Some(vec![1.0, 0.0]));
+
+        let channel = weights_channel(ChannelInterpolation::Step, vec![0.0, 1.0], vec![0.0, 1.0, 1.0, 0.0]);
+        assert_eq!(channel.sample_weights(0.5), Some(vec![0.0, 1.0]));
+    }
+
+    #[test]
+    fn cubic_weights_skip_tangents() {
+        // (in, value, out) for each key, two targets each
+        let morph_weights = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+        let channel = weights_channel(ChannelInterpolation::CubicSpline, vec![0.0, 1.0], morph_weights);
+        assert_eq!(channel.sample_weights(0.0), Some(vec![0.0, 1.0]));
+        let weights = channel.sample_weights(0.5).unwrap();
+        assert!((weights[0] - 0.5).abs() < 1e-5 && (weights[1] - 0.5).abs() < 1e-5);
+        assert_eq!(channel.sample_weights(1.0), Some(vec![1.0, 0.0]));
+    }
+
+    const QUAD_AND_TRIANGLE_OBJ: &str = "o quad
+v -1 0 -1
+v 1 0 -1
+v 1 0 1
+v -1 0 1
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 1 0
+f 1/1/1 2/2/1 3/3/1 4/4/1
+o triangle
+v 0 0 0
+v 1 0 0
+v 0 1 0
+f 5 6 7
+";
+
+    fn obj_asset(source: &str) -> ModelAsset {
+        let mut reader = std::io::BufReader::new(source.as_bytes());
+        let (models, materials) =
+            tobj::load_obj_buf(&mut reader, &tobj::GPU_LOAD_OPTIONS, |_| Err(tobj::LoadError::OpenFileFailed)).unwrap();
+        ModelAsset::from_obj_models("test.obj", "test.obj", models, materials)
+    }
+
+    #[test]
+    fn obj_objects_are_triangulated() {
+        let asset = obj_asset(QUAD_AND_TRIANGLE_OBJ);
+        assert_eq!(asset.objects.len(), 2);
+        assert_eq!(asset.nodes.len(), 2);
+        assert_eq!(asset.objects[0].vertices.len(), 4);
+        assert_eq!(asset.objects[0].indices.len(), 6);
+        assert_eq!(asset.objects[1].vertices.len(), 3);
+        assert_eq!(asset.objects[1].indices.len(), 3);
+    }
+
+    #[test]
+    fn obj_without_normals_gets_face_normals() {
+        let asset = obj_asset(QUAD_AND_TRIANGLE_OBJ);
+        for vertex in &asset.objects[1].vertices {
+            let normal = Vec3::from(vertex.normal);
+            assert!(normal.abs_diff_eq(Vec3::Z, 1e-5), "{:?}", normal);
+        }
+        // flipped v of the obj texture coords
+        assert_eq!(asset.objects[0].vertices[0].tex_coords, [0.0, 1.0]);
+    }
+
+    #[test]
+    fn heightmap_mesh_uses_every_pixel() {
+        let heights = [0.0, 0.5, 1.0, 0.0, 0.5, 1.0, 0.0, 0.5, 1.0];
+        let asset = ModelAsset::from_heights("heightmap", &heights, 3, 3, Vec2::new(10.0, 20.0), 4.0, 1);
+        let object = &asset.objects[0];
+        assert_eq!(object.vertices.len(), 9);
+        assert_eq!(object.indices.len(), 2 * 2 * 6);
+        assert_eq!(object.vertices[0].position, [-5.0, 0.0, 10.0]);
+        assert_eq!(object.vertices[8].position, [5.0, 4.0, -10.0]);
+        assert_eq!(object.bounding_box.min, Vec3::new(-5.0, 0.0, -10.0));
+        assert_eq!(object.bounding_box.max, Vec3::new(5.0, 4.0, 10.0));
+    }
+
+    #[test]
+    fn heightmap_step_keeps_the_last_row_and_column() {
+        // 4 columns, 3 rows, step 2 uses columns 0, 2, 3 and rows 0, 2
+        let heights = [0.0; 12];
+        let asset = ModelAsset::from_heights("heightmap", &heights, 3, 4, Vec2::ONE, 1.0, 2);
+        let object = &asset.objects[0];
+        assert_eq!(object.vertices.len(), 3 * 2);
+        assert_eq!(object.indices.len(), 2 * 6);
+        assert_eq!(object.vertices[2].tex_coords, [1.0, 0.0]);
+        assert_eq!(object.vertices[5].tex_coords, [1.0, 1.0]);
+    }
 }
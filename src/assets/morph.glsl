@@ -0,0 +1,23 @@
+// morph targets, see docs/shaders.md
+uniform sampler2D morphTargets;
+uniform int morphTargetsCount;
+uniform int morphVertexCount;
+uniform ivec4 morphTargetIndices0;
+uniform ivec4 morphTargetIndices1;
+uniform vec4 morphWeights0;
+uniform vec4 morphWeights1;
+
+// channel: 0 - position, 1 - normal
+vec3 morphDelta(int target, int channel) {
+    int texel = (target * morphVertexCount + gl_VertexID) * 2 + channel;
+    return texelFetch(morphTargets, ivec2(texel % 1024, texel / 1024), 0).xyz;
+}
+
+void applyMorphTargets(inout vec3 position, inout vec3 normal) {
+    for (int i = 0; i < morphTargetsCount; i++) {
+        int target = i < 4 ? morphTargetIndices0[i] : morphTargetIndices1[i - 4];
+        float weight = i < 4 ? morphWeights0[i] : morphWeights1[i - 4];
+        position += morphDelta(target, 0) * weight;
+        normal += morphDelta(target, 1) * weight;
+    }
+}
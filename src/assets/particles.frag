@@ -0,0 +1,25 @@
+#version 330
+
+in vec2 uv;
+in vec4 particleColor;
+in float viewDepth;
+
+uniform sampler2D tex;
+uniform sampler2D depthTexture;
+uniform mat4 inverseProj;
+uniform vec2 resolution;
+// 0 disables soft particles
+uniform float softDistance;
+
+out vec4 color;
+
+void main() {
+    color = texture(tex, uv) * particleColor;
+
+    if (softDistance > 0.0) {
+        float depth = texture(depthTexture, gl_FragCoord.xy / resolution).r;
+        vec4 scenePosition = inverseProj * vec4(0.0, 0.0, depth * 2.0 - 1.0, 1.0);
+        float sceneDepth = abs(scenePosition.z / scenePosition.w);
+        color.a *= clamp((sceneDepth - viewDepth) / softDistance, 0.0, 1.0);
+    }
+}
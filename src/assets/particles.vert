@@ -0,0 +1,63 @@
+#version 330
+
+// quad corner, -0.5 - 0.5
+in vec2 corner;
+
+in vec3 particle_position;
+in float particle_age;
+in float particle_lifetime;
+in float particle_rotation;
+
+// particles' space to render space
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 proj;
+// row 0 - colour over life, row 1 - size over life in r, texels are evenly spaced over the life
+uniform sampler2D curves;
+// columns, rows
+uniform vec2 sheetSize;
+// 0 plays all frames once over the lifetime
+uniform float sheetFps;
+
+out vec2 uv;
+out vec4 particleColor;
+out float viewDepth;
+
+vec4 sampleCurve(int row, float life) {
+    int last = textureSize(curves, 0).x - 1;
+    float x = life * float(last);
+    int left = int(floor(x));
+    vec4 a = texelFetch(curves, ivec2(left, row), 0);
+    vec4 b = texelFetch(curves, ivec2(min(left + 1, last), row), 0);
+    return mix(a, b, x - float(left));
+}
+
+void main() {
+    uv = vec2(0.0);
+    particleColor = vec4(0.0);
+    viewDepth = 0.0;
+    // dead slot, moved out of the clip volume
+    if (particle_age >= particle_lifetime) {
+        gl_Position = vec4(2.0, 2.0, 2.0, 1.0);
+        return;
+    }
+
+    float life = clamp(particle_age / particle_lifetime, 0.0, 1.0);
+    float size = sampleCurve(1, life).r;
+
+    float s = sin(particle_rotation);
+    float c = cos(particle_rotation);
+    vec4 viewPosition = view * model * vec4(particle_position, 1.0);
+    viewPosition.xy += mat2(c, s, -s, c) * corner * size;
+
+    // sheet frames go left to right, top to bottom
+    float frames = sheetSize.x * sheetSize.y;
+    float frame = sheetFps > 0.0 ? mod(particle_age * sheetFps, frames) : min(life * frames, frames - 1.0);
+    frame = floor(frame);
+    vec2 cell = vec2(mod(frame, sheetSize.x), floor(frame / sheetSize.x));
+    uv = (vec2(corner.x + 0.5, 0.5 - corner.y) + cell) / sheetSize;
+
+    particleColor = sampleCurve(0, life);
+    viewDepth = abs(viewPosition.z);
+    gl_Position = proj * viewPosition;
+}
@@ -0,0 +1,41 @@
+#version 330
+
+// one vertex per particle slot, written back with transform feedback
+in vec3 particle_position;
+in vec3 particle_velocity;
+in float particle_age;
+in float particle_lifetime;
+in float particle_rotation;
+in float particle_rotation_speed;
+
+uniform float deltaTime;
+// in particles' space, emitter's one for local particles
+uniform vec3 gravity;
+// velocity multiplier for this step
+uniform float dragFactor;
+
+out vec3 next_position;
+out vec3 next_velocity;
+out float next_age;
+out float next_lifetime;
+out float next_rotation;
+out float next_rotation_speed;
+
+void main() {
+    next_position = particle_position;
+    next_velocity = particle_velocity;
+    next_age = particle_age;
+    next_lifetime = particle_lifetime;
+    next_rotation = particle_rotation;
+    next_rotation_speed = particle_rotation_speed;
+
+    // dead slots keep their state until the emitter reuses them
+    if (particle_age < particle_lifetime) {
+        next_age += deltaTime;
+        next_velocity = (particle_velocity + gravity * deltaTime) * dragFactor;
+        next_position += next_velocity * deltaTime;
+        next_rotation += particle_rotation_speed * deltaTime;
+    }
+
+    gl_Position = vec4(0.0);
+}
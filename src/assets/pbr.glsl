@@ -0,0 +1,135 @@
+// gltf metallic-roughness shading of default shaders, needs fog.glsl, shadows.glsl and lights.glsl above it
+uniform sampler2D tex;
+uniform int baseColorTexCoord;
+uniform vec4 baseColorFactor;
+uniform float metallicFactor;
+uniform float roughnessFactor;
+uniform sampler2D metallicRoughnessTexture;
+uniform int metallicRoughnessTexCoord;
+uniform sampler2D normalTexture;
+uniform bool hasNormalTexture;
+uniform int normalTexCoord;
+uniform float normalScale;
+uniform sampler2D occlusionTexture;
+uniform float occlusionStrength;
+uniform int occlusionTexCoord;
+uniform sampler2D emissiveTexture;
+uniform vec3 emissiveFactor;
+uniform int emissiveTexCoord;
+uniform int alphaMode;
+uniform float alphaCutoff;
+
+uniform sampler2D lightmapTexture;
+uniform bool hasLightmap;
+uniform float lightmapIntensity;
+
+uniform vec3 sunDirection;
+uniform vec3 sunColor;
+uniform vec3 ambientColor;
+uniform vec3 cameraPosition;
+
+const float PI = 3.14159265;
+
+// 0 - fully visible, 0 - 1 fading out, -1 - 0 fading in, see LOD in docs/shaders.md
+void lodDither(float lodFade) {
+    float dither = fract(52.9829189 * fract(dot(gl_FragCoord.xy, vec2(0.06711056, 0.00583715))));
+    if ((lodFade > 0.0 && dither < lodFade) || (lodFade < 0.0 && dither >= 1.0 + lodFade)) {
+        discard;
+    }
+}
+
+vec2 materialUv(int texCoord, vec2 uv0, vec2 uv1) {
+    return texCoord == 0 ? uv0 : uv1;
+}
+
+// cook-torrance with ggx distribution and schlick-smith geometry, times n.l.
+// lights' intensity is scaled by pi, so a white light with intensity 1 gives lambert diffuse of the albedo
+vec3 brdf(vec3 n, vec3 v, vec3 l, vec3 albedo, float metallic, float roughness) {
+    float nl = max(dot(n, l), 0.0);
+    if (nl <= 0.0) {
+        return vec3(0.0);
+    }
+
+    vec3 h = normalize(v + l);
+    float nv = max(dot(n, v), 0.0001);
+    float nh = max(dot(n, h), 0.0);
+    float vh = max(dot(v, h), 0.0);
+
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denominator = nh * nh * (a2 - 1.0) + 1.0;
+    float distribution = a2 / (PI * denominator * denominator);
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    float geometry = nv / (nv * (1.0 - k) + k) * nl / (nl * (1.0 - k) + k);
+
+    vec3 f0 = mix(vec3(0.04), albedo, metallic);
+    vec3 fresnel = f0 + (1.0 - f0) * pow(1.0 - vh, 5.0);
+
+    vec3 specular = distribution * geometry * fresnel / (4.0 * nv * nl);
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * albedo / PI;
+    return (diffuse + specular) * nl * PI;
+}
+
+// split sum environment brdf approximation (Karis), ambient light has no direction to integrate over
+vec3 ambientSpecular(vec3 f0, float roughness, float nv) {
+    vec4 r = roughness * vec4(-1.0, -0.0275, -0.572, 0.022) + vec4(1.0, 0.0425, 1.04, -0.04);
+    float a004 = min(r.x * r.x, exp2(-9.28 * nv)) * r.x + r.y;
+    vec2 scaleBias = vec2(-1.04, 1.04) * a004 + r.zw;
+    return f0 * scaleBias.x + scaleBias.y;
+}
+
+// position - render space, tangent.w - bitangent sign, viewDepth - distance along the camera's view direction
+vec4 shadeMaterial(vec3 position, vec3 vertexNormal, vec4 tangent, vec2 uv0, vec2 uv1, vec4 vertexColor, float viewDepth) {
+    vec4 baseColor = texture(tex, materialUv(baseColorTexCoord, uv0, uv1)) * baseColorFactor * vertexColor;
+    if (alphaMode == 0) {
+        baseColor.a = 1.0;
+    } else if (alphaMode == 1) {
+        if (baseColor.a < alphaCutoff) {
+            discard;
+        }
+        baseColor.a = 1.0;
+    }
+
+    vec4 metallicRoughness = texture(metallicRoughnessTexture, materialUv(metallicRoughnessTexCoord, uv0, uv1));
+    float metallic = clamp(metallicFactor * metallicRoughness.b, 0.0, 1.0);
+    float roughness = clamp(roughnessFactor * metallicRoughness.g, 0.04, 1.0);
+
+    // double sided materials are drawn without culling, their back faces get flipped normals
+    vec3 n = normalize(gl_FrontFacing ? vertexNormal : -vertexNormal);
+    if (hasNormalTexture && dot(tangent.xyz, tangent.xyz) > 0.0) {
+        vec3 t = normalize(tangent.xyz - n * dot(n, tangent.xyz));
+        vec3 b = cross(n, t) * (tangent.w < 0.0 ? -1.0 : 1.0);
+        vec3 mapped = texture(normalTexture, materialUv(normalTexCoord, uv0, uv1)).xyz * 2.0 - 1.0;
+        mapped.xy *= normalScale;
+        n = normalize(mat3(t, b, n) * mapped);
+    }
+
+    vec3 v = normalize(cameraPosition - position);
+    vec3 albedo = baseColor.rgb;
+
+    float sunBias = max(0.002 * (1.0 - max(dot(n, sunDirection), 0.0)), 0.0005);
+    vec3 direct = brdf(n, v, sunDirection, albedo, metallic, roughness) * sunColor * sunLit(position, viewDepth, sunBias);
+    for (int i = 0; i < lights_count.x; i++) {
+        vec3 toLight;
+        vec3 radiance = pointLightRadiance(i, position, n, toLight);
+        direct += brdf(n, v, toLight, albedo, metallic, roughness) * radiance;
+    }
+    for (int i = 0; i < lights_count.y; i++) {
+        vec3 toLight;
+        vec3 radiance = spotLightRadiance(i, position, n, toLight);
+        direct += brdf(n, v, toLight, albedo, metallic, roughness) * radiance;
+    }
+
+    float ao = 1.0 + occlusionStrength * (texture(occlusionTexture, materialUv(occlusionTexCoord, uv0, uv1)).r - 1.0);
+    vec3 ambient = ambientColor * ao;
+    if (hasLightmap) {
+        ambient = texture(lightmapTexture, uv1).rgb * lightmapIntensity * ao;
+    }
+    vec3 f0 = mix(vec3(0.04), albedo, metallic);
+    vec3 indirect = ambient * (albedo * (1.0 - metallic) + ambientSpecular(f0, roughness, max(dot(n, v), 0.0)));
+
+    vec3 emissive = texture(emissiveTexture, materialUv(emissiveTexCoord, uv0, uv1)).rgb * emissiveFactor;
+
+    vec3 color = direct + indirect + emissive;
+    return vec4(applyFog(color, length(position - cameraPosition)), baseColor.a);
+}
@@ -0,0 +1,20 @@
+#version 330
+
+in vec2 uv;
+
+uniform sampler2D colorTexture;
+// one texel along the blur axis
+uniform vec2 direction;
+
+out vec4 color;
+
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+    vec3 result = texture(colorTexture, uv).rgb * weights[0];
+    for (int i = 1; i < 5; i++) {
+        result += texture(colorTexture, uv + direction * i).rgb * weights[i];
+        result += texture(colorTexture, uv - direction * i).rgb * weights[i];
+    }
+    color = vec4(result, 1.0);
+}
@@ -0,0 +1,15 @@
+#version 330
+
+in vec2 uv;
+
+uniform sampler2D colorTexture;
+uniform float threshold;
+
+out vec4 color;
+
+void main() {
+    vec3 texel = texture(colorTexture, uv).rgb;
+    float brightness = max(texel.r, max(texel.g, texel.b));
+    float contribution = max(brightness - threshold, 0.0) / max(brightness, 0.0001);
+    color = vec4(texel * contribution, 1.0);
+}
@@ -0,0 +1,11 @@
+#version 330
+
+in vec2 uv;
+
+uniform sampler2D colorTexture;
+
+out vec4 color;
+
+void main() {
+    color = vec4(texture(colorTexture, uv).rgb, 1.0);
+}
@@ -0,0 +1,31 @@
+#version 330
+
+in vec2 uv;
+
+uniform sampler2D colorTexture;
+// horizontal strip of lutSize slices, blue picks a slice, red goes right and green goes down
+uniform sampler2D lut;
+uniform float lutSize;
+uniform float intensity;
+
+out vec4 color;
+
+void main() {
+    vec3 linear = clamp(texture(colorTexture, uv).rgb, 0.0, 1.0);
+    // luts are made for srgb colours
+    vec3 srgb = pow(linear, vec3(1.0 / 2.2));
+
+    float blue = srgb.b * (lutSize - 1.0);
+    float slice_low = floor(blue);
+    float slice_high = min(slice_low + 1.0, lutSize - 1.0);
+
+    float x = (srgb.r * (lutSize - 1.0) + 0.5) / (lutSize * lutSize);
+    // the lut is uploaded flipped, so the top row is at v = 1
+    float y = 1.0 - (srgb.g * (lutSize - 1.0) + 0.5) / lutSize;
+
+    vec3 low = texture(lut, vec2(x + slice_low / lutSize, y)).rgb;
+    vec3 high = texture(lut, vec2(x + slice_high / lutSize, y)).rgb;
+    vec3 graded = mix(low, high, blue - slice_low);
+
+    color = vec4(mix(linear, pow(graded, vec3(2.2)), intensity), 1.0);
+}
@@ -0,0 +1,25 @@
+#version 330
+
+in vec2 uv;
+
+uniform sampler2D colorTexture;
+uniform sampler2D bloomTexture;
+uniform float bloomIntensity;
+uniform float exposure;
+uniform bool tonemapping;
+
+out vec4 color;
+
+// ACES filmic curve fit by Krzysztof Narkowicz
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main() {
+    vec3 hdr = texture(colorTexture, uv).rgb + texture(bloomTexture, uv).rgb * bloomIntensity;
+    hdr *= exposure;
+    if (tonemapping) {
+        hdr = aces(hdr);
+    }
+    color = vec4(hdr, 1.0);
+}
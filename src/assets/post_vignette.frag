@@ -0,0 +1,17 @@
+#version 330
+
+in vec2 uv;
+
+uniform sampler2D colorTexture;
+uniform float intensity;
+// distance from the center where darkening starts
+uniform float radius;
+uniform float smoothness;
+
+out vec4 color;
+
+void main() {
+    float distance = length(uv - 0.5) * 1.41421356;
+    float vignette = 1.0 - smoothstep(radius, radius + smoothness, distance) * intensity;
+    color = vec4(texture(colorTexture, uv).rgb * vignette, 1.0);
+}
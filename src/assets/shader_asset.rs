@@ -8,6 +8,24 @@ pub static mut DEFAULT_FRAGMENT_SHADER_PATH: &str = "shaders/default.frag";
 pub static mut DEFAULT_INSTANCED_VERTEX_SHADER_PATH: &str = "shaders/default_instanced.vert";
 pub static mut DEFAULT_INSTANCED_FRAGMENT_SHADER_PATH: &str = "shaders/default_instanced.frag";
 
+/// Sources that shaders can pull in with `#include "<name>"` on its own line.
+pub const SHADER_INCLUDES: [(&str, &str); 6] = [
+    ("fog.glsl", include_str!("fog.glsl")),
+    ("shadows.glsl", include_str!("shadows.glsl")),
+    ("lights.glsl", include_str!("lights.glsl")),
+    ("pbr.glsl", include_str!("pbr.glsl")),
+    ("skinning.glsl", include_str!("skinning.glsl")),
+    ("morph.glsl", include_str!("morph.glsl")),
+];
+
+/// Default shaders shipped with the engine, a file at the same path in the assets folder overrides them.
+pub const BUILT_IN_SHADERS: [(&str, &str); 4] = [
+    ("shaders/default.vert", include_str!("default.vert")),
+    ("shaders/default.frag", include_str!("default.frag")),
+    ("shaders/default_instanced.vert", include_str!("default_instanced.vert")),
+    ("shaders/default_instanced.frag", include_str!("default_instanced.frag")),
+];
+
 #[derive(Debug, Clone)]
 pub struct ShaderAsset {
     pub vertex_shader_source: String,
@@ -54,9 +72,8 @@ impl ShaderAsset {
     }
 
     pub fn load_from_file(path: ShaderAssetPath) -> Result<ShaderAsset, ShaderError> {
-        let vertex_shader_source = read_to_string(get_full_asset_path(&path.vertex_shader_path));
-        let fragment_shader_source =
-            read_to_string(get_full_asset_path(&path.fragment_shader_path));
+        let vertex_shader_source = read_shader_source(&path.vertex_shader_path);
+        let fragment_shader_source = read_shader_source(&path.fragment_shader_path);
 
         if vertex_shader_source.is_err() {
             let vertex_shader_source = vertex_shader_source.err().unwrap();
@@ -76,8 +93,8 @@ impl ShaderAsset {
             return Err(ShaderError::FragShaderLoadErr);
         }
 
-        let vertex_shader_source = vertex_shader_source.ok().unwrap();
-        let fragment_shader_source = fragment_shader_source.ok().unwrap();
+        let vertex_shader_source = expand_includes(&vertex_shader_source.ok().unwrap());
+        let fragment_shader_source = expand_includes(&fragment_shader_source.ok().unwrap());
 
         let asset = ShaderAsset {
             vertex_shader_source,
@@ -88,6 +105,40 @@ impl ShaderAsset {
     }
 }
 
+fn read_shader_source(path: &str) -> std::io::Result<String> {
+    match read_to_string(get_full_asset_path(path)) {
+        Ok(source) => Ok(source),
+        Err(err) => match BUILT_IN_SHADERS.iter().find(|(built_in_path, _)| *built_in_path == path) {
+            Some((_, source)) => Ok(source.to_string()),
+            None => Err(err),
+        },
+    }
+}
+
+/// Replaces `#include "<name>"` lines with SHADER_INCLUDES sources, unknown ones are left as they are.
+pub fn expand_includes(source: &str) -> String {
+    let mut expanded = String::with_capacity(source.len());
+    for line in source.lines() {
+        let include_name = line
+            .trim()
+            .strip_prefix("#include")
+            .map(|name| name.trim().trim_matches('"'));
+        match include_name {
+            Some(name) => match SHADER_INCLUDES.iter().find(|(include, _)| *include == name) {
+                Some((_, include_source)) => expanded.push_str(include_source),
+                None => {
+                    error(&format!("shader include error!\nunknown include: {}", name));
+                    expanded.push_str(line);
+                }
+            },
+            None => expanded.push_str(line),
+        }
+        expanded.push('\n');
+    }
+
+    expanded
+}
+
 pub fn get_default_vertex_shader_path() -> String {
     unsafe { DEFAULT_VERTEX_SHADER_PATH.into() }
 }
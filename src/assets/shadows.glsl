@@ -0,0 +1,46 @@
+// sun shadow cascades, see docs/shaders.md
+layout(std140) uniform cascades {
+    ivec4 cascades_count;
+    mat4 cascades_splits;
+    mat4 cascade_view_proj_0;
+    mat4 cascade_view_proj_1;
+    mat4 cascade_view_proj_2;
+    mat4 cascade_view_proj_3;
+};
+uniform sampler2D cascadeShadowTexture0;
+uniform sampler2D cascadeShadowTexture1;
+uniform sampler2D cascadeShadowTexture2;
+uniform sampler2D cascadeShadowTexture3;
+
+float cascadeLit(int cascade, vec3 position, float bias) {
+    mat4 viewProj = cascade == 0 ? cascade_view_proj_0 : cascade == 1 ? cascade_view_proj_1 : cascade == 2 ? cascade_view_proj_2 : cascade_view_proj_3;
+    vec4 shadowPosition = viewProj * vec4(position, 1.0);
+    vec3 coords = shadowPosition.xyz / shadowPosition.w * 0.5 + 0.5;
+    if (coords.z > 1.0) {
+        return 1.0;
+    }
+
+    float depth;
+    if (cascade == 0) depth = texture(cascadeShadowTexture0, coords.xy).r;
+    else if (cascade == 1) depth = texture(cascadeShadowTexture1, coords.xy).r;
+    else if (cascade == 2) depth = texture(cascadeShadowTexture2, coords.xy).r;
+    else depth = texture(cascadeShadowTexture3, coords.xy).r;
+
+    return coords.z - bias > depth ? 0.0 : 1.0;
+}
+
+// 0 - in the sun's shadow, 1 - lit. position is render space, viewDepth is the distance along the camera's view direction
+float sunLit(vec3 position, float viewDepth, float bias) {
+    for (int i = 0; i < cascades_count.x; i++) {
+        vec4 split = cascades_splits[i];
+        if (viewDepth < split.y) {
+            float lit = cascadeLit(i, position, bias);
+            if (viewDepth > split.z && i + 1 < cascades_count.x) {
+                lit = mix(lit, cascadeLit(i + 1, position, bias), (viewDepth - split.z) / (split.y - split.z));
+            }
+            return lit;
+        }
+    }
+
+    return 1.0;
+}
@@ -0,0 +1,22 @@
+// joints of skinned meshes, see docs/shaders.md
+layout(std140) uniform jointsMats {
+    mat4 joints_mats[128];
+};
+layout(std140) uniform jointsInverseBindMats {
+    mat4 joints_inverse_bind_mats[128];
+};
+
+// identity for vertices without weights
+mat4 skinMatrix(vec4 joints, vec4 weights) {
+    float total = weights.x + weights.y + weights.z + weights.w;
+    if (total <= 0.0) {
+        return mat4(1.0);
+    }
+
+    mat4 skin = mat4(0.0);
+    for (int i = 0; i < 4; i++) {
+        int joint = int(joints[i]);
+        skin += joints_mats[joint] * joints_inverse_bind_mats[joint] * weights[i];
+    }
+    return skin / total;
+}
@@ -0,0 +1,58 @@
+#version 330
+
+in vec2 uv;
+
+uniform mat4 inverseViewProj;
+// 1 - gradient, 2 - skybox
+uniform int skyMode;
+uniform vec3 zenithColor;
+uniform vec3 horizonColor;
+uniform vec3 groundColor;
+uniform vec3 sunColor;
+// cosine of the sun's angular radius
+uniform float sunSize;
+uniform vec3 sunDirection;
+uniform samplerCube skybox;
+uniform float skyboxIntensity;
+
+out vec4 color;
+
+vec3 gradient(vec3 dir) {
+    // darker sky when the sun is low
+    float daylight = clamp(sunDirection.y * 2.0 + 0.5, 0.05, 1.0);
+
+    vec3 sky;
+    if (dir.y > 0.0) {
+        sky = mix(horizonColor, zenithColor, sqrt(dir.y));
+    } else {
+        sky = mix(horizonColor, groundColor, sqrt(-dir.y));
+    }
+
+    // horizon gets sun's colour at sunrise and sunset
+    vec2 sun_flat = normalize(sunDirection.xz + vec2(0.0001));
+    float towards_sun = max(dot(normalize(dir.xz + vec2(0.0001)), sun_flat), 0.0);
+    float horizon_glow = pow(towards_sun, 8.0) * (1.0 - abs(sunDirection.y)) * (1.0 - abs(dir.y));
+    sky = mix(sky, normalize(sunColor) * 1.5, horizon_glow * 0.5);
+
+    sky *= daylight;
+
+    float sun = dot(dir, sunDirection);
+    sky += sunColor * smoothstep(sunSize - 0.0004, sunSize + 0.0004, sun);
+    sky += sunColor * pow(max(sun, 0.0), 256.0) * 0.05;
+
+    return sky;
+}
+
+void main() {
+    vec2 ndc = uv * 2.0 - 1.0;
+    vec4 near = inverseViewProj * vec4(ndc, -1.0, 1.0);
+    vec4 far = inverseViewProj * vec4(ndc, 1.0, 1.0);
+    vec3 dir = normalize(far.xyz / far.w - near.xyz / near.w);
+
+    if (skyMode == 2) {
+        // skybox images are srgb
+        color = vec4(pow(texture(skybox, dir).rgb, vec3(2.2)) * skyboxIntensity, 1.0);
+    } else {
+        color = vec4(gradient(dir), 1.0);
+    }
+}
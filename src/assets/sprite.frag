@@ -0,0 +1,15 @@
+#version 330
+
+in vec2 uv;
+
+uniform sampler2D tex;
+uniform vec4 tint;
+
+out vec4 color;
+
+void main() {
+    color = texture(tex, uv) * tint;
+    if (color.a < 0.01) {
+        discard;
+    }
+}
@@ -0,0 +1,44 @@
+#version 330
+
+// quad corner, -0.5 - 0.5
+in vec2 corner;
+
+uniform mat4 view;
+uniform mat4 proj;
+// render space
+uniform vec3 center;
+uniform vec2 size;
+// 0 - world units, 1 - pixels
+uniform int sizeMode;
+// 0 - faces the camera, 1 - rotates only around axis
+uniform int facing;
+uniform vec3 axis;
+uniform vec3 cameraPosition;
+uniform vec2 resolution;
+// x, y, width, height in 0 - 1, y goes down from the top of the texture
+uniform vec4 region;
+
+out vec2 uv;
+
+void main() {
+    uv = region.xy + vec2(corner.x + 0.5, 0.5 - corner.y) * region.zw;
+
+    if (sizeMode == 1) {
+        vec4 clipCenter = proj * view * vec4(center, 1.0);
+        clipCenter.xy += corner * size / resolution * 2.0 * clipCenter.w;
+        gl_Position = clipCenter;
+        return;
+    }
+
+    vec3 right = vec3(view[0][0], view[1][0], view[2][0]);
+    vec3 up = vec3(view[0][1], view[1][1], view[2][1]);
+    if (facing == 1) {
+        up = normalize(axis);
+        vec3 toCamera = cameraPosition - center;
+        right = cross(up, toCamera);
+        right = length(right) > 0.0001 ? normalize(right) : vec3(view[0][0], view[1][0], view[2][0]);
+    }
+
+    vec3 position = center + right * corner.x * size.x + up * corner.y * size.y;
+    gl_Position = proj * view * vec4(position, 1.0);
+}
@@ -0,0 +1,59 @@
+#version 330
+
+in vec3 renderPosition;
+in vec3 terrainNormal;
+in vec2 layerUv;
+in vec2 splatUv;
+in float viewDepth;
+
+// rgba - weights of layers 0-3
+uniform sampler2D splatMap;
+uniform sampler2D layer0;
+uniform sampler2D layer1;
+uniform sampler2D layer2;
+uniform sampler2D layer3;
+uniform vec3 layerColor0;
+uniform vec3 layerColor1;
+uniform vec3 layerColor2;
+uniform vec3 layerColor3;
+// world units per texture repeat of each layer
+uniform vec4 layerTiling;
+
+uniform vec3 sunDirection;
+uniform vec3 sunColor;
+uniform vec3 ambientColor;
+
+uniform vec3 cameraPosition;
+#include "fog.glsl"
+#include "shadows.glsl"
+#include "lights.glsl"
+
+out vec4 color;
+
+void main() {
+    vec4 weights = texture(splatMap, splatUv);
+    weights /= max(weights.r + weights.g + weights.b + weights.a, 0.0001);
+
+    vec3 albedo = texture(layer0, layerUv / layerTiling.x).rgb * layerColor0 * weights.r
+        + texture(layer1, layerUv / layerTiling.y).rgb * layerColor1 * weights.g
+        + texture(layer2, layerUv / layerTiling.z).rgb * layerColor2 * weights.b
+        + texture(layer3, layerUv / layerTiling.w).rgb * layerColor3 * weights.a;
+
+    vec3 normal = normalize(terrainNormal);
+    float lambert = max(dot(normal, sunDirection), 0.0);
+    float bias = max(0.002 * (1.0 - lambert), 0.0005);
+    vec3 light = ambientColor + sunColor * lambert * sunLit(renderPosition, viewDepth, bias);
+    for (int i = 0; i < lights_count.x; i++) {
+        vec3 toLight;
+        vec3 radiance = pointLightRadiance(i, renderPosition, normal, toLight);
+        light += radiance * max(dot(normal, toLight), 0.0);
+    }
+    for (int i = 0; i < lights_count.y; i++) {
+        vec3 toLight;
+        vec3 radiance = spotLightRadiance(i, renderPosition, normal, toLight);
+        light += radiance * max(dot(normal, toLight), 0.0);
+    }
+    vec3 lit = albedo * light;
+
+    color = vec4(applyFog(lit, length(renderPosition - cameraPosition)), 1.0);
+}
@@ -0,0 +1,28 @@
+#version 330
+
+in vec3 position;
+in vec3 normal;
+in vec2 tex_coords;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 proj;
+
+out vec3 renderPosition;
+out vec3 terrainNormal;
+// local xz in world units, for layers tiling
+out vec2 layerUv;
+out vec2 splatUv;
+out float viewDepth;
+
+void main() {
+    vec4 worldPosition = model * vec4(position, 1.0);
+    vec4 viewPosition = view * worldPosition;
+
+    renderPosition = worldPosition.xyz;
+    terrainNormal = normal;
+    layerUv = vec2(position.x, -position.z);
+    splatUv = tex_coords;
+    viewDepth = -viewPosition.z;
+    gl_Position = proj * viewPosition;
+}
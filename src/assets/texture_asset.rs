@@ -1,3 +1,5 @@
+use image::DynamicImage;
+
 use crate::managers::{assets::get_full_asset_path, debugger};
 
 pub static mut DEFAULT_TEXTURE_PATH: &str = "textures/default_texture.png";
@@ -14,6 +16,44 @@ pub enum TextureAssetError {
     TextureCreationError,
 }
 
+#[derive(Debug, Clone)]
+pub enum HeightmapError {
+    LoadError,
+    TooSmall,
+}
+
+/// Grayscale heightmap as 0 - 1 heights row by row (black is 0), rows and columns count.
+/// Heightmaps smaller than 2x2 are errors.
+pub fn load_heightmap(path: &str) -> Result<(Vec<f32>, usize, usize), HeightmapError> {
+    let full_path = get_full_asset_path(path);
+    let image = match image::open(&full_path) {
+        Ok(image) => image,
+        Err(err) => {
+            debugger::error(&format!("heightmap loading error!\npath: {}\nerr: {}", full_path, err));
+            return Err(HeightmapError::LoadError);
+        }
+    };
+
+    let heightmap = heightmap_from_image(image);
+    if let Err(HeightmapError::TooSmall) = heightmap {
+        debugger::error(&format!("heightmap loading error!\npath: {}\nerr: heightmap should be at least 2x2", full_path));
+    }
+
+    heightmap
+}
+
+/// `load_heightmap` for an image that is already decoded.
+pub fn heightmap_from_image(image: DynamicImage) -> Result<(Vec<f32>, usize, usize), HeightmapError> {
+    let image = image.into_luma16();
+    let (columns, rows) = (image.width() as usize, image.height() as usize);
+    if columns < 2 || rows < 2 {
+        return Err(HeightmapError::TooSmall);
+    }
+
+    let heights = image.pixels().map(|pixel| pixel.0[0] as f32 / u16::MAX as f32).collect();
+    Ok((heights, rows, columns))
+}
+
 impl TextureAsset {
     pub fn from_file(path: &str) -> Result<TextureAsset, TextureAssetError> {
         let image = image::open(get_full_asset_path(path));
@@ -53,6 +93,26 @@ impl TextureAsset {
         */
     }
 
+    /// Decodes an encoded image (png, jpeg...), `name` is only used in errors.
+    pub fn from_bytes(bytes: &[u8], name: &str) -> Result<TextureAsset, TextureAssetError> {
+        let image = match image::load_from_memory(bytes) {
+            Ok(image) => image.to_rgba8(),
+            Err(error) => {
+                debugger::error(&format!(
+                    "failed to create image asset({})\nimage error: {:?}",
+                    name, error
+                ));
+                return Err(TextureAssetError::LoadError);
+            }
+        };
+        let image_dimensions = image.dimensions();
+
+        Ok(TextureAsset {
+            image_raw: image.into_raw(),
+            image_dimensions,
+        })
+    }
+
     pub fn default_texture() -> Result<TextureAsset, TextureAssetError> {
         let image = image::open(get_full_asset_path(&get_default_texture_path()));
         match image {
@@ -87,3 +147,26 @@ pub fn set_default_texture_path(path: &'static str) {
 pub fn get_default_texture_path() -> String {
     unsafe { DEFAULT_TEXTURE_PATH.into() }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GrayImage;
+
+    #[test]
+    fn heightmap_is_read_row_by_row() {
+        let image = GrayImage::from_raw(3, 2, vec![0, 255, 0, 255, 0, 255]).unwrap();
+        let (heights, rows, columns) = heightmap_from_image(DynamicImage::ImageLuma8(image)).unwrap();
+        assert_eq!((rows, columns), (2, 3));
+        assert_eq!(heights, vec![0.0, 1.0, 0.0, 1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn heightmap_smaller_than_2x2_is_too_small() {
+        for (width, height) in [(1, 4), (4, 1), (1, 1)] {
+            let image = GrayImage::new(width, height);
+            let heightmap = heightmap_from_image(DynamicImage::ImageLuma8(image));
+            assert!(matches!(heightmap, Err(HeightmapError::TooSmall)), "{}x{}", width, height);
+        }
+    }
+}
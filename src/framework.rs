@@ -6,9 +6,11 @@ use crate::{
     managers::{
         self,
         assets::get_full_asset_path,
+        debug_draw,
         input, navigation,
         networking,
         physics,
+        post_process::{self, PostProcessTextures},
         render::{self, ShadowTextures},
         sound::{self, set_listener_transform},
         systems::{self, SystemValue},
@@ -75,7 +77,8 @@ pub fn start_game_with_render(debug_mode: DebugMode) {
     let mut win_w = window.inner_size().width;
     let mut win_h = window.inner_size().height;
 
-    let shadow_textures = ShadowTextures::new(&display, 4096, 4096);
+    let mut shadow_textures = ShadowTextures::new(&display, 1024);
+    let mut post_process_textures = PostProcessTextures::new(&display);
 
     event_loop.run(move |ev, window_target| {
         match ev {
@@ -110,6 +113,7 @@ pub fn start_game_with_render(debug_mode: DebugMode) {
                                 }
 
                                 systems::ui_render(ctx);
+                                debug_draw::draw_labels(ctx);
                             });
 
                             set_listener_transform(
@@ -118,11 +122,21 @@ pub fn start_game_with_render(debug_mode: DebugMode) {
                                 render::get_camera_front(),
                             );
 
+                            shadow_textures.update(&display);
+                            post_process_textures.update(&display);
                             let mut target = display.draw();
 
-                            render::draw(&display, &mut target, &shadow_textures);
-                            //game_main::render();
-                            render::debug_draw(&display, &mut target);
+                            {
+                                let mut scene = post_process_textures.scene_framebuffer(&display);
+                                render::draw(&display, &mut scene, &shadow_textures);
+                                //game_main::render();
+                            }
+                            let image = post_process::apply(&display, &post_process_textures);
+                            {
+                                let mut overlay = post_process::overlay_framebuffer(&display, &post_process_textures, image);
+                                render::debug_draw(&display, &mut overlay);
+                            }
+                            post_process::present(&display, &mut target, image);
                             egui_glium.paint(&display, &mut target);
 
                             target.finish().unwrap();
@@ -250,6 +264,11 @@ pub fn get_delta_time() -> Duration {
     unsafe { DELTA_TIME }
 }
 
+/// JetBrains Mono that egui uses too.
+pub fn get_font_data() -> &'static [u8] {
+    &FONT
+}
+
 #[derive(Clone, Copy)]
 pub enum DebugMode {
     None,
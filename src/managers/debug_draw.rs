@@ -0,0 +1,374 @@
+use egui_glium::egui_winit::egui::{self, Align2, Color32, FontId, LayerId};
+use glam::{Mat4, Quat, Vec3, Vec4};
+use glium::{
+    framebuffer::SimpleFrameBuffer, glutin::surface::WindowSurface, implement_vertex, index::{NoIndices, PrimitiveType}, uniform, Display, Surface, VertexBuffer
+};
+
+use super::{debugger, render};
+use crate::{framework, math_utils::{deg_to_rad, Aabb}};
+
+const DEBUG_LINES_VERTEX_SHADER: &str = include_str!("../assets/debug_lines.vert");
+const DEBUG_LINES_FRAGMENT_SHADER: &str = include_str!("../assets/debug_lines.frag");
+
+/// Segments used for circles, spheres and capsules.
+const CIRCLE_SEGMENTS: usize = 24;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DebugStyle {
+    /// rgba
+    pub color: Vec4,
+    /// if false, shape is visible through everything
+    pub depth_test: bool,
+    /// seconds, 0 means only the next frame
+    pub lifetime: f32,
+}
+
+impl Default for DebugStyle {
+    fn default() -> Self {
+        DebugStyle {
+            color: Vec4::ONE,
+            depth_test: true,
+            lifetime: 0.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct DebugVertex {
+    position: [f32; 3],
+    color: [f32; 4],
+}
+implement_vertex!(DebugVertex, position, color);
+
+#[derive(Debug, Clone, Copy)]
+struct DebugLine {
+    start: Vec3,
+    end: Vec3,
+    style: DebugStyle,
+}
+
+#[derive(Debug, Clone)]
+struct DebugLabel {
+    position: Vec3,
+    text: String,
+    style: DebugStyle,
+}
+
+static mut DEBUG_LINES: Vec<DebugLine> = Vec::new();
+static mut DEBUG_LABELS: Vec<DebugLabel> = Vec::new();
+static mut DEBUG_VERTEX_BUFFER: Option<VertexBuffer<DebugVertex>> = None;
+
+// all positions are in world space
+
+pub fn line(start: Vec3, end: Vec3, style: DebugStyle) {
+    unsafe {
+        DEBUG_LINES.push(DebugLine { start, end, style });
+    }
+}
+
+pub fn arrow(start: Vec3, end: Vec3, style: DebugStyle) {
+    line(start, end, style);
+
+    let direction = end - start;
+    let length = direction.length();
+    if length < f32::EPSILON {
+        return;
+    }
+    let direction = direction / length;
+    let side = direction.any_orthonormal_vector();
+    let up = direction.cross(side);
+    let head_length = length * 0.2;
+    let head_base = end - direction * head_length;
+    for offset in [side, -side, up, -up] {
+        line(end, head_base + offset * head_length * 0.5, style);
+    }
+}
+
+pub fn aabb(aabb: &Aabb, style: DebugStyle) {
+    box_edges(&aabb.corners(), style);
+}
+
+/// Box with `half_extents` rotated by `rotation` (euler, degrees) around its center.
+pub fn obb(center: Vec3, half_extents: Vec3, rotation: Vec3, style: DebugStyle) {
+    let rotation = euler_to_quat(rotation);
+    let corners = Aabb {
+        min: -half_extents,
+        max: half_extents,
+    }
+    .corners()
+    .map(|corner| center + rotation * corner);
+
+    box_edges(&corners, style);
+}
+
+pub fn circle(center: Vec3, normal: Vec3, radius: f32, style: DebugStyle) {
+    let normal = normal.normalize_or_zero();
+    if normal == Vec3::ZERO {
+        return;
+    }
+    let side = normal.any_orthonormal_vector() * radius;
+    let up = normal.cross(side);
+
+    let points: Vec<Vec3> = (0..=CIRCLE_SEGMENTS)
+        .map(|i| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + side * angle.cos() + up * angle.sin()
+        })
+        .collect();
+    path(&points, style);
+}
+
+pub fn sphere(center: Vec3, radius: f32, style: DebugStyle) {
+    circle(center, Vec3::X, radius, style);
+    circle(center, Vec3::Y, radius, style);
+    circle(center, Vec3::Z, radius, style);
+}
+
+/// Capsule between centers of its two hemispheres.
+pub fn capsule(start: Vec3, end: Vec3, radius: f32, style: DebugStyle) {
+    let axis = (end - start).normalize_or_zero();
+    let axis = if axis == Vec3::ZERO { Vec3::Y } else { axis };
+    let side = axis.any_orthonormal_vector() * radius;
+    let up = axis.cross(side);
+
+    circle(start, axis, radius, style);
+    circle(end, axis, radius, style);
+    for offset in [side, -side, up, -up] {
+        line(start + offset, end + offset, style);
+    }
+
+    // hemispheres as two half circles each
+    for (center, direction) in [(start, -axis), (end, axis)] {
+        for offset in [side, up] {
+            let points: Vec<Vec3> = (0..=CIRCLE_SEGMENTS / 2)
+                .map(|i| {
+                    let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+                    center + offset * angle.cos() + direction * radius * angle.sin()
+                })
+                .collect();
+            path(&points, style);
+        }
+    }
+}
+
+/// Grid on xz plane with `cells` x `cells` cells.
+pub fn grid(center: Vec3, cell_size: f32, cells: u32, style: DebugStyle) {
+    let half_size = cell_size * cells as f32 / 2.0;
+    for i in 0..=cells {
+        let offset = -half_size + i as f32 * cell_size;
+        line(
+            center + Vec3::new(offset, 0.0, -half_size),
+            center + Vec3::new(offset, 0.0, half_size),
+            style,
+        );
+        line(
+            center + Vec3::new(-half_size, 0.0, offset),
+            center + Vec3::new(half_size, 0.0, offset),
+            style,
+        );
+    }
+}
+
+pub fn path(points: &[Vec3], style: DebugStyle) {
+    for pair in points.windows(2) {
+        line(pair[0], pair[1], style);
+    }
+}
+
+/// Text drawn over everything at `position`, `depth_test` is ignored.
+pub fn text(position: Vec3, text: &str, style: DebugStyle) {
+    unsafe {
+        DEBUG_LABELS.push(DebugLabel {
+            position,
+            text: text.into(),
+            style,
+        });
+    }
+}
+
+/// Removes everything including shapes with lifetime.
+pub fn clear() {
+    unsafe {
+        DEBUG_LINES.clear();
+        DEBUG_LABELS.clear();
+    }
+}
+
+fn box_edges(corners: &[Vec3; 8], style: DebugStyle) {
+    // corners differ by one coordinate on every edge
+    const EDGES: [(usize, usize); 12] = [
+        (0, 1), (2, 3), (4, 5), (6, 7),
+        (0, 2), (1, 3), (4, 6), (5, 7),
+        (0, 4), (1, 5), (2, 6), (3, 7),
+    ];
+    for (a, b) in EDGES {
+        line(corners[a], corners[b], style);
+    }
+}
+
+fn euler_to_quat(rotation: Vec3) -> Quat {
+    Quat::from_euler(
+        glam::EulerRot::XYZ,
+        deg_to_rad(rotation.x),
+        deg_to_rad(rotation.y),
+        deg_to_rad(rotation.z),
+    )
+}
+
+fn to_render_space(position: Vec3) -> [f32; 3] {
+    [position.x, position.y, -position.z]
+}
+
+/// Uploads all lines into one vertex buffer and draws them over every screen view, then removes expired shapes.
+pub fn draw(display: &Display<WindowSurface>, target: &mut SimpleFrameBuffer) {
+    let lines = unsafe { &mut DEBUG_LINES };
+    if !lines.is_empty() {
+        draw_lines(display, target, lines);
+    }
+
+    let delta_time = framework::get_delta_time().as_secs_f32();
+    unsafe {
+        DEBUG_LINES.retain_mut(|line| {
+            line.style.lifetime -= delta_time;
+            line.style.lifetime > 0.0
+        });
+        DEBUG_LABELS.retain_mut(|label| {
+            label.style.lifetime -= delta_time;
+            label.style.lifetime > 0.0
+        });
+    }
+}
+
+fn draw_lines(display: &Display<WindowSurface>, target: &mut SimpleFrameBuffer, lines: &[DebugLine]) {
+    let program = match render::get_program(display, DEBUG_LINES_VERTEX_SHADER, DEBUG_LINES_FRAGMENT_SHADER) {
+        Ok(program) => program,
+        Err(err) => {
+            debugger::error(&format!("debug lines program creation error!\nerr: {}", err));
+            return;
+        }
+    };
+
+    // depth tested lines go first, so each group is one slice
+    let mut vertices = Vec::with_capacity(lines.len() * 2);
+    for depth_test in [true, false] {
+        for line in lines.iter().filter(|line| line.style.depth_test == depth_test) {
+            let color = line.style.color.to_array();
+            vertices.push(DebugVertex {
+                position: to_render_space(line.start),
+                color,
+            });
+            vertices.push(DebugVertex {
+                position: to_render_space(line.end),
+                color,
+            });
+        }
+    }
+    let depth_tested_count = lines.iter().filter(|line| line.style.depth_test).count() * 2;
+
+    unsafe {
+        let too_small = match &DEBUG_VERTEX_BUFFER {
+            Some(buffer) => buffer.len() < vertices.len(),
+            None => true,
+        };
+        if too_small {
+            match VertexBuffer::empty_dynamic(display, vertices.len().next_power_of_two()) {
+                Ok(buffer) => DEBUG_VERTEX_BUFFER = Some(buffer),
+                Err(err) => {
+                    debugger::error(&format!("debug lines vertex buffer creation error!\nerr: {}", err));
+                    DEBUG_VERTEX_BUFFER = None;
+                    return;
+                }
+            }
+        }
+    }
+    let vertex_buffer = unsafe { DEBUG_VERTEX_BUFFER.as_ref().unwrap() };
+    vertex_buffer.slice(0..vertices.len()).unwrap().write(&vertices);
+
+    let (width, height) = target.get_dimensions();
+    render::for_each_screen_view(|viewport| {
+        let uniforms = uniform! {
+            view: render::get_view_matrix().to_cols_array_2d(),
+            proj: render::get_projection_matrix().to_cols_array_2d(),
+        };
+
+        for (range, depth_test) in [
+            (0..depth_tested_count, glium::draw_parameters::DepthTest::IfLess),
+            (depth_tested_count..vertices.len(), glium::draw_parameters::DepthTest::Overwrite),
+        ] {
+            if range.is_empty() {
+                continue;
+            }
+
+            let draw_params = glium::DrawParameters {
+                depth: glium::Depth {
+                    test: depth_test,
+                    write: false,
+                    ..Default::default()
+                },
+                blend: glium::draw_parameters::Blend::alpha_blending(),
+                viewport: Some(viewport.to_rect(width, height)),
+                ..Default::default()
+            };
+
+            let result = target.draw(
+                vertex_buffer.slice(range).unwrap(),
+                NoIndices(PrimitiveType::LinesList),
+                program,
+                &uniforms,
+                &draw_params,
+            );
+            if let Err(err) = result {
+                debugger::error(&format!("debug lines draw error!\nerr: {}", err));
+            }
+        }
+    });
+}
+
+/// Text labels are drawn with egui, call it inside egui's run.
+pub fn draw_labels(ctx: &egui::Context) {
+    let labels = unsafe { &DEBUG_LABELS };
+    if labels.is_empty() {
+        return;
+    }
+
+    let screen_rect = ctx.screen_rect();
+    let painter = ctx.layer_painter(LayerId::background());
+
+    // every screen view of the last frame gets the labels, its viewport is from the bottom left corner
+    render::for_each_screen_view(|viewport| {
+        let view_proj = render::get_projection_matrix() * render::get_view_matrix();
+        let view_rect = egui::Rect::from_min_size(
+            egui::pos2(
+                screen_rect.min.x + viewport.x * screen_rect.width(),
+                screen_rect.min.y + (1.0 - viewport.y - viewport.height) * screen_rect.height(),
+            ),
+            egui::vec2(viewport.width * screen_rect.width(), viewport.height * screen_rect.height()),
+        );
+        let painter = painter.with_clip_rect(view_rect);
+
+        for label in labels {
+            draw_label(&painter, label, &view_proj, view_rect);
+        }
+    });
+}
+
+fn draw_label(painter: &egui::Painter, label: &DebugLabel, view_proj: &Mat4, view_rect: egui::Rect) {
+    let clip = *view_proj * Vec3::from(to_render_space(label.position)).extend(1.0);
+    if clip.w <= 0.0 {
+        return;
+    }
+    let ndc = clip.truncate() / clip.w;
+    let screen_position = egui::pos2(
+        view_rect.min.x + (ndc.x * 0.5 + 0.5) * view_rect.width(),
+        view_rect.min.y + (0.5 - ndc.y * 0.5) * view_rect.height(),
+    );
+
+    let color = (label.style.color.clamp(Vec4::ZERO, Vec4::ONE) * 255.0).to_array();
+    painter.text(
+        screen_position,
+        Align2::CENTER_CENTER,
+        &label.text,
+        FontId::proportional(14.0),
+        Color32::from_rgba_unmultiplied(color[0] as u8, color[1] as u8, color[2] as u8, color[3] as u8),
+    );
+}
@@ -1,11 +1,14 @@
 pub mod assets;
+pub mod debug_draw;
 pub mod debugger;
 pub mod input;
 pub mod navigation;
 pub mod networking;
 pub mod physics;
+pub mod post_process;
 pub mod render;
 pub mod scripting;
+pub mod sky;
 pub mod sound;
 pub mod systems;
 pub mod ui;
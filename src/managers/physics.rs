@@ -18,7 +18,7 @@ use rapier3d::{
         ColliderShape, InteractionGroups, NarrowPhase, Ray,
     },
     math::{Point, Real},
-    na::vector,
+    na::{vector, DMatrix},
     pipeline::{ActiveEvents, PhysicsPipeline, QueryFilter, QueryPipeline},
 };
 
@@ -270,11 +270,11 @@ pub fn collider_type_to_collider_builder(
                     });
                     object.indices.iter().for_each(|ind| {
                         if temp_indices.len() < 3 {
-                            temp_indices.push(*ind as u32);
+                            temp_indices.push(*ind);
                         } else {
                             indices.push([temp_indices[0], temp_indices[1], temp_indices[2]]);
                             temp_indices.clear();
-                            temp_indices.push(*ind as u32);
+                            temp_indices.push(*ind);
                         }
                     });
                 }
@@ -283,6 +283,10 @@ pub fn collider_type_to_collider_builder(
             //dbg!(&positions_nalgebra, &indices);
             collider_builder = ColliderBuilder::trimesh(positions_nalgebra, indices);
         }
+        BodyColliderType::Heightfield(heights, rows, columns, size) => {
+            let heights = DMatrix::from_row_slice(rows, columns, &heights);
+            collider_builder = ColliderBuilder::heightfield(heights, size.into());
+        }
     }
 
     collider_builder = collider_builder.solver_groups(InteractionGroups::new(
@@ -311,6 +315,9 @@ pub enum BodyColliderType {
     Cylinder(f32, f32),
     /// first is verts position, second is indices,
     TriangleMesh(ModelAsset),
+    /// heights row by row (rows go along z), rows, columns, scale.
+    /// x and z of scale are full size of the field, y multiplies heights. the field is centered on the body
+    Heightfield(Vec<f32>, usize, usize, Vec3),
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -633,6 +640,7 @@ pub fn collider_type_to_render_collider(
             None, None, *radius, *height, is_sensor,
         )),
         BodyColliderType::TriangleMesh(_) => None,
+        BodyColliderType::Heightfield(..) => None,
     }
 }
 
@@ -647,5 +655,6 @@ pub fn collider_type_to_rapier_shape(collider: &BodyColliderType) -> Option<Coll
             Some(ColliderShape::cylinder(*height, *radius))
         }
         BodyColliderType::TriangleMesh(_) => None,
+        BodyColliderType::Heightfield(..) => None,
     }
 }
@@ -0,0 +1,444 @@
+use glium::{
+    framebuffer::SimpleFrameBuffer,
+    glutin::surface::WindowSurface,
+    index::{NoIndices, PrimitiveType},
+    texture::{DepthTexture2d, MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat},
+    uniform,
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction, Uniforms},
+    vertex::EmptyVertexAttributes,
+    Display, Frame, Program, Surface,
+};
+use once_cell::sync::Lazy;
+use std::time::Instant;
+
+use super::{debugger, render};
+use crate::assets::texture_asset::TextureAsset;
+
+/// Fullscreen triangle with `uv` output.
+pub const FULLSCREEN_VERTEX_SHADER: &str = include_str!("../assets/post_fullscreen.vert");
+const COPY_FRAGMENT_SHADER: &str = include_str!("../assets/post_copy.frag");
+const BRIGHT_FRAGMENT_SHADER: &str = include_str!("../assets/post_bright.frag");
+const BLUR_FRAGMENT_SHADER: &str = include_str!("../assets/post_blur.frag");
+const TONEMAP_FRAGMENT_SHADER: &str = include_str!("../assets/post_tonemap.frag");
+const LUT_FRAGMENT_SHADER: &str = include_str!("../assets/post_lut.frag");
+const FXAA_FRAGMENT_SHADER: &str = include_str!("../assets/post_fxaa.frag");
+const VIGNETTE_FRAGMENT_SHADER: &str = include_str!("../assets/post_vignette.frag");
+
+#[derive(Debug, Clone)]
+pub struct PostProcessSettings {
+    pub tonemapping: bool,
+    pub exposure: f32,
+    pub bloom: bool,
+    /// brightness where bloom starts
+    pub bloom_threshold: f32,
+    pub bloom_intensity: f32,
+    pub bloom_blur_passes: u32,
+    pub color_grading: bool,
+    /// path to a lut strip (e.g. 256x16), colour grading is skipped if it's empty
+    pub lut_path: String,
+    pub lut_intensity: f32,
+    pub fxaa: bool,
+    pub vignette: bool,
+    pub vignette_intensity: f32,
+    pub vignette_radius: f32,
+    pub vignette_smoothness: f32,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        PostProcessSettings {
+            tonemapping: true,
+            exposure: 1.0,
+            bloom: true,
+            bloom_threshold: 1.0,
+            bloom_intensity: 0.5,
+            bloom_blur_passes: 3,
+            color_grading: false,
+            lut_path: String::new(),
+            lut_intensity: 1.0,
+            fxaa: true,
+            vignette: false,
+            vignette_intensity: 0.4,
+            vignette_radius: 0.6,
+            vignette_smoothness: 0.5,
+        }
+    }
+}
+
+static mut POST_PROCESS_SETTINGS: Lazy<PostProcessSettings> = Lazy::new(PostProcessSettings::default);
+
+pub fn set_post_process_settings(mut settings: PostProcessSettings) {
+    settings.exposure = settings.exposure.max(0.0);
+    settings.bloom_threshold = settings.bloom_threshold.max(0.0);
+    settings.bloom_intensity = settings.bloom_intensity.max(0.0);
+    settings.bloom_blur_passes = settings.bloom_blur_passes.clamp(1, 16);
+    settings.lut_intensity = settings.lut_intensity.clamp(0.0, 1.0);
+    settings.vignette_intensity = settings.vignette_intensity.clamp(0.0, 1.0);
+    settings.vignette_smoothness = settings.vignette_smoothness.max(0.001);
+
+    unsafe {
+        *POST_PROCESS_SETTINGS = settings;
+    }
+}
+
+pub fn get_post_process_settings() -> PostProcessSettings {
+    unsafe { POST_PROCESS_SETTINGS.clone() }
+}
+
+/// Turns a built-in (tonemapping, bloom, color_grading, fxaa, vignette) or a custom pass on or off.
+/// Returns false if there's no pass with this name.
+pub fn set_pass_enabled(name: &str, enabled: bool) -> bool {
+    let mut settings = get_post_process_settings();
+    match name {
+        "tonemapping" => settings.tonemapping = enabled,
+        "bloom" => settings.bloom = enabled,
+        "color_grading" => settings.color_grading = enabled,
+        "fxaa" => settings.fxaa = enabled,
+        "vignette" => settings.vignette = enabled,
+        _ => {
+            return unsafe {
+                match CUSTOM_PASSES.iter_mut().find(|pass| pass.name == name) {
+                    Some(pass) => {
+                        pass.enabled = enabled;
+                        true
+                    }
+                    None => false,
+                }
+            }
+        }
+    }
+    set_post_process_settings(settings);
+
+    true
+}
+
+#[derive(Debug)]
+pub struct CustomPass {
+    pub name: String,
+    pub fragment_shader_source: String,
+    pub enabled: bool,
+    failed: bool,
+}
+
+static mut CUSTOM_PASSES: Vec<CustomPass> = Vec::new();
+static START_TIME: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// Custom passes run after the built-in ones, in the order they were added.
+/// See docs/shaders.md for uniforms they get.
+pub fn add_custom_pass(name: &str, fragment_shader_source: &str) {
+    unsafe {
+        if CUSTOM_PASSES.iter().any(|pass| pass.name == name) {
+            debugger::warn(&format!("add_custom_pass: pass {} already exists, replacing it", name));
+            remove_custom_pass(name);
+        }
+
+        CUSTOM_PASSES.push(CustomPass {
+            name: name.into(),
+            fragment_shader_source: fragment_shader_source.into(),
+            enabled: true,
+            failed: false,
+        });
+    }
+}
+
+pub fn remove_custom_pass(name: &str) {
+    unsafe {
+        CUSTOM_PASSES.retain(|pass| pass.name != name);
+    }
+}
+
+pub fn get_custom_passes_names() -> Vec<String> {
+    unsafe { CUSTOM_PASSES.iter().map(|pass| pass.name.clone()).collect() }
+}
+
+pub fn is_custom_pass_enabled(name: &str) -> bool {
+    unsafe {
+        CUSTOM_PASSES
+            .iter()
+            .any(|pass| pass.name == name && pass.enabled)
+    }
+}
+
+/// Scene is rendered into `scene` and `scene_depth`, passes ping pong between `ping` and `pong`.
+pub struct PostProcessTextures {
+    pub scene: Texture2d,
+    pub scene_depth: DepthTexture2d,
+    ping: Texture2d,
+    pong: Texture2d,
+    bloom_a: Texture2d,
+    bloom_b: Texture2d,
+    lut: Option<Texture2d>,
+    lut_path: String,
+}
+
+impl PostProcessTextures {
+    pub fn new(display: &Display<WindowSurface>) -> PostProcessTextures {
+        let (width, height) = display.get_framebuffer_dimensions();
+        let (width, height) = (width.max(1), height.max(1));
+
+        PostProcessTextures {
+            scene: new_hdr_texture(display, width, height),
+            scene_depth: DepthTexture2d::empty(display, width, height).unwrap(),
+            ping: new_hdr_texture(display, width, height),
+            pong: new_hdr_texture(display, width, height),
+            bloom_a: new_hdr_texture(display, (width / 2).max(1), (height / 2).max(1)),
+            bloom_b: new_hdr_texture(display, (width / 2).max(1), (height / 2).max(1)),
+            lut: None,
+            lut_path: String::new(),
+        }
+    }
+
+    /// Recreates textures if window was resized and reloads the lut if its path changed.
+    pub fn update(&mut self, display: &Display<WindowSurface>) {
+        let (width, height) = display.get_framebuffer_dimensions();
+        if (width.max(1), height.max(1)) != self.scene.dimensions() {
+            let lut = self.lut.take();
+            let lut_path = std::mem::take(&mut self.lut_path);
+            *self = PostProcessTextures::new(display);
+            self.lut = lut;
+            self.lut_path = lut_path;
+        }
+
+        let lut_path = get_post_process_settings().lut_path;
+        if lut_path != self.lut_path {
+            self.lut = load_lut(display, &lut_path);
+            self.lut_path = lut_path;
+        }
+    }
+
+    pub fn scene_framebuffer(&self, display: &Display<WindowSurface>) -> SimpleFrameBuffer<'_> {
+        SimpleFrameBuffer::with_depth_buffer(display, &self.scene, &self.scene_depth).unwrap()
+    }
+}
+
+fn new_hdr_texture(display: &Display<WindowSurface>, width: u32, height: u32) -> Texture2d {
+    Texture2d::empty_with_format(
+        display,
+        UncompressedFloatFormat::F16F16F16F16,
+        MipmapsOption::NoMipmap,
+        width,
+        height,
+    )
+    .unwrap()
+}
+
+fn load_lut(display: &Display<WindowSurface>, path: &str) -> Option<Texture2d> {
+    if path.is_empty() {
+        return None;
+    }
+
+    let asset = TextureAsset::from_file(path).ok()?;
+    let (width, height) = asset.image_dimensions;
+    if width != height * height {
+        debugger::error(&format!(
+            "lut {} should be a strip of {} slices ({}x{}), got {}x{}",
+            path, height, height * height, height, width, height
+        ));
+        return None;
+    }
+
+    let image = RawImage2d::from_raw_rgba_reversed(&asset.image_raw, asset.image_dimensions);
+    match Texture2d::with_mipmaps(display, image, MipmapsOption::NoMipmap) {
+        Ok(texture) => Some(texture),
+        Err(err) => {
+            debugger::error(&format!("lut texture creation error!\nerr: {}", err));
+            None
+        }
+    }
+}
+
+fn sampled(texture: &Texture2d) -> Sampler<'_, Texture2d> {
+    Sampler::new(texture)
+        .minify_filter(MinifySamplerFilter::Linear)
+        .magnify_filter(MagnifySamplerFilter::Linear)
+        .wrap_function(SamplerWrapFunction::Clamp)
+}
+
+fn draw_fullscreen<S: Surface, U: Uniforms>(surface: &mut S, program: &Program, uniforms: &U) {
+    surface
+        .draw(
+            EmptyVertexAttributes { len: 3 },
+            NoIndices(PrimitiveType::TrianglesList),
+            program,
+            uniforms,
+            &Default::default(),
+        )
+        .unwrap();
+}
+
+fn get_program(display: &Display<WindowSurface>, fragment_shader_source: &str) -> Option<&'static Program> {
+    match render::get_program(display, FULLSCREEN_VERTEX_SHADER, fragment_shader_source) {
+        Ok(program) => Some(program),
+        Err(err) => {
+            debugger::error(&format!("post process program creation error!\nerr: {}", err));
+            None
+        }
+    }
+}
+
+/// Passes read the previous pass' output and write into the other ping pong texture.
+struct PassChain<'a> {
+    textures: &'a PostProcessTextures,
+    current: &'a Texture2d,
+}
+
+impl<'a> PassChain<'a> {
+    fn next_target(&self) -> &'a Texture2d {
+        if std::ptr::eq(self.current, &self.textures.ping) {
+            &self.textures.pong
+        } else {
+            &self.textures.ping
+        }
+    }
+
+    fn run<U: Uniforms>(&mut self, display: &Display<WindowSurface>, program: &Program, uniforms: &U) {
+        let target = self.next_target();
+        let mut framebuffer = SimpleFrameBuffer::new(display, target).unwrap();
+        draw_fullscreen(&mut framebuffer, program, uniforms);
+        self.current = target;
+    }
+}
+
+/// Runs enabled passes over the scene, returns the texture with the result.
+pub fn apply<'a>(display: &Display<WindowSurface>, textures: &'a PostProcessTextures) -> &'a Texture2d {
+    let settings = get_post_process_settings();
+    let mut chain = PassChain {
+        textures,
+        current: &textures.scene,
+    };
+
+    let bloom = settings.bloom && bloom(display, textures, &settings);
+
+    if settings.tonemapping || bloom {
+        if let Some(program) = get_program(display, TONEMAP_FRAGMENT_SHADER) {
+            let uniforms = uniform! {
+                colorTexture: sampled(chain.current),
+                bloomTexture: sampled(&textures.bloom_a),
+                bloomIntensity: if bloom { settings.bloom_intensity } else { 0.0 },
+                exposure: settings.exposure,
+                tonemapping: settings.tonemapping,
+            };
+            chain.run(display, program, &uniforms);
+        }
+    }
+
+    if let (true, Some(lut)) = (settings.color_grading, &textures.lut) {
+        if let Some(program) = get_program(display, LUT_FRAGMENT_SHADER) {
+            let uniforms = uniform! {
+                colorTexture: sampled(chain.current),
+                lut: sampled(lut),
+                lutSize: lut.height() as f32,
+                intensity: settings.lut_intensity,
+            };
+            chain.run(display, program, &uniforms);
+        }
+    }
+
+    let (width, height) = textures.scene.dimensions();
+    let resolution = [width as f32, height as f32];
+
+    if settings.fxaa {
+        if let Some(program) = get_program(display, FXAA_FRAGMENT_SHADER) {
+            let uniforms = uniform! {
+                colorTexture: sampled(chain.current),
+                resolution: resolution,
+            };
+            chain.run(display, program, &uniforms);
+        }
+    }
+
+    if settings.vignette {
+        if let Some(program) = get_program(display, VIGNETTE_FRAGMENT_SHADER) {
+            let uniforms = uniform! {
+                colorTexture: sampled(chain.current),
+                intensity: settings.vignette_intensity,
+                radius: settings.vignette_radius,
+                smoothness: settings.vignette_smoothness,
+            };
+            chain.run(display, program, &uniforms);
+        }
+    }
+
+    let time = START_TIME.elapsed().as_secs_f32();
+    let custom_passes = unsafe { &mut CUSTOM_PASSES };
+    for pass in custom_passes.iter_mut().filter(|pass| pass.enabled && !pass.failed) {
+        let program = match render::get_program(display, FULLSCREEN_VERTEX_SHADER, &pass.fragment_shader_source) {
+            Ok(program) => program,
+            Err(err) => {
+                debugger::error(&format!(
+                    "custom post process pass {} program creation error, disabling it!\nerr: {}",
+                    pass.name, err
+                ));
+                pass.failed = true;
+                continue;
+            }
+        };
+
+        let uniforms = uniform! {
+            colorTexture: sampled(chain.current),
+            depthTexture: textures.scene_depth.sampled(),
+            resolution: resolution,
+            time: time,
+        };
+        chain.run(display, program, &uniforms);
+    }
+
+    chain.current
+}
+
+/// Post processed image with scene depth, for drawing over it after post processing.
+pub fn overlay_framebuffer<'a>(
+    display: &Display<WindowSurface>,
+    textures: &'a PostProcessTextures,
+    image: &'a Texture2d,
+) -> SimpleFrameBuffer<'a> {
+    SimpleFrameBuffer::with_depth_buffer(display, image, &textures.scene_depth).unwrap()
+}
+
+/// Copies the post processed image to the screen.
+pub fn present(display: &Display<WindowSurface>, target: &mut Frame, image: &Texture2d) {
+    if let Some(program) = get_program(display, COPY_FRAGMENT_SHADER) {
+        let uniforms = uniform! {
+            colorTexture: sampled(image),
+        };
+        draw_fullscreen(target, program, &uniforms);
+    }
+}
+
+/// Bright parts of the scene blurred into `bloom_a`.
+fn bloom(display: &Display<WindowSurface>, textures: &PostProcessTextures, settings: &PostProcessSettings) -> bool {
+    let (bright_program, blur_program) = match (
+        get_program(display, BRIGHT_FRAGMENT_SHADER),
+        get_program(display, BLUR_FRAGMENT_SHADER),
+    ) {
+        (Some(bright_program), Some(blur_program)) => (bright_program, blur_program),
+        _ => return false,
+    };
+
+    let mut bloom_a = SimpleFrameBuffer::new(display, &textures.bloom_a).unwrap();
+    let mut bloom_b = SimpleFrameBuffer::new(display, &textures.bloom_b).unwrap();
+
+    let uniforms = uniform! {
+        colorTexture: sampled(&textures.scene),
+        threshold: settings.bloom_threshold,
+    };
+    draw_fullscreen(&mut bloom_a, bright_program, &uniforms);
+
+    let (width, height) = textures.bloom_a.dimensions();
+    let texel = [1.0 / width as f32, 1.0 / height as f32];
+    for _ in 0..settings.bloom_blur_passes {
+        let uniforms = uniform! {
+            colorTexture: sampled(&textures.bloom_a),
+            direction: [texel[0], 0.0],
+        };
+        draw_fullscreen(&mut bloom_b, blur_program, &uniforms);
+
+        let uniforms = uniform! {
+            colorTexture: sampled(&textures.bloom_b),
+            direction: [0.0, texel[1]],
+        };
+        draw_fullscreen(&mut bloom_a, blur_program, &uniforms);
+    }
+
+    true
+}
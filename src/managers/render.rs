@@ -1,16 +1,22 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
 
-use crate::math_utils::deg_to_rad;
+use crate::{
+    assets::{model_asset::{AlphaMode, Material, MaterialTexture, ModelAsset}, texture_asset::TextureAsset},
+    math_utils::{deg_to_rad, deg_vec_to_rad, Aabb, Frustum},
+};
+use egui_glium::egui_winit::egui;
 use glam::{Mat4, Quat, Vec3, Vec4};
 use glium::{
-    framebuffer::SimpleFrameBuffer, glutin::surface::WindowSurface, implement_vertex, index::PrimitiveType, texture::DepthTexture2d, uniform, Display, Frame, IndexBuffer, Program, Surface, VertexBuffer
+    buffer::BufferCreationError, framebuffer::SimpleFrameBuffer, glutin::surface::WindowSurface, implement_uniform_block, implement_vertex, index::PrimitiveType, texture::{ClientFormat, CubeLayer, DepthCubemap, DepthTexture2d, MipmapsOption, RawImage2d, Texture2d, TextureCreationError, UncompressedFloatFormat}, uniform, uniforms::{MagnifySamplerFilter, UniformBuffer}, program::{ProgramCreationInput, TransformFeedbackMode}, BlitMask, BlitTarget, CapabilitiesSource, Display, GlObject, Handle, ProgramCreationError, IndexBuffer, Program, Rect, Surface, VertexBuffer
 };
 use once_cell::sync::Lazy;
 
 use super::{
+    debug_draw, debugger,
     physics::{RenderColliderType, RenderRay},
-    systems,
+    sky, systems,
 };
+use crate::objects::Transform;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Vertex {
@@ -19,11 +25,25 @@ pub struct Vertex {
     pub tex_coords: [f32; 2],
     pub joints: [f32; 4],
     pub weights: [f32; 4],
+    pub color: [f32; 4],
+    pub tangent: [f32; 4],
+    pub tex_coords_1: [f32; 2],
 }
-implement_vertex!(Vertex, position, normal, tex_coords, joints, weights);
+implement_vertex!(
+    Vertex,
+    position,
+    normal,
+    tex_coords,
+    joints,
+    weights,
+    color,
+    tangent,
+    tex_coords_1
+);
 
 pub fn init(display: &Display<WindowSurface>) {
     unsafe {
+        MAX_TEXTURE_SIZE = display.get_capabilities().max_texture_size.max(1) as u32;
         COLLIDER_CUBOID_VERTEX_BUFFER = Some(VertexBuffer::new(display, &CUBE_VERTS_LIST).unwrap());
         COLLIDER_CUBOID_INDEX_BUFFER = Some(
             IndexBuffer::new(display, PrimitiveType::TrianglesList, &CUBE_INDICES_LIST).unwrap(),
@@ -46,171 +66,176 @@ pub fn init(display: &Display<WindowSurface>) {
             )
             .unwrap(),
         );
+
+        let white_image = RawImage2d::from_raw_rgba(vec![255u8, 255, 255, 255], (1, 1));
+        WHITE_TEXTURE = Some(
+            Texture2d::new(display, white_image).expect("failed to create a white fallback texture"),
+        );
     }
 }
 
-/// Call only after drawing everything.
-pub fn debug_draw(display: &Display<WindowSurface>, target: &mut Frame) {
-    let proj = get_projection_matrix().to_cols_array_2d();
-    let view = get_view_matrix().to_cols_array_2d();
+/// Call after post processing with the post processed image and scene depth,
+/// shapes are drawn over every screen view with its camera.
+pub fn debug_draw(display: &Display<WindowSurface>, target: &mut SimpleFrameBuffer) {
+    debug_draw::draw(display, target);
 
-    unsafe {
-        RENDER_RAYS.iter().for_each(|ray| {
+    let (width, height) = target.get_dimensions();
+    for_each_screen_view(|viewport| {
+        let viewport = viewport.to_rect(width, height);
+        let proj = get_projection_matrix().to_cols_array_2d();
+        let view = get_view_matrix().to_cols_array_2d();
+
+        unsafe {
+            if !RENDER_RAYS.is_empty() {
+                draw_rays(display, target, proj, view, viewport);
+            }
+        }
+
+        let colliders = unsafe { &RENDER_COLLIDERS };
+        colliders.iter().for_each(|collider| {
+            let mvp_and_sensor = calculate_collider_mvp_and_sensor(collider);
             let uniforms = uniform! {
-                proj: proj,
-                view: view,
+                mvp: mvp_and_sensor.0,
+                sensor: mvp_and_sensor.1
             };
 
-            let draw_params = glium::DrawParameters {
-                depth: glium::Depth {
-                    test: glium::draw_parameters::DepthTest::IfLess,
-                    write: true,
+            unsafe {
+                let draw_params = glium::DrawParameters {
+                    depth: glium::Depth {
+                        test: glium::draw_parameters::DepthTest::IfLess,
+                        write: true,
+                        ..Default::default()
+                    },
+                    blend: glium::draw_parameters::Blend::alpha_blending(),
+                    viewport: Some(viewport),
                     ..Default::default()
-                },
-                blend: glium::draw_parameters::Blend::alpha_blending(),
-                ..Default::default()
-            };
-
-            let origin = ray.origin;
-            let dir = origin + ray.direction;
-
-            let verts_list: [Vertex; 9] = [
-                Vertex {
-                    position: [origin.x - 0.15, origin.y + 0.15, origin.z],
-                    normal: [0.0, 0.0, 0.0],
-                    tex_coords: [0.0, 0.0],
-                    joints: [0.0, 0.0, 0.0, 0.0],
-                    weights: [0.0, 0.0, 0.0, 0.0],
-                },
-                Vertex {
-                    position: [origin.x + 0.15, origin.y + 0.15, origin.z],
-                    normal: [0.0, 0.0, 0.0],
-                    tex_coords: [0.0, 0.0],
-                    joints: [0.0, 0.0, 0.0, 0.0],
-                    weights: [0.0, 0.0, 0.0, 0.0],
-                },
-                Vertex {
-                    position: [origin.x - 0.15, origin.y - 0.15, origin.z],
-                    normal: [0.0, 0.0, 0.0],
-                    tex_coords: [0.0, 0.0],
-                    joints: [0.0, 0.0, 0.0, 0.0],
-                    weights: [0.0, 0.0, 0.0, 0.0],
-                },
-                Vertex {
-                    position: [origin.x + 0.15, origin.y - 0.15, origin.z],
-                    normal: [0.0, 0.0, 0.0],
-                    tex_coords: [0.0, 0.0],
-                    joints: [0.0, 0.0, 0.0, 0.0],
-                    weights: [0.0, 0.0, 0.0, 0.0],
-                },
-                Vertex {
-                    position: [dir.x - 0.15, dir.y + 0.15, dir.z],
-                    normal: [0.0, 0.0, 0.0],
-                    tex_coords: [0.0, 0.0],
-                    joints: [0.0, 0.0, 0.0, 0.0],
-                    weights: [0.0, 0.0, 0.0, 0.0],
-                },
-                Vertex {
-                    position: [dir.x + 0.15, dir.y + 0.15, dir.z],
-                    normal: [0.0, 0.0, 0.0],
-                    tex_coords: [0.0, 0.0],
-                    joints: [0.0, 0.0, 0.0, 0.0],
-                    weights: [0.0, 0.0, 0.0, 0.0],
-                },
-                Vertex {
-                    position: [dir.x - 0.15, dir.y - 0.15, dir.z],
-                    normal: [0.0, 0.0, 0.0],
-                    tex_coords: [0.0, 0.0],
-                    joints: [0.0, 0.0, 0.0, 0.0],
-                    weights: [0.0, 0.0, 0.0, 0.0],
-                },
-                Vertex {
-                    position: [dir.x + 0.15, dir.y - 0.15, dir.z],
-                    normal: [0.0, 0.0, 0.0],
-                    tex_coords: [0.0, 0.0],
-                    joints: [0.0, 0.0, 0.0, 0.0],
-                    weights: [0.0, 0.0, 0.0, 0.0],
-                },
-                Vertex {
-                    position: [dir.x, dir.y, dir.z + 0.15],
-                    normal: [0.0, 0.0, 0.0],
-                    tex_coords: [0.0, 0.0],
-                    joints: [0.0, 0.0, 0.0, 0.0],
-                    weights: [0.0, 0.0, 0.0, 0.0],
-                },
-            ];
-
-            let indices: [u32; 48] = [
-                0, 1, 2, 1, 3, 2, 4, 5, 6, 6, 7, 5, 0, 2, 4, 0, 2, 6, 1, 3, 5, 1, 3, 7, 0, 1, 4, 0,
-                1, 5, 2, 3, 6, 2, 3, 7, 4, 5, 8, 4, 6, 8, 5, 7, 8, 6, 7, 8,
-            ];
-
-            //dbg!(ray);
-            //dbg!(origin);
-            //dbg!(dir);
-
-            let vert_buffer = VertexBuffer::new(display, &verts_list)
-                .expect("failed to create vertex buffer while debug rendering rays");
-            let index_buffer = IndexBuffer::new(display, PrimitiveType::TriangleFan, &indices)
-                .expect("failed to create index buffer while debug rendering rays");
-
-            let shader = RAY_SHADER.take().unwrap();
-
-            target // drawing solid semi-transparent cuboid
-                .draw(
-                    &vert_buffer,
-                    &index_buffer,
-                    &shader,
-                    &uniforms,
-                    &draw_params,
-                )
-                .unwrap();
+                };
+
+                let vert_buffer = COLLIDER_CUBOID_VERTEX_BUFFER.take().unwrap();
+                let index_buffer = COLLIDER_CUBOID_INDEX_BUFFER.take().unwrap();
+                let shader = COLLIDER_CUBOID_SHADER.take().unwrap();
+
+                target // drawing solid semi-transparent cuboid
+                    .draw(
+                        &vert_buffer,
+                        &index_buffer,
+                        &shader,
+                        &uniforms,
+                        &draw_params,
+                    )
+                    .unwrap();
 
-            RAY_SHADER = Some(shader);
+                COLLIDER_CUBOID_SHADER = Some(shader);
+                COLLIDER_CUBOID_VERTEX_BUFFER = Some(vert_buffer);
+                COLLIDER_CUBOID_INDEX_BUFFER = Some(index_buffer);
+            }
         });
+    });
+
+    unsafe {
         RENDER_RAYS.clear();
+        RENDER_COLLIDERS.clear();
     }
+}
 
-    let colliders = unsafe { &mut RENDER_COLLIDERS };
-    colliders.iter().for_each(|collider| {
-        let mvp_and_sensor = calculate_collider_mvp_and_sensor(collider);
-        let uniforms = uniform! {
-            mvp: mvp_and_sensor.0,
-            sensor: mvp_and_sensor.1
-        };
-
-        unsafe {
-            let draw_params = glium::DrawParameters {
-                depth: glium::Depth {
-                    test: glium::draw_parameters::DepthTest::IfLess,
-                    write: true,
-                    ..Default::default()
-                },
-                blend: glium::draw_parameters::Blend::alpha_blending(),
-                ..Default::default()
-            };
-
-            let vert_buffer = COLLIDER_CUBOID_VERTEX_BUFFER.take().unwrap();
-            let index_buffer = COLLIDER_CUBOID_INDEX_BUFFER.take().unwrap();
-            let shader = COLLIDER_CUBOID_SHADER.take().unwrap();
-
-            target // drawing solid semi-transparent cuboid
-                .draw(
-                    &vert_buffer,
-                    &index_buffer,
-                    &shader,
-                    &uniforms,
-                    &draw_params,
-                )
-                .unwrap();
-
-            COLLIDER_CUBOID_SHADER = Some(shader);
-            COLLIDER_CUBOID_VERTEX_BUFFER = Some(vert_buffer);
-            COLLIDER_CUBOID_INDEX_BUFFER = Some(index_buffer);
+// all rays are drawn with one draw call, buffers grow when there's more rays than before
+unsafe fn draw_rays(
+    display: &Display<WindowSurface>,
+    target: &mut SimpleFrameBuffer,
+    proj: [[f32; 4]; 4],
+    view: [[f32; 4]; 4],
+    viewport: Rect,
+) {
+    let uniforms = uniform! {
+        proj: proj,
+        view: view,
+    };
+
+    let draw_params = glium::DrawParameters {
+        depth: glium::Depth {
+            test: glium::draw_parameters::DepthTest::IfLess,
+            write: true,
+            ..Default::default()
+        },
+        blend: glium::draw_parameters::Blend::alpha_blending(),
+        viewport: Some(viewport),
+        ..Default::default()
+    };
+
+    let mut verts_list: Vec<Vertex> = Vec::with_capacity(RENDER_RAYS.len() * 9);
+    let mut indices: Vec<u32> = Vec::with_capacity(RENDER_RAYS.len() * RAY_INDICES.len());
+    for ray in &RENDER_RAYS {
+        let origin = ray.origin;
+        let dir = origin + ray.direction;
+        let first_index = verts_list.len() as u32;
+
+        let positions = [
+            [origin.x - 0.15, origin.y + 0.15, origin.z],
+            [origin.x + 0.15, origin.y + 0.15, origin.z],
+            [origin.x - 0.15, origin.y - 0.15, origin.z],
+            [origin.x + 0.15, origin.y - 0.15, origin.z],
+            [dir.x - 0.15, dir.y + 0.15, dir.z],
+            [dir.x + 0.15, dir.y + 0.15, dir.z],
+            [dir.x - 0.15, dir.y - 0.15, dir.z],
+            [dir.x + 0.15, dir.y - 0.15, dir.z],
+            [dir.x, dir.y, dir.z + 0.15],
+        ];
+        for position in positions {
+            verts_list.push(Vertex {
+                position,
+                normal: [0.0, 0.0, 0.0],
+                tex_coords: [0.0, 0.0],
+                joints: [0.0, 0.0, 0.0, 0.0],
+                weights: [0.0, 0.0, 0.0, 0.0],
+                color: [1.0, 1.0, 1.0, 1.0],
+                tangent: [0.0, 0.0, 0.0, 0.0],
+                tex_coords_1: [0.0, 0.0],
+            });
         }
-    });
+        indices.extend(RAY_INDICES.iter().map(|index| index + first_index));
+    }
 
-    unsafe { RENDER_COLLIDERS.clear() }
+    let vertex_buffer_too_small = match &RAYS_VERTEX_BUFFER {
+        Some(buffer) => buffer.len() < verts_list.len(),
+        None => true,
+    };
+    if vertex_buffer_too_small {
+        RAYS_VERTEX_BUFFER = Some(
+            VertexBuffer::empty_dynamic(display, verts_list.len().next_power_of_two())
+                .expect("failed to create vertex buffer while debug rendering rays"),
+        );
+    }
+    let index_buffer_too_small = match &RAYS_INDEX_BUFFER {
+        Some(buffer) => buffer.len() < indices.len(),
+        None => true,
+    };
+    if index_buffer_too_small {
+        RAYS_INDEX_BUFFER = Some(
+            IndexBuffer::empty_dynamic(
+                display,
+                PrimitiveType::TrianglesList,
+                indices.len().next_power_of_two(),
+            )
+            .expect("failed to create index buffer while debug rendering rays"),
+        );
+    }
+
+    let vertex_buffer = RAYS_VERTEX_BUFFER.as_ref().unwrap();
+    let index_buffer = RAYS_INDEX_BUFFER.as_ref().unwrap();
+    let vertex_slice = vertex_buffer.slice(0..verts_list.len()).unwrap();
+    let index_slice = index_buffer.slice(0..indices.len()).unwrap();
+    vertex_slice.write(&verts_list);
+    index_slice.write(&indices);
+
+    target // drawing solid semi-transparent cuboids
+        .draw(
+            vertex_slice,
+            index_slice,
+            RAY_SHADER.as_ref().unwrap(),
+            &uniforms,
+            &draw_params,
+        )
+        .unwrap();
 }
 
 pub fn add_collider_to_draw(col: RenderColliderType) {
@@ -225,41 +250,641 @@ pub fn add_ray_to_draw(ray: RenderRay) {
     }
 }
 
-pub fn draw(display: &Display<WindowSurface>, target: &mut Frame, shadow_textures: &ShadowTextures) {
-    //target.clear_color_and_depth((0.6, 0.91, 0.88, 1.0), 1.0);
-    target.clear_color_srgb_and_depth((0.7, 0.7, 0.9, 1.0), 1.0);
+static mut FRAME_INDEX: u64 = 0;
 
-    let mut closest_shadow_fbo =
-        SimpleFrameBuffer::depth_only(display, &shadow_textures.closest).unwrap();
-    closest_shadow_fbo.clear_color_srgb(1.0, 1.0, 1.0, 1.0);
-    closest_shadow_fbo.clear_depth(1.0);
+/// Grows by one every drawn frame, objects use it to do some work once per frame instead of once per view.
+pub fn get_frame_index() -> u64 {
+    unsafe { FRAME_INDEX }
+}
+
+pub fn draw(display: &Display<WindowSurface>, target: &mut SimpleFrameBuffer, shadow_textures: &ShadowTextures) {
+    unsafe {
+        FRAME_INDEX += 1;
+    }
+    reset_culling_stats();
+    update_camera_vectors();
+
+    let cameras = unsafe { std::mem::take(&mut CAMERAS) };
+    let mut screen_cameras: Vec<&CameraData> = cameras
+        .iter()
+        .filter(|camera| matches!(camera.target, CameraTarget::Screen(_)))
+        .collect();
+    screen_cameras.sort_by_key(|camera| camera.order);
+
+    // shadows follow the first screen camera, or the default one if there's none
+    let (target_width, target_height) = target.get_dimensions();
+    match screen_cameras.first() {
+        Some(camera) => {
+            let (width, height) = camera.target_size(target_width, target_height);
+            set_active_camera(Some(camera), width, height);
+        }
+        None => set_active_camera(None, target_width, target_height),
+    }
 
-    let mut furthest_shadow_fbo =
-        SimpleFrameBuffer::depth_only(display, &shadow_textures.furthest).unwrap();
-    furthest_shadow_fbo.clear_color_srgb(1.0, 1.0, 1.0, 1.0);
-    furthest_shadow_fbo.clear_depth(1.0);
+    select_shadowed_lights();
+    render_lights_shadows(display, shadow_textures);
 
     let view = get_view_matrix();
-    let cascades = Cascades::new(view);
-    systems::shadow_render(
-        &cascades.closest.as_mat4(),
-        display,
-        &mut closest_shadow_fbo,
-    );
-    systems::shadow_render(
-        &cascades.furthest.as_mat4(),
-        display,
-        &mut furthest_shadow_fbo,
-    );
+    let cascades = Cascades::new(view, &shadow_textures.cascades_resolutions());
+    for (i, view_proj) in cascades.view_projs.iter().enumerate() {
+        let mut cascade_fbo =
+            SimpleFrameBuffer::depth_only(display, &shadow_textures.cascades[i]).unwrap();
+        cascade_fbo.clear_color_srgb(1.0, 1.0, 1.0, 1.0);
+        cascade_fbo.clear_depth(1.0);
+
+        systems::shadow_render(view_proj, display, &mut cascade_fbo);
+    }
 
-    update_camera_vectors();
+    // render targets go first, so screen cameras see this frame's textures
+    for camera in cameras.iter().filter(|camera| matches!(camera.target, CameraTarget::Texture { .. })) {
+        draw_camera_to_texture(display, camera, target_width, target_height, &cascades, shadow_textures);
+    }
+
+    unsafe {
+        SCREEN_VIEWS.clear();
+    }
+    if screen_cameras.is_empty() {
+        set_active_camera(None, target_width, target_height);
+        draw_view(display, target, &cascades, shadow_textures);
+        unsafe {
+            SCREEN_VIEWS.push(ScreenView {
+                camera: None,
+                viewport: Viewport::FULL,
+            });
+        }
+    } else {
+        target.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+        for camera in screen_cameras {
+            let viewport = match camera.target {
+                CameraTarget::Screen(viewport) => viewport,
+                CameraTarget::Texture { .. } => continue,
+            };
+            let textures = match draw_camera_to_texture(display, camera, target_width, target_height, &cascades, shadow_textures) {
+                Some(textures) => textures,
+                None => continue,
+            };
+            unsafe {
+                SCREEN_VIEWS.push(ScreenView {
+                    camera: ACTIVE_CAMERA.clone(),
+                    viewport,
+                });
+            }
+
+            let (width, height) = textures.color.dimensions();
+            let camera_fbo = SimpleFrameBuffer::with_depth_buffer(display, &textures.color, &textures.depth).unwrap();
+            target.blit_buffers_from_simple_framebuffer(
+                &camera_fbo,
+                &Rect {
+                    left: 0,
+                    bottom: 0,
+                    width,
+                    height,
+                },
+                &BlitTarget {
+                    left: (viewport.x * target_width as f32) as u32,
+                    bottom: (viewport.y * target_height as f32) as u32,
+                    width: width as i32,
+                    height: height as i32,
+                },
+                MagnifySamplerFilter::Nearest,
+                BlitMask::color_and_depth(),
+            );
+        }
+    }
+
+    // debug draw and everything after it use the default camera
+    set_active_camera(None, target_width, target_height);
+}
+
+fn draw_view(
+    display: &Display<WindowSurface>,
+    target: &mut SimpleFrameBuffer,
+    cascades: &Cascades,
+    shadow_textures: &ShadowTextures,
+) {
+    unsafe {
+        CAMERA_FRUSTUM = Some(Frustum::from_view_proj(&(get_projection_matrix() * get_view_matrix())));
+        SCENE_DEPTH_COPIED = false;
+    }
+    begin_view_culling_stats();
+
+    sky::draw(display, target);
+    systems::render(display, target, cascades, shadow_textures);
+}
 
-    systems::render(&display, target, &cascades, shadow_textures);
+fn draw_camera_to_texture(
+    display: &Display<WindowSurface>,
+    camera: &CameraData,
+    target_width: u32,
+    target_height: u32,
+    cascades: &Cascades,
+    shadow_textures: &ShadowTextures,
+) -> Option<&'static CameraTextures> {
+    let (width, height) = camera.target_size(target_width, target_height);
+    let textures = CameraTextures::get_or_create(display, &camera.name, width, height)?;
+
+    set_active_camera(Some(camera), width, height);
+    let mut camera_fbo = SimpleFrameBuffer::with_depth_buffer(display, &textures.color, &textures.depth).unwrap();
+    draw_view(display, &mut camera_fbo, cascades, shadow_textures);
+
+    Some(textures)
 }
 
 pub fn update() {
     unsafe {
         INSTANCED_POSITIONS.clear();
+        POINT_LIGHTS.clear();
+        SPOT_LIGHTS.clear();
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CullingStats {
+    pub drawn: usize,
+    pub culled: usize,
+    pub shadow_drawn: usize,
+    pub shadow_culled: usize,
+    /// Cameras (views) rendered in the frame, drawn and culled are summed over all of them.
+    pub views: usize,
+}
+
+static mut CULLING_STATS: CullingStats = CullingStats {
+    drawn: 0,
+    culled: 0,
+    shadow_drawn: 0,
+    shadow_culled: 0,
+    views: 0,
+};
+
+static mut VIEWS_CULLING_STATS: Vec<CullingStats> = Vec::new();
+
+fn reset_culling_stats() {
+    unsafe {
+        CULLING_STATS = CullingStats::default();
+        VIEWS_CULLING_STATS.clear();
+    }
+}
+
+fn begin_view_culling_stats() {
+    unsafe {
+        CULLING_STATS.views += 1;
+        VIEWS_CULLING_STATS.push(CullingStats {
+            views: 1,
+            ..Default::default()
+        });
+    }
+}
+
+/// Meshes (or instances) drawn and culled in the last frame, summed over all views.
+pub fn get_culling_stats() -> CullingStats {
+    unsafe { CULLING_STATS }
+}
+
+/// Drawn and culled counts of each view rendered in the last frame, in render order.
+pub fn get_views_culling_stats() -> Vec<CullingStats> {
+    unsafe { VIEWS_CULLING_STATS.clone() }
+}
+
+static mut CAMERA_FRUSTUM: Option<Frustum> = None;
+
+/// Checks camera's frustum and counts the result in culling stats.
+pub fn is_visible(aabb: &Aabb) -> bool {
+    let visible = match unsafe { &CAMERA_FRUSTUM } {
+        Some(frustum) => frustum.intersects_aabb(aabb),
+        None => true,
+    };
+    unsafe {
+        let view_stats = VIEWS_CULLING_STATS.last_mut();
+        match visible {
+            true => {
+                CULLING_STATS.drawn += 1;
+                if let Some(stats) = view_stats {
+                    stats.drawn += 1;
+                }
+            }
+            false => {
+                CULLING_STATS.culled += 1;
+                if let Some(stats) = view_stats {
+                    stats.culled += 1;
+                }
+            }
+        }
+    }
+
+    visible
+}
+
+/// Same as is_visible, but for shadow maps' frustums.
+pub fn is_shadow_visible(aabb: &Aabb, frustum: &Frustum) -> bool {
+    let visible = frustum.intersects_aabb(aabb);
+    unsafe {
+        match visible {
+            true => CULLING_STATS.shadow_drawn += 1,
+            false => CULLING_STATS.shadow_culled += 1,
+        }
+    }
+
+    visible
+}
+
+/// Height of a sphere on the screen, 1 is the whole screen height.
+pub fn get_screen_size(center: Vec3, radius: f32) -> f32 {
+    let proj = get_projection_matrix();
+    // orthographic projection doesn't divide by w
+    if proj.w_axis.w == 1.0 {
+        return radius * proj.y_axis.y;
+    }
+
+    let distance = center.distance(get_camera_render_position()).max(0.001);
+    radius * proj.y_axis.y / distance
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LodMetric {
+    /// distance from the camera to the bounding box center
+    Distance,
+    /// bounding sphere height on the screen, see get_screen_size
+    ScreenSize,
+}
+
+/// Picks a LOD of a model with `_LOD<n>` meshes.
+#[derive(Debug, Clone)]
+pub struct LodSettings {
+    pub metric: LodMetric,
+    /// thresholds[i] is where LOD i + 1 starts, growing distances or shrinking screen sizes
+    pub thresholds: Vec<f32>,
+    /// width of the band before a threshold where two LODs are cross-faded, 0 switches instantly
+    pub cross_fade: f32,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        LodSettings {
+            metric: LodMetric::Distance,
+            thresholds: vec![25.0, 50.0, 100.0],
+            cross_fade: 0.0,
+        }
+    }
+}
+
+impl LodSettings {
+    /// `bounding_box` is in render space.
+    pub fn select(&self, bounding_box: &Aabb, lods_count: usize) -> LodSelection {
+        if lods_count < 2 {
+            return LodSelection::default();
+        }
+
+        let center = bounding_box.center();
+        let value = match self.metric {
+            LodMetric::Distance => center.distance(get_camera_render_position()),
+            LodMetric::ScreenSize => {
+                let radius = (bounding_box.max - bounding_box.min).length() / 2.0;
+                get_screen_size(center, radius)
+            }
+        };
+
+        self.select_by_value(value, lods_count)
+    }
+
+    /// `select` with the distance or the screen size of the model already known.
+    fn select_by_value(&self, value: f32, lods_count: usize) -> LodSelection {
+        // screen sizes shrink with distance, so they're negated to grow like distances
+        let sign = match self.metric {
+            LodMetric::Distance => 1.0,
+            LodMetric::ScreenSize => -1.0,
+        };
+        let value = value * sign;
+
+        let mut lod = 0;
+        for threshold in self.thresholds.iter().take(lods_count - 1) {
+            let threshold = threshold * sign;
+            if value < threshold {
+                let fade_start = threshold - self.cross_fade;
+                if self.cross_fade > 0.0 && value > fade_start {
+                    return LodSelection {
+                        lod,
+                        next: Some(lod + 1),
+                        fade: (value - fade_start) / self.cross_fade,
+                    };
+                }
+                break;
+            }
+            lod += 1;
+        }
+
+        LodSelection {
+            lod,
+            next: None,
+            fade: 0.0,
+        }
+    }
+
+    pub fn inspector_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.metric, LodMetric::Distance, "distance");
+            ui.radio_value(&mut self.metric, LodMetric::ScreenSize, "screen size");
+        });
+        let speed = match self.metric {
+            LodMetric::Distance => 0.5,
+            LodMetric::ScreenSize => 0.005,
+        };
+        for (i, threshold) in self.thresholds.iter_mut().enumerate() {
+            ui.add(egui::DragValue::new(threshold).speed(speed).prefix(format!("LOD {}: ", i + 1)));
+        }
+        ui.horizontal(|ui| {
+            if ui.button("add threshold").clicked() {
+                let last = self.thresholds.last().copied().unwrap_or(0.0);
+                self.thresholds.push(match self.metric {
+                    LodMetric::Distance => last * 2.0 + 10.0,
+                    LodMetric::ScreenSize => last / 2.0,
+                });
+            }
+            if ui.button("remove threshold").clicked() {
+                self.thresholds.pop();
+            }
+        });
+        ui.add(egui::DragValue::new(&mut self.cross_fade).speed(speed).prefix("cross-fade: "));
+        self.cross_fade = self.cross_fade.max(0.0);
+    }
+}
+
+/// LOD that is drawn and, inside a cross-fade band, the next one that fades in.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LodSelection {
+    pub lod: usize,
+    pub next: Option<usize>,
+    /// 0 - 1 progress from `lod` to `next`
+    pub fade: f32,
+}
+
+impl LodSelection {
+    /// `lodFade` value for a mesh, None if the mesh isn't drawn.
+    /// Meshes without LOD are always drawn, see docs/shaders.md for fade values.
+    pub fn mesh_fade(&self, mesh_lod: Option<usize>) -> Option<f32> {
+        match mesh_lod {
+            None => Some(0.0),
+            Some(lod) if lod == self.lod => Some(self.fade),
+            Some(lod) if Some(lod) == self.next => Some(self.fade - 1.0),
+            Some(_) => None,
+        }
+    }
+}
+
+/// A mesh that an object wants to draw in the current view.
+/// Objects add items in Object::render and draw them in Object::render_queued.
+pub struct RenderQueueItem {
+    pub object_id: u128,
+    pub mesh_index: usize,
+    /// gl names, they stay the same while the program, texture and vertex buffer are alive
+    pub program_id: u64,
+    pub texture_id: u32,
+    pub mesh_id: u32,
+    pub transparent: bool,
+    pub camera_distance: f32,
+}
+
+impl RenderQueueItem {
+    pub fn new<V: Copy>(
+        object_id: u128,
+        mesh_index: usize,
+        program: &Program,
+        texture: &Texture2d,
+        mesh: &VertexBuffer<V>,
+        transparent: bool,
+        camera_distance: f32,
+    ) -> RenderQueueItem {
+        let program_id = match program.get_id() {
+            Handle::Id(id) => id as u64,
+            Handle::Handle(handle) => handle as usize as u64,
+        };
+
+        RenderQueueItem {
+            object_id,
+            mesh_index,
+            program_id,
+            texture_id: texture.get_id(),
+            mesh_id: mesh.get_id(),
+            transparent,
+            camera_distance,
+        }
+    }
+}
+
+/// Meshes of one view. It's filled while systems render their objects and drawn right after that,
+/// see `systems::render`.
+#[derive(Default)]
+pub struct RenderQueue {
+    items: Vec<RenderQueueItem>,
+}
+
+impl RenderQueue {
+    pub fn add(&mut self, item: RenderQueueItem) {
+        self.items.push(item);
+    }
+
+    /// Opaque items are sorted by program, texture and mesh, so there's less state changes,
+    /// transparent items go after them from back to front.
+    pub fn into_draw_order(self) -> Vec<RenderQueueItem> {
+        let (mut opaque, mut transparent): (Vec<RenderQueueItem>, Vec<RenderQueueItem>) =
+            self.items.into_iter().partition(|item| !item.transparent);
+
+        opaque.sort_by_key(|item| (item.program_id, item.texture_id, item.mesh_id));
+        transparent.sort_by(|a, b| b.camera_distance.total_cmp(&a.camera_distance));
+
+        let mut stats = RenderQueueStats {
+            opaque: opaque.len(),
+            transparent: transparent.len(),
+            program_switches: 0,
+            texture_switches: 0,
+        };
+        opaque.append(&mut transparent);
+
+        let mut last_item: Option<&RenderQueueItem> = None;
+        for item in &opaque {
+            match last_item {
+                Some(last_item) => {
+                    if last_item.program_id != item.program_id {
+                        stats.program_switches += 1;
+                    }
+                    if last_item.texture_id != item.texture_id {
+                        stats.texture_switches += 1;
+                    }
+                }
+                None => {
+                    stats.program_switches += 1;
+                    stats.texture_switches += 1;
+                }
+            }
+            last_item = Some(item);
+        }
+
+        unsafe {
+            RENDER_QUEUE_STATS = stats;
+        }
+
+        opaque
+    }
+}
+
+static mut RENDER_QUEUE_STATS: RenderQueueStats = RenderQueueStats {
+    opaque: 0,
+    transparent: 0,
+    program_switches: 0,
+    texture_switches: 0,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RenderQueueStats {
+    pub opaque: usize,
+    pub transparent: usize,
+    pub program_switches: usize,
+    pub texture_switches: usize,
+}
+
+/// Render queue of the last drawn view.
+pub fn get_render_queue_stats() -> RenderQueueStats {
+    unsafe { RENDER_QUEUE_STATS }
+}
+
+static mut SCENE_DEPTH_COPY: Option<DepthTexture2d> = None;
+static mut SCENE_DEPTH_COPIED: bool = false;
+
+/// Copies target's depth for soft particles and such, `systems::render` calls it once per view
+/// right before transparent items, so opaque objects are already there.
+pub fn copy_scene_depth(display: &Display<WindowSurface>, target: &SimpleFrameBuffer) {
+    let (width, height) = target.get_dimensions();
+    unsafe {
+        let too_small = match &SCENE_DEPTH_COPY {
+            Some(texture) => texture.dimensions() != (width, height),
+            None => true,
+        };
+        if too_small {
+            SCENE_DEPTH_COPY = match DepthTexture2d::empty(display, width, height) {
+                Ok(texture) => Some(texture),
+                Err(err) => {
+                    debugger::error(&format!("scene depth copy texture creation error!\nerr: {}", err));
+                    None
+                }
+            };
+        }
+
+        let texture = match &SCENE_DEPTH_COPY {
+            Some(texture) => texture,
+            None => return,
+        };
+        let copy_fbo = match SimpleFrameBuffer::depth_only(display, texture) {
+            Ok(fbo) => fbo,
+            Err(err) => {
+                debugger::error(&format!("scene depth copy framebuffer creation error!\nerr: {}", err));
+                return;
+            }
+        };
+        copy_fbo.blit_buffers_from_simple_framebuffer(
+            target,
+            &Rect {
+                left: 0,
+                bottom: 0,
+                width,
+                height,
+            },
+            &BlitTarget {
+                left: 0,
+                bottom: 0,
+                width: width as i32,
+                height: height as i32,
+            },
+            MagnifySamplerFilter::Nearest,
+            BlitMask::depth(),
+        );
+        SCENE_DEPTH_COPIED = true;
+    }
+}
+
+/// Depth of the opaque objects of the current view, None before `copy_scene_depth` runs in it.
+pub fn get_scene_depth() -> Option<&'static DepthTexture2d> {
+    unsafe {
+        match SCENE_DEPTH_COPIED {
+            true => SCENE_DEPTH_COPY.as_ref(),
+            false => None,
+        }
+    }
+}
+
+/// Opaque and masked materials are drawn without blending, transparent ones don't write depth.
+pub fn material_draw_parameters(material: &Material) -> glium::DrawParameters<'static> {
+    let transparent = material.alpha_mode == AlphaMode::Blend;
+
+    glium::DrawParameters {
+        depth: glium::Depth {
+            test: glium::draw_parameters::DepthTest::IfLess,
+            write: !transparent,
+            ..Default::default()
+        },
+        blend: match transparent {
+            true => glium::draw_parameters::Blend::alpha_blending(),
+            false => glium::draw_parameters::Blend::default(),
+        },
+        backface_culling: if material.double_sided {
+            glium::draw_parameters::BackfaceCullingMode::CullingDisabled
+        } else {
+            glium::draw_parameters::BackfaceCullingMode::CullClockwise
+        },
+        polygon_mode: glium::draw_parameters::PolygonMode::Fill,
+        ..Default::default()
+    }
+}
+
+static mut PROGRAMS: Lazy<HashMap<(String, String), Box<Program>>> = Lazy::new(HashMap::new);
+
+/// Objects with the same shaders share one program, so render queue can batch them.
+pub fn get_program(
+    display: &Display<WindowSurface>,
+    vertex_shader_source: &str,
+    fragment_shader_source: &str,
+) -> Result<&'static Program, ProgramCreationError> {
+    let key = (vertex_shader_source.to_string(), fragment_shader_source.to_string());
+    unsafe {
+        if !PROGRAMS.contains_key(&key) {
+            let program = Program::from_source(display, vertex_shader_source, fragment_shader_source, None)?;
+            PROGRAMS.insert(key.clone(), Box::new(program));
+        }
+
+        Ok(PROGRAMS.get(&key).unwrap())
+    }
+}
+
+static mut FEEDBACK_PROGRAMS: Lazy<HashMap<String, Box<Program>>> = Lazy::new(HashMap::new);
+
+/// Program that writes `varyings` of its vertex shader into a buffer with transform feedback,
+/// for GPU simulations like particles. Varyings are interleaved in the given order.
+pub fn get_transform_feedback_program(
+    display: &Display<WindowSurface>,
+    vertex_shader_source: &str,
+    fragment_shader_source: &str,
+    varyings: &[&str],
+) -> Result<&'static Program, ProgramCreationError> {
+    unsafe {
+        if !FEEDBACK_PROGRAMS.contains_key(vertex_shader_source) {
+            let program = Program::new(
+                display,
+                ProgramCreationInput::SourceCode {
+                    vertex_shader: vertex_shader_source,
+                    tessellation_control_shader: None,
+                    tessellation_evaluation_shader: None,
+                    geometry_shader: None,
+                    fragment_shader: fragment_shader_source,
+                    transform_feedback_varyings: Some((
+                        varyings.iter().map(|varying| varying.to_string()).collect(),
+                        TransformFeedbackMode::Interleaved,
+                    )),
+                    outputs_srgb: true,
+                    uses_point_size: false,
+                },
+            )?;
+            FEEDBACK_PROGRAMS.insert(vertex_shader_source.to_string(), Box::new(program));
+        }
+
+        Ok(FEEDBACK_PROGRAMS.get(vertex_shader_source).unwrap())
     }
 }
 
@@ -286,6 +911,11 @@ pub static mut LIGHT_POSITION: Vec3 = Vec3 {
     z: 0.0,
 };
 
+/// linear, sun light that default shaders and terrain get as `sunColor`
+static mut SUN_COLOR: Vec3 = Vec3::new(1.0, 0.97, 0.9);
+/// linear, light of surfaces that the sun and lights don't reach, `ambientColor` in shaders
+static mut AMBIENT_COLOR: Vec3 = Vec3::new(0.25, 0.27, 0.3);
+
 pub static mut CAMERA_LOCATION: CameraLocation = CameraLocation {
     position: ZERO_VEC3,
     rotation: ZERO_VEC3,
@@ -324,53 +954,99 @@ pub fn get_light_direction() -> Vec3 {
     unsafe { LIGHT_POSITION }
 }
 
-pub fn get_view_matrix() -> Mat4 {
+pub fn set_sun_color(color: Vec3) {
     unsafe {
-        let mut camera_position = CAMERA_LOCATION.position.clone();
-        camera_position.x = -camera_position.x;
-        camera_position.z = -camera_position.z;
-        Mat4::look_at_lh(
-            camera_position,
-            camera_position + CAMERA_LOCATION.front,
-            DEFAULT_UP_VECTOR,
-        )
+        SUN_COLOR = color;
     }
 }
 
-pub fn get_projection_matrix() -> Mat4 {
-    unsafe { Mat4::perspective_rh_gl(CAMERA_LOCATION.fov, ASPECT_RATIO, 0.001, 500.0) }
+pub fn get_sun_color() -> Vec3 {
+    unsafe { SUN_COLOR }
 }
 
-fn update_camera_vectors() {
+pub fn set_ambient_color(color: Vec3) {
     unsafe {
-        let front = Vec3 {
-            x: -CAMERA_LOCATION.rotation.y.to_radians().sin()
-                * CAMERA_LOCATION.rotation.x.to_radians().cos(),
-            y: -(CAMERA_LOCATION.rotation.x).to_radians().sin(),
-            z: -(CAMERA_LOCATION.rotation.y).to_radians().cos()
-                * -(CAMERA_LOCATION.rotation.x).to_radians().cos(),
-        };
-        CAMERA_LOCATION.front = front.normalize();
-        CAMERA_LOCATION.right = CAMERA_LOCATION.front.cross(DEFAULT_UP_VECTOR).normalize(); 
-        CAMERA_LOCATION.up = CAMERA_LOCATION
-            .right
-            .cross(CAMERA_LOCATION.front)
-            .normalize();
+        AMBIENT_COLOR = color;
     }
 }
 
-pub fn get_camera_position() -> Vec3 {
-    unsafe { CAMERA_LOCATION.position }
+pub fn get_ambient_color() -> Vec3 {
+    unsafe { AMBIENT_COLOR }
 }
 
-pub fn get_camera_front() -> Vec3 {
-    let mut front = unsafe { CAMERA_LOCATION.front };
-    front.y = -front.y;
-    front
+/// Normalized direction towards the sun, the one sun shadows are cast along.
+pub fn get_sun_direction() -> Vec3 {
+    (get_light_direction() + Vec3::new(0.0, 20.0, 0.0)).normalize()
 }
 
-pub fn get_camera_right() -> Vec3 {
-    unsafe { CAMERA_LOCATION.right }
+/// View of the camera that's being rendered, or the default camera's view.
+pub fn get_view_matrix() -> Mat4 {
+    unsafe {
+        match &ACTIVE_CAMERA {
+            Some(camera) => camera.view,
+            None => camera_view_matrix(CAMERA_LOCATION.position, CAMERA_LOCATION.front),
+        }
+    }
+}
+
+pub fn get_projection_matrix() -> Mat4 {
+    unsafe {
+        match &ACTIVE_CAMERA {
+            Some(camera) => camera.projection,
+            None => Mat4::perspective_rh_gl(CAMERA_LOCATION.fov, ASPECT_RATIO, 0.001, 500.0),
+        }
+    }
+}
+
+fn camera_view_matrix(position: Vec3, front: Vec3) -> Mat4 {
+    let mut camera_position = position;
+    camera_position.x = -camera_position.x;
+    camera_position.z = -camera_position.z;
+    Mat4::look_at_lh(camera_position, camera_position + front, DEFAULT_UP_VECTOR)
+}
+
+fn camera_front(rotation: Vec3) -> Vec3 {
+    Vec3 {
+        x: -rotation.y.to_radians().sin() * rotation.x.to_radians().cos(),
+        y: -(rotation.x).to_radians().sin(),
+        z: -(rotation.y).to_radians().cos() * -(rotation.x).to_radians().cos(),
+    }
+    .normalize()
+}
+
+fn update_camera_vectors() {
+    unsafe {
+        CAMERA_LOCATION.front = camera_front(CAMERA_LOCATION.rotation);
+        CAMERA_LOCATION.right = CAMERA_LOCATION.front.cross(DEFAULT_UP_VECTOR).normalize(); 
+        CAMERA_LOCATION.up = CAMERA_LOCATION
+            .right
+            .cross(CAMERA_LOCATION.front)
+            .normalize();
+    }
+}
+
+/// Camera position in the same space as model matrices.
+pub fn get_camera_render_position() -> Vec3 {
+    get_view_matrix().inverse().w_axis.truncate()
+}
+
+pub fn get_camera_position() -> Vec3 {
+    unsafe {
+        match &ACTIVE_CAMERA {
+            Some(camera) => camera.position,
+            None => CAMERA_LOCATION.position,
+        }
+    }
+}
+
+pub fn get_camera_front() -> Vec3 {
+    let mut front = unsafe { CAMERA_LOCATION.front };
+    front.y = -front.y;
+    front
+}
+
+pub fn get_camera_right() -> Vec3 {
+    unsafe { CAMERA_LOCATION.right }
 }
 
 pub fn get_camera_rotation() -> Vec3 {
@@ -398,6 +1074,9 @@ const CUBE_VERTS_LIST: [Vertex; 8] = [
         joints: [0.0, 0.0, 0.0, 0.0],
         tex_coords: [0.0, 0.0],
         weights: [0.0, 0.0, 0.0, 0.0],
+        color: [1.0, 1.0, 1.0, 1.0],
+        tangent: [0.0, 0.0, 0.0, 0.0],
+        tex_coords_1: [0.0, 0.0],
     },
     Vertex {
         position: [1.0, -1.0, -1.0],
@@ -405,6 +1084,9 @@ const CUBE_VERTS_LIST: [Vertex; 8] = [
         joints: [0.0, 0.0, 0.0, 0.0],
         tex_coords: [0.0, 0.0],
         weights: [0.0, 0.0, 0.0, 0.0],
+        color: [1.0, 1.0, 1.0, 1.0],
+        tangent: [0.0, 0.0, 0.0, 0.0],
+        tex_coords_1: [0.0, 0.0],
     },
     Vertex {
         position: [1.0, 1.0, 1.0],
@@ -412,6 +1094,9 @@ const CUBE_VERTS_LIST: [Vertex; 8] = [
         joints: [0.0, 0.0, 0.0, 0.0],
         tex_coords: [0.0, 0.0],
         weights: [0.0, 0.0, 0.0, 0.0],
+        color: [1.0, 1.0, 1.0, 1.0],
+        tangent: [0.0, 0.0, 0.0, 0.0],
+        tex_coords_1: [0.0, 0.0],
     },
     Vertex {
         position: [1.0, -1.0, 1.0],
@@ -419,6 +1104,9 @@ const CUBE_VERTS_LIST: [Vertex; 8] = [
         joints: [0.0, 0.0, 0.0, 0.0],
         tex_coords: [0.0, 0.0],
         weights: [0.0, 0.0, 0.0, 0.0],
+        color: [1.0, 1.0, 1.0, 1.0],
+        tangent: [0.0, 0.0, 0.0, 0.0],
+        tex_coords_1: [0.0, 0.0],
     },
     Vertex {
         position: [-1.0, 1.0, -1.0],
@@ -426,6 +1114,9 @@ const CUBE_VERTS_LIST: [Vertex; 8] = [
         joints: [0.0, 0.0, 0.0, 0.0],
         tex_coords: [0.0, 0.0],
         weights: [0.0, 0.0, 0.0, 0.0],
+        color: [1.0, 1.0, 1.0, 1.0],
+        tangent: [0.0, 0.0, 0.0, 0.0],
+        tex_coords_1: [0.0, 0.0],
     },
     Vertex {
         position: [-1.0, -1.0, -1.0],
@@ -433,6 +1124,9 @@ const CUBE_VERTS_LIST: [Vertex; 8] = [
         joints: [0.0, 0.0, 0.0, 0.0],
         tex_coords: [0.0, 0.0],
         weights: [0.0, 0.0, 0.0, 0.0],
+        color: [1.0, 1.0, 1.0, 1.0],
+        tangent: [0.0, 0.0, 0.0, 0.0],
+        tex_coords_1: [0.0, 0.0],
     },
     Vertex {
         position: [-1.0, 1.0, 1.0],
@@ -440,6 +1134,9 @@ const CUBE_VERTS_LIST: [Vertex; 8] = [
         joints: [0.0, 0.0, 0.0, 0.0],
         tex_coords: [0.0, 0.0],
         weights: [0.0, 0.0, 0.0, 0.0],
+        color: [1.0, 1.0, 1.0, 1.0],
+        tangent: [0.0, 0.0, 0.0, 0.0],
+        tex_coords_1: [0.0, 0.0],
     },
     Vertex {
         position: [-1.0, -1.0, 1.0],
@@ -447,6 +1144,9 @@ const CUBE_VERTS_LIST: [Vertex; 8] = [
         joints: [0.0, 0.0, 0.0, 0.0],
         tex_coords: [0.0, 0.0],
         weights: [0.0, 0.0, 0.0, 0.0],
+        color: [1.0, 1.0, 1.0, 1.0],
+        tangent: [0.0, 0.0, 0.0, 0.0],
+        tex_coords_1: [0.0, 0.0],
     },
 ];
 const CUBE_INDICES_LIST: [u32; 36] = [
@@ -458,36 +1158,171 @@ static mut COLLIDER_CUBOID_INDEX_BUFFER: Option<IndexBuffer<u32>> = None;
 static mut RENDER_COLLIDERS: Vec<RenderColliderType> = vec![];
 static mut RENDER_RAYS: Vec<RenderRay> = vec![];
 static mut RAY_SHADER: Option<Program> = None;
+static mut RAYS_VERTEX_BUFFER: Option<VertexBuffer<Vertex>> = None;
+static mut RAYS_INDEX_BUFFER: Option<IndexBuffer<u32>> = None;
+const RAY_INDICES: [u32; 48] = [
+    0, 1, 2, 1, 3, 2, 4, 5, 6, 6, 7, 5, 0, 2, 4, 0, 2, 6, 1, 3, 5, 1, 3, 7, 0, 1, 4, 0, 1, 5, 2, 3,
+    6, 2, 3, 7, 4, 5, 8, 4, 6, 8, 5, 7, 8, 6, 7, 8,
+];
+
+static mut WHITE_TEXTURE: Option<Texture2d> = None;
+static mut DEFAULT_TEXTURE: Option<Texture2d> = None;
+
+/// 1x1 white texture for missing material textures.
+pub fn get_white_texture() -> &'static Texture2d {
+    unsafe {
+        WHITE_TEXTURE
+            .as_ref()
+            .expect("white texture is created in render::init")
+    }
+}
+
+/// Default texture from `texture_asset::get_default_texture_path`, loaded once for all objects.
+pub fn get_default_texture(display: &Display<WindowSurface>) -> Option<&'static Texture2d> {
+    unsafe {
+        if DEFAULT_TEXTURE.is_none() {
+            match TextureAsset::default_texture() {
+                Ok(asset) => {
+                    match create_srgb_texture(display, &asset.image_raw, asset.image_dimensions, true) {
+                        Ok(tx) => DEFAULT_TEXTURE = Some(tx),
+                        Err(err) => debugger::error(&format!("default texture creation error!\nerr: {}", err)),
+                    }
+                }
+                Err(err) => debugger::error(&format!("failed to open a default texture.\nerror: {:?}", err)),
+            }
+        }
+
+        DEFAULT_TEXTURE.as_ref()
+    }
+}
+static SRGB_TO_LINEAR: Lazy<[u16; 256]> = Lazy::new(|| {
+    let mut table = [0; 256];
+    for (value, linear) in table.iter_mut().enumerate() {
+        let srgb = value as f32 / 255.0;
+        let converted = if srgb <= 0.04045 {
+            srgb / 12.92
+        } else {
+            ((srgb + 0.055) / 1.055).powf(2.4)
+        };
+        *linear = (converted * 65535.0).round() as u16;
+    }
+    table
+});
+
+/// Uploads an srgb rgba8 image (base color, emissive, sprites, particles...) converted to linear 16 bit channels,
+/// so shaders and filtering get linear colours. Alpha isn't converted. `reversed` flips rows like `from_raw_rgba_reversed`.
+pub fn create_srgb_texture(
+    display: &Display<WindowSurface>,
+    image_raw: &[u8],
+    dimensions: (u32, u32),
+    reversed: bool,
+) -> Result<Texture2d, TextureCreationError> {
+    let table = &*SRGB_TO_LINEAR;
+    let row_len = dimensions.0 as usize * 4;
+    let mut data: Vec<u16> = Vec::with_capacity(image_raw.len());
+    let mut push_row = |row: &[u8]| {
+        for pixel in row.chunks_exact(4) {
+            data.extend_from_slice(&[
+                table[pixel[0] as usize],
+                table[pixel[1] as usize],
+                table[pixel[2] as usize],
+                pixel[3] as u16 * 257,
+            ]);
+        }
+    };
+    if row_len > 0 {
+        match reversed {
+            true => image_raw.chunks_exact(row_len).rev().for_each(&mut push_row),
+            false => image_raw.chunks_exact(row_len).for_each(&mut push_row),
+        }
+    }
+
+    let image = RawImage2d {
+        data: Cow::Owned(data),
+        width: dimensions.0,
+        height: dimensions.1,
+        format: ClientFormat::U16U16U16U16,
+    };
+    Texture2d::with_format(
+        display,
+        image,
+        UncompressedFloatFormat::U16U16U16U16,
+        MipmapsOption::AutoGeneratedMipmaps,
+    )
+}
+
 static mut COLLIDER_CUBOID_SHADER: Option<Program> = None;
 
-static mut INSTANCED_POSITIONS: Lazy<HashMap<String, Vec<Mat4>>> = Lazy::new(|| HashMap::new());
+static mut INSTANCED_POSITIONS: Lazy<HashMap<String, Vec<InstanceData>>> = Lazy::new(HashMap::new);
+
+/// Per-instance data for MasterInstancedModelObject, instanced shaders get every field as an attribute.
+#[derive(Debug, Clone, Copy)]
+pub struct InstanceData {
+    /// render space
+    pub model: Mat4,
+    /// linear rgba
+    pub tint: Vec4,
+    /// seconds or radians, shader decides
+    pub wind_phase: f32,
+    /// layer of a texture array
+    pub texture_index: i32,
+    pub custom: Vec4,
+}
+
+impl Default for InstanceData {
+    fn default() -> Self {
+        InstanceData {
+            model: Mat4::IDENTITY,
+            tint: Vec4::ONE,
+            wind_phase: 0.0,
+            texture_index: 0,
+            custom: Vec4::ZERO,
+        }
+    }
+}
+
+impl InstanceData {
+    /// `transform` is world space.
+    pub fn from_transform(transform: &Transform) -> InstanceData {
+        let rotation = deg_vec_to_rad(transform.rotation);
+        let rotation = Quat::from_euler(glam::EulerRot::XYZ, rotation.x, rotation.y, rotation.z);
+        let mut translation = transform.position;
+        translation.z = -translation.z;
+
+        InstanceData {
+            model: Mat4::from_scale_rotation_translation(transform.scale, rotation, translation),
+            ..Default::default()
+        }
+    }
+}
 
-pub fn add_instance_position(instance: &str, position: Mat4) {
+/// Instances added here are only drawn in the current frame.
+pub fn add_instance(instance: &str, data: InstanceData) {
     unsafe {
         match INSTANCED_POSITIONS.get_mut(instance) {
-            Some(positions) => positions.push(position),
+            Some(instances) => instances.push(data),
             None => {
-                INSTANCED_POSITIONS.insert(instance.into(), vec![position]);
+                INSTANCED_POSITIONS.insert(instance.into(), vec![data]);
             },
         }
     }
 }
 
-pub fn add_instance_positions_vec(instance: &str, positions: &Vec<Mat4>) {
+pub fn add_instances_vec(instance: &str, instances: &Vec<InstanceData>) {
     unsafe {
         match INSTANCED_POSITIONS.get_mut(instance) {
-            Some(instanced_positions) => instanced_positions.extend(positions.iter()),
+            Some(frame_instances) => frame_instances.extend(instances.iter()),
             None => {
-                INSTANCED_POSITIONS.insert(instance.into(), positions.to_owned());
+                INSTANCED_POSITIONS.insert(instance.into(), instances.to_owned());
             },
         }
     }
 }
 
-pub fn get_instance_positions(instance: &str) -> Option<&Vec<Mat4>> {
+pub fn get_instances(instance: &str) -> Option<&Vec<InstanceData>> {
     unsafe {
         match INSTANCED_POSITIONS.get(instance) {
-            Some(positions) => Some(positions),
+            Some(instances) => Some(instances),
             None => None,
         }
     }
@@ -596,82 +1431,224 @@ pub fn calculate_collider_mvp_and_sensor(collider: &RenderColliderType) -> ([[f3
     }
 }
 
-struct SunCamera {
-    pub view: Mat4,
-    pub proj: Mat4,
+pub const MAX_CASCADES: usize = 4;
+/// GL_MAX_TEXTURE_SIZE, read in init(). Cascades resolutions are clamped to it
+static mut MAX_TEXTURE_SIZE: u32 = 4096;
+/// Log part of split scheme starts here, camera's near plane is too close for it.
+const CASCADES_SPLIT_NEAR: f32 = 0.1;
+/// How far behind a cascade shadow casters are still caught.
+const SHADOW_CASTERS_DISTANCE: f32 = 150.0;
+
+#[derive(Debug, Clone)]
+pub struct ShadowSettings {
+    /// one value per cascade, no more than MAX_CASCADES
+    pub cascades_resolutions: Vec<u32>,
+    /// shadows end at this distance from the camera
+    pub shadow_distance: f32,
+    /// 0.0 - linear splits, 1.0 - logarithmic splits, blends between them in between
+    pub split_lambda: f32,
+    /// part of a cascade (0.0 - 1.0) at its end that is blended with the next cascade
+    pub blend_fraction: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowSettings {
+            cascades_resolutions: vec![4096, 4096, 2048],
+            shadow_distance: 300.0,
+            split_lambda: 0.8,
+            blend_fraction: 0.1,
+        }
+    }
+}
+
+static mut SHADOW_SETTINGS: Lazy<ShadowSettings> = Lazy::new(ShadowSettings::default);
+
+/// Shadow textures are recreated in the next frame if resolutions changed.
+pub fn set_shadow_settings(mut settings: ShadowSettings) {
+    if settings.cascades_resolutions.is_empty() {
+        debugger::warn("set_shadow_settings: no cascades resolutions, using 1 cascade of 2048");
+        settings.cascades_resolutions.push(2048);
+    }
+    if settings.cascades_resolutions.len() > MAX_CASCADES {
+        debugger::warn(&format!(
+            "set_shadow_settings: {} cascades requested, max is {}",
+            settings.cascades_resolutions.len(),
+            MAX_CASCADES
+        ));
+        settings.cascades_resolutions.truncate(MAX_CASCADES);
+    }
+    let max_resolution = unsafe { MAX_TEXTURE_SIZE };
+    for resolution in &mut settings.cascades_resolutions {
+        if !(1..=max_resolution).contains(resolution) {
+            debugger::warn(&format!(
+                "set_shadow_settings: cascade resolution {} is out of 1 - {}, clamping it",
+                resolution, max_resolution
+            ));
+            *resolution = (*resolution).clamp(1, max_resolution);
+        }
+    }
+    settings.split_lambda = settings.split_lambda.clamp(0.0, 1.0);
+    settings.blend_fraction = settings.blend_fraction.clamp(0.0, 1.0);
+
+    unsafe {
+        *SHADOW_SETTINGS = settings;
+    }
 }
 
+pub fn get_shadow_settings() -> ShadowSettings {
+    unsafe { SHADOW_SETTINGS.clone() }
+}
+
+/// Cascades for default shaders, `cascades` uniform block.
+/// glium has no uniform block arrays shorter than 5, so view projections are separate fields.
+#[derive(Debug, Clone, Copy)]
+pub struct CascadesBlock {
+    /// x - cascades count
+    pub cascades_count: [i32; 4],
+    /// column per cascade: x - near, y - far, z - blend start. view space distances
+    pub cascades_splits: [[f32; 4]; 4],
+    pub cascade_view_proj_0: [[f32; 4]; 4],
+    pub cascade_view_proj_1: [[f32; 4]; 4],
+    pub cascade_view_proj_2: [[f32; 4]; 4],
+    pub cascade_view_proj_3: [[f32; 4]; 4],
+}
+implement_uniform_block!(
+    CascadesBlock,
+    cascades_count,
+    cascades_splits,
+    cascade_view_proj_0,
+    cascade_view_proj_1,
+    cascade_view_proj_2,
+    cascade_view_proj_3
+);
+
 pub struct Cascades {
-    closest: SunCamera,
-    furthest: SunCamera,
-    pub closest_view_proj: Mat4,
-    pub furthest_view_proj: Mat4,
+    pub view_projs: Vec<Mat4>,
+    /// near and far distance of each cascade
+    pub splits: Vec<(f32, f32)>,
+    pub blend_fraction: f32,
 }
 
 impl Cascades {
-    pub fn new(view: Mat4) -> Cascades {
-        let closest = SunCamera::new(view, 0.0, Some(50.0), None);
-        let furthest = SunCamera::new(view, 50.0, None, None); //Some(100.0));
-        let closest_view_proj = closest.as_mat4();
-        let furthest_view_proj = furthest.as_mat4();
+    pub fn new(view: Mat4, resolutions: &[u32]) -> Cascades {
+        let settings = get_shadow_settings();
+        let splits = Self::split_distances(&settings, resolutions.len());
+
+        let mut view_projs = Vec::new();
+        for (i, (near, far)) in splits.iter().enumerate() {
+            let sun_camera = SunCamera::new(view, *near, *far, resolutions[i]);
+            view_projs.push(sun_camera.as_mat4());
+        }
 
         Cascades {
-            closest,
-            furthest,
-            closest_view_proj,
-            furthest_view_proj,
+            view_projs,
+            splits,
+            blend_fraction: settings.blend_fraction,
         }
     }
-}
 
-impl SunCamera {
-    fn get_sun_camera_projection_matrix(corners: &CameraCorners) -> Mat4 {
-        //dbg!(corners.min_x, corners.max_x);
-        //dbg!(corners.min_y, corners.max_y);
-        //dbg!(corners.min_z, corners.max_z);
-
-        Mat4::orthographic_rh_gl(
-            corners.min_x - 50.0,
-            corners.max_x + 20.0,
-            corners.min_y - 50.0,
-            corners.max_y + 50.0,
-            corners.min_z - 100.0,
-            //corners.min_z + corners.max_z / 2.0,
-            corners.max_z + 200.0,
-        )
-    }
+    // https://developer.nvidia.com/gpugems/gpugems3/part-ii-light-and-shadows/chapter-10-parallel-split-shadow-maps-programmable-gpus
+    fn split_distances(settings: &ShadowSettings, count: usize) -> Vec<(f32, f32)> {
+        let near = CASCADES_SPLIT_NEAR;
+        let far = settings.shadow_distance.max(near + 1.0);
+
+        let mut splits = Vec::new();
+        let mut last_split = 0.0;
+        for i in 1..=count {
+            let part = i as f32 / count as f32;
+            let log_split = near * (far / near).powf(part);
+            let linear_split = near + (far - near) * part;
+            let split = settings.split_lambda * log_split + (1.0 - settings.split_lambda) * linear_split;
+
+            splits.push((last_split, split));
+            last_split = split;
+        }
 
-    fn get_sun_camera_view_matrix(corners: &CameraCorners, additional_y: Option<f32>) -> Mat4 {
-        //let direction = get_light_direction().normalize();
-        let view_center = corners.get_center();
-        let view_up = Vec3::new(0.0, 1.0, 0.0);
+        splits
+    }
 
-        //let main_camera_position = get_camera_position();
-        let additional_y = if let Some(additional_y) = additional_y {
-            Vec3::new(0.0, additional_y, 0.0)
-        } else {
-            Vec3::ZERO
-        };
+    pub fn as_block(&self) -> CascadesBlock {
+        let mut view_projs = [Mat4::IDENTITY.to_cols_array_2d(); MAX_CASCADES];
+        let mut splits = [[0.0; 4]; MAX_CASCADES];
 
-        let sun_camera_position =
-            get_light_direction() + view_center + Vec3::new(0.0, 20.0, 0.0) + additional_y; //Vec3::new(0.0, corners.max_y/* / 2.0*/, 0.0);
-                                                                                            //dbg!(&sun_camera_position);
+        for (i, view_proj) in self.view_projs.iter().enumerate() {
+            let (near, far) = self.splits[i];
+            view_projs[i] = view_proj.to_cols_array_2d();
+            splits[i] = [near, far, far - (far - near) * self.blend_fraction, 0.0];
+        }
 
-        let view_matrix = Mat4::look_at_rh(sun_camera_position, view_center, view_up);
+        CascadesBlock {
+            cascades_count: [self.view_projs.len() as i32, 0, 0, 0],
+            cascades_splits: splits,
+            cascade_view_proj_0: view_projs[0],
+            cascade_view_proj_1: view_projs[1],
+            cascade_view_proj_2: view_projs[2],
+            cascade_view_proj_3: view_projs[3],
+        }
+    }
 
-        view_matrix
+    /// First and last cascades for shaders that still use
+    /// `closestShadowViewProj` and `furthestShadowViewProj` uniforms.
+    pub fn closest_and_furthest_view_projs(&self) -> [[[f32; 4]; 4]; 2] {
+        let closest = self.view_projs.first().copied().unwrap_or(Mat4::IDENTITY);
+        let furthest = self.view_projs.last().copied().unwrap_or(Mat4::IDENTITY);
+        [closest.to_cols_array_2d(), furthest.to_cols_array_2d()]
     }
+}
 
-    pub fn new(
-        view: Mat4,
-        start_distance: f32,
-        end_distance: Option<f32>,
-        additional_y: Option<f32>,
-    ) -> SunCamera {
-        let corners = CameraCorners::new(start_distance, end_distance, view);
-        let proj = Self::get_sun_camera_projection_matrix(&corners);
-        let view = Self::get_sun_camera_view_matrix(&corners, additional_y);
-        SunCamera { view, proj }
+struct SunCamera {
+    pub view: Mat4,
+    pub proj: Mat4,
+}
+
+impl SunCamera {
+    pub fn new(view: Mat4, start_distance: f32, end_distance: f32, resolution: u32) -> SunCamera {
+        let corners =
+            CameraCorners::get_camera_corners(CameraCorners::get_camera_proj(start_distance, Some(end_distance)), view);
+
+        let mut center = Vec3::ZERO;
+        for corner in &corners {
+            center += *corner;
+        }
+        center /= corners.len() as f32;
+
+        // bounding sphere doesn't change size when the camera rotates
+        let mut radius: f32 = 0.0;
+        for corner in &corners {
+            radius = radius.max(corner.distance(center));
+        }
+        radius = (radius * 16.0).ceil() / 16.0;
+
+        let to_light = get_sun_direction();
+        let view_up = if to_light.abs_diff_eq(Vec3::Y, 0.001) {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+        let sun_camera_position = center + to_light * (radius + SHADOW_CASTERS_DISTANCE);
+        let sun_view = Mat4::look_at_rh(sun_camera_position, center, view_up);
+        let mut sun_proj = Mat4::orthographic_rh_gl(
+            -radius,
+            radius,
+            -radius,
+            radius,
+            0.0,
+            radius * 2.0 + SHADOW_CASTERS_DISTANCE,
+        );
+
+        // snapping to texels, so shadows don't shimmer when the camera moves
+        let half_resolution = resolution as f32 / 2.0;
+        let shadow_origin = sun_proj * sun_view * Vec4::W * half_resolution;
+        let rounded_origin = shadow_origin.round();
+        let offset = (rounded_origin - shadow_origin) / half_resolution;
+        sun_proj.w_axis.x += offset.x;
+        sun_proj.w_axis.y += offset.y;
+
+        SunCamera {
+            view: sun_view,
+            proj: sun_proj,
+        }
     }
 
     pub fn as_mat4(&self) -> Mat4 {
@@ -679,14 +1656,7 @@ impl SunCamera {
     }
 }
 
-struct CameraCorners {
-    min_x: f32,
-    max_x: f32,
-    min_y: f32,
-    max_y: f32,
-    min_z: f32,
-    max_z: f32,
-}
+struct CameraCorners;
 
 impl CameraCorners {
     // https://learnopengl.com/Guest-Articles/2021/CSM
@@ -715,7 +1685,6 @@ impl CameraCorners {
             vec3_frustum_corners.push(Vec3::new(corner.x, corner.y, corner.z));
         }
 
-        //dbg!(&vec3_frustum_corners);
         vec3_frustum_corners
     }
 
@@ -725,88 +1694,901 @@ impl CameraCorners {
             Some(distance) => distance,
             None => 500.0,
         };
-        //dbg!(start_distance, end_distance);
+        let (fov, aspect_ratio) = unsafe {
+            match &ACTIVE_CAMERA {
+                Some(camera) => (camera.fov, camera.aspect_ratio),
+                None => (CAMERA_LOCATION.fov, ASPECT_RATIO),
+            }
+        };
+        Mat4::perspective_rh_gl(fov, aspect_ratio, start_distance, end_distance)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// vertical fov in degrees
+    Perspective { fov: f32 },
+    /// visible height in world units
+    Orthographic { height: f32 },
+}
+
+/// Rectangle of the screen in 0-1 coordinates from the bottom left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    pub const FULL: Viewport = Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    /// Pixels of a `width` x `height` target, the same ones screen cameras are blitted into.
+    pub fn to_rect(self, width: u32, height: u32) -> Rect {
+        Rect {
+            left: (self.x * width as f32) as u32,
+            bottom: (self.y * height as f32) as u32,
+            width: ((self.width * width as f32) as u32).max(1),
+            height: ((self.height * height as f32) as u32).max(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraTarget {
+    Screen(Viewport),
+    /// rendered into a texture that can be got with `get_camera_texture`
+    Texture { width: u32, height: u32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct CameraData {
+    pub name: String,
+    pub position: Vec3,
+    pub rotation: Vec3,
+    pub projection: Projection,
+    pub near: f32,
+    pub far: f32,
+    pub target: CameraTarget,
+    /// screen cameras with lower order are drawn first
+    pub order: i32,
+}
+
+impl CameraData {
+    fn target_size(&self, screen_width: u32, screen_height: u32) -> (u32, u32) {
+        match self.target {
+            CameraTarget::Screen(viewport) => (
+                ((viewport.width * screen_width as f32) as u32).max(1),
+                ((viewport.height * screen_height as f32) as u32).max(1),
+            ),
+            CameraTarget::Texture { width, height } => (width.max(1), height.max(1)),
+        }
+    }
+
+    fn projection_matrix(&self, aspect_ratio: f32) -> Mat4 {
+        match self.projection {
+            Projection::Perspective { fov } => {
+                Mat4::perspective_rh_gl(deg_to_rad(fov), aspect_ratio, self.near, self.far)
+            }
+            Projection::Orthographic { height } => {
+                let half_height = height / 2.0;
+                let half_width = half_height * aspect_ratio;
+                Mat4::orthographic_rh_gl(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.near,
+                    self.far,
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ActiveCamera {
+    name: String,
+    position: Vec3,
+    view: Mat4,
+    projection: Mat4,
+    /// used to split shadow cascades, radians
+    fov: f32,
+    aspect_ratio: f32,
+}
+
+static mut CAMERAS: Vec<CameraData> = Vec::new();
+static mut ACTIVE_CAMERA: Option<ActiveCamera> = None;
+
+/// Screen camera (None is the default one) and where it was drawn.
+#[derive(Debug, Clone)]
+struct ScreenView {
+    camera: Option<ActiveCamera>,
+    viewport: Viewport,
+}
+
+static mut SCREEN_VIEWS: Vec<ScreenView> = Vec::new();
+
+/// Runs `draw` for every screen view of the last frame with its camera active, debug drawing
+/// goes over the post processed image like this. The default camera is active after it.
+pub fn for_each_screen_view(mut draw: impl FnMut(&Viewport)) {
+    let views = unsafe { SCREEN_VIEWS.clone() };
+    for view in &views {
         unsafe {
-            Mat4::perspective_rh_gl(
-                CAMERA_LOCATION.fov,
-                ASPECT_RATIO,
-                start_distance,
-                end_distance,
-            )
+            ACTIVE_CAMERA = view.camera.clone();
         }
+        draw(&view.viewport);
+    }
+    unsafe {
+        ACTIVE_CAMERA = None;
+    }
+}
+static mut CAMERA_TEXTURES: Lazy<HashMap<String, Box<CameraTextures>>> = Lazy::new(HashMap::new);
+
+/// Cameras are added every frame, if there's any screen camera the default one isn't drawn.
+pub fn add_camera(camera: CameraData) {
+    unsafe {
+        CAMERAS.push(camera);
     }
+}
 
-    /*pub fn get_sun_eye(&self) -> Vec3 {
-        Vec3::new(self.min_x, self.max_y, self.min_z)
-    }*/
+/// None is the default camera.
+fn set_active_camera(camera: Option<&CameraData>, width: u32, height: u32) {
+    let aspect_ratio = width as f32 / height.max(1) as f32;
+    unsafe {
+        ACTIVE_CAMERA = camera.map(|camera| ActiveCamera {
+            name: camera.name.clone(),
+            position: camera.position,
+            view: camera_view_matrix(camera.position, camera_front(camera.rotation)),
+            projection: camera.projection_matrix(aspect_ratio),
+            fov: match camera.projection {
+                Projection::Perspective { fov } => deg_to_rad(fov),
+                Projection::Orthographic { .. } => CAMERA_LOCATION.fov,
+            },
+            aspect_ratio,
+        });
+    }
+}
 
-    pub fn new(start_distance: f32, end_distance: Option<f32>, view: Mat4) -> CameraCorners {
-        let corners =
-            Self::get_camera_corners(Self::get_camera_proj(start_distance, end_distance), view);
-        //dbg!(&corners);
+/// Last frame's image of a camera. None while that camera is being rendered,
+/// so objects showing it don't read the texture it's drawn into.
+pub fn get_camera_texture(camera_name: &str) -> Option<&'static Texture2d> {
+    unsafe {
+        if let Some(camera) = &ACTIVE_CAMERA {
+            if camera.name == camera_name {
+                return None;
+            }
+        }
 
-        let mut min_x = 0.0;
-        let mut min_y = 0.0;
-        let mut min_z = 0.0;
+        CAMERA_TEXTURES.get(camera_name).map(|textures| &textures.color)
+    }
+}
 
-        let mut max_x = 0.0;
-        let mut max_y = 0.0;
-        let mut max_z = 0.0;
+pub struct CameraTextures {
+    pub color: Texture2d,
+    pub depth: DepthTexture2d,
+}
 
-        for corner in corners {
-            if corner.x > max_x {
-                max_x = corner.x;
+impl CameraTextures {
+    fn get_or_create(
+        display: &Display<WindowSurface>,
+        camera_name: &str,
+        width: u32,
+        height: u32,
+    ) -> Option<&'static CameraTextures> {
+        unsafe {
+            let up_to_date = match CAMERA_TEXTURES.get(camera_name) {
+                Some(textures) => textures.color.dimensions() == (width, height),
+                None => false,
+            };
+            if !up_to_date {
+                let color = Texture2d::empty_with_format(
+                    display,
+                    UncompressedFloatFormat::F16F16F16F16,
+                    MipmapsOption::NoMipmap,
+                    width,
+                    height,
+                );
+                let depth = DepthTexture2d::empty(display, width, height);
+                match (color, depth) {
+                    (Ok(color), Ok(depth)) => {
+                        CAMERA_TEXTURES.insert(camera_name.into(), Box::new(CameraTextures { color, depth }));
+                    }
+                    _ => {
+                        debugger::error(&format!("failed to create render target for camera {}", camera_name));
+                        return None;
+                    }
+                }
             }
-            if corner.x < min_x {
-                min_x = corner.x;
+
+            CAMERA_TEXTURES.get(camera_name).map(|textures| textures.as_ref())
+        }
+    }
+}
+
+pub struct ShadowTextures {
+    pub cascades: Vec<DepthTexture2d>,
+    /// from shadow settings, cascades can have other sizes if their creation failed
+    requested_resolutions: Vec<u32>,
+    pub point: Vec<DepthCubemap>,
+    pub spot: Vec<DepthTexture2d>,
+}
+
+impl ShadowTextures {
+    pub fn new(display: &Display<WindowSurface>, lights_size: u32) -> ShadowTextures {
+        let requested_resolutions = get_shadow_settings().cascades_resolutions;
+        let cascades = Self::new_cascades(display, &requested_resolutions);
+
+        let mut point = Vec::new();
+        for _ in 0..MAX_SHADOWED_POINT_LIGHTS {
+            point.push(DepthCubemap::empty(display, lights_size).unwrap());
+        }
+        let mut spot = Vec::new();
+        for _ in 0..MAX_SHADOWED_SPOT_LIGHTS {
+            spot.push(DepthTexture2d::empty(display, lights_size, lights_size).unwrap());
+        }
+
+        ShadowTextures {
+            cascades,
+            requested_resolutions,
+            point,
+            spot,
+        }
+    }
+
+    fn new_cascades(display: &Display<WindowSurface>, resolutions: &Vec<u32>) -> Vec<DepthTexture2d> {
+        let mut cascades = Vec::new();
+        for resolution in resolutions {
+            match DepthTexture2d::empty(display, *resolution, *resolution) {
+                Ok(texture) => cascades.push(texture),
+                Err(err) => debugger::error(&format!(
+                    "shadow cascade texture creation error!\nresolution: {}\nerr: {}",
+                    resolution, err
+                )),
             }
+        }
 
-            if corner.y > max_y {
-                max_y = corner.y;
+        // shaders need at least one cascade texture, a 1x1 one has no shadows but still works
+        if cascades.is_empty() {
+            match DepthTexture2d::empty(display, 1, 1) {
+                Ok(texture) => cascades.push(texture),
+                Err(err) => debugger::error(&format!("shadow cascade texture creation error!\nerr: {}", err)),
             }
-            if corner.y < min_y {
-                min_y = corner.y;
+        }
+
+        cascades
+    }
+
+    /// Recreates cascades textures if shadow settings changed.
+    pub fn update(&mut self, display: &Display<WindowSurface>) {
+        let resolutions = get_shadow_settings().cascades_resolutions;
+        if resolutions != self.requested_resolutions {
+            self.cascades = Self::new_cascades(display, &resolutions);
+            self.requested_resolutions = resolutions;
+        }
+    }
+
+    pub fn cascades_resolutions(&self) -> Vec<u32> {
+        self.cascades.iter().map(|texture| texture.width()).collect()
+    }
+
+    /// Cascade's texture, or the last one if there's less cascades than `index`.
+    /// Shaders always have MAX_CASCADES samplers, so every one of them needs something.
+    pub fn cascade(&self, index: usize) -> &DepthTexture2d {
+        match self.cascades.get(index) {
+            Some(texture) => texture,
+            None => self.cascades.last().unwrap(),
+        }
+    }
+
+    /// Last cascade, `furthestShadowTexture` of shaders that don't use cascades yet.
+    pub fn furthest(&self) -> &DepthTexture2d {
+        self.cascade(MAX_CASCADES - 1)
+    }
+}
+
+pub const MAX_POINT_LIGHTS: usize = 8;
+pub const MAX_SPOT_LIGHTS: usize = 8;
+pub const MAX_SHADOWED_POINT_LIGHTS: usize = 2;
+pub const MAX_SHADOWED_SPOT_LIGHTS: usize = 2;
+/// Near plane of point and spot lights shadow maps, far plane is light's range.
+pub const LIGHT_SHADOW_NEAR: f32 = 0.05;
+/// Lights' range is clamped to this, so their shadow maps' far plane is always past the near one.
+pub const MIN_LIGHT_RANGE: f32 = LIGHT_SHADOW_NEAR * 2.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PointLightData {
+    pub position: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub range: f32,
+    pub cast_shadows: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SpotLightData {
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub range: f32,
+    /// degrees
+    pub inner_angle: f32,
+    /// degrees
+    pub outer_angle: f32,
+    pub cast_shadows: bool,
+}
+
+static mut POINT_LIGHTS: Vec<PointLightData> = vec![];
+static mut SPOT_LIGHTS: Vec<SpotLightData> = vec![];
+// indices in POINT_LIGHTS and SPOT_LIGHTS, index in these vecs is shadow map index
+static mut SHADOWED_POINT_LIGHTS: Vec<usize> = vec![];
+static mut SHADOWED_SPOT_LIGHTS: Vec<usize> = vec![];
+static mut SPOT_SHADOW_VIEW_PROJS: [Mat4; MAX_SHADOWED_SPOT_LIGHTS] =
+    [Mat4::IDENTITY; MAX_SHADOWED_SPOT_LIGHTS];
+
+/// Position and direction are in render space (z is flipped like in model matrices).
+/// Lights are cleared every frame, so light objects should add themselves in update().
+pub fn add_point_light(light: PointLightData) {
+    unsafe {
+        POINT_LIGHTS.push(light);
+    }
+}
+
+pub fn add_spot_light(light: SpotLightData) {
+    unsafe {
+        SPOT_LIGHTS.push(light);
+    }
+}
+
+pub fn get_point_lights_count() -> usize {
+    unsafe { POINT_LIGHTS.len() }
+}
+
+pub fn get_spot_lights_count() -> usize {
+    unsafe { SPOT_LIGHTS.len() }
+}
+
+pub fn get_spot_shadow_view_proj(shadow_index: usize) -> Mat4 {
+    unsafe { SPOT_SHADOW_VIEW_PROJS[shadow_index] }
+}
+
+/// Lights data for default shaders, `Lights` uniform block.
+#[derive(Debug, Clone, Copy)]
+pub struct LightsBlock {
+    /// x - point lights count, y - spot lights count
+    pub lights_count: [i32; 4],
+    pub point_position_range: [[f32; 4]; MAX_POINT_LIGHTS],
+    pub point_color_intensity: [[f32; 4]; MAX_POINT_LIGHTS],
+    /// x - shadow map index or -1
+    pub point_shadow_index: [[i32; 4]; MAX_POINT_LIGHTS],
+    pub spot_position_range: [[f32; 4]; MAX_SPOT_LIGHTS],
+    pub spot_color_intensity: [[f32; 4]; MAX_SPOT_LIGHTS],
+    pub spot_direction: [[f32; 4]; MAX_SPOT_LIGHTS],
+    /// x - cos of inner angle, y - cos of outer angle
+    pub spot_cone: [[f32; 4]; MAX_SPOT_LIGHTS],
+    /// x - shadow map index or -1
+    pub spot_shadow_index: [[i32; 4]; MAX_SPOT_LIGHTS],
+}
+implement_uniform_block!(
+    LightsBlock,
+    lights_count,
+    point_position_range,
+    point_color_intensity,
+    point_shadow_index,
+    spot_position_range,
+    spot_color_intensity,
+    spot_direction,
+    spot_cone,
+    spot_shadow_index
+);
+
+/// Closest lights whose range reaches `bounds` (render space),
+/// no more than MAX_POINT_LIGHTS and MAX_SPOT_LIGHTS.
+pub fn get_lights_block(bounds: &Aabb) -> LightsBlock {
+    let mut block = LightsBlock {
+        lights_count: [0; 4],
+        point_position_range: [[0.0; 4]; MAX_POINT_LIGHTS],
+        point_color_intensity: [[0.0; 4]; MAX_POINT_LIGHTS],
+        point_shadow_index: [[-1; 4]; MAX_POINT_LIGHTS],
+        spot_position_range: [[0.0; 4]; MAX_SPOT_LIGHTS],
+        spot_color_intensity: [[0.0; 4]; MAX_SPOT_LIGHTS],
+        spot_direction: [[0.0; 4]; MAX_SPOT_LIGHTS],
+        spot_cone: [[0.0; 4]; MAX_SPOT_LIGHTS],
+        spot_shadow_index: [[-1; 4]; MAX_SPOT_LIGHTS],
+    };
+
+    let point_lights = unsafe { &POINT_LIGHTS };
+    let spot_lights = unsafe { &SPOT_LIGHTS };
+
+    let point_indices = closest_lights(
+        point_lights.iter().map(|light| (light.position, light.range)),
+        bounds,
+        MAX_POINT_LIGHTS,
+    );
+    for (i, light_index) in point_indices.iter().enumerate() {
+        let light = &point_lights[*light_index];
+        block.point_position_range[i] = light.position.extend(light.range).into();
+        block.point_color_intensity[i] = light.color.extend(light.intensity).into();
+        block.point_shadow_index[i][0] = shadow_index(unsafe { &SHADOWED_POINT_LIGHTS }, *light_index);
+    }
+
+    let spot_indices = closest_lights(
+        spot_lights.iter().map(|light| (light.position, light.range)),
+        bounds,
+        MAX_SPOT_LIGHTS,
+    );
+    for (i, light_index) in spot_indices.iter().enumerate() {
+        let light = &spot_lights[*light_index];
+        block.spot_position_range[i] = light.position.extend(light.range).into();
+        block.spot_color_intensity[i] = light.color.extend(light.intensity).into();
+        block.spot_direction[i] = light.direction.normalize_or_zero().extend(0.0).into();
+        block.spot_cone[i] = [
+            light.inner_angle.to_radians().cos(),
+            light.outer_angle.to_radians().cos(),
+            0.0,
+            0.0,
+        ];
+        block.spot_shadow_index[i][0] = shadow_index(unsafe { &SHADOWED_SPOT_LIGHTS }, *light_index);
+    }
+
+    block.lights_count = [point_indices.len() as i32, spot_indices.len() as i32, 0, 0];
+
+    block
+}
+
+fn closest_lights(
+    lights: impl Iterator<Item = (Vec3, f32)>,
+    bounds: &Aabb,
+    max: usize,
+) -> Vec<usize> {
+    let mut in_range: Vec<(usize, f32)> = lights
+        .enumerate()
+        .map(|(idx, (light_position, range))| (idx, bounds.distance(light_position), range))
+        .filter(|(_, distance, range)| distance <= range)
+        .map(|(idx, distance, _)| (idx, distance))
+        .collect();
+    in_range.sort_by(|a, b| a.1.total_cmp(&b.1));
+    in_range.truncate(max);
+
+    in_range.iter().map(|(idx, _)| *idx).collect()
+}
+
+fn shadow_index(shadowed_lights: &[usize], light_index: usize) -> i32 {
+    match shadowed_lights.iter().position(|idx| *idx == light_index) {
+        Some(shadow_index) => shadow_index as i32,
+        None => -1,
+    }
+}
+
+/// Shadows are given to shadow casting lights closest to the camera.
+fn select_shadowed_lights() {
+    let camera_position = get_camera_render_position();
+    let camera_bounds = Aabb {
+        min: camera_position,
+        max: camera_position,
+    };
+
+    unsafe {
+        SHADOWED_POINT_LIGHTS = closest_lights(
+            POINT_LIGHTS
+                .iter()
+                .map(|light| (light.position, if light.cast_shadows { f32::MAX } else { -1.0 })),
+            &camera_bounds,
+            MAX_SHADOWED_POINT_LIGHTS,
+        );
+        SHADOWED_SPOT_LIGHTS = closest_lights(
+            SPOT_LIGHTS
+                .iter()
+                .map(|light| (light.position, if light.cast_shadows { f32::MAX } else { -1.0 })),
+            &camera_bounds,
+            MAX_SHADOWED_SPOT_LIGHTS,
+        );
+    }
+}
+
+fn render_lights_shadows(display: &Display<WindowSurface>, shadow_textures: &ShadowTextures) {
+    // gl cube map faces order and up vectors
+    let faces = [
+        (CubeLayer::PositiveX, Vec3::X, Vec3::NEG_Y),
+        (CubeLayer::NegativeX, Vec3::NEG_X, Vec3::NEG_Y),
+        (CubeLayer::PositiveY, Vec3::Y, Vec3::Z),
+        (CubeLayer::NegativeY, Vec3::NEG_Y, Vec3::NEG_Z),
+        (CubeLayer::PositiveZ, Vec3::Z, Vec3::NEG_Y),
+        (CubeLayer::NegativeZ, Vec3::NEG_Z, Vec3::NEG_Y),
+    ];
+
+    let shadowed_point_lights = unsafe { &SHADOWED_POINT_LIGHTS };
+    for (shadow_index, light_index) in shadowed_point_lights.iter().enumerate() {
+        let light = unsafe { &POINT_LIGHTS[*light_index] };
+        let proj = Mat4::perspective_rh_gl(90.0_f32.to_radians(), 1.0, LIGHT_SHADOW_NEAR, light.range);
+
+        for (layer, direction, up) in faces {
+            let image = shadow_textures.point[shadow_index].main_level().image(layer);
+            let mut fbo = SimpleFrameBuffer::depth_only(display, image).unwrap();
+            fbo.clear_depth(1.0);
+
+            let view = Mat4::look_at_rh(light.position, light.position + direction, up);
+            systems::shadow_render(&(proj * view), display, &mut fbo);
+        }
+    }
+
+    let shadowed_spot_lights = unsafe { &SHADOWED_SPOT_LIGHTS };
+    for (shadow_index, light_index) in shadowed_spot_lights.iter().enumerate() {
+        let light = unsafe { &SPOT_LIGHTS[*light_index] };
+        let direction = light.direction.normalize_or_zero();
+        let up = if direction.abs_diff_eq(Vec3::Y, 0.001) || direction.abs_diff_eq(Vec3::NEG_Y, 0.001) {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+        let fov = (light.outer_angle * 2.0).clamp(1.0, 179.0).to_radians();
+        let proj = Mat4::perspective_rh_gl(fov, 1.0, LIGHT_SHADOW_NEAR, light.range);
+        let view = Mat4::look_at_rh(light.position, light.position + direction, up);
+        let view_proj = proj * view;
+        unsafe {
+            SPOT_SHADOW_VIEW_PROJS[shadow_index] = view_proj;
+        }
+
+        let mut fbo = SimpleFrameBuffer::depth_only(display, &shadow_textures.spot[shadow_index]).unwrap();
+        fbo.clear_depth(1.0);
+        systems::shadow_render(&view_proj, display, &mut fbo);
+    }
+}
+
+/// Gpu copies of model asset's material textures.
+/// Materials without a texture get a white 1x1 texture, so factors can be used as is.
+#[derive(Debug)]
+pub struct MaterialTextures {
+    textures: Vec<Option<Texture2d>>,
+}
+
+impl MaterialTextures {
+    pub fn new(display: &Display<WindowSurface>, asset: &ModelAsset) -> MaterialTextures {
+        let srgb_textures: Vec<usize> = asset
+            .objects
+            .iter()
+            .flat_map(|object| [object.material.base_color_texture, object.material.emissive_texture])
+            .flatten()
+            .map(|texture| texture.texture_index)
+            .collect();
+
+        let mut textures = Vec::new();
+        for (texture_index, texture_asset) in asset.textures.iter().enumerate() {
+            let texture = match texture_asset {
+                Some(texture_asset) => {
+                    // normal, metallic-roughness and occlusion textures hold data, colour ones are srgb
+                    let texture = match srgb_textures.contains(&texture_index) {
+                        true => create_srgb_texture(display, &texture_asset.image_raw, texture_asset.image_dimensions, false),
+                        false => Texture2d::new(
+                            display,
+                            RawImage2d::from_raw_rgba(texture_asset.image_raw.clone(), texture_asset.image_dimensions),
+                        ),
+                    };
+                    match texture {
+                        Ok(tx) => Some(tx),
+                        Err(err) => {
+                            debugger::error(&format!(
+                                "material texture creation error!\nmodel asset: {}\nerr: {}",
+                                asset.path, err
+                            ));
+                            None
+                        }
+                    }
+                }
+                None => None,
+            };
+            textures.push(texture);
+        }
+
+        MaterialTextures { textures }
+    }
+
+    pub fn get(&self, texture: &Option<MaterialTexture>) -> &Texture2d {
+        match texture {
+            Some(texture) => match self.textures.get(texture.texture_index) {
+                Some(Some(tx)) => tx,
+                _ => get_white_texture(),
+            },
+            None => get_white_texture(),
+        }
+    }
+}
+
+/// Texture coords set of a material texture for shaders, 0 when there's no texture.
+pub fn material_tex_coord(texture: &Option<MaterialTexture>) -> i32 {
+    texture.as_ref().map_or(0, |texture| texture.tex_coord as i32)
+}
+
+/// Baked lighting and occlusion, sampled with the second texture coords set (`TEXCOORD_1`).
+#[derive(Debug)]
+pub struct Lightmap {
+    asset: TextureAsset,
+    pub intensity: f32,
+    texture: Option<Texture2d>,
+    /// the white texture is used after a failed creation, so it's not retried every frame
+    failed: bool,
+}
+
+impl Lightmap {
+    pub fn new(asset: TextureAsset, intensity: f32) -> Lightmap {
+        Lightmap {
+            asset,
+            intensity,
+            texture: None,
+            failed: false,
+        }
+    }
+
+    /// Creates the texture on first use.
+    pub fn prepare(&mut self, display: &Display<WindowSurface>) {
+        if self.texture.is_some() || self.failed {
+            return;
+        }
+
+        let image = RawImage2d::from_raw_rgba(self.asset.image_raw.clone(), self.asset.image_dimensions);
+        match Texture2d::new(display, image) {
+            Ok(tx) => self.texture = Some(tx),
+            Err(err) => {
+                debugger::error(&format!("lightmap texture creation error!\nerr: {}", err));
+                self.failed = true;
+            }
+        }
+    }
+
+    pub fn texture(&self) -> &Texture2d {
+        self.texture.as_ref().unwrap_or(get_white_texture())
+    }
+}
+
+/// Width of morph target textures, rows are added as needed.
+pub const MORPH_TEXTURE_WIDTH: usize = 1024;
+/// Targets with the highest weights that are passed to shaders, the rest are ignored.
+pub const MAX_ACTIVE_MORPH_TARGETS: usize = 8;
+
+/// Morph target deltas of every model asset's object in rgb32f textures.
+/// Texel `(target * vertices_count + vertex) * 2` is the position delta, the next one is the normal delta.
+#[derive(Debug)]
+pub struct MorphTargetTextures {
+    textures: Vec<Option<Texture2d>>,
+}
+
+impl MorphTargetTextures {
+    pub fn new(display: &Display<WindowSurface>, asset: &ModelAsset) -> MorphTargetTextures {
+        let mut textures = Vec::new();
+        for object in &asset.objects {
+            if object.morph_targets.is_empty() {
+                textures.push(None);
+                continue;
             }
 
-            if corner.z > max_z {
-                max_z = corner.z;
+            let texels_count = object.morph_targets.len() * object.vertices.len() * 2;
+            let height = texels_count.div_ceil(MORPH_TEXTURE_WIDTH);
+            let mut data: Vec<f32> = Vec::with_capacity(MORPH_TEXTURE_WIDTH * height * 3);
+            for target in &object.morph_targets {
+                for (position, normal) in target.positions.iter().zip(&target.normals) {
+                    data.extend_from_slice(position);
+                    data.extend_from_slice(normal);
+                }
             }
-            if corner.z < min_z {
-                min_z = corner.z;
+            data.resize(MORPH_TEXTURE_WIDTH * height * 3, 0.0);
+
+            let image = RawImage2d {
+                data: std::borrow::Cow::Owned(data),
+                width: MORPH_TEXTURE_WIDTH as u32,
+                height: height as u32,
+                format: ClientFormat::F32F32F32,
+            };
+            match Texture2d::with_format(display, image, UncompressedFloatFormat::F32F32F32, MipmapsOption::NoMipmap) {
+                Ok(tx) => textures.push(Some(tx)),
+                Err(err) => {
+                    debugger::error(&format!(
+                        "morph target texture creation error!\nmodel asset: {}\nerr: {}",
+                        asset.path, err
+                    ));
+                    textures.push(None);
+                }
             }
         }
 
-        CameraCorners {
-            min_x,
-            max_x,
-            min_y,
-            max_y,
-            min_z,
-            max_z,
+        MorphTargetTextures { textures }
+    }
+
+    /// White texture for objects without morph targets, shaders don't read it when `morphTargetsCount` is 0.
+    pub fn get(&self, object_index: usize) -> &Texture2d {
+        match self.textures.get(object_index) {
+            Some(Some(tx)) => tx,
+            _ => get_white_texture(),
         }
     }
 
-    pub fn get_center(&self) -> Vec3 {
-        let center_x = (self.min_x + self.max_x) / 2.0;
-        let center_y = (self.min_y + self.max_y) / 2.0;
-        let center_z = (self.min_z + self.max_z) / 2.0;
+    pub fn has_targets(&self, object_index: usize) -> bool {
+        matches!(self.textures.get(object_index), Some(Some(_)))
+    }
+}
+
+/// Up to MAX_ACTIVE_MORPH_TARGETS targets with the highest weights, packed for the shader.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MorphUniforms {
+    pub count: i32,
+    pub indices: [[i32; 4]; 2],
+    pub weights: [[f32; 4]; 2],
+}
+
+impl MorphUniforms {
+    pub fn from_weights(weights: &[f32]) -> MorphUniforms {
+        let mut active: Vec<(usize, f32)> = weights
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, weight)| *weight != 0.0)
+            .collect();
+        active.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+        active.truncate(MAX_ACTIVE_MORPH_TARGETS);
+
+        let mut uniforms = MorphUniforms {
+            count: active.len() as i32,
+            ..Default::default()
+        };
+        for (i, (index, weight)) in active.into_iter().enumerate() {
+            uniforms.indices[i / 4][i % 4] = index as i32;
+            uniforms.weights[i / 4][i % 4] = weight;
+        }
 
-        Vec3::new(center_x, center_y, center_z)
+        uniforms
     }
 }
 
-pub struct ShadowTextures {
-    pub closest: DepthTexture2d,
-    pub furthest: DepthTexture2d,
+pub type JointsMats = [[[f32; 4]; 4]; 128];
+
+/// Uniform buffers of a model object, created once and written every frame.
+#[derive(Debug)]
+pub struct ModelUniformBuffers {
+    pub joints: UniformBuffer<JointsMats>,
+    pub inverse_bind_mats: UniformBuffer<JointsMats>,
+    pub lights: UniformBuffer<LightsBlock>,
+    pub cascades: UniformBuffer<CascadesBlock>,
 }
 
-impl ShadowTextures {
-    pub fn new(display: &Display<WindowSurface>, closest_size: u32, furthest_size: u32) -> ShadowTextures {
-        let closest =
-            glium::texture::DepthTexture2d::empty(display, closest_size, closest_size).unwrap(); // 1st Cascade
-        let furthest =
-            glium::texture::DepthTexture2d::empty(display, furthest_size, furthest_size).unwrap(); // 2st Cascade
+impl ModelUniformBuffers {
+    pub fn new(
+        display: &Display<WindowSurface>,
+        asset: &ModelAsset,
+    ) -> Result<ModelUniformBuffers, BufferCreationError> {
+        Ok(ModelUniformBuffers {
+            joints: UniformBuffer::empty_dynamic(display)?,
+            inverse_bind_mats: UniformBuffer::new(display, asset.joints_inverse_bind_mats)?,
+            lights: UniformBuffer::empty_dynamic(display)?,
+            cascades: UniformBuffer::empty_dynamic(display)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance_lods(cross_fade: f32) -> LodSettings {
+        LodSettings {
+            metric: LodMetric::Distance,
+            thresholds: vec![10.0, 20.0, 40.0],
+            cross_fade,
+        }
+    }
+
+    fn assert_lod(selection: LodSelection, lod: usize, next: Option<usize>) {
+        assert_eq!((selection.lod, selection.next), (lod, next), "{:?}", selection);
+    }
+
+    #[test]
+    fn lod_switches_at_distance_thresholds() {
+        let settings = distance_lods(0.0);
+        assert_lod(settings.select_by_value(5.0, 4), 0, None);
+        assert_lod(settings.select_by_value(10.0, 4), 1, None);
+        assert_lod(settings.select_by_value(25.0, 4), 2, None);
+        assert_lod(settings.select_by_value(1000.0, 4), 3, None);
+    }
+
+    #[test]
+    fn lod_is_limited_by_lods_count() {
+        let settings = distance_lods(0.0);
+        assert_lod(settings.select_by_value(1000.0, 2), 1, None);
+        assert_lod(settings.select_by_value(1000.0, 1), 0, None);
+    }
+
+    #[test]
+    fn lod_switches_at_screen_size_thresholds() {
+        let settings = LodSettings {
+            metric: LodMetric::ScreenSize,
+            thresholds: vec![0.5, 0.1],
+            cross_fade: 0.0,
+        };
+        assert_lod(settings.select_by_value(0.8, 3), 0, None);
+        assert_lod(settings.select_by_value(0.3, 3), 1, None);
+        assert_lod(settings.select_by_value(0.05, 3), 2, None);
+    }
+
+    #[test]
+    fn lods_cross_fade_before_thresholds() {
+        let settings = distance_lods(4.0);
+        assert_lod(settings.select_by_value(5.0, 4), 0, None);
+        let selection = settings.select_by_value(7.0, 4);
+        assert_lod(selection, 0, Some(1));
+        assert!((selection.fade - 0.25).abs() < 1e-5);
+        let selection = settings.select_by_value(19.0, 4);
+        assert_lod(selection, 1, Some(2));
+        assert!((selection.fade - 0.75).abs() < 1e-5);
+        // no band after the last threshold
+        assert_lod(settings.select_by_value(100.0, 4), 3, None);
+    }
+
+    #[test]
+    fn cross_faded_meshes_get_opposite_fades() {
+        let selection = LodSelection {
+            lod: 1,
+            next: Some(2),
+            fade: 0.25,
+        };
+        assert_eq!(selection.mesh_fade(None), Some(0.0));
+        assert_eq!(selection.mesh_fade(Some(1)), Some(0.25));
+        assert_eq!(selection.mesh_fade(Some(2)), Some(-0.75));
+        assert_eq!(selection.mesh_fade(Some(0)), None);
+    }
+
+    fn shadow_settings(shadow_distance: f32, split_lambda: f32) -> ShadowSettings {
+        ShadowSettings {
+            shadow_distance,
+            split_lambda,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn cascades_cover_the_shadow_distance() {
+        for lambda in [0.0, 0.5, 1.0] {
+            let splits = Cascades::split_distances(&shadow_settings(300.0, lambda), 4);
+            assert_eq!(splits.len(), 4);
+            assert_eq!(splits[0].0, 0.0);
+            assert!((splits[3].1 - 300.0).abs() < 1e-3, "{:?}", splits);
+            for pair in splits.windows(2) {
+                assert_eq!(pair[0].1, pair[1].0);
+                assert!(pair[0].1 > pair[0].0);
+            }
+        }
+    }
+
+    #[test]
+    fn linear_and_logarithmic_cascade_splits() {
+        let near = CASCADES_SPLIT_NEAR;
+        let linear = Cascades::split_distances(&shadow_settings(100.0, 0.0), 2);
+        assert!((linear[0].1 - (near + (100.0 - near) / 2.0)).abs() < 1e-3, "{:?}", linear);
 
-        ShadowTextures { closest, furthest }
+        let logarithmic = Cascades::split_distances(&shadow_settings(100.0, 1.0), 2);
+        assert!((logarithmic[0].1 - (near * 100.0_f32).sqrt()).abs() < 1e-3, "{:?}", logarithmic);
+    }
+
+    #[test]
+    fn cascades_shadow_distance_is_past_the_split_near() {
+        let splits = Cascades::split_distances(&shadow_settings(0.0, 0.8), 2);
+        assert!((splits[1].1 - (CASCADES_SPLIT_NEAR + 1.0)).abs() < 1e-5, "{:?}", splits);
+    }
+
+    #[test]
+    fn cascades_block_blends_at_the_end_of_cascades() {
+        let cascades = Cascades {
+            view_projs: vec![Mat4::IDENTITY; 2],
+            splits: vec![(0.0, 10.0), (10.0, 50.0)],
+            blend_fraction: 0.1,
+        };
+        let block = cascades.as_block();
+        assert_eq!(block.cascades_count[0], 2);
+        assert_eq!(block.cascades_splits[0], [0.0, 10.0, 9.0, 0.0]);
+        assert_eq!(block.cascades_splits[1], [10.0, 50.0, 46.0, 0.0]);
     }
 }
@@ -9,16 +9,18 @@ use crate::{
         sound_asset::SoundAsset,
         texture_asset::TextureAsset,
     }, framework, managers::{
-        self, debugger, input::{self, InputEventType}, networking::{self, Message, MessageContents, MessageReceiver, MessageReliability, SyncObjectMessage}, physics::{BodyColliderType, CollisionGroups}, saves, systems::{self, SystemValue}
+        self, assets::get_full_asset_path, debug_draw::{self, DebugStyle}, debugger, input::{self, InputEventType}, networking::{self, Message, MessageContents, MessageReceiver, MessageReliability, SyncObjectMessage}, physics::{BodyColliderType, CollisionGroups}, post_process, render::{self, CameraTarget, Projection, ShadowSettings, Viewport}, saves, sky::{self, Fog, FogMode, GradientSky, Sky}, systems::{self, SystemValue}
     }, objects::{
-        camera_position::CameraPosition, character_controller::CharacterController, empty_object::EmptyObject, instanced_model_object::InstancedModelObject, instanced_model_transform_holder::InstancedModelTransformHolder, master_instanced_model_object::MasterInstancedModelObject, model_object::ModelObject, nav_obstacle::NavObstacle, navmesh::NavigationGround, ray::Ray, sound_emitter::SoundEmitter, trigger::Trigger, Object, Transform
-    }, systems::System
+        camera::Camera, camera_position::CameraPosition, character_controller::CharacterController, empty_object::EmptyObject, instanced_model_object::InstancedModelObject, instanced_model_transform_holder::InstancedModelTransformHolder, master_instanced_model_object::MasterInstancedModelObject, model_object::ModelObject, nav_obstacle::NavObstacle, navmesh::NavigationGround, particle_emitter::{ParticleEmitter, ParticleEmitterSettings}, point_light::PointLight, ray::Ray, sound_emitter::SoundEmitter, spot_light::SpotLight, sprite::Sprite, terrain::Terrain, trigger::Trigger, Object, Transform
+    }, math_utils::Aabb, systems::System
 };
 use ez_al::SoundSourceType;
-use glam::{Vec2, Vec3};
+use glam::{Vec2, Vec3, Vec4};
 use mlua::Lua;
 use winit::{event::MouseButton, keyboard::KeyCode};
 
+type SkyGradientArgs = (Vec<f32>, Vec<f32>, Vec<f32>, Option<Vec<f32>>, Option<f32>);
+
 pub fn add_lua_vm_to_list(system_id: String, lua: Lua) {
     unsafe {
         SYSTEMS_LUA_VMS.insert(system_id.clone(), lua);
@@ -257,7 +259,7 @@ pub fn add_lua_vm_to_list(system_id: String, lua: Lua) {
                     let shader_asset = ShaderAsset::load_from_file(shader_asset_path);
                     match shader_asset {
                         Ok(shader_asset) => {
-                            let model_asset = ModelAsset::from_gltf(&model_asset_path);
+                            let model_asset = ModelAsset::from_file(&model_asset_path);
                             match model_asset {
                                 Ok(model_asset) => {
                                     let object = ModelObject::new(&name, model_asset, texture_asset, shader_asset);
@@ -289,6 +291,76 @@ pub fn add_lua_vm_to_list(system_id: String, lua: Lua) {
             )),
         }
 
+        let system_id_for_functions = system_id.clone();
+        let new_lod_model_object = lua.create_function_mut(
+            move |lua, (name, model_asset_paths, texture_asset_path, vertex_shader_asset_path, fragment_shader_asset_path):
+            (String, Vec<String>, Option<String>, Option<String>, Option<String>)| {
+            let system_option = systems::get_system_mut_with_id(&system_id_for_functions);
+            match system_option {
+                Some(system) => {
+                    let texture_asset;
+                    match texture_asset_path {
+                        Some(path) => {
+                            let asset = TextureAsset::from_file(&path);
+                            match asset {
+                                Ok(asset) => texture_asset = Some(asset),
+                                Err(err) => {
+                                    debugger::warn(&format!("lua warning: error when calling new_lod_model_object, failed to load texture asset!\nerr: {:?}", err));
+                                    texture_asset = None;
+                                },
+                            }
+                        },
+                        None => texture_asset = None,
+                    }
+                    let mut shader_asset_path = ShaderAssetPath {
+                        vertex_shader_path: assets::shader_asset::get_default_vertex_shader_path(),
+                        fragment_shader_path: assets::shader_asset::get_default_fragment_shader_path(),
+                    };
+                    if let Some(vertex_shader_asset_path) = vertex_shader_asset_path {
+                        shader_asset_path.vertex_shader_path = vertex_shader_asset_path;
+                    }
+                    if let Some(fragment_shader_asset_path) = fragment_shader_asset_path {
+                        shader_asset_path.fragment_shader_path = fragment_shader_asset_path;
+                    }
+                    let shader_asset = ShaderAsset::load_from_file(shader_asset_path);
+                    match shader_asset {
+                        Ok(shader_asset) => {
+                            let model_asset = model_asset_paths
+                                .iter()
+                                .map(|path| ModelAsset::from_file(path))
+                                .collect::<Result<Vec<ModelAsset>, _>>()
+                                .and_then(ModelAsset::from_lod_assets);
+                            match model_asset {
+                                Ok(model_asset) => {
+                                    let object = ModelObject::new(&name, model_asset, texture_asset, shader_asset);
+                                    add_to_system_or_parent(lua, system, Box::new(object));
+                                },
+                                Err(err) => 
+                                    debugger::error(&format!("lua error: error when calling new_lod_model_object, failed to load a model asset!\nerr: {:?}", err)),
+                            }
+                        },
+                        Err(err) => 
+                            debugger::error(&format!("lua error: error when calling new_lod_model_object, failed to load a shader asset!\nerr: {:?}", err)),
+                    }
+                },
+                None => debugger::error("failed to call new_lod_model_object, system not found"),
+            }
+            
+            Ok(())
+        });
+
+        match new_lod_model_object {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("new_lod_model_object", func) {
+                    debugger::error(&format!("failed to add a function new_lod_model_object as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function new_lod_model_object in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
         let system_id_for_functions = system_id.clone();
         let new_master_instanced_model_object = lua.create_function_mut(
             move |lua, (name, model_asset_path, texture_asset_path, vertex_shader_asset_path, fragment_shader_asset_path):
@@ -323,7 +395,7 @@ pub fn add_lua_vm_to_list(system_id: String, lua: Lua) {
                     let shader_asset = ShaderAsset::load_from_file(shader_asset_path);
                     match shader_asset {
                         Ok(shader_asset) => {
-                            let model_asset = ModelAsset::from_gltf(&model_asset_path);
+                            let model_asset = ModelAsset::from_file(&model_asset_path);
                             match model_asset {
                                 Ok(model_asset) => {
                                     let object = MasterInstancedModelObject::new(&name, model_asset, texture_asset, shader_asset);
@@ -355,6 +427,76 @@ pub fn add_lua_vm_to_list(system_id: String, lua: Lua) {
             )),
         }
 
+        let system_id_for_functions = system_id.clone();
+        let new_lod_master_instanced_model_object = lua.create_function_mut(
+            move |lua, (name, model_asset_paths, texture_asset_path, vertex_shader_asset_path, fragment_shader_asset_path):
+            (String, Vec<String>, Option<String>, Option<String>, Option<String>)| {
+            let system_option = systems::get_system_mut_with_id(&system_id_for_functions);
+            match system_option {
+                Some(system) => {
+                    let texture_asset;
+                    match texture_asset_path {
+                        Some(path) => {
+                            let asset = TextureAsset::from_file(&path);
+                            match asset {
+                                Ok(asset) => texture_asset = Some(asset),
+                                Err(err) => {
+                                    debugger::warn(&format!("lua warning: error when calling new_lod_master_instanced_model_object, failed to load texture asset!\nerr: {:?}", err));
+                                    texture_asset = None;
+                                },
+                            }
+                        },
+                        None => texture_asset = None,
+                    }
+                    let mut shader_asset_path = ShaderAssetPath {
+                        vertex_shader_path: assets::shader_asset::get_default_instanced_vertex_shader_path(),
+                        fragment_shader_path: assets::shader_asset::get_default_instanced_fragment_shader_path(),
+                    };
+                    if let Some(vertex_shader_asset_path) = vertex_shader_asset_path {
+                        shader_asset_path.vertex_shader_path = vertex_shader_asset_path;
+                    }
+                    if let Some(fragment_shader_asset_path) = fragment_shader_asset_path {
+                        shader_asset_path.fragment_shader_path = fragment_shader_asset_path;
+                    }
+                    let shader_asset = ShaderAsset::load_from_file(shader_asset_path);
+                    match shader_asset {
+                        Ok(shader_asset) => {
+                            let model_asset = model_asset_paths
+                                .iter()
+                                .map(|path| ModelAsset::from_file(path))
+                                .collect::<Result<Vec<ModelAsset>, _>>()
+                                .and_then(ModelAsset::from_lod_assets);
+                            match model_asset {
+                                Ok(model_asset) => {
+                                    let object = MasterInstancedModelObject::new(&name, model_asset, texture_asset, shader_asset);
+                                    add_to_system_or_parent(lua, system, Box::new(object));
+                                },
+                                Err(err) => 
+                                    debugger::error(&format!("lua error: error when calling new_lod_master_instanced_model_object, failed to load a model asset!\nerr: {:?}", err)),
+                            }
+                        },
+                        Err(err) => 
+                            debugger::error(&format!("lua error: error when calling new_lod_master_instanced_model_object, failed to load a shader asset!\nerr: {:?}", err)),
+                    }
+                },
+                None => debugger::error("failed to call new_lod_master_instanced_model_object, system not found"),
+            }
+            
+            Ok(())
+        });
+
+        match new_lod_master_instanced_model_object {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("new_lod_master_instanced_model_object", func) {
+                    debugger::error(&format!("failed to add a function new_lod_master_instanced_model_object as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function new_lod_master_instanced_model_object in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
         let system_id_for_functions = system_id.clone();
         let new_instanced_model_object = lua.create_function_mut(move |lua, (name, instance): (String, String)| {
             let system_option = systems::get_system_mut_with_id(&system_id_for_functions);
@@ -612,144 +754,421 @@ pub fn add_lua_vm_to_list(system_id: String, lua: Lua) {
 
 
         let system_id_for_functions = system_id.clone();
-        let set_object_position = lua.create_function_mut(
-            move |_, (name, pos_x, pos_y, pos_z): (String, f32, f32, f32)| {
-                match managers::systems::get_system_mut_with_id(&system_id_for_functions) {
-                    Some(system) => {
-                        let object_option = system.find_object_mut(&name);
-                        match object_option {
-                            Some(object) => {
-                                object.set_position(Vec3::new(pos_x, pos_y, pos_z), true)
-                            }
-                            None => debugger::error(
-                                "failed to call set_object_position, object not found",
-                            ),
-                        }
-                    }
-                    None => debugger::error("failed to call set_object_position, system not found"),
-                }
+        let new_point_light = lua.create_function_mut(move |lua, (name, r, g, b, intensity, range, cast_shadows): (String, f32, f32, f32, f32, f32, Option<bool>)| {
+            let system_option = systems::get_system_mut_with_id(&system_id_for_functions);
+            match system_option {
+                Some(system) => {
+                    let mut object = PointLight::new(&name, Vec3::new(r, g, b), intensity, range);
+                    object.cast_shadows = cast_shadows.unwrap_or(false);
+                    add_to_system_or_parent(lua, system, Box::new(object));
+                },
+                None => debugger::error("failed to call new_point_light, system not found"),
+            }
 
-                Ok(())
-            },
-        );
+            Ok(())
+        });
 
-        match set_object_position {
+        match new_point_light {
             Ok(func) => {
-                if let Err(err) = lua.globals().set("set_object_position", func) {
-                    debugger::error(&format!("failed to add a function set_object_position as a lua global in system {}\nerror: {}", system_id, err));
+                if let Err(err) = lua.globals().set("new_point_light", func) {
+                    debugger::error(&format!("failed to add a function new_point_light as a lua global in system {}\nerror: {}", system_id, err));
                 }
             }
             Err(err) => debugger::error(&format!(
-                "failed to create a function set_object_position in system {}\nerror: {}",
+                "failed to create a function new_point_light in system {}\nerror: {}",
                 system_id, err
             )),
         }
 
+
+
         let system_id_for_functions = system_id.clone();
-        let set_object_rotation = lua.create_function_mut(
-            move |_, (name, rot_x, rot_y, rot_z): (String, f32, f32, f32)| {
-                match managers::systems::get_system_mut_with_id(&system_id_for_functions) {
-                    Some(system) => {
-                        let object_option = system.find_object_mut(&name);
-                        match object_option {
-                            Some(object) => {
-                                object.set_rotation(Vec3::new(rot_x, rot_y, rot_z), true)
-                            }
-                            None => debugger::error(
-                                "failed to call set_object_rotation, object not found",
-                            ),
-                        }
-                    }
-                    None => debugger::error("failed to call set_object_rotation, system not found"),
-                }
+        let new_camera = lua.create_function_mut(move |lua, (name, fov, render_target_width, render_target_height): (String, Option<f32>, Option<u32>, Option<u32>)| {
+            let system_option = systems::get_system_mut_with_id(&system_id_for_functions);
+            match system_option {
+                Some(system) => {
+                    let target = match (render_target_width, render_target_height) {
+                        (Some(width), Some(height)) => CameraTarget::Texture { width, height },
+                        _ => CameraTarget::Screen(Viewport::FULL),
+                    };
+                    let object = Camera::new(&name, Projection::Perspective { fov: fov.unwrap_or(60.0) }, target);
+                    add_to_system_or_parent(lua, system, Box::new(object));
+                },
+                None => debugger::error("failed to call new_camera, system not found"),
+            }
 
-                Ok(())
-            },
-        );
+            Ok(())
+        });
 
-        match set_object_rotation {
+        match new_camera {
             Ok(func) => {
-                if let Err(err) = lua.globals().set("set_object_rotation", func) {
-                    debugger::error(&format!("failed to add a function set_object_rotation as a lua global in system {}\nerror: {}", system_id, err));
+                if let Err(err) = lua.globals().set("new_camera", func) {
+                    debugger::error(&format!("failed to add a function new_camera as a lua global in system {}\nerror: {}", system_id, err));
                 }
             }
             Err(err) => debugger::error(&format!(
-                "failed to create a function set_object_rotation in system {}\nerror: {}",
+                "failed to create a function new_camera in system {}\nerror: {}",
                 system_id, err
             )),
         }
 
+
+
         let system_id_for_functions = system_id.clone();
-        let set_object_scale = lua.create_function_mut(
-            move |_, (name, sc_x, sc_y, sc_z): (String, f32, f32, f32)| {
-                match managers::systems::get_system_mut_with_id(&system_id_for_functions) {
-                    Some(system) => {
-                        let object_option = system.find_object_mut(&name);
-                        match object_option {
-                            Some(object) => object.set_scale(Vec3::new(sc_x, sc_y, sc_z)),
-                            None => {
-                                debugger::error("failed to call set_object_scale, object not found")
+        let new_particle_emitter_object = lua.create_function_mut(move |lua, (name, definition_path): (String, Option<String>)| {
+            let system_option = systems::get_system_mut_with_id(&system_id_for_functions);
+            match system_option {
+                Some(system) => {
+                    let object = match definition_path {
+                        Some(definition_path) => match ParticleEmitter::from_file(&name, &definition_path) {
+                            Ok(object) => object,
+                            Err(err) => {
+                                debugger::error(&format!("lua error: error when calling new_particle_emitter_object, failed to load emitter definition!\nerr: {:?}", err));
+                                return Ok(());
                             }
-                        }
-                    }
-                    None => debugger::error("failed to call set_object_scale, system not found"),
+                        },
+                        None => ParticleEmitter::new(&name, ParticleEmitterSettings::default()),
+                    };
+                    add_to_system_or_parent(lua, system, Box::new(object));
+                },
+                None => debugger::error("failed to call new_particle_emitter_object, system not found"),
+            }
+
+            Ok(())
+        });
+
+        match new_particle_emitter_object {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("new_particle_emitter_object", func) {
+                    debugger::error(&format!("failed to add a function new_particle_emitter_object as a lua global in system {}\nerror: {}", system_id, err));
                 }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function new_particle_emitter_object in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
 
-                Ok(())
-            },
-        );
+        let system_id_for_functions = system_id.clone();
+        let new_terrain_object = lua.create_function_mut(move |lua, (name, heightmap_path, size_x, size_z, height): (String, String, f32, f32, f32)| {
+            let system_option = systems::get_system_mut_with_id(&system_id_for_functions);
+            match system_option {
+                Some(system) => match Terrain::from_heightmap(&name, &heightmap_path, Vec2::new(size_x, size_z), height) {
+                    Ok(object) => add_to_system_or_parent(lua, system, Box::new(object)),
+                    Err(err) => debugger::error(&format!("lua error: error when calling new_terrain_object, failed to load heightmap!\nerr: {:?}", err)),
+                },
+                None => debugger::error("failed to call new_terrain_object, system not found"),
+            }
 
-        match set_object_scale {
+            Ok(())
+        });
+
+        match new_terrain_object {
             Ok(func) => {
-                if let Err(err) = lua.globals().set("set_object_scale", func) {
-                    debugger::error(&format!("failed to add a function set_object_scale as a lua global in system {}\nerror: {}", system_id, err));
+                if let Err(err) = lua.globals().set("new_terrain_object", func) {
+                    debugger::error(&format!("failed to add a function new_terrain_object as a lua global in system {}\nerror: {}", system_id, err));
                 }
             }
             Err(err) => debugger::error(&format!(
-                "failed to create a function set_object_scale in system {}\nerror: {}",
+                "failed to create a function new_terrain_object in system {}\nerror: {}",
                 system_id, err
             )),
         }
 
         let system_id_for_functions = system_id.clone();
-        let get_object_position = lua.create_function_mut(move |_, name: String| {
-            match managers::systems::get_system_mut_with_id(&system_id_for_functions) {
+        let new_heightmap_model_object = lua.create_function_mut(
+            move |lua, (name, heightmap_path, size_x, size_z, height, step, texture_asset_path):
+            (String, String, f32, f32, f32, Option<usize>, Option<String>)| {
+            let system_option = systems::get_system_mut_with_id(&system_id_for_functions);
+            match system_option {
                 Some(system) => {
-                    let object_option = system.find_object_mut(&name);
-                    match object_option {
-                        Some(object) => {
-                            return Ok(vec![
-                                object.global_transform().position.x,
-                                object.global_transform().position.y,
-                                object.global_transform().position.z,
-                            ])
-                        }
-                        None => {
-                            debugger::error("failed to call get_object_position, object not found")
-                        }
+                    let texture_asset = match texture_asset_path {
+                        Some(path) => match TextureAsset::from_file(&path) {
+                            Ok(asset) => Some(asset),
+                            Err(err) => {
+                                debugger::warn(&format!("lua warning: error when calling new_heightmap_model_object, failed to load texture asset!\nerr: {:?}", err));
+                                None
+                            },
+                        },
+                        None => None,
+                    };
+                    match ModelAsset::from_heightmap(&heightmap_path, Vec2::new(size_x, size_z), height, step.unwrap_or(1)) {
+                        Ok(model_asset) => match ShaderAsset::load_default_shader() {
+                            Ok(shader_asset) => {
+                                let object = ModelObject::new(&name, model_asset, texture_asset, shader_asset);
+                                add_to_system_or_parent(lua, system, Box::new(object));
+                            },
+                            Err(err) =>
+                                debugger::error(&format!("lua error: error when calling new_heightmap_model_object, failed to load a shader asset!\nerr: {:?}", err)),
+                        },
+                        Err(err) =>
+                            debugger::error(&format!("lua error: error when calling new_heightmap_model_object, failed to load heightmap!\nerr: {:?}", err)),
                     }
-                }
-                None => debugger::error("failed to call get_object_position, system not found"),
+                },
+                None => debugger::error("failed to call new_heightmap_model_object, system not found"),
             }
 
-            Ok(vec![])
+            Ok(())
         });
 
-        match get_object_position {
+        match new_heightmap_model_object {
             Ok(func) => {
-                if let Err(err) = lua.globals().set("get_object_position", func) {
-                    debugger::error(&format!("failed to add a function get_object_position as a lua global in system {}\nerror: {}", system_id, err));
+                if let Err(err) = lua.globals().set("new_heightmap_model_object", func) {
+                    debugger::error(&format!("failed to add a function new_heightmap_model_object as a lua global in system {}\nerror: {}", system_id, err));
                 }
             }
             Err(err) => debugger::error(&format!(
-                "failed to create a function get_object_position in system {}\nerror: {}",
+                "failed to create a function new_heightmap_model_object in system {}\nerror: {}",
                 system_id, err
             )),
         }
 
         let system_id_for_functions = system_id.clone();
-        let get_object_rotation = lua.create_function_mut(move |_, name: String| {
-            match managers::systems::get_system_with_id(&system_id_for_functions) {
+        let new_generated_terrain_object = lua.create_function_mut(move |lua, (name, resolution, size_x, size_z, height, seed): (String, usize, f32, f32, f32, Option<u32>)| {
+            let system_option = systems::get_system_mut_with_id(&system_id_for_functions);
+            match system_option {
+                Some(system) => {
+                    let object = Terrain::generated(&name, resolution, Vec2::new(size_x, size_z), height, seed.unwrap_or(0));
+                    add_to_system_or_parent(lua, system, Box::new(object));
+                },
+                None => debugger::error("failed to call new_generated_terrain_object, system not found"),
+            }
+
+            Ok(())
+        });
+
+        match new_generated_terrain_object {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("new_generated_terrain_object", func) {
+                    debugger::error(&format!("failed to add a function new_generated_terrain_object as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function new_generated_terrain_object in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let system_id_for_functions = system_id.clone();
+        let new_sprite_object = lua.create_function_mut(move |lua, (name, texture_path, width, height): (String, Option<String>, f32, f32)| {
+            let system_option = systems::get_system_mut_with_id(&system_id_for_functions);
+            match system_option {
+                Some(system) => {
+                    let object = Sprite::new(&name, texture_path.as_deref(), Vec2::new(width, height));
+                    add_to_system_or_parent(lua, system, Box::new(object));
+                },
+                None => debugger::error("failed to call new_sprite_object, system not found"),
+            }
+
+            Ok(())
+        });
+
+        match new_sprite_object {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("new_sprite_object", func) {
+                    debugger::error(&format!("failed to add a function new_sprite_object as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function new_sprite_object in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let system_id_for_functions = system_id.clone();
+        let new_text_label_object = lua.create_function_mut(move |lua, (name, text, height): (String, String, f32)| {
+            let system_option = systems::get_system_mut_with_id(&system_id_for_functions);
+            match system_option {
+                Some(system) => {
+                    let object = Sprite::text_label(&name, &text, height);
+                    add_to_system_or_parent(lua, system, Box::new(object));
+                },
+                None => debugger::error("failed to call new_text_label_object, system not found"),
+            }
+
+            Ok(())
+        });
+
+        match new_text_label_object {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("new_text_label_object", func) {
+                    debugger::error(&format!("failed to add a function new_text_label_object as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function new_text_label_object in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let system_id_for_functions = system_id.clone();
+        let new_spot_light = lua.create_function_mut(move |lua, (name, r, g, b, intensity, range, inner_angle, outer_angle, cast_shadows): (String, f32, f32, f32, f32, f32, f32, f32, Option<bool>)| {
+            let system_option = systems::get_system_mut_with_id(&system_id_for_functions);
+            match system_option {
+                Some(system) => {
+                    let mut object = SpotLight::new(&name, Vec3::new(r, g, b), intensity, range, inner_angle, outer_angle);
+                    object.cast_shadows = cast_shadows.unwrap_or(false);
+                    add_to_system_or_parent(lua, system, Box::new(object));
+                },
+                None => debugger::error("failed to call new_spot_light, system not found"),
+            }
+
+            Ok(())
+        });
+
+        match new_spot_light {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("new_spot_light", func) {
+                    debugger::error(&format!("failed to add a function new_spot_light as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function new_spot_light in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+
+
+        let system_id_for_functions = system_id.clone();
+        let set_object_position = lua.create_function_mut(
+            move |_, (name, pos_x, pos_y, pos_z): (String, f32, f32, f32)| {
+                match managers::systems::get_system_mut_with_id(&system_id_for_functions) {
+                    Some(system) => {
+                        let object_option = system.find_object_mut(&name);
+                        match object_option {
+                            Some(object) => {
+                                object.set_position(Vec3::new(pos_x, pos_y, pos_z), true)
+                            }
+                            None => debugger::error(
+                                "failed to call set_object_position, object not found",
+                            ),
+                        }
+                    }
+                    None => debugger::error("failed to call set_object_position, system not found"),
+                }
+
+                Ok(())
+            },
+        );
+
+        match set_object_position {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("set_object_position", func) {
+                    debugger::error(&format!("failed to add a function set_object_position as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function set_object_position in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let system_id_for_functions = system_id.clone();
+        let set_object_rotation = lua.create_function_mut(
+            move |_, (name, rot_x, rot_y, rot_z): (String, f32, f32, f32)| {
+                match managers::systems::get_system_mut_with_id(&system_id_for_functions) {
+                    Some(system) => {
+                        let object_option = system.find_object_mut(&name);
+                        match object_option {
+                            Some(object) => {
+                                object.set_rotation(Vec3::new(rot_x, rot_y, rot_z), true)
+                            }
+                            None => debugger::error(
+                                "failed to call set_object_rotation, object not found",
+                            ),
+                        }
+                    }
+                    None => debugger::error("failed to call set_object_rotation, system not found"),
+                }
+
+                Ok(())
+            },
+        );
+
+        match set_object_rotation {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("set_object_rotation", func) {
+                    debugger::error(&format!("failed to add a function set_object_rotation as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function set_object_rotation in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let system_id_for_functions = system_id.clone();
+        let set_object_scale = lua.create_function_mut(
+            move |_, (name, sc_x, sc_y, sc_z): (String, f32, f32, f32)| {
+                match managers::systems::get_system_mut_with_id(&system_id_for_functions) {
+                    Some(system) => {
+                        let object_option = system.find_object_mut(&name);
+                        match object_option {
+                            Some(object) => object.set_scale(Vec3::new(sc_x, sc_y, sc_z)),
+                            None => {
+                                debugger::error("failed to call set_object_scale, object not found")
+                            }
+                        }
+                    }
+                    None => debugger::error("failed to call set_object_scale, system not found"),
+                }
+
+                Ok(())
+            },
+        );
+
+        match set_object_scale {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("set_object_scale", func) {
+                    debugger::error(&format!("failed to add a function set_object_scale as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function set_object_scale in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let system_id_for_functions = system_id.clone();
+        let get_object_position = lua.create_function_mut(move |_, name: String| {
+            match managers::systems::get_system_mut_with_id(&system_id_for_functions) {
+                Some(system) => {
+                    let object_option = system.find_object_mut(&name);
+                    match object_option {
+                        Some(object) => {
+                            return Ok(vec![
+                                object.global_transform().position.x,
+                                object.global_transform().position.y,
+                                object.global_transform().position.z,
+                            ])
+                        }
+                        None => {
+                            debugger::error("failed to call get_object_position, object not found")
+                        }
+                    }
+                }
+                None => debugger::error("failed to call get_object_position, system not found"),
+            }
+
+            Ok(vec![])
+        });
+
+        match get_object_position {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("get_object_position", func) {
+                    debugger::error(&format!("failed to add a function get_object_position as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function get_object_position in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let system_id_for_functions = system_id.clone();
+        let get_object_rotation = lua.create_function_mut(move |_, name: String| {
+            match managers::systems::get_system_with_id(&system_id_for_functions) {
                 Some(system) => {
                     let object_option = system.find_object(&name);
                     match object_option {
@@ -864,253 +1283,789 @@ pub fn add_lua_vm_to_list(system_id: String, lua: Lua) {
                             },
                         };
 
-                    } else if let networking::NetworkingMode::Client(_) = networking::get_current_networking_mode() {
-                        dbg!(networking::get_id());
-                        let message = Message::new_from_client(MessageContents::Custom(contents), system_id_for_functions.clone(), message_id);
-                        let _ = system.send_message(reliability, message);
-                    }
+                    } else if let networking::NetworkingMode::Client(_) = networking::get_current_networking_mode() {
+                        dbg!(networking::get_id());
+                        let message = Message::new_from_client(MessageContents::Custom(contents), system_id_for_functions.clone(), message_id);
+                        let _ = system.send_message(reliability, message);
+                    }
+                }
+                None => debugger::error("failed to call send_custom_message, system not found"),
+            }
+
+            Ok(())
+        });
+
+        match send_custom_message {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("send_custom_message", func) {
+                    debugger::error(&format!("failed to add a function send_custom_message as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function send_custom_message in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let system_id_for_functions = system_id.clone();
+        let send_sync_object_message = lua.create_function_mut(move 
+            |_, (is_reliable, message_id, object_name, pos, rot, scale, receiver, client_id): 
+            (bool, String, String, [f32; 3], [f32; 3], [f32; 3], Option<String>, Option<u64>)| {
+            let contents = MessageContents::SyncObject(SyncObjectMessage {
+                object_name,
+                transform: Transform {
+                    position: pos.into(),
+                    rotation: rot.into(),
+                    scale: scale.into()
+                },
+            });
+            match managers::systems::get_system_mut_with_id(&system_id_for_functions) {
+                Some(system) => {
+                    let reliability = match is_reliable {
+                        true => MessageReliability::Reliable,
+                        false => MessageReliability::Unreliable,
+                    };
+                    if let networking::NetworkingMode::Server(_) = networking::get_current_networking_mode() {
+                        match receiver {
+                            Some(receiver) => {
+                                let receiver = match receiver.as_str() {
+                                    "Everybody" => MessageReceiver::Everybody,
+                                    "OneClient" => {
+                                        match client_id {
+                                            Some(id) => MessageReceiver::OneClient(id),
+                                            None => {
+                                                debugger::error("lua send_sync_object_message error: message receiver set to 'Client', but client id is 0");
+                                                MessageReceiver::OneClient(0)
+                                            },
+                                        }
+                                    }, 
+                                    "EverybodyExcept" => {
+                                        match client_id {
+                                            Some(id) => MessageReceiver::EverybodyExcept(id),
+                                            None => {
+                                                debugger::error("lua send_sync_object_message error: message receiver set to 'EverybodyExcept', but client id is 0");
+                                                MessageReceiver::OneClient(0)
+                                            },
+                                        }
+                                    },
+                                    _ => {
+                                        debugger::error("lua send_sync_object_message error: receiver arg is wrong! Possible values: 'Everybody', 'OneClient', 'EverybodyExcept'");
+                                        MessageReceiver::OneClient(0)
+                                    },
+                                };
+                                let message = Message::new_from_server(receiver, contents, system_id_for_functions.clone(), message_id);
+                                let _ = system.send_message(reliability, message);
+                            },
+                            None => {
+                                debugger::error("lua send_sync_object_message error: receiver arg is nil! Possible values: 'Everybody', 'OneClient', 'EverybodyExcept'");
+                            },
+                        };
+
+                    } else if let networking::NetworkingMode::Client(_) = networking::get_current_networking_mode() {
+                        let message = Message::new_from_client(contents, system_id_for_functions.clone(), message_id);
+                        let _ = system.send_message(reliability, message);
+                    }
+                }
+                None => debugger::error("failed to call send_sync_object_message, system not found"),
+            }
+
+            Ok(())
+        });
+
+        match send_sync_object_message {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("send_sync_object_message", func) {
+                    debugger::error(&format!("failed to add a function send_sync_object_message as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function send_sync_object_message in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+
+        
+
+/*
+
+        match send_sync_object_message_to_everybody {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("send_sync_object_message_to_everybody", func) {
+                    debugger::error(&format!("failed to add a function send_sync_object_message_to_everybody as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function send_sync_object_message_to_everybody in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let system_id_for_functions = system_id.clone();
+        let send_sync_object_message_to_one_client = lua.create_function_mut(
+            move |_, (client_id, is_reliable, message_id, object_name, pos, rot, scale): (u64, bool, String, String, [f32; 3], [f32; 3], [f32; 3])| {
+            match managers::systems::get_system_mut_with_id(&system_id_for_functions) {
+                Some(system) => {
+                    let reliability = match is_reliable {
+                        true => MessageReliability::Reliable,
+                        false => MessageReliability::Unreliable,
+                    };
+                    let message = Message {
+                        receiver: MessageReceiver::OneClient(client_id),
+                        system_id: system.system_id().into(),
+                        message_id,
+                        contents: MessageContents::SyncObject(SyncObjectMessage {
+                            object_name,
+                            transform: Transform {
+                                position: pos.into(),
+                                rotation: rot.into(),
+                                scale: scale.into()
+                            },
+                        }),
+                    };
+
+                    let _ = system.send_message(reliability, message);
+                },
+                None => debugger::error("failed to call send_sync_object_message_to_one_client, system not found"),
+            }
+
+            Ok(())
+        });
+
+        match send_sync_object_message_to_one_client {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("send_sync_object_message_to_one_client", func) {
+                    debugger::error(&format!("failed to add a function send_sync_object_message_to_one_client as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function send_sync_object_message_to_one_client in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let system_id_for_functions = system_id.clone();
+        let send_sync_object_message_to_everybody_except = lua.create_function_mut(
+            move |_, (client_id, is_reliable, message_id, object_name, pos, rot, scale): (u64, bool, String, String, [f32; 3], [f32; 3], [f32; 3])| {
+            match managers::systems::get_system_mut_with_id(&system_id_for_functions) {
+                Some(system) => {
+                    let reliability = match is_reliable {
+                        true => MessageReliability::Reliable,
+                        false => MessageReliability::Unreliable,
+                    };
+                    let message = Message {
+                        receiver: MessageReceiver::EverybodyExcept(client_id),
+                        system_id: system.system_id().into(),
+                        message_id,
+                        contents: MessageContents::SyncObject(SyncObjectMessage {
+                            object_name,
+                            transform: Transform {
+                                position: pos.into(),
+                                rotation: rot.into(),
+                                scale: scale.into()
+                            },
+                        }),
+                    };
+
+                    let _ = system.send_message(reliability, message);
+                }
+                None => debugger::error("failed to call send_sync_object_message_to_everybody_except, system not found"),
+            }
+
+            Ok(())
+        });
+
+        match send_sync_object_message_to_everybody_except {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("send_sync_object_message_to_everybody_except", func) {
+                    debugger::error(&format!("failed to add a function send_sync_object_message_to_everybody_except as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function send_sync_object_message_to_everybody_except in system {}\nerror: {}",
+                system_id, err
+            )),
+        }*/
+
+        //let system_id_for_functions = system_id.clone();
+        let get_network_events = lua.create_function_mut(
+            move |_, _: ()| {
+                let mut events: Vec<HashMap<&str, String>> = Vec::new();
+                for ev in networking::get_network_events() {
+                    match ev {
+                        networking::NetworkEvent::ClientConnected(id) => {
+                            let mut ev = HashMap::new();
+                            ev.insert("type", "ClientConnected".into());
+                            ev.insert("id", id.to_string());
+                            events.push(ev);
+                        },
+                        networking::NetworkEvent::ClientDisconnected(id, reason) => {
+                            let mut ev = HashMap::new();
+                            ev.insert("type", "ClientDisconnected".into());
+                            ev.insert("id", id.to_string());
+                            ev.insert("reason", reason.clone());
+                            events.push(ev);
+                        },
+                        networking::NetworkEvent::ConnectedSuccessfully => {
+                            let mut ev = HashMap::new();
+                            ev.insert("type", "ConnectedSuccessfully".into());
+                            events.push(ev);
+                        },
+                        networking::NetworkEvent::Disconnected(_) => {
+                            let mut ev = HashMap::new();
+                            ev.insert("type", "Disconnected".into());
+                            events.push(ev);
+                        },
+                    }
+                }
+                Ok(events)
+            }
+        );
+
+        match get_network_events {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("get_network_events", func) {
+                    debugger::error(&format!("failed to add a function get_network_events as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function get_network_events in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let set_shadow_settings = lua.create_function_mut(
+            move |_, (cascades_resolutions, shadow_distance, split_lambda, blend_fraction): (Vec<u32>, f32, f32, f32)| {
+                render::set_shadow_settings(ShadowSettings {
+                    cascades_resolutions,
+                    shadow_distance,
+                    split_lambda,
+                    blend_fraction,
+                });
+                Ok(())
+            }
+        );
+
+        match set_shadow_settings {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("set_shadow_settings", func) {
+                    debugger::error(&format!("failed to add a function set_shadow_settings as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function set_shadow_settings in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let set_post_process_pass = lua.create_function_mut(
+            move |_, (name, enabled): (String, bool)| {
+                if !post_process::set_pass_enabled(&name, enabled) {
+                    debugger::error(&format!("set_post_process_pass error: no post process pass named {}", name));
+                }
+                Ok(())
+            }
+        );
+
+        match set_post_process_pass {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("set_post_process_pass", func) {
+                    debugger::error(&format!("failed to add a function set_post_process_pass as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function set_post_process_pass in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let set_exposure = lua.create_function_mut(
+            move |_, exposure: f32| {
+                let mut settings = post_process::get_post_process_settings();
+                settings.exposure = exposure;
+                post_process::set_post_process_settings(settings);
+                Ok(())
+            }
+        );
+
+        match set_exposure {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("set_exposure", func) {
+                    debugger::error(&format!("failed to add a function set_exposure as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function set_exposure in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let set_bloom_settings = lua.create_function_mut(
+            move |_, (threshold, intensity): (f32, f32)| {
+                let mut settings = post_process::get_post_process_settings();
+                settings.bloom_threshold = threshold;
+                settings.bloom_intensity = intensity;
+                post_process::set_post_process_settings(settings);
+                Ok(())
+            }
+        );
+
+        match set_bloom_settings {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("set_bloom_settings", func) {
+                    debugger::error(&format!("failed to add a function set_bloom_settings as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function set_bloom_settings in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let set_color_grading_lut = lua.create_function_mut(
+            move |_, (lut_path, intensity): (String, Option<f32>)| {
+                let mut settings = post_process::get_post_process_settings();
+                settings.lut_path = lut_path;
+                settings.lut_intensity = intensity.unwrap_or(1.0);
+                settings.color_grading = true;
+                post_process::set_post_process_settings(settings);
+                Ok(())
+            }
+        );
+
+        match set_color_grading_lut {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("set_color_grading_lut", func) {
+                    debugger::error(&format!("failed to add a function set_color_grading_lut as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function set_color_grading_lut in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let add_post_process_pass = lua.create_function_mut(
+            move |_, (name, fragment_shader_path): (String, String)| {
+                match std::fs::read_to_string(get_full_asset_path(&fragment_shader_path)) {
+                    Ok(source) => post_process::add_custom_pass(&name, &source),
+                    Err(err) => debugger::error(&format!(
+                        "add_post_process_pass error: failed to read fragment shader {}\nerror: {}",
+                        fragment_shader_path, err
+                    )),
+                }
+                Ok(())
+            }
+        );
+
+        match add_post_process_pass {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("add_post_process_pass", func) {
+                    debugger::error(&format!("failed to add a function add_post_process_pass as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function add_post_process_pass in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let remove_post_process_pass = lua.create_function_mut(
+            move |_, name: String| {
+                post_process::remove_custom_pass(&name);
+                Ok(())
+            }
+        );
+
+        match remove_post_process_pass {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("remove_post_process_pass", func) {
+                    debugger::error(&format!("failed to add a function remove_post_process_pass as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function remove_post_process_pass in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let set_sun_color = lua.create_function_mut(
+            move |_, (r, g, b): (f32, f32, f32)| {
+                render::set_sun_color(Vec3::new(r, g, b));
+                Ok(())
+            }
+        );
+
+        match set_sun_color {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("set_sun_color", func) {
+                    debugger::error(&format!("failed to add a function set_sun_color as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function set_sun_color in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let set_ambient_color = lua.create_function_mut(
+            move |_, (r, g, b): (f32, f32, f32)| {
+                render::set_ambient_color(Vec3::new(r, g, b));
+                Ok(())
+            }
+        );
+
+        match set_ambient_color {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("set_ambient_color", func) {
+                    debugger::error(&format!("failed to add a function set_ambient_color as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function set_ambient_color in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let set_sky_color = lua.create_function_mut(
+            move |_, (r, g, b): (f32, f32, f32)| {
+                sky::set_sky(Sky::Color(Vec3::new(r, g, b)));
+                Ok(())
+            }
+        );
+
+        match set_sky_color {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("set_sky_color", func) {
+                    debugger::error(&format!("failed to add a function set_sky_color as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function set_sky_color in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let set_sky_gradient = lua.create_function_mut(
+            move |_, (zenith, horizon, ground, sun_color, sun_size): SkyGradientArgs| {
+                let default_gradient = GradientSky::default();
+                let color = |values: Vec<f32>, default: Vec3| match values.as_slice() {
+                    [r, g, b] => Vec3::new(*r, *g, *b),
+                    _ => {
+                        debugger::error("set_sky_gradient error: colours should be {r, g, b} tables");
+                        default
+                    }
+                };
+
+                sky::set_sky(Sky::Gradient(GradientSky {
+                    zenith_color: color(zenith, default_gradient.zenith_color),
+                    horizon_color: color(horizon, default_gradient.horizon_color),
+                    ground_color: color(ground, default_gradient.ground_color),
+                    sun_color: match sun_color {
+                        Some(sun_color) => color(sun_color, default_gradient.sun_color),
+                        None => default_gradient.sun_color,
+                    },
+                    sun_size: sun_size.unwrap_or(default_gradient.sun_size),
+                }));
+                Ok(())
+            }
+        );
+
+        match set_sky_gradient {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("set_sky_gradient", func) {
+                    debugger::error(&format!("failed to add a function set_sky_gradient as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function set_sky_gradient in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let set_skybox = lua.create_function_mut(
+            move |_, (faces, intensity): (Vec<String>, Option<f32>)| {
+                match <[String; 6]>::try_from(faces) {
+                    Ok(faces) => sky::set_sky(Sky::Skybox {
+                        faces,
+                        intensity: intensity.unwrap_or(1.0),
+                    }),
+                    Err(_) => debugger::error("set_skybox error: skybox needs 6 faces (+x, -x, +y, -y, +z, -z)"),
+                }
+                Ok(())
+            }
+        );
+
+        match set_skybox {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("set_skybox", func) {
+                    debugger::error(&format!("failed to add a function set_skybox as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function set_skybox in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let set_fog_linear = lua.create_function_mut(
+            move |_, (r, g, b, start, end): (f32, f32, f32, f32, f32)| {
+                sky::set_fog(Fog {
+                    mode: FogMode::Linear,
+                    color: Vec3::new(r, g, b),
+                    start,
+                    end,
+                    ..sky::get_fog()
+                });
+                Ok(())
+            }
+        );
+
+        match set_fog_linear {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("set_fog_linear", func) {
+                    debugger::error(&format!("failed to add a function set_fog_linear as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function set_fog_linear in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let set_fog_exponential = lua.create_function_mut(
+            move |_, (r, g, b, density): (f32, f32, f32, f32)| {
+                sky::set_fog(Fog {
+                    mode: FogMode::Exponential,
+                    color: Vec3::new(r, g, b),
+                    density,
+                    ..sky::get_fog()
+                });
+                Ok(())
+            }
+        );
+
+        match set_fog_exponential {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("set_fog_exponential", func) {
+                    debugger::error(&format!("failed to add a function set_fog_exponential as a lua global in system {}\nerror: {}", system_id, err));
                 }
-                None => debugger::error("failed to call send_custom_message, system not found"),
             }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function set_fog_exponential in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
 
-            Ok(())
-        });
+        let disable_fog = lua.create_function_mut(
+            move |_, ()| {
+                sky::set_fog(Fog {
+                    mode: FogMode::None,
+                    ..sky::get_fog()
+                });
+                Ok(())
+            }
+        );
 
-        match send_custom_message {
+        match disable_fog {
             Ok(func) => {
-                if let Err(err) = lua.globals().set("send_custom_message", func) {
-                    debugger::error(&format!("failed to add a function send_custom_message as a lua global in system {}\nerror: {}", system_id, err));
+                if let Err(err) = lua.globals().set("disable_fog", func) {
+                    debugger::error(&format!("failed to add a function disable_fog as a lua global in system {}\nerror: {}", system_id, err));
                 }
             }
             Err(err) => debugger::error(&format!(
-                "failed to create a function send_custom_message in system {}\nerror: {}",
+                "failed to create a function disable_fog in system {}\nerror: {}",
                 system_id, err
             )),
         }
 
-        let system_id_for_functions = system_id.clone();
-        let send_sync_object_message = lua.create_function_mut(move 
-            |_, (is_reliable, message_id, object_name, pos, rot, scale, receiver, client_id): 
-            (bool, String, String, [f32; 3], [f32; 3], [f32; 3], Option<String>, Option<u64>)| {
-            let contents = MessageContents::SyncObject(SyncObjectMessage {
-                object_name,
-                transform: Transform {
-                    position: pos.into(),
-                    rotation: rot.into(),
-                    scale: scale.into()
-                },
-            });
-            match managers::systems::get_system_mut_with_id(&system_id_for_functions) {
-                Some(system) => {
-                    let reliability = match is_reliable {
-                        true => MessageReliability::Reliable,
-                        false => MessageReliability::Unreliable,
-                    };
-                    if let networking::NetworkingMode::Server(_) = networking::get_current_networking_mode() {
-                        match receiver {
-                            Some(receiver) => {
-                                let receiver = match receiver.as_str() {
-                                    "Everybody" => MessageReceiver::Everybody,
-                                    "OneClient" => {
-                                        match client_id {
-                                            Some(id) => MessageReceiver::OneClient(id),
-                                            None => {
-                                                debugger::error("lua send_sync_object_message error: message receiver set to 'Client', but client id is 0");
-                                                MessageReceiver::OneClient(0)
-                                            },
-                                        }
-                                    }, 
-                                    "EverybodyExcept" => {
-                                        match client_id {
-                                            Some(id) => MessageReceiver::EverybodyExcept(id),
-                                            None => {
-                                                debugger::error("lua send_sync_object_message error: message receiver set to 'EverybodyExcept', but client id is 0");
-                                                MessageReceiver::OneClient(0)
-                                            },
-                                        }
-                                    },
-                                    _ => {
-                                        debugger::error("lua send_sync_object_message error: receiver arg is wrong! Possible values: 'Everybody', 'OneClient', 'EverybodyExcept'");
-                                        MessageReceiver::OneClient(0)
-                                    },
-                                };
-                                let message = Message::new_from_server(receiver, contents, system_id_for_functions.clone(), message_id);
-                                let _ = system.send_message(reliability, message);
-                            },
-                            None => {
-                                debugger::error("lua send_sync_object_message error: receiver arg is nil! Possible values: 'Everybody', 'OneClient', 'EverybodyExcept'");
-                            },
-                        };
+        let debug_line = lua.create_function_mut(
+            move |_, (start, end, color, depth_test, lifetime): DebugSegmentArgs| {
+                debug_draw::line(start.into(), end.into(), debug_style(color, depth_test, lifetime));
+                Ok(())
+            }
+        );
 
-                    } else if let networking::NetworkingMode::Client(_) = networking::get_current_networking_mode() {
-                        let message = Message::new_from_client(contents, system_id_for_functions.clone(), message_id);
-                        let _ = system.send_message(reliability, message);
-                    }
+        match debug_line {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("debug_line", func) {
+                    debugger::error(&format!("failed to add a function debug_line as a lua global in system {}\nerror: {}", system_id, err));
                 }
-                None => debugger::error("failed to call send_sync_object_message, system not found"),
             }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function debug_line in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
 
-            Ok(())
-        });
+        let debug_arrow = lua.create_function_mut(
+            move |_, (start, end, color, depth_test, lifetime): DebugSegmentArgs| {
+                debug_draw::arrow(start.into(), end.into(), debug_style(color, depth_test, lifetime));
+                Ok(())
+            }
+        );
 
-        match send_sync_object_message {
+        match debug_arrow {
             Ok(func) => {
-                if let Err(err) = lua.globals().set("send_sync_object_message", func) {
-                    debugger::error(&format!("failed to add a function send_sync_object_message as a lua global in system {}\nerror: {}", system_id, err));
+                if let Err(err) = lua.globals().set("debug_arrow", func) {
+                    debugger::error(&format!("failed to add a function debug_arrow as a lua global in system {}\nerror: {}", system_id, err));
                 }
             }
             Err(err) => debugger::error(&format!(
-                "failed to create a function send_sync_object_message in system {}\nerror: {}",
+                "failed to create a function debug_arrow in system {}\nerror: {}",
                 system_id, err
             )),
         }
 
+        let debug_box = lua.create_function_mut(
+            move |_, (min, max, color, depth_test, lifetime): DebugSegmentArgs| {
+                debug_draw::aabb(&Aabb { min: min.into(), max: max.into() }, debug_style(color, depth_test, lifetime));
+                Ok(())
+            }
+        );
 
-        
+        match debug_box {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("debug_box", func) {
+                    debugger::error(&format!("failed to add a function debug_box as a lua global in system {}\nerror: {}", system_id, err));
+                }
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function debug_box in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
 
-/*
+        let debug_obb = lua.create_function_mut(
+            move |_, (center, half_extents, rotation, color, depth_test, lifetime): DebugObbArgs| {
+                debug_draw::obb(center.into(), half_extents.into(), rotation.into(), debug_style(color, depth_test, lifetime));
+                Ok(())
+            }
+        );
 
-        match send_sync_object_message_to_everybody {
+        match debug_obb {
             Ok(func) => {
-                if let Err(err) = lua.globals().set("send_sync_object_message_to_everybody", func) {
-                    debugger::error(&format!("failed to add a function send_sync_object_message_to_everybody as a lua global in system {}\nerror: {}", system_id, err));
+                if let Err(err) = lua.globals().set("debug_obb", func) {
+                    debugger::error(&format!("failed to add a function debug_obb as a lua global in system {}\nerror: {}", system_id, err));
                 }
             }
             Err(err) => debugger::error(&format!(
-                "failed to create a function send_sync_object_message_to_everybody in system {}\nerror: {}",
+                "failed to create a function debug_obb in system {}\nerror: {}",
                 system_id, err
             )),
         }
 
-        let system_id_for_functions = system_id.clone();
-        let send_sync_object_message_to_one_client = lua.create_function_mut(
-            move |_, (client_id, is_reliable, message_id, object_name, pos, rot, scale): (u64, bool, String, String, [f32; 3], [f32; 3], [f32; 3])| {
-            match managers::systems::get_system_mut_with_id(&system_id_for_functions) {
-                Some(system) => {
-                    let reliability = match is_reliable {
-                        true => MessageReliability::Reliable,
-                        false => MessageReliability::Unreliable,
-                    };
-                    let message = Message {
-                        receiver: MessageReceiver::OneClient(client_id),
-                        system_id: system.system_id().into(),
-                        message_id,
-                        contents: MessageContents::SyncObject(SyncObjectMessage {
-                            object_name,
-                            transform: Transform {
-                                position: pos.into(),
-                                rotation: rot.into(),
-                                scale: scale.into()
-                            },
-                        }),
-                    };
+        let debug_sphere = lua.create_function_mut(
+            move |_, (center, radius, color, depth_test, lifetime): DebugSphereArgs| {
+                debug_draw::sphere(center.into(), radius, debug_style(color, depth_test, lifetime));
+                Ok(())
+            }
+        );
 
-                    let _ = system.send_message(reliability, message);
-                },
-                None => debugger::error("failed to call send_sync_object_message_to_one_client, system not found"),
+        match debug_sphere {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("debug_sphere", func) {
+                    debugger::error(&format!("failed to add a function debug_sphere as a lua global in system {}\nerror: {}", system_id, err));
+                }
             }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function debug_sphere in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
 
-            Ok(())
-        });
+        let debug_capsule = lua.create_function_mut(
+            move |_, (start, end, radius, color, depth_test, lifetime): DebugCapsuleArgs| {
+                debug_draw::capsule(start.into(), end.into(), radius, debug_style(color, depth_test, lifetime));
+                Ok(())
+            }
+        );
 
-        match send_sync_object_message_to_one_client {
+        match debug_capsule {
             Ok(func) => {
-                if let Err(err) = lua.globals().set("send_sync_object_message_to_one_client", func) {
-                    debugger::error(&format!("failed to add a function send_sync_object_message_to_one_client as a lua global in system {}\nerror: {}", system_id, err));
+                if let Err(err) = lua.globals().set("debug_capsule", func) {
+                    debugger::error(&format!("failed to add a function debug_capsule as a lua global in system {}\nerror: {}", system_id, err));
                 }
             }
             Err(err) => debugger::error(&format!(
-                "failed to create a function send_sync_object_message_to_one_client in system {}\nerror: {}",
+                "failed to create a function debug_capsule in system {}\nerror: {}",
                 system_id, err
             )),
         }
 
-        let system_id_for_functions = system_id.clone();
-        let send_sync_object_message_to_everybody_except = lua.create_function_mut(
-            move |_, (client_id, is_reliable, message_id, object_name, pos, rot, scale): (u64, bool, String, String, [f32; 3], [f32; 3], [f32; 3])| {
-            match managers::systems::get_system_mut_with_id(&system_id_for_functions) {
-                Some(system) => {
-                    let reliability = match is_reliable {
-                        true => MessageReliability::Reliable,
-                        false => MessageReliability::Unreliable,
-                    };
-                    let message = Message {
-                        receiver: MessageReceiver::EverybodyExcept(client_id),
-                        system_id: system.system_id().into(),
-                        message_id,
-                        contents: MessageContents::SyncObject(SyncObjectMessage {
-                            object_name,
-                            transform: Transform {
-                                position: pos.into(),
-                                rotation: rot.into(),
-                                scale: scale.into()
-                            },
-                        }),
-                    };
+        let debug_grid = lua.create_function_mut(
+            move |_, (center, cell_size, cells, color, depth_test, lifetime): DebugGridArgs| {
+                debug_draw::grid(center.into(), cell_size, cells, debug_style(color, depth_test, lifetime));
+                Ok(())
+            }
+        );
 
-                    let _ = system.send_message(reliability, message);
+        match debug_grid {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("debug_grid", func) {
+                    debugger::error(&format!("failed to add a function debug_grid as a lua global in system {}\nerror: {}", system_id, err));
                 }
-                None => debugger::error("failed to call send_sync_object_message_to_everybody_except, system not found"),
             }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function debug_grid in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
 
-            Ok(())
-        });
+        let debug_path = lua.create_function_mut(
+            move |_, (points, color, depth_test, lifetime): DebugPathArgs| {
+                let points: Vec<Vec3> = points.into_iter().map(Vec3::from).collect();
+                debug_draw::path(&points, debug_style(color, depth_test, lifetime));
+                Ok(())
+            }
+        );
 
-        match send_sync_object_message_to_everybody_except {
+        match debug_path {
             Ok(func) => {
-                if let Err(err) = lua.globals().set("send_sync_object_message_to_everybody_except", func) {
-                    debugger::error(&format!("failed to add a function send_sync_object_message_to_everybody_except as a lua global in system {}\nerror: {}", system_id, err));
+                if let Err(err) = lua.globals().set("debug_path", func) {
+                    debugger::error(&format!("failed to add a function debug_path as a lua global in system {}\nerror: {}", system_id, err));
                 }
             }
             Err(err) => debugger::error(&format!(
-                "failed to create a function send_sync_object_message_to_everybody_except in system {}\nerror: {}",
+                "failed to create a function debug_path in system {}\nerror: {}",
                 system_id, err
             )),
-        }*/
+        }
 
-        //let system_id_for_functions = system_id.clone();
-        let get_network_events = lua.create_function_mut(
-            move |_, _: ()| {
-                let mut events: Vec<HashMap<&str, String>> = Vec::new();
-                for ev in networking::get_network_events() {
-                    match ev {
-                        networking::NetworkEvent::ClientConnected(id) => {
-                            let mut ev = HashMap::new();
-                            ev.insert("type", "ClientConnected".into());
-                            ev.insert("id", id.to_string());
-                            events.push(ev);
-                        },
-                        networking::NetworkEvent::ClientDisconnected(id, reason) => {
-                            let mut ev = HashMap::new();
-                            ev.insert("type", "ClientDisconnected".into());
-                            ev.insert("id", id.to_string());
-                            ev.insert("reason", reason.clone());
-                            events.push(ev);
-                        },
-                        networking::NetworkEvent::ConnectedSuccessfully => {
-                            let mut ev = HashMap::new();
-                            ev.insert("type", "ConnectedSuccessfully".into());
-                            events.push(ev);
-                        },
-                        networking::NetworkEvent::Disconnected(_) => {
-                            let mut ev = HashMap::new();
-                            ev.insert("type", "Disconnected".into());
-                            events.push(ev);
-                        },
-                    }
+        let debug_text = lua.create_function_mut(
+            move |_, (position, text, color, depth_test, lifetime): DebugTextArgs| {
+                debug_draw::text(position.into(), &text, debug_style(color, depth_test, lifetime));
+                Ok(())
+            }
+        );
+
+        match debug_text {
+            Ok(func) => {
+                if let Err(err) = lua.globals().set("debug_text", func) {
+                    debugger::error(&format!("failed to add a function debug_text as a lua global in system {}\nerror: {}", system_id, err));
                 }
-                Ok(events)
+            }
+            Err(err) => debugger::error(&format!(
+                "failed to create a function debug_text in system {}\nerror: {}",
+                system_id, err
+            )),
+        }
+
+        let debug_clear = lua.create_function_mut(
+            move |_, (): ()| {
+                debug_draw::clear();
+                Ok(())
             }
         );
 
-        match get_network_events {
+        match debug_clear {
             Ok(func) => {
-                if let Err(err) = lua.globals().set("get_network_events", func) {
-                    debugger::error(&format!("failed to add a function get_network_events as a lua global in system {}\nerror: {}", system_id, err));
+                if let Err(err) = lua.globals().set("debug_clear", func) {
+                    debugger::error(&format!("failed to add a function debug_clear as a lua global in system {}\nerror: {}", system_id, err));
                 }
             }
             Err(err) => debugger::error(&format!(
-                "failed to create a function get_network_events in system {}\nerror: {}",
+                "failed to create a function debug_clear in system {}\nerror: {}",
                 system_id, err
             )),
         }
@@ -1508,7 +2463,7 @@ pub fn add_lua_vm_to_list(system_id: String, lua: Lua) {
                     let shader_asset = ShaderAsset::load_from_file(shader_asset_path);
                     match shader_asset {
                         Ok(shader_asset) => {
-                            let model_asset = ModelAsset::from_gltf(&model_asset_path);
+                            let model_asset = ModelAsset::from_file(&model_asset_path);
                             match model_asset {
                                 Ok(model_asset) => {
                                     for name in names {
@@ -1560,3 +2515,21 @@ fn add_to_system_or_parent(lua: &Lua, system: &mut Box<dyn System>, object: Box<
     }
     system.add_object(object);
 }
+
+// lua arguments of the debug drawing functions, each one ends with the optional style
+type DebugSegmentArgs = ([f32; 3], [f32; 3], Option<[f32; 4]>, Option<bool>, Option<f32>);
+type DebugObbArgs = ([f32; 3], [f32; 3], [f32; 3], Option<[f32; 4]>, Option<bool>, Option<f32>);
+type DebugSphereArgs = ([f32; 3], f32, Option<[f32; 4]>, Option<bool>, Option<f32>);
+type DebugCapsuleArgs = ([f32; 3], [f32; 3], f32, Option<[f32; 4]>, Option<bool>, Option<f32>);
+type DebugGridArgs = ([f32; 3], f32, u32, Option<[f32; 4]>, Option<bool>, Option<f32>);
+type DebugPathArgs = (Vec<[f32; 3]>, Option<[f32; 4]>, Option<bool>, Option<f32>);
+type DebugTextArgs = ([f32; 3], String, Option<[f32; 4]>, Option<bool>, Option<f32>);
+
+fn debug_style(color: Option<[f32; 4]>, depth_test: Option<bool>, lifetime: Option<f32>) -> DebugStyle {
+    let default_style = DebugStyle::default();
+    DebugStyle {
+        color: color.map(Vec4::from).unwrap_or(default_style.color),
+        depth_test: depth_test.unwrap_or(default_style.depth_test),
+        lifetime: lifetime.unwrap_or(default_style.lifetime),
+    }
+}
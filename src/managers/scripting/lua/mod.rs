@@ -1,11 +1,11 @@
 pub mod lua_functions;
 use crate::{
-    assets::model_asset::ModelAsset, framework::Framework, managers::{
-        assets, debugger, networking::{Message, MessageContents}, physics::{BodyColliderType, BodyType, CollisionGroups, RenderColliderType}, scripting::lua::lua_functions::add_lua_vm_to_list, systems::{self, CallList, SystemValue}
-    }, objects::{character_controller::CharacterController, model_object::ModelObject, ray::Ray, sound_emitter::SoundEmitter, trigger::Trigger}, systems::System
+    assets::{model_asset::ModelAsset, texture_asset::TextureAsset}, framework::Framework, managers::{
+        assets, debugger, networking::{Message, MessageContents}, physics::{BodyColliderType, BodyType, CollisionGroups, RenderColliderType}, render::{CameraTarget, InstanceData, LodMetric, LodSettings, Projection, Viewport}, scripting::lua::lua_functions::add_lua_vm_to_list, systems::{self, CallList, SystemValue}
+    }, objects::{animation::{AnimationBlendMode, AnimationEvent, AnimationEventCallback, BASE_LAYER}, animator::{Animator, AnimatorParameter}, camera::Camera, character_controller::CharacterController, instanced_model_object::InstancedModelObject, master_instanced_model_object::{InstanceId, MasterInstancedModelObject}, Transform, model_object::ModelObject, particle_emitter::ParticleEmitter, point_light::PointLight, ray::Ray, sound_emitter::SoundEmitter, spot_light::SpotLight, sprite::{Sprite, SpriteFacing, SpriteSize}, terrain::{Terrain, TerrainLayer}, trigger::Trigger}, systems::System
 };
 use crate::objects::Object;
-use glam::Vec3;
+use glam::{Vec2, Vec3, Vec4};
 use mlua::{Error, FromLua, Function, IntoLua, Lua, LuaOptions, StdLib, UserData};
 use once_cell::sync::Lazy;
 use std::{collections::HashMap, fs};
@@ -279,6 +279,15 @@ pub struct ObjectHandle {
     pub name: String,
 }
 
+/// Objects can be passed to methods as handles or by name.
+fn object_name_from_lua(value: &mlua::Value) -> Option<String> {
+    match value {
+        mlua::Value::String(name) => name.to_str().ok().map(|name| name.to_string()),
+        mlua::Value::UserData(data) => data.borrow::<ObjectHandle>().ok().map(|handle| handle.name.clone()),
+        _ => None,
+    }
+}
+
 impl UserData for ObjectHandle {
     fn add_fields<'lua, F: mlua::prelude::LuaUserDataFields<'lua, Self>>(fields: &mut F) {}
 
@@ -604,7 +613,7 @@ impl UserData for ObjectHandle {
         });
 
         // body_type = "None"/"Fixed"/""/"Ball"/"Cylinder"
-        // model_path - path to the GLTF model
+        // model_path - path to the GLTF or OBJ model
         // render_collider_type = "None"/"Cuboid"/"Capsule"/"Ball"/"Cylinder"
         // collider_size_x, collider_size_y, collider_size_z - works only for render collider, some of them may be ignored
         // mass
@@ -622,7 +631,7 @@ impl UserData for ObjectHandle {
             match systems::get_system_mut_with_id(&this.system_id) {
                 Some(system) => match system.find_object_mut(&this.name) {
                     Some(object) => {
-                        let model_asset = ModelAsset::from_gltf(&model_path);
+                        let model_asset = ModelAsset::from_file(&model_path);
                         match model_asset {
                             Ok(model_asset) => {
                                 let body_collider = Some(BodyColliderType::TriangleMesh(model_asset));
@@ -791,19 +800,20 @@ impl UserData for ObjectHandle {
             match systems::get_system_mut_with_id(&this.system_id) {
                 Some(system) => match system.find_object_mut(&this.name) {
                     Some(object) => {
-                        match object.downcast_mut::<ModelObject>() {
-                            Some(object) => {
-                                if let Err(err) = object.play_animation(&anim_name) {
-                                    debugger::error(
-                                        &format!("lua error(system {}): play_animation failed! error in ModelObject '{}': {:?}", 
-                                           this.system_id, this.name, err));
-                                }
-                            },
-                            None => {
-                                debugger::error(
-                                    &format!("lua error(system {}): play_animation failed in object: {}. this object is not ModelObject!", 
-                                        this.system_id, this.name));
-                            },
+                        let result = if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.play_animation(&anim_name)
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.play_animation(&anim_name)
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): play_animation failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!", 
+                                    this.system_id, this.name));
+                            return Ok(());
+                        };
+                        if let Err(err) = result {
+                            debugger::error(
+                                &format!("lua error(system {}): play_animation failed! error in object '{}': {:?}", 
+                                   this.system_id, this.name, err));
                         }
                     }
                     None => {
@@ -820,130 +830,139 @@ impl UserData for ObjectHandle {
             Ok(())
         });
 
-        methods.add_method("intersection_position", |_, this, _: ()| {
+        methods.add_method("cross_fade_animation", |_, this, (anim_name, duration, layer, looping): (String, f32, Option<String>, Option<bool>)| {
+            let layer = layer.unwrap_or(BASE_LAYER.into());
             match systems::get_system_mut_with_id(&this.system_id) {
                 Some(system) => match system.find_object_mut(&this.name) {
                     Some(object) => {
-                        return match object.downcast_mut::<Ray>() {
-                            Some(object) => {
-                                return match object.intersection_position() {
-                                    Some(pos) => Ok(Some([pos.x, pos.y, pos.z])),
-                                    None => Ok(None),
-                                }
-                            },
-                            None => {
-                                debugger::error(
-                                    &format!("lua error(system {}): intersection_position failed in object: {}. this object is not Ray!", 
-                                        this.system_id, this.name));
-                                Ok(None)
-                            },
+                        let result = if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.cross_fade(&anim_name, duration, &layer, looping.unwrap_or(object.is_looping()))
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.cross_fade(&anim_name, duration, &layer, looping.unwrap_or(object.is_looping()))
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): cross_fade_animation failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                            return Ok(());
+                        };
+                        if let Err(err) = result {
+                            debugger::error(
+                                &format!("lua error(system {}): cross_fade_animation failed! error in object '{}': {:?}",
+                                    this.system_id, this.name, err));
                         }
                     }
                     None => {
                         debugger::error(
-                            &format!("lua error: intersection_position failed! failed to get object {} in system {}", this.name, this.system_id));
+                            &format!("lua error: cross_fade_animation failed! failed to get object {} in system {}", this.name, this.system_id));
                     },
                 },
                 None => debugger::error(&format!(
-                        "lua error: intersection_position failed! failed to get system {} to find object {}",
+                        "lua error: cross_fade_animation failed! failed to get system {} to find object {}",
                         this.system_id, this.name
                 )),
             }
 
-            Ok(None)
+            Ok(())
         });
 
-        methods.add_method("is_intersecting", |_, this, _: ()| {
+        methods.add_method("blend_animation", |_, this, (anim_name, weight, fade_duration, layer, looping): (String, f32, Option<f32>, Option<String>, Option<bool>)| {
+            let layer = layer.unwrap_or(BASE_LAYER.into());
             match systems::get_system_mut_with_id(&this.system_id) {
                 Some(system) => match system.find_object_mut(&this.name) {
                     Some(object) => {
-                        return match object.downcast_mut::<Ray>() {
-                            Some(object) => {
-                                Ok(object.is_intersecting())
-                            },
-                            None => {
-                                match object.downcast_mut::<Trigger>() {
-                                    Some(object) => Ok(object.is_intersecting()),
-                                    None => {
-                                        debugger::error(
-                                            &format!("lua error(system {}): is_intersecting failed in object: {}. this object is neither Ray nor Trigger!",
-                                                this.system_id, this.name));
-                                        Ok(false)
-                                    },
-                                }
-                            },
+                        let result = if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.blend_animation(&anim_name, weight, fade_duration.unwrap_or(0.0), &layer, looping.unwrap_or(object.is_looping()))
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.blend_animation(&anim_name, weight, fade_duration.unwrap_or(0.0), &layer, looping.unwrap_or(object.is_looping()))
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): blend_animation failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                            return Ok(());
+                        };
+                        if let Err(err) = result {
+                            debugger::error(
+                                &format!("lua error(system {}): blend_animation failed! error in object '{}': {:?}",
+                                    this.system_id, this.name, err));
                         }
                     }
                     None => {
                         debugger::error(
-                            &format!("lua error: is_intersecting failed! failed to get object {} in system {}", this.name, this.system_id));
+                            &format!("lua error: blend_animation failed! failed to get object {} in system {}", this.name, this.system_id));
                     },
                 },
                 None => debugger::error(&format!(
-                        "lua error: is_intersecting failed! failed to get system {} to find object {}",
+                        "lua error: blend_animation failed! failed to get system {} to find object {}",
                         this.system_id, this.name
                 )),
             }
 
-            Ok(false)
+            Ok(())
         });
 
-        methods.add_method("is_intersecting_with_group", |_, this, group: String| {
+        methods.add_method("set_animation_weight", |_, this, (anim_name, weight, fade_duration, layer): (String, f32, Option<f32>, Option<String>)| {
+            let layer = layer.unwrap_or(BASE_LAYER.into());
             match systems::get_system_mut_with_id(&this.system_id) {
                 Some(system) => match system.find_object_mut(&this.name) {
                     Some(object) => {
-                        return match object.downcast_mut::<Trigger>() {
-                            Some(object) => {
-                                Ok(object.is_intersecting_with_group(group.into()))
-                            },
-                            None => {
-                                debugger::error(
-                                    &format!("lua error(system {}): is_intersecting_with_group failed in object: {}. this object is not Trigger!", 
-                                        this.system_id, this.name));
-                                Ok(false)
-                            },
+                        let result = if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.set_animation_weight(&anim_name, weight, fade_duration.unwrap_or(0.0), &layer)
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.set_animation_weight(&anim_name, weight, fade_duration.unwrap_or(0.0), &layer)
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): set_animation_weight failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                            return Ok(());
+                        };
+                        if let Err(err) = result {
+                            debugger::error(
+                                &format!("lua error(system {}): set_animation_weight failed! error in object '{}': {:?}",
+                                    this.system_id, this.name, err));
                         }
                     }
                     None => {
                         debugger::error(
-                            &format!("lua error: is_intersecting_with_group failed! failed to get object {} in system {}", this.name, this.system_id));
+                            &format!("lua error: set_animation_weight failed! failed to get object {} in system {}", this.name, this.system_id));
                     },
                 },
                 None => debugger::error(&format!(
-                        "lua error: is_intersecting_with_group failed! failed to get system {} to find object {}",
+                        "lua error: set_animation_weight failed! failed to get system {} to find object {}",
                         this.system_id, this.name
                 )),
             }
 
-            Ok(false)
+            Ok(())
         });
 
-        methods.add_method("set_looping", |_, this, should_loop: bool| {
+        methods.add_method("stop_animation", |_, this, (anim_name, fade_duration, layer): (Option<String>, Option<f32>, Option<String>)| {
+            let layer = layer.unwrap_or(BASE_LAYER.into());
             match systems::get_system_mut_with_id(&this.system_id) {
                 Some(system) => match system.find_object_mut(&this.name) {
                     Some(object) => {
-                        return match object.downcast_mut::<SoundEmitter>() {
-                            Some(object) => Ok(object.set_looping(should_loop)),
-                            None => {
-                                match object.downcast_mut::<ModelObject>() {
-                                    Some(object) => Ok(object.set_looping(should_loop)),
-                                    None => {
-                                        debugger::error(
-                                            &format!("lua error(system {}): set_looping failed in object: {}. this object is neiter SoundEmitter nor ModelObject!",
-                                            this.system_id, this.name));
-                                        Ok(())
-                                    },
-                                }
-                            },
+                        let result = if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.stop_animation(anim_name.as_deref(), fade_duration.unwrap_or(0.0), &layer)
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.stop_animation(anim_name.as_deref(), fade_duration.unwrap_or(0.0), &layer)
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): stop_animation failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                            return Ok(());
+                        };
+                        if let Err(err) = result {
+                            debugger::error(
+                                &format!("lua error(system {}): stop_animation failed! error in object '{}': {:?}",
+                                    this.system_id, this.name, err));
                         }
                     }
                     None => {
                         debugger::error(
-                            &format!("lua error: set_looping failed! failed to get object {} in system {}", this.name, this.system_id));
+                            &format!("lua error: stop_animation failed! failed to get object {} in system {}", this.name, this.system_id));
                     },
                 },
                 None => debugger::error(&format!(
-                        "lua error: set_looping failed! failed to get system {} to find object {}",
+                        "lua error: stop_animation failed! failed to get system {} to find object {}",
                         this.system_id, this.name
                 )),
             }
@@ -951,93 +970,228 @@ impl UserData for ObjectHandle {
             Ok(())
         });
 
-        methods.add_method("looping", |_, this, _: ()| {
+        methods.add_method("add_animation_layer", |_, this, (name, blend_mode, mask_joint): (String, String, Option<String>)| {
+            let blend_mode = match blend_mode.as_str() {
+                "override" => AnimationBlendMode::Override,
+                "additive" => AnimationBlendMode::Additive,
+                _ => {
+                    debugger::error(&format!("lua error(system {}): add_animation_layer failed! unknown blend mode '{}', use 'override' or 'additive'", this.system_id, blend_mode));
+                    return Ok(());
+                }
+            };
             match systems::get_system_mut_with_id(&this.system_id) {
                 Some(system) => match system.find_object_mut(&this.name) {
                     Some(object) => {
-                        return match object.downcast_mut::<SoundEmitter>() {
-                            Some(object) => Ok(object.is_looping()),
+                        let result = if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.add_animation_layer(&name, blend_mode, mask_joint.as_deref())
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.add_animation_layer(&name, blend_mode, mask_joint.as_deref())
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): add_animation_layer failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                            return Ok(());
+                        };
+                        if let Err(err) = result {
+                            debugger::error(
+                                &format!("lua error(system {}): add_animation_layer failed! error in object '{}': {:?}",
+                                    this.system_id, this.name, err));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: add_animation_layer failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: add_animation_layer failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("remove_animation_layer", |_, this, name: String| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        let result = if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.remove_animation_layer(&name)
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.remove_animation_layer(&name)
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): remove_animation_layer failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                            return Ok(());
+                        };
+                        if let Err(err) = result {
+                            debugger::error(
+                                &format!("lua error(system {}): remove_animation_layer failed! error in object '{}': {:?}",
+                                    this.system_id, this.name, err));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: remove_animation_layer failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: remove_animation_layer failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_animation_layer_weight", |_, this, (name, weight, fade_duration): (String, f32, Option<f32>)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        let result = if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.set_animation_layer_weight(&name, weight, fade_duration.unwrap_or(0.0))
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.set_animation_layer_weight(&name, weight, fade_duration.unwrap_or(0.0))
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): set_animation_layer_weight failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                            return Ok(());
+                        };
+                        if let Err(err) = result {
+                            debugger::error(
+                                &format!("lua error(system {}): set_animation_layer_weight failed! error in object '{}': {:?}",
+                                    this.system_id, this.name, err));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_animation_layer_weight failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_animation_layer_weight failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_animator", |_, this, path: Option<String>| {
+            let animator = match path {
+                Some(path) => match Animator::from_file(&path) {
+                    Ok(animator) => Some(animator),
+                    Err(err) => {
+                        debugger::error(&format!("lua error(system {}): set_animator failed! failed to load '{}': {:?}", this.system_id, path, err));
+                        return Ok(());
+                    }
+                },
+                None => None,
+            };
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<ModelObject>() {
+                            Some(object) => {
+                                object.set_animator(animator);
+                            },
                             None => {
-                                match object.downcast_mut::<ModelObject>() {
-                                    Some(object) => Ok(object.is_looping()),
-                                    None => {
-                                        debugger::error(
-                                            &format!("lua error(system {}): is_looping failed in object: {}. this object is neiter SoundEmitter nor ModelObject!",
-                                            this.system_id, this.name));
-                                        Ok(false)
-                                    },
-                                }
+                                debugger::error(
+                                    &format!("lua error(system {}): set_animator failed in object: {}. this object is not ModelObject!",
+                                        this.system_id, this.name));
                             },
                         }
                     }
                     None => {
                         debugger::error(
-                            &format!("lua error: is_looping failed! failed to get object {} in system {}", this.name, this.system_id));
+                            &format!("lua error: set_animator failed! failed to get object {} in system {}", this.name, this.system_id));
                     },
                 },
                 None => debugger::error(&format!(
-                        "lua error: is_looping failed! failed to get system {} to find object {}",
+                        "lua error: set_animator failed! failed to get system {} to find object {}",
                         this.system_id, this.name
                 )),
             }
 
-            Ok(false)
+            Ok(())
         });
 
-        methods.add_method("current_animation", |_, this, _: ()| {
+        methods.add_method("set_animator_float", |_, this, (name, value): (String, f32)| {
             match systems::get_system_mut_with_id(&this.system_id) {
                 Some(system) => match system.find_object_mut(&this.name) {
                     Some(object) => {
-                        return match object.downcast_mut::<ModelObject>() {
+                        match object.downcast_mut::<ModelObject>() {
                             Some(object) => {
-                                return match object.current_animation() {
-                                    Some(animation) => Ok(Some(animation.to_string())),
-                                    None => Ok(None),
+                                match object.animator_mut() {
+                                    Some(animator) => {
+                                        if let Err(err) = animator.set_float(&name, value) {
+                                            debugger::error(
+                                                &format!("lua error(system {}): set_animator_float failed! error in ModelObject '{}': {:?}",
+                                                    this.system_id, this.name, err));
+                                        }
+                                    },
+                                    None => debugger::error(
+                                        &format!("lua error(system {}): set_animator_float failed! ModelObject '{}' has no animator",
+                                            this.system_id, this.name)),
                                 }
                             },
                             None => {
                                 debugger::error(
-                                    &format!("lua error(system {}): current_animation failed in object: {}. this object is not ModelObject!",
-                                    this.system_id, this.name));
-                                Ok(None)
+                                    &format!("lua error(system {}): set_animator_float failed in object: {}. this object is not ModelObject!",
+                                        this.system_id, this.name));
                             },
                         }
                     }
                     None => {
                         debugger::error(
-                            &format!("lua error: current_animation failed! failed to get object {} in system {}", this.name, this.system_id));
+                            &format!("lua error: set_animator_float failed! failed to get object {} in system {}", this.name, this.system_id));
                     },
                 },
                 None => debugger::error(&format!(
-                        "lua error: current_animation failed! failed to get system {} to find object {}",
+                        "lua error: set_animator_float failed! failed to get system {} to find object {}",
                         this.system_id, this.name
                 )),
             }
 
-            Ok(None)
+            Ok(())
         });
 
-        methods.add_method("play_sound", |_, this, _: ()| {
+        methods.add_method("set_animator_bool", |_, this, (name, value): (String, bool)| {
             match systems::get_system_mut_with_id(&this.system_id) {
                 Some(system) => match system.find_object_mut(&this.name) {
                     Some(object) => {
-                        return match object.downcast_mut::<SoundEmitter>() {
-                            Some(object) => Ok(object.play_sound()),
+                        match object.downcast_mut::<ModelObject>() {
+                            Some(object) => {
+                                match object.animator_mut() {
+                                    Some(animator) => {
+                                        if let Err(err) = animator.set_bool(&name, value) {
+                                            debugger::error(
+                                                &format!("lua error(system {}): set_animator_bool failed! error in ModelObject '{}': {:?}",
+                                                    this.system_id, this.name, err));
+                                        }
+                                    },
+                                    None => debugger::error(
+                                        &format!("lua error(system {}): set_animator_bool failed! ModelObject '{}' has no animator",
+                                            this.system_id, this.name)),
+                                }
+                            },
                             None => {
                                 debugger::error(
-                                    &format!("lua error(system {}): play_sound failed in object: {}. this object is not SoundEmitter!", 
+                                    &format!("lua error(system {}): set_animator_bool failed in object: {}. this object is not ModelObject!",
                                         this.system_id, this.name));
-                                Ok(())
                             },
                         }
                     }
                     None => {
                         debugger::error(
-                            &format!("lua error: play_sound failed! failed to get object {} in system {}", this.name, this.system_id));
+                            &format!("lua error: set_animator_bool failed! failed to get object {} in system {}", this.name, this.system_id));
                     },
                 },
                 None => debugger::error(&format!(
-                        "lua error: play_sound failed! failed to get system {} to find object {}",
+                        "lua error: set_animator_bool failed! failed to get system {} to find object {}",
                         this.system_id, this.name
                 )),
             }
@@ -1045,28 +1199,39 @@ impl UserData for ObjectHandle {
             Ok(())
         });
 
-        methods.add_method("set_max_distance", |_, this, distance: f32| {
+        methods.add_method("set_animator_trigger", |_, this, name: String| {
             match systems::get_system_mut_with_id(&this.system_id) {
                 Some(system) => match system.find_object_mut(&this.name) {
                     Some(object) => {
-                        match object.downcast_mut::<SoundEmitter>() {
+                        match object.downcast_mut::<ModelObject>() {
                             Some(object) => {
-                                let _ = object.set_max_distance(distance);
+                                match object.animator_mut() {
+                                    Some(animator) => {
+                                        if let Err(err) = animator.set_trigger(&name) {
+                                            debugger::error(
+                                                &format!("lua error(system {}): set_animator_trigger failed! error in ModelObject '{}': {:?}",
+                                                    this.system_id, this.name, err));
+                                        }
+                                    },
+                                    None => debugger::error(
+                                        &format!("lua error(system {}): set_animator_trigger failed! ModelObject '{}' has no animator",
+                                            this.system_id, this.name)),
+                                }
                             },
                             None => {
                                 debugger::error(
-                                    &format!("lua error(system {}): set_max_distance failed in object: {}. this object is not SoundEmitter!",
+                                    &format!("lua error(system {}): set_animator_trigger failed in object: {}. this object is not ModelObject!",
                                         this.system_id, this.name));
                             },
                         }
                     }
                     None => {
                         debugger::error(
-                            &format!("lua error: set_max_distance failed! failed to get object {} in system {}", this.name, this.system_id));
+                            &format!("lua error: set_animator_trigger failed! failed to get object {} in system {}", this.name, this.system_id));
                     },
                 },
                 None => debugger::error(&format!(
-                        "lua error: set_max_distance failed! failed to get system {} to find object {}",
+                        "lua error: set_animator_trigger failed! failed to get system {} to find object {}",
                         this.system_id, this.name
                 )),
             }
@@ -1074,58 +1239,79 @@ impl UserData for ObjectHandle {
             Ok(())
         });
 
-        methods.add_method("get_max_distance", |_, this, _: ()| {
+        methods.add_method("reset_animator_trigger", |_, this, name: String| {
             match systems::get_system_mut_with_id(&this.system_id) {
                 Some(system) => match system.find_object_mut(&this.name) {
                     Some(object) => {
-                        match object.downcast_mut::<SoundEmitter>() {
+                        match object.downcast_mut::<ModelObject>() {
                             Some(object) => {
-                                match object.get_max_distance() {
-                                    Some(distance) => return Ok(Some(distance)),
-                                    None => (),
+                                match object.animator_mut() {
+                                    Some(animator) => {
+                                        if let Err(err) = animator.reset_trigger(&name) {
+                                            debugger::error(
+                                                &format!("lua error(system {}): reset_animator_trigger failed! error in ModelObject '{}': {:?}",
+                                                    this.system_id, this.name, err));
+                                        }
+                                    },
+                                    None => debugger::error(
+                                        &format!("lua error(system {}): reset_animator_trigger failed! ModelObject '{}' has no animator",
+                                            this.system_id, this.name)),
                                 }
                             },
                             None => {
                                 debugger::error(
-                                    &format!("lua error(system {}): get_max_distance failed in object: {}. this object is not SoundEmitter!",
+                                    &format!("lua error(system {}): reset_animator_trigger failed in object: {}. this object is not ModelObject!",
                                         this.system_id, this.name));
                             },
                         }
                     }
                     None => {
                         debugger::error(
-                            &format!("lua error: get_max_distance failed! failed to get object {} in system {}", this.name, this.system_id));
+                            &format!("lua error: reset_animator_trigger failed! failed to get object {} in system {}", this.name, this.system_id));
                     },
                 },
                 None => debugger::error(&format!(
-                        "lua error: get_max_distance failed! failed to get system {} to find object {}",
+                        "lua error: reset_animator_trigger failed! failed to get system {} to find object {}",
                         this.system_id, this.name
                 )),
             }
 
-            Ok(None)
+            Ok(())
         });
 
-        methods.add_method("move_controller", |_, this, (x, y, z): (f32, f32, f32)| {
+        methods.add_method("play_animator_state", |_, this, (name, duration): (String, Option<f32>)| {
             match systems::get_system_mut_with_id(&this.system_id) {
                 Some(system) => match system.find_object_mut(&this.name) {
                     Some(object) => {
-                        match object.downcast_mut::<CharacterController>() {
-                            Some(object) => object.move_controller(Vec3::new(x, y, z)),
+                        match object.downcast_mut::<ModelObject>() {
+                            Some(object) => {
+                                match object.animator_mut() {
+                                    Some(animator) => {
+                                        if let Err(err) = animator.play_state(&name, duration.unwrap_or(0.0)) {
+                                            debugger::error(
+                                                &format!("lua error(system {}): play_animator_state failed! error in ModelObject '{}': {:?}",
+                                                    this.system_id, this.name, err));
+                                        }
+                                    },
+                                    None => debugger::error(
+                                        &format!("lua error(system {}): play_animator_state failed! ModelObject '{}' has no animator",
+                                            this.system_id, this.name)),
+                                }
+                            },
                             None => {
                                 debugger::error(
-                                    &format!("lua error(system {}): move_controller failed in object: {}. this object is not CharacterController!",
+                                    &format!("lua error(system {}): play_animator_state failed in object: {}. this object is not ModelObject!",
                                         this.system_id, this.name));
                             },
                         }
                     }
                     None => {
                         debugger::error(
-                            &format!("lua error: move_controller failed! failed to get object {} in system {}", this.name, this.system_id));
+                            &format!("lua error: play_animator_state failed! failed to get object {} in system {}", this.name, this.system_id));
                     },
                 },
                 None => debugger::error(&format!(
-                        "lua error: move_controller failed! failed to get system {} to find object {}",
+                        "lua error: play_animator_state failed! failed to get system {} to find object {}",
                         this.system_id, this.name
                 )),
             }
@@ -1133,43 +1319,2141 @@ impl UserData for ObjectHandle {
             Ok(())
         });
 
-        methods.add_method("walk_to", |_, this, (x, y, z, speed): (f32, f32, f32, f32)| {
+        methods.add_method("animator_state", |_, this, _: ()| {
             match systems::get_system_mut_with_id(&this.system_id) {
                 Some(system) => match system.find_object_mut(&this.name) {
                     Some(object) => {
-                        match object.downcast_mut::<CharacterController>() {
-                            Some(object) => object.walk_to(Vec3::new(x, y, z), speed),
+                        match object.downcast_mut::<ModelObject>() {
+                            Some(object) => {
+                                return Ok(object.animator().map(|animator| animator.current_state().to_string()));
+                            },
                             None => {
                                 debugger::error(
-                                    &format!("lua error(system {}): walk_to failed in object: {}. this object is not CharacterController!",
+                                    &format!("lua error(system {}): animator_state failed in object: {}. this object is not ModelObject!",
                                         this.system_id, this.name));
                             },
                         }
                     }
                     None => {
                         debugger::error(
-                            &format!("lua error: walk_to failed! failed to get object {} in system {}", this.name, this.system_id));
+                            &format!("lua error: animator_state failed! failed to get object {} in system {}", this.name, this.system_id));
                     },
                 },
                 None => debugger::error(&format!(
-                        "lua error: walk_to failed! failed to get system {} to find object {}",
+                        "lua error: animator_state failed! failed to get system {} to find object {}",
                         this.system_id, this.name
                 )),
             }
 
-            Ok(())
+            Ok(None)
         });
-        // i could've used a macro
-    }
-}
 
-// body_type = "None"/"Fixed"/""/"Ball"/"Cylinder"
-// body_collider_type = "None"/"Cuboid"/"Capsule"/"Ball"/"Cylinder"
-// render_type = "None"/"Cuboid"/"Capsule"/"Ball"/"Cylinder"
-// collider_size_x, collider_size_y, collider_size_z - some of them may be ignored
-// mass
-// membership_bits* - bitmask of object collider membership, filter_bits* - bitmask of stuff collider can interact with
-// * = optional
+        methods.add_method("animator_parameter", |_, this, name: String| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<ModelObject>() {
+                            Some(object) => {
+                                return Ok(match object.animator().and_then(|animator| animator.parameter(&name)) {
+                                    Some(AnimatorParameter::Float(value)) => mlua::Value::Number(value as f64),
+                                    Some(AnimatorParameter::Bool(value)) | Some(AnimatorParameter::Trigger(value)) => mlua::Value::Boolean(value),
+                                    None => mlua::Value::Nil,
+                                });
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): animator_parameter failed in object: {}. this object is not ModelObject!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: animator_parameter failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: animator_parameter failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(mlua::Value::Nil)
+        });
+
+        methods.add_method("set_animation_speed", |_, this, (speed, anim_name, layer): (f32, Option<String>, Option<String>)| {
+            let layer = layer.unwrap_or(BASE_LAYER.into());
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        let result = if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            match &anim_name {
+                                Some(anim_name) => object.animation_settings.set_track_speed(&layer, anim_name, speed),
+                                None => {
+                                    object.animation_settings.speed = speed;
+                                    Ok(())
+                                }
+                            }
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            match &anim_name {
+                                Some(anim_name) => object.animation_settings.set_track_speed(&layer, anim_name, speed),
+                                None => {
+                                    object.animation_settings.speed = speed;
+                                    Ok(())
+                                }
+                            }
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): set_animation_speed failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                            return Ok(());
+                        };
+                        if let Err(err) = result {
+                            debugger::error(
+                                &format!("lua error(system {}): set_animation_speed failed! error in object '{}': {:?}",
+                                    this.system_id, this.name, err));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_animation_speed failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_animation_speed failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("pause_animation", |_, this, _: ()| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.animation_settings.paused = true;
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.animation_settings.paused = true;
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): pause_animation failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: pause_animation failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: pause_animation failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("resume_animation", |_, this, _: ()| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.animation_settings.paused = false;
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.animation_settings.paused = false;
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): resume_animation failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: resume_animation failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: resume_animation failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("is_animation_paused", |_, this, _: ()| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            return Ok(object.animation_settings.paused);
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            return Ok(object.animation_settings.paused);
+                        }
+                        debugger::error(
+                            &format!("lua error(system {}): is_animation_paused failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                this.system_id, this.name));
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: is_animation_paused failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: is_animation_paused failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(false)
+        });
+
+        methods.add_method("seek_animation", |_, this, (time, anim_name, layer): (f32, Option<String>, Option<String>)| {
+            let layer = layer.unwrap_or(BASE_LAYER.into());
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        let result = if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.animation_settings.seek(&layer, anim_name.as_deref(), time)
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.animation_settings.seek(&layer, anim_name.as_deref(), time)
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): seek_animation failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                            return Ok(());
+                        };
+                        if let Err(err) = result {
+                            debugger::error(
+                                &format!("lua error(system {}): seek_animation failed! error in object '{}': {:?}",
+                                    this.system_id, this.name, err));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: seek_animation failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: seek_animation failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("animation_time", |_, this, (anim_name, layer): (Option<String>, Option<String>)| {
+            let layer = layer.unwrap_or(BASE_LAYER.into());
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            return Ok(object.animation_settings.track(&layer, anim_name.as_deref()).map(|track| track.time()));
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            return Ok(object.animation_settings.track(&layer, anim_name.as_deref()).map(|track| track.time()));
+                        }
+                        debugger::error(
+                            &format!("lua error(system {}): animation_time failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                this.system_id, this.name));
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: animation_time failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: animation_time failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(None)
+        });
+
+        methods.add_method("normalized_animation_time", |_, this, (anim_name, layer): (Option<String>, Option<String>)| {
+            let layer = layer.unwrap_or(BASE_LAYER.into());
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            return Ok(object.animation_settings.track(&layer, anim_name.as_deref()).map(|track| track.normalized_time()));
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            return Ok(object.animation_settings.track(&layer, anim_name.as_deref()).map(|track| track.normalized_time()));
+                        }
+                        debugger::error(
+                            &format!("lua error(system {}): normalized_animation_time failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                this.system_id, this.name));
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: normalized_animation_time failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: normalized_animation_time failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(None)
+        });
+
+        methods.add_method("add_animation_event", |_, this, (anim_name, time, event_name): (String, f32, String)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        let result = if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.add_animation_event(&anim_name, time, &event_name)
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.add_animation_event(&anim_name, time, &event_name)
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): add_animation_event failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                            return Ok(());
+                        };
+                        if let Err(err) = result {
+                            debugger::error(
+                                &format!("lua error(system {}): add_animation_event failed! error in object '{}': {:?}",
+                                    this.system_id, this.name, err));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: add_animation_event failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: add_animation_event failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("on_animation_event", |lua, this, callback: Function| {
+            let key = match lua.create_registry_value(callback) {
+                Ok(key) => key,
+                Err(err) => {
+                    debugger::error(&format!("lua error(system {}): on_animation_event failed! failed to store the callback: {}", this.system_id, err));
+                    return Ok(());
+                }
+            };
+            let system_id = this.system_id.clone();
+            let object_name = this.name.clone();
+            let callback: AnimationEventCallback = Box::new(move |event: &AnimationEvent| {
+                let lua = match lua_vm_ref(system_id.clone()) {
+                    Some(lua) => lua,
+                    None => return debugger::error(&format!("lua animation event error(system {}): can't get lua vm reference", system_id)),
+                };
+                let call_result = match lua.registry_value::<Function>(&key) {
+                    Ok(func) => func.call::<_, ()>((event.name.clone(), event.animation.clone(), event.layer.clone(), event.time)),
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = call_result {
+                    debugger::error(&format!(
+                        "lua error(system {}): animation event callback of object '{}' failed! event: {}\nerror: {}",
+                        system_id, object_name, event.name, err));
+                }
+            });
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.animation_settings.add_event_callback(callback);
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.animation_settings.add_event_callback(callback);
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): on_animation_event failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: on_animation_event failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: on_animation_event failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("clear_animation_event_callbacks", |_, this, _: ()| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.animation_settings.clear_event_callbacks();
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.animation_settings.clear_event_callbacks();
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): clear_animation_event_callbacks failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: clear_animation_event_callbacks failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: clear_animation_event_callbacks failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_morph_weight", |_, this, (name, weight): (String, f32)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        let result = if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.set_morph_weight(&name, weight)
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.set_morph_weight(&name, weight)
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): set_morph_weight failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                            return Ok(());
+                        };
+                        if let Err(err) = result {
+                            debugger::error(
+                                &format!("lua error(system {}): set_morph_weight failed! error in object '{}': {:?}",
+                                    this.system_id, this.name, err));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_morph_weight failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_morph_weight failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("clear_morph_weight", |_, this, name: String| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.clear_morph_weight(&name);
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.clear_morph_weight(&name);
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): clear_morph_weight failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: clear_morph_weight failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: clear_morph_weight failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("morph_weight", |_, this, name: String| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            return Ok(object.morph_weight(&name));
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            return Ok(object.morph_weight(&name));
+                        }
+                        debugger::error(
+                            &format!("lua error(system {}): morph_weight failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                this.system_id, this.name));
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: morph_weight failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: morph_weight failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(None)
+        });
+
+        methods.add_method("attach_to_joint", |_, this, (child, joint_name, offset): (mlua::Value, String, Option<[f32; 6]>)| {
+            let child_name = match object_name_from_lua(&child) {
+                Some(name) => name,
+                None => {
+                    debugger::error(&format!("lua error(system {}): attach_to_joint failed! child should be an object or an object name", this.system_id));
+                    return Ok(());
+                }
+            };
+            let offset = offset.unwrap_or([0.0; 6]);
+            let offset = Transform {
+                position: Vec3::new(offset[0], offset[1], offset[2]),
+                rotation: Vec3::new(offset[3], offset[4], offset[5]),
+                scale: Vec3::ONE,
+            };
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<ModelObject>() {
+                            Some(object) => {
+                                if let Err(err) = object.attach_to_joint(&child_name, &joint_name, offset) {
+                                    debugger::error(
+                                        &format!("lua error(system {}): attach_to_joint failed! error in ModelObject '{}': {:?}",
+                                            this.system_id, this.name, err));
+                                }
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): attach_to_joint failed in object: {}. this object is not ModelObject!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: attach_to_joint failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: attach_to_joint failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("detach_from_joint", |_, this, child: mlua::Value| {
+            let child_name = match object_name_from_lua(&child) {
+                Some(name) => name,
+                None => {
+                    debugger::error(&format!("lua error(system {}): detach_from_joint failed! child should be an object or an object name", this.system_id));
+                    return Ok(());
+                }
+            };
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<ModelObject>() {
+                            Some(object) => {
+                                object.detach_from_joint(&child_name);
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): detach_from_joint failed in object: {}. this object is not ModelObject!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: detach_from_joint failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: detach_from_joint failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("joint_transform", |_, this, joint_name: String| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<ModelObject>() {
+                            Some(object) => {
+                                return Ok(object.joint_transform(&joint_name).map(|transform| [transform.position.to_array(), transform.rotation.to_array()]));
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): joint_transform failed in object: {}. this object is not ModelObject!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: joint_transform failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: joint_transform failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(None)
+        });
+
+        methods.add_method("set_root_motion", |_, this, (joint_name, rotation, apply_to_object): (Option<String>, Option<bool>, Option<bool>)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<ModelObject>() {
+                            Some(object) => {
+                                if let Err(err) = object.set_root_motion(joint_name.as_deref(), rotation.unwrap_or(false), apply_to_object.unwrap_or(false)) {
+                                    debugger::error(
+                                        &format!("lua error(system {}): set_root_motion failed! error in ModelObject '{}': {:?}",
+                                            this.system_id, this.name, err));
+                                }
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_root_motion failed in object: {}. this object is not ModelObject!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_root_motion failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_root_motion failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("disable_root_motion", |_, this, _: ()| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<ModelObject>() {
+                            Some(object) => {
+                                object.disable_root_motion();
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): disable_root_motion failed in object: {}. this object is not ModelObject!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: disable_root_motion failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: disable_root_motion failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("take_root_motion", |_, this, _: ()| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<ModelObject>() {
+                            Some(object) => {
+                                let root_motion = object.take_root_motion();
+                                return Ok([root_motion.translation.x, root_motion.translation.y, root_motion.translation.z, root_motion.yaw]);
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): take_root_motion failed in object: {}. this object is not ModelObject!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: take_root_motion failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: take_root_motion failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok([0.0; 4])
+        });
+
+        methods.add_method("set_lightmap", |_, this, (path, intensity): (Option<String>, Option<f32>)| {
+            let texture = match path {
+                Some(path) => match TextureAsset::from_file(&path) {
+                    Ok(asset) => Some(asset),
+                    Err(err) => {
+                        debugger::error(&format!("lua error: set_lightmap failed! failed to load texture asset\nerr: {:?}", err));
+                        return Ok(());
+                    }
+                },
+                None => None,
+            };
+            let intensity = intensity.unwrap_or(1.0);
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.set_lightmap(texture, intensity);
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.set_lightmap(texture, intensity);
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): set_lightmap failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_lightmap failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_lightmap failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_skinned_bounds_scale", |_, this, scale: f32| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.set_skinned_bounds_scale(scale);
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.set_skinned_bounds_scale(scale);
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): set_skinned_bounds_scale failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                                    this.system_id, this.name));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_skinned_bounds_scale failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_skinned_bounds_scale failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("intersection_position", |_, this, _: ()| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        return match object.downcast_mut::<Ray>() {
+                            Some(object) => {
+                                return match object.intersection_position() {
+                                    Some(pos) => Ok(Some([pos.x, pos.y, pos.z])),
+                                    None => Ok(None),
+                                }
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): intersection_position failed in object: {}. this object is not Ray!", 
+                                        this.system_id, this.name));
+                                Ok(None)
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: intersection_position failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: intersection_position failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(None)
+        });
+
+        methods.add_method("is_intersecting", |_, this, _: ()| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        return match object.downcast_mut::<Ray>() {
+                            Some(object) => {
+                                Ok(object.is_intersecting())
+                            },
+                            None => {
+                                match object.downcast_mut::<Trigger>() {
+                                    Some(object) => Ok(object.is_intersecting()),
+                                    None => {
+                                        debugger::error(
+                                            &format!("lua error(system {}): is_intersecting failed in object: {}. this object is neither Ray nor Trigger!",
+                                                this.system_id, this.name));
+                                        Ok(false)
+                                    },
+                                }
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: is_intersecting failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: is_intersecting failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(false)
+        });
+
+        methods.add_method("is_intersecting_with_group", |_, this, group: String| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        return match object.downcast_mut::<Trigger>() {
+                            Some(object) => {
+                                Ok(object.is_intersecting_with_group(group.into()))
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): is_intersecting_with_group failed in object: {}. this object is not Trigger!", 
+                                        this.system_id, this.name));
+                                Ok(false)
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: is_intersecting_with_group failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: is_intersecting_with_group failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(false)
+        });
+
+        methods.add_method("set_looping", |_, this, should_loop: bool| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        return match object.downcast_mut::<SoundEmitter>() {
+                            Some(object) => Ok(object.set_looping(should_loop)),
+                            None => {
+                                if let Some(object) = object.downcast_mut::<ModelObject>() {
+                                    object.set_looping(should_loop);
+                                    Ok(())
+                                } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                                    object.set_looping(should_loop);
+                                    Ok(())
+                                } else {
+                                    debugger::error(
+                                        &format!("lua error(system {}): set_looping failed in object: {}. this object is neither SoundEmitter, ModelObject nor MasterInstancedModelObject!",
+                                        this.system_id, this.name));
+                                    Ok(())
+                                }
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_looping failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_looping failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("looping", |_, this, _: ()| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        return match object.downcast_mut::<SoundEmitter>() {
+                            Some(object) => Ok(object.is_looping()),
+                            None => {
+                                if let Some(object) = object.downcast_mut::<ModelObject>() {
+                                    Ok(object.is_looping())
+                                } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                                    Ok(object.is_looping())
+                                } else {
+                                    debugger::error(
+                                        &format!("lua error(system {}): is_looping failed in object: {}. this object is neither SoundEmitter, ModelObject nor MasterInstancedModelObject!",
+                                        this.system_id, this.name));
+                                    Ok(false)
+                                }
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: is_looping failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: is_looping failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(false)
+        });
+
+        methods.add_method("current_animation", |_, this, _: ()| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            return Ok(object.current_animation().map(|animation| animation.to_string()));
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            return Ok(object.current_animation().map(|animation| animation.to_string()));
+                        }
+                        debugger::error(
+                            &format!("lua error(system {}): current_animation failed in object: {}. this object is neither ModelObject nor MasterInstancedModelObject!",
+                            this.system_id, this.name));
+                        return Ok(None);
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: current_animation failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: current_animation failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(None)
+        });
+
+        methods.add_method("play_sound", |_, this, _: ()| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        return match object.downcast_mut::<SoundEmitter>() {
+                            Some(object) => Ok(object.play_sound()),
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): play_sound failed in object: {}. this object is not SoundEmitter!", 
+                                        this.system_id, this.name));
+                                Ok(())
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: play_sound failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: play_sound failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_max_distance", |_, this, distance: f32| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<SoundEmitter>() {
+                            Some(object) => {
+                                let _ = object.set_max_distance(distance);
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_max_distance failed in object: {}. this object is not SoundEmitter!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_max_distance failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_max_distance failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("get_max_distance", |_, this, _: ()| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<SoundEmitter>() {
+                            Some(object) => {
+                                match object.get_max_distance() {
+                                    Some(distance) => return Ok(Some(distance)),
+                                    None => (),
+                                }
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): get_max_distance failed in object: {}. this object is not SoundEmitter!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: get_max_distance failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: get_max_distance failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(None)
+        });
+
+        methods.add_method("move_controller", |_, this, (x, y, z): (f32, f32, f32)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<CharacterController>() {
+                            Some(object) => object.move_controller(Vec3::new(x, y, z)),
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): move_controller failed in object: {}. this object is not CharacterController!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: move_controller failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: move_controller failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("apply_root_motion", |_, this, (x, y, z, yaw): (f32, f32, f32, Option<f32>)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<CharacterController>() {
+                            Some(object) => object.apply_root_motion(Vec3::new(x, y, z), yaw.unwrap_or(0.0)),
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): apply_root_motion failed in object: {}. this object is not CharacterController!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: apply_root_motion failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: apply_root_motion failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("walk_to", |_, this, (x, y, z, speed): (f32, f32, f32, f32)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<CharacterController>() {
+                            Some(object) => object.walk_to(Vec3::new(x, y, z), speed),
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): walk_to failed in object: {}. this object is not CharacterController!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: walk_to failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: walk_to failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_light_color", |_, this, (r, g, b): (f32, f32, f32)| {
+            with_light_object(this, "set_light_color", |light| match light {
+                LightObject::Point(light) => light.color = Vec3::new(r, g, b),
+                LightObject::Spot(light) => light.color = Vec3::new(r, g, b),
+            });
+
+            Ok(())
+        });
+
+        methods.add_method("set_light_intensity", |_, this, intensity: f32| {
+            with_light_object(this, "set_light_intensity", |light| match light {
+                LightObject::Point(light) => light.intensity = intensity,
+                LightObject::Spot(light) => light.intensity = intensity,
+            });
+
+            Ok(())
+        });
+
+        methods.add_method("set_light_range", |_, this, range: f32| {
+            with_light_object(this, "set_light_range", |light| match light {
+                LightObject::Point(light) => light.set_range(range),
+                LightObject::Spot(light) => light.set_range(range),
+            });
+
+            Ok(())
+        });
+
+        methods.add_method("set_light_cast_shadows", |_, this, cast_shadows: bool| {
+            with_light_object(this, "set_light_cast_shadows", |light| match light {
+                LightObject::Point(light) => light.cast_shadows = cast_shadows,
+                LightObject::Spot(light) => light.cast_shadows = cast_shadows,
+            });
+
+            Ok(())
+        });
+
+        methods.add_method("set_spot_light_angles", |_, this, (inner_angle, outer_angle): (f32, f32)| {
+            with_light_object(this, "set_spot_light_angles", |light| match light {
+                LightObject::Spot(light) => {
+                    light.inner_angle = inner_angle;
+                    light.outer_angle = outer_angle;
+                }
+                LightObject::Point(_) => debugger::error(
+                    &format!("lua error(system {}): set_spot_light_angles failed in object: {}. this object is not SpotLight!",
+                        this.system_id, this.name)),
+            });
+
+            Ok(())
+        });
+
+        methods.add_method("set_camera_perspective", |_, this, fov: f32| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Camera>() {
+                            Some(object) => {
+                                object.projection = Projection::Perspective { fov };
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_camera_perspective failed in object: {}. this object is not Camera!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_camera_perspective failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_camera_perspective failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_camera_orthographic", |_, this, height: f32| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Camera>() {
+                            Some(object) => {
+                                object.projection = Projection::Orthographic { height };
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_camera_orthographic failed in object: {}. this object is not Camera!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_camera_orthographic failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_camera_orthographic failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_camera_clip_planes", |_, this, (near, far): (f32, f32)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Camera>() {
+                            Some(object) => {
+                                object.near = near;
+                                object.far = far;
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_camera_clip_planes failed in object: {}. this object is not Camera!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_camera_clip_planes failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_camera_clip_planes failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_camera_viewport", |_, this, (x, y, width, height): (f32, f32, f32, f32)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Camera>() {
+                            Some(object) => {
+                                object.target = CameraTarget::Screen(Viewport { x, y, width, height });
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_camera_viewport failed in object: {}. this object is not Camera!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_camera_viewport failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_camera_viewport failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_camera_render_target", |_, this, (width, height): (u32, u32)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Camera>() {
+                            Some(object) => {
+                                object.target = CameraTarget::Texture { width, height };
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_camera_render_target failed in object: {}. this object is not Camera!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_camera_render_target failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_camera_render_target failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_camera_order", |_, this, order: i32| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Camera>() {
+                            Some(object) => {
+                                object.order = order;
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_camera_order failed in object: {}. this object is not Camera!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_camera_order failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_camera_order failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_camera_enabled", |_, this, enabled: bool| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Camera>() {
+                            Some(object) => {
+                                object.enabled = enabled;
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_camera_enabled failed in object: {}. this object is not Camera!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_camera_enabled failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_camera_enabled failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_camera_texture", |_, this, camera_name: Option<String>| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<ModelObject>() {
+                            Some(object) => {
+                                object.set_camera_texture(camera_name.as_deref());
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_camera_texture failed in object: {}. this object is not ModelObject!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_camera_texture failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_camera_texture failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_lod_distances", |_, this, (thresholds, cross_fade): (Vec<f32>, Option<f32>)| {
+            let settings = LodSettings {
+                metric: LodMetric::Distance,
+                thresholds,
+                cross_fade: cross_fade.unwrap_or(0.0).max(0.0),
+            };
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.set_lod_settings(settings);
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.set_lod_settings(settings);
+                        } else if let Some(object) = object.downcast_mut::<Terrain>() {
+                            object.lod_settings = settings;
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): set_lod_distances failed in object: {}. this object is not ModelObject, MasterInstancedModelObject or Terrain!",
+                                    this.system_id, this.name));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_lod_distances failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_lod_distances failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_lod_screen_sizes", |_, this, (thresholds, cross_fade): (Vec<f32>, Option<f32>)| {
+            let settings = LodSettings {
+                metric: LodMetric::ScreenSize,
+                thresholds,
+                cross_fade: cross_fade.unwrap_or(0.0).max(0.0),
+            };
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        if let Some(object) = object.downcast_mut::<ModelObject>() {
+                            object.set_lod_settings(settings);
+                        } else if let Some(object) = object.downcast_mut::<MasterInstancedModelObject>() {
+                            object.set_lod_settings(settings);
+                        } else if let Some(object) = object.downcast_mut::<Terrain>() {
+                            object.lod_settings = settings;
+                        } else {
+                            debugger::error(
+                                &format!("lua error(system {}): set_lod_screen_sizes failed in object: {}. this object is not ModelObject, MasterInstancedModelObject or Terrain!",
+                                    this.system_id, this.name));
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_lod_screen_sizes failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_lod_screen_sizes failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("emit_particles", |_, this, count: u32| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<ParticleEmitter>() {
+                            Some(object) => {
+                                object.burst(count);
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): emit_particles failed in object: {}. this object is not ParticleEmitter!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: emit_particles failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: emit_particles failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_particles_emitting", |_, this, emitting: bool| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<ParticleEmitter>() {
+                            Some(object) => {
+                                object.emitting = emitting;
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_particles_emitting failed in object: {}. this object is not ParticleEmitter!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_particles_emitting failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_particles_emitting failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("restart_particles", |_, this, ()| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<ParticleEmitter>() {
+                            Some(object) => {
+                                object.restart();
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): restart_particles failed in object: {}. this object is not ParticleEmitter!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: restart_particles failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: restart_particles failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+        methods.add_method("terrain_height_at", |_, this, (x, z): (f32, f32)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Terrain>() {
+                            Some(object) => {
+                                return Ok(object.height_at(x, z));
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): terrain_height_at failed in object: {}. this object is not Terrain!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: terrain_height_at failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: terrain_height_at failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(None)
+        });
+
+        methods.add_method("terrain_normal_at", |_, this, (x, z): (f32, f32)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Terrain>() {
+                            Some(object) => {
+                                return Ok(object.normal_at(x, z).map(|normal| normal.to_array()));
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): terrain_normal_at failed in object: {}. this object is not Terrain!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: terrain_normal_at failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: terrain_normal_at failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(None)
+        });
+
+        methods.add_method("set_terrain_layer", |_, this, (index, texture_path, tiling, r, g, b): (usize, Option<String>, f32, Option<f32>, Option<f32>, Option<f32>)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Terrain>() {
+                            Some(object) => {
+                                object.set_layer(index, TerrainLayer {
+                                    texture: texture_path,
+                                    color: Vec3::new(r.unwrap_or(1.0), g.unwrap_or(1.0), b.unwrap_or(1.0)),
+                                    tiling,
+                                });
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_terrain_layer failed in object: {}. this object is not Terrain!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_terrain_layer failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_terrain_layer failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_terrain_splat_map", |_, this, path: Option<String>| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Terrain>() {
+                            Some(object) => {
+                                object.set_splat_map(path);
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_terrain_splat_map failed in object: {}. this object is not Terrain!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_terrain_splat_map failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_terrain_splat_map failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_sprite_texture", |_, this, texture_path: Option<String>| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Sprite>() {
+                            Some(object) => {
+                                object.set_texture(texture_path);
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_sprite_texture failed in object: {}. this object is not Sprite!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_sprite_texture failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_sprite_texture failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_sprite_region", |_, this, (x, y, width, height): (f32, f32, f32, f32)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Sprite>() {
+                            Some(object) => {
+                                object.set_region(Vec4::new(x, y, width, height));
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_sprite_region failed in object: {}. this object is not Sprite!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_sprite_region failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_sprite_region failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_sprite_color", |_, this, (r, g, b, a): (f32, f32, f32, Option<f32>)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Sprite>() {
+                            Some(object) => {
+                                object.color = Vec4::new(r, g, b, a.unwrap_or(1.0));
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_sprite_color failed in object: {}. this object is not Sprite!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_sprite_color failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_sprite_color failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_sprite_size", |_, this, (width, height, screen_space): (f32, f32, Option<bool>)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Sprite>() {
+                            Some(object) => {
+                                object.size = Vec2::new(width, height);
+                                object.size_mode = match screen_space.unwrap_or(false) {
+                                    true => SpriteSize::Screen,
+                                    false => SpriteSize::World,
+                                };
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_sprite_size failed in object: {}. this object is not Sprite!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_sprite_size failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_sprite_size failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_sprite_facing", |_, this, (facing, axis_x, axis_y, axis_z): (String, Option<f32>, Option<f32>, Option<f32>)| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Sprite>() {
+                            Some(object) => {
+                                match facing.as_str() {
+                                    "camera" => object.facing = SpriteFacing::Camera,
+                                    "axis" => object.facing = SpriteFacing::Axis(Vec3::new(
+                                        axis_x.unwrap_or(0.0),
+                                        axis_y.unwrap_or(1.0),
+                                        axis_z.unwrap_or(0.0),
+                                    )),
+                                    _ => debugger::error(&format!("lua error: set_sprite_facing failed! unknown facing {}, it should be camera or axis", facing)),
+                                }
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_sprite_facing failed in object: {}. this object is not Sprite!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_sprite_facing failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_sprite_facing failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_sprite_depth_test", |_, this, depth_test: bool| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Sprite>() {
+                            Some(object) => {
+                                object.depth_test = depth_test;
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_sprite_depth_test failed in object: {}. this object is not Sprite!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_sprite_depth_test failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_sprite_depth_test failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_label_text", |_, this, text: String| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<Sprite>() {
+                            Some(object) => {
+                                object.set_text(&text);
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_label_text failed in object: {}. this object is not Sprite!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_label_text failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_label_text failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("add_instance", |_, this, (transform, tint, wind_phase, texture_index, custom): LuaInstanceArgs| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<MasterInstancedModelObject>() {
+                            Some(object) => {
+                                return Ok(Some(object.add_instance(lua_instance_data(transform, tint, wind_phase, texture_index, custom)).to_bits()));
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): add_instance failed in object: {}. this object is not MasterInstancedModelObject!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: add_instance failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: add_instance failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(None)
+        });
+
+        methods.add_method("update_instance", |_, this, (id, transform, tint, wind_phase, texture_index, custom): LuaInstanceUpdateArgs| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<MasterInstancedModelObject>() {
+                            Some(object) => {
+                                object.update_instance(InstanceId::from_bits(id), lua_instance_data(transform, tint, wind_phase, texture_index, custom));
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): update_instance failed in object: {}. this object is not MasterInstancedModelObject!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: update_instance failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: update_instance failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("remove_instance", |_, this, id: u64| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<MasterInstancedModelObject>() {
+                            Some(object) => {
+                                object.remove_instance(InstanceId::from_bits(id));
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): remove_instance failed in object: {}. this object is not MasterInstancedModelObject!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: remove_instance failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: remove_instance failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("clear_instances", |_, this, (): ()| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<MasterInstancedModelObject>() {
+                            Some(object) => {
+                                object.clear_instances();
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): clear_instances failed in object: {}. this object is not MasterInstancedModelObject!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: clear_instances failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: clear_instances failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_instance_cull_distance", |_, this, distance: Option<f32>| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<MasterInstancedModelObject>() {
+                            Some(object) => {
+                                object.cull_distance = distance;
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_instance_cull_distance failed in object: {}. this object is not MasterInstancedModelObject!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_instance_cull_distance failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_instance_cull_distance failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        methods.add_method("set_instance_data", |_, this, (tint, wind_phase, texture_index, custom): LuaInstanceDataArgs| {
+            match systems::get_system_mut_with_id(&this.system_id) {
+                Some(system) => match system.find_object_mut(&this.name) {
+                    Some(object) => {
+                        match object.downcast_mut::<InstancedModelObject>() {
+                            Some(object) => {
+                                object.instance_data = lua_instance_data([0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0], tint, wind_phase, texture_index, custom);
+                            },
+                            None => {
+                                debugger::error(
+                                    &format!("lua error(system {}): set_instance_data failed in object: {}. this object is not InstancedModelObject!",
+                                        this.system_id, this.name));
+                            },
+                        }
+                    }
+                    None => {
+                        debugger::error(
+                            &format!("lua error: set_instance_data failed! failed to get object {} in system {}", this.name, this.system_id));
+                    },
+                },
+                None => debugger::error(&format!(
+                        "lua error: set_instance_data failed! failed to get system {} to find object {}",
+                        this.system_id, this.name
+                )),
+            }
+
+            Ok(())
+        });
+
+        // i could've used a macro
+    }
+}
+
+enum LightObject<'a> {
+    Point(&'a mut PointLight),
+    Spot(&'a mut SpotLight),
+}
+
+/// Calls `f` with the PointLight or SpotLight `this` refers to, failures are reported with `method` name.
+fn with_light_object(this: &ObjectHandle, method: &str, f: impl FnOnce(LightObject)) {
+    match systems::get_system_mut_with_id(&this.system_id) {
+        Some(system) => match system.find_object_mut(&this.name) {
+            Some(object) => {
+                if let Some(light) = object.downcast_mut::<PointLight>() {
+                    f(LightObject::Point(light));
+                } else if let Some(light) = object.downcast_mut::<SpotLight>() {
+                    f(LightObject::Spot(light));
+                } else {
+                    debugger::error(
+                        &format!("lua error(system {}): {} failed in object: {}. this object is neither PointLight nor SpotLight!",
+                            this.system_id, method, this.name));
+                }
+            }
+            None => {
+                debugger::error(
+                    &format!("lua error: {} failed! failed to get object {} in system {}", method, this.name, this.system_id));
+            },
+        },
+        None => debugger::error(&format!(
+                "lua error: {} failed! failed to get system {} to find object {}",
+                method, this.system_id, this.name
+        )),
+    }
+}
+
+// body_type = "None"/"Fixed"/""/"Ball"/"Cylinder"
+// body_collider_type = "None"/"Cuboid"/"Capsule"/"Ball"/"Cylinder"
+// render_type = "None"/"Cuboid"/"Capsule"/"Ball"/"Cylinder"
+// collider_size_x, collider_size_y, collider_size_z - some of them may be ignored
+// mass
+// membership_bits* - bitmask of object collider membership, filter_bits* - bitmask of stuff collider can interact with
+// * = optional
+// transform = {position x, y, z, rotation x, y, z, scale x, y, z}, world space
+// transform, then tint, wind phase, texture index and custom data of an instance
+type LuaInstanceArgs = ([f32; 9], Option<[f32; 4]>, Option<f32>, Option<i32>, Option<[f32; 4]>);
+type LuaInstanceUpdateArgs = (u64, [f32; 9], Option<[f32; 4]>, Option<f32>, Option<i32>, Option<[f32; 4]>);
+type LuaInstanceDataArgs = (Option<[f32; 4]>, Option<f32>, Option<i32>, Option<[f32; 4]>);
+
+fn lua_instance_data(transform: [f32; 9], tint: Option<[f32; 4]>, wind_phase: Option<f32>, texture_index: Option<i32>, custom: Option<[f32; 4]>) -> InstanceData {
+    let transform = Transform {
+        position: Vec3::new(transform[0], transform[1], transform[2]),
+        rotation: Vec3::new(transform[3], transform[4], transform[5]),
+        scale: Vec3::new(transform[6], transform[7], transform[8]),
+    };
+
+    InstanceData {
+        tint: tint.map_or(Vec4::ONE, Vec4::from),
+        wind_phase: wind_phase.unwrap_or(0.0),
+        texture_index: texture_index.unwrap_or(0),
+        custom: custom.map_or(Vec4::ZERO, Vec4::from),
+        ..InstanceData::from_transform(&transform)
+    }
+}
+
 fn lua_body_render_colliders_and_groups_to_rust(object_name: String, object_system_id: String, body_collider: Option<BodyColliderType>, body_type: String, render_collider_type: String, collider_size_x: f32, collider_size_y: f32, collider_size_z: f32, membership_bits: Option<u32>, filter_bits: Option<u32>) 
     -> (Option<RenderColliderType>, Option<BodyType>, Option<CollisionGroups>, Option<CollisionGroups>) {
     let render_collider_type = match render_collider_type.as_str() {
@@ -0,0 +1,281 @@
+use glam::Vec3;
+use glium::{
+    framebuffer::SimpleFrameBuffer,
+    glutin::surface::WindowSurface,
+    index::{NoIndices, PrimitiveType},
+    texture::{CubeLayer, Cubemap, RawImage2d, Texture2d},
+    uniform,
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction},
+    vertex::EmptyVertexAttributes,
+    Display, Surface,
+};
+use once_cell::sync::Lazy;
+
+use super::{debugger, post_process::FULLSCREEN_VERTEX_SHADER, render};
+use crate::{assets::texture_asset::TextureAsset, math_utils::deg_to_rad};
+
+const SKY_FRAGMENT_SHADER: &str = include_str!("../assets/sky.frag");
+
+/// Colours are linear.
+#[derive(Debug, Clone)]
+pub enum Sky {
+    Color(Vec3),
+    Gradient(GradientSky),
+    /// faces paths in +x, -x, +y, -y, +z, -z order
+    Skybox { faces: [String; 6], intensity: f32 },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GradientSky {
+    pub zenith_color: Vec3,
+    pub horizon_color: Vec3,
+    pub ground_color: Vec3,
+    /// colours above 1 glow with bloom
+    pub sun_color: Vec3,
+    /// angular radius in degrees
+    pub sun_size: f32,
+}
+
+impl Default for GradientSky {
+    fn default() -> Self {
+        GradientSky {
+            zenith_color: Vec3::new(0.15, 0.35, 0.8),
+            horizon_color: Vec3::new(0.6, 0.7, 0.85),
+            ground_color: Vec3::new(0.25, 0.23, 0.2),
+            sun_color: Vec3::new(10.0, 9.0, 8.0),
+            sun_size: 1.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FogMode {
+    None,
+    Linear,
+    Exponential,
+}
+
+impl FogMode {
+    pub fn as_shader_int(&self) -> i32 {
+        match self {
+            FogMode::None => 0,
+            FogMode::Linear => 1,
+            FogMode::Exponential => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Fog {
+    pub mode: FogMode,
+    pub color: Vec3,
+    /// linear fog starts and ends at these distances from the camera
+    pub start: f32,
+    pub end: f32,
+    /// exponential fog density
+    pub density: f32,
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Fog {
+            mode: FogMode::None,
+            color: Vec3::new(0.6, 0.7, 0.85),
+            start: 20.0,
+            end: 200.0,
+            density: 0.01,
+        }
+    }
+}
+
+// (0.7, 0.7, 0.9) srgb
+static mut SKY: Lazy<Sky> = Lazy::new(|| Sky::Color(Vec3::new(0.448, 0.448, 0.787)));
+static mut FOG: Lazy<Fog> = Lazy::new(Fog::default);
+static mut SKYBOX: Option<([String; 6], Cubemap)> = None;
+
+pub fn set_sky(sky: Sky) {
+    unsafe {
+        *SKY = sky;
+    }
+}
+
+pub fn get_sky() -> Sky {
+    unsafe { SKY.clone() }
+}
+
+pub fn set_fog(mut fog: Fog) {
+    fog.start = fog.start.max(0.0);
+    fog.end = fog.end.max(fog.start + 0.001);
+    fog.density = fog.density.max(0.0);
+
+    unsafe {
+        *FOG = fog;
+    }
+}
+
+pub fn get_fog() -> Fog {
+    unsafe { *FOG }
+}
+
+/// Clears the target with sky's colour and draws the gradient or skybox over it.
+pub fn draw(display: &Display<WindowSurface>, target: &mut SimpleFrameBuffer) {
+    let sky = get_sky();
+    let clear_color = match &sky {
+        Sky::Color(color) => *color,
+        Sky::Gradient(gradient) => gradient.horizon_color,
+        Sky::Skybox { .. } => Vec3::ZERO,
+    };
+    target.clear_color_and_depth((clear_color.x, clear_color.y, clear_color.z, 1.0), 1.0);
+
+    let (sky_mode, gradient, skybox_intensity) = match &sky {
+        Sky::Color(_) => return,
+        Sky::Gradient(gradient) => (1, *gradient, 0.0),
+        Sky::Skybox { faces, intensity } => {
+            if !update_skybox(display, faces) {
+                return;
+            }
+            (2, GradientSky::default(), *intensity)
+        }
+    };
+
+    let program = match render::get_program(display, FULLSCREEN_VERTEX_SHADER, SKY_FRAGMENT_SHADER) {
+        Ok(program) => program,
+        Err(err) => {
+            debugger::error(&format!("sky program creation error!\nerr: {}", err));
+            return;
+        }
+    };
+
+    let inverse_view_proj = (render::get_projection_matrix() * render::get_view_matrix()).inverse();
+    // sky is drawn even if skybox isn't loaded, so the sampler needs something
+    let skybox = unsafe {
+        match &SKYBOX {
+            Some((_, cubemap)) => cubemap,
+            None => match empty_cubemap(display) {
+                Some(cubemap) => cubemap,
+                None => return,
+            },
+        }
+    };
+
+    let uniforms = uniform! {
+        inverseViewProj: inverse_view_proj.to_cols_array_2d(),
+        skyMode: sky_mode,
+        zenithColor: gradient.zenith_color.to_array(),
+        horizonColor: gradient.horizon_color.to_array(),
+        groundColor: gradient.ground_color.to_array(),
+        sunColor: gradient.sun_color.to_array(),
+        sunSize: deg_to_rad(gradient.sun_size).cos(),
+        sunDirection: render::get_sun_direction().to_array(),
+        skybox: skybox
+            .sampled()
+            .minify_filter(MinifySamplerFilter::Linear)
+            .magnify_filter(MagnifySamplerFilter::Linear)
+            .wrap_function(SamplerWrapFunction::Clamp),
+        skyboxIntensity: skybox_intensity,
+    };
+
+    let draw_params = glium::DrawParameters {
+        depth: glium::Depth {
+            test: glium::draw_parameters::DepthTest::Overwrite,
+            write: false,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    target
+        .draw(
+            EmptyVertexAttributes { len: 3 },
+            NoIndices(PrimitiveType::TrianglesList),
+            program,
+            &uniforms,
+            &draw_params,
+        )
+        .unwrap();
+}
+
+static mut EMPTY_CUBEMAP: Option<Cubemap> = None;
+
+fn empty_cubemap(display: &Display<WindowSurface>) -> Option<&'static Cubemap> {
+    unsafe {
+        if EMPTY_CUBEMAP.is_none() {
+            EMPTY_CUBEMAP = Cubemap::empty(display, 1).ok();
+        }
+
+        EMPTY_CUBEMAP.as_ref()
+    }
+}
+
+/// Loads skybox faces if they changed, returns false if they can't be loaded.
+fn update_skybox(display: &Display<WindowSurface>, faces: &[String; 6]) -> bool {
+    unsafe {
+        if let Some((loaded_faces, _)) = &SKYBOX {
+            if loaded_faces == faces {
+                return true;
+            }
+        }
+
+        SKYBOX = load_skybox(display, faces).map(|cubemap| (faces.clone(), cubemap));
+        if SKYBOX.is_none() {
+            // so it isn't loaded again every frame
+            debugger::warn("skybox loading failed, using the gradient sky");
+            *SKY = Sky::Gradient(GradientSky::default());
+            return false;
+        }
+    }
+
+    true
+}
+
+fn load_skybox(display: &Display<WindowSurface>, faces: &[String; 6]) -> Option<Cubemap> {
+    let layers = [
+        CubeLayer::PositiveX,
+        CubeLayer::NegativeX,
+        CubeLayer::PositiveY,
+        CubeLayer::NegativeY,
+        CubeLayer::PositiveZ,
+        CubeLayer::NegativeZ,
+    ];
+
+    let mut assets = Vec::new();
+    for path in faces {
+        assets.push(TextureAsset::from_file(path).ok()?);
+    }
+
+    let size = assets[0].image_dimensions.0;
+    for (asset, path) in assets.iter().zip(faces) {
+        if asset.image_dimensions != (size, size) {
+            debugger::error(&format!(
+                "skybox face {} should be {}x{}, got {}x{}",
+                path, size, size, asset.image_dimensions.0, asset.image_dimensions.1
+            ));
+            return None;
+        }
+    }
+
+    let cubemap = match Cubemap::empty(display, size) {
+        Ok(cubemap) => cubemap,
+        Err(err) => {
+            debugger::error(&format!("skybox cubemap creation error!\nerr: {}", err));
+            return None;
+        }
+    };
+
+    for (asset, layer) in assets.into_iter().zip(layers) {
+        // cubemap faces are stored top to bottom, so images aren't flipped
+        let image = RawImage2d::from_raw_rgba(asset.image_raw, asset.image_dimensions);
+        let texture = match Texture2d::new(display, image) {
+            Ok(texture) => texture,
+            Err(err) => {
+                debugger::error(&format!("skybox face texture creation error!\nerr: {}", err));
+                return None;
+            }
+        };
+
+        let face = SimpleFrameBuffer::new(display, cubemap.main_level().image(layer)).unwrap();
+        texture.as_surface().fill(&face, MagnifySamplerFilter::Linear);
+    }
+
+    Some(cubemap)
+}
@@ -1,14 +1,14 @@
 use std::collections::HashMap;
 
-use crate::{framework::Framework, objects::ObjectGroup, systems::System};
+use crate::{framework::Framework, objects::{Object, ObjectGroup}, systems::System};
 use egui_glium::egui_winit::egui::Context;
 use glam::Mat4;
-use glium::{framebuffer::SimpleFrameBuffer, glutin::surface::WindowSurface, Display, Frame};
+use glium::{framebuffer::SimpleFrameBuffer, glutin::surface::WindowSurface, Display};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
 use super::{
-    debugger, networking, render::{Cascades, ShadowTextures}
+    debugger, networking, render::{self, Cascades, RenderQueue, ShadowTextures}
 };
 
 static mut SYSTEMS: Vec<Box<dyn System>> = vec![];
@@ -60,7 +60,7 @@ pub fn get_systems_iter<'a>() -> std::slice::Iter<'a, Box<dyn System>> {
 
 pub fn render(
     display: &Display<WindowSurface>,
-    target: &mut Frame,
+    target: &mut SimpleFrameBuffer,
     cascades: &Cascades,
     shadow_textures: &ShadowTextures,
 ) {
@@ -71,14 +71,57 @@ pub fn render(
                 //system.render_objects();
             }
         } else {
+            let mut queue = RenderQueue::default();
             for system in &mut SYSTEMS {
                 system.client_render();
-                system.render_objects(display, target, cascades, shadow_textures);
+                system.render_objects(display, target, cascades, shadow_textures, &mut queue);
+            }
+
+            // objects are looked up again by id, so nothing is kept between render and render_queued
+            let mut objects_paths: HashMap<u128, Vec<usize>> = HashMap::new();
+            for (system_index, system) in SYSTEMS.iter().enumerate() {
+                collect_objects_paths(system.objects_list(), &mut vec![system_index], &mut objects_paths);
+            }
+            let mut scene_depth_copied = false;
+            for item in queue.into_draw_order() {
+                if item.transparent && !scene_depth_copied {
+                    render::copy_scene_depth(display, target);
+                    scene_depth_copied = true;
+                }
+                let object = match objects_paths.get(&item.object_id) {
+                    Some(path) => match SYSTEMS.get_mut(path[0]) {
+                        Some(system) => object_by_path(system.objects_list_mut(), &path[1..]),
+                        None => None,
+                    },
+                    None => None,
+                };
+                match object {
+                    Some(object) => object.render_queued(display, target, shadow_textures, item.mesh_index),
+                    None => debugger::warn(&format!("render queue: object {} wasn't found", item.object_id)),
+                }
             }
         }
     }
 }
 
+/// Indices of an object in its system's objects list and in its parents' children lists.
+fn collect_objects_paths(objects: &Vec<Box<dyn Object>>, path: &mut Vec<usize>, paths: &mut HashMap<u128, Vec<usize>>) {
+    for (index, object) in objects.iter().enumerate() {
+        path.push(index);
+        paths.insert(*object.object_id(), path.clone());
+        collect_objects_paths(object.children_list(), path, paths);
+        path.pop();
+    }
+}
+
+fn object_by_path<'a>(objects: &'a mut Vec<Box<dyn Object>>, path: &[usize]) -> Option<&'a mut Box<dyn Object>> {
+    let object = objects.get_mut(*path.first()?)?;
+    match path.len() {
+        1 => Some(object),
+        _ => object_by_path(object.children_list_mut(), &path[1..]),
+    }
+}
+
 pub fn ui_render(
     ctx: &Context,
 ) {
@@ -1,15 +1,61 @@
 use egui_glium::egui_winit::egui::{self, ComboBox, TextEdit, Ui};
 use glam::Vec3;
+use std::collections::VecDeque;
 
-use crate::framework::{set_debug_mode, DebugMode};
+use crate::framework::{self, set_debug_mode, DebugMode};
 
-use super::{physics::RenderColliderType, systems};
+use super::{physics::RenderColliderType, post_process, render, systems};
 
 // inspector
 pub fn draw_inspector(ui: &mut Ui, fps: &usize, ui_state: &mut UiState) {
     ui.label(format!("fps: {}", fps));
+    ui_state
+        .frame_times
+        .push_back(framework::get_delta_time().as_secs_f32() * 1000.0);
+    if ui_state.frame_times.len() > FRAME_TIMES_COUNT {
+        ui_state.frame_times.pop_front();
+    }
+    let frame_times_count = ui_state.frame_times.len() as f32;
+    let average_frame_time = ui_state.frame_times.iter().sum::<f32>() / frame_times_count;
+    let max_frame_time = ui_state.frame_times.iter().cloned().fold(0.0, f32::max);
+    ui.label(format!(
+        "frame time: {:.2} ms avg, {:.2} ms max (last {} frames)",
+        average_frame_time, max_frame_time, frame_times_count
+    ));
+    ui.label(format!(
+        "lights: {} point, {} spot",
+        render::get_point_lights_count(),
+        render::get_spot_lights_count()
+    ));
+    let culling_stats = render::get_culling_stats();
+    ui.label(format!(
+        "drawn: {}, culled: {} ({} views)",
+        culling_stats.drawn, culling_stats.culled, culling_stats.views
+    ));
+    if culling_stats.views > 1 {
+        for (i, view_stats) in render::get_views_culling_stats().iter().enumerate() {
+            ui.label(format!(
+                "  view {}: drawn: {}, culled: {}",
+                i, view_stats.drawn, view_stats.culled
+            ));
+        }
+    }
+    ui.label(format!(
+        "shadows drawn: {}, culled: {}",
+        culling_stats.shadow_drawn, culling_stats.shadow_culled
+    ));
+    let queue_stats = render::get_render_queue_stats();
+    ui.label(format!(
+        "queued opaque: {}, transparent: {}",
+        queue_stats.opaque, queue_stats.transparent
+    ));
+    ui.label(format!(
+        "program switches: {}, texture switches: {}",
+        queue_stats.program_switches, queue_stats.texture_switches
+    ));
     ui.checkbox(&mut ui_state.full_debug_checkbox_val, "full debug");
     handle_full_debug_checkbox_value(ui_state.full_debug_checkbox_val);
+    ui.collapsing("post processing", draw_post_process_settings);
 
     ui.separator();
 
@@ -291,12 +337,40 @@ pub fn draw_inspector(ui: &mut Ui, fps: &usize, ui_state: &mut UiState) {
     }
 }
 
+fn draw_post_process_settings(ui: &mut Ui) {
+    let mut settings = post_process::get_post_process_settings();
+
+    ui.checkbox(&mut settings.tonemapping, "tonemapping");
+    ui.add(egui::Slider::new(&mut settings.exposure, 0.0..=8.0).text("exposure"));
+    ui.checkbox(&mut settings.bloom, "bloom");
+    ui.add(egui::Slider::new(&mut settings.bloom_threshold, 0.0..=4.0).text("bloom threshold"));
+    ui.add(egui::Slider::new(&mut settings.bloom_intensity, 0.0..=2.0).text("bloom intensity"));
+    ui.checkbox(&mut settings.color_grading, "color grading");
+    ui.add(egui::Slider::new(&mut settings.lut_intensity, 0.0..=1.0).text("lut intensity"));
+    ui.checkbox(&mut settings.fxaa, "fxaa");
+    ui.checkbox(&mut settings.vignette, "vignette");
+    ui.add(egui::Slider::new(&mut settings.vignette_intensity, 0.0..=1.0).text("vignette intensity"));
+
+    post_process::set_post_process_settings(settings);
+
+    for name in post_process::get_custom_passes_names() {
+        let mut enabled = post_process::is_custom_pass_enabled(&name);
+        if ui.checkbox(&mut enabled, &name).changed() {
+            post_process::set_pass_enabled(&name, enabled);
+        }
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct UiState {
     full_debug_checkbox_val: bool,
     selected_inspector_object: Option<SelectedInspectorObject>,
+    /// last FRAME_TIMES_COUNT frame times in ms
+    frame_times: VecDeque<f32>,
 }
 
+const FRAME_TIMES_COUNT: usize = 300;
+
 #[derive(Default, Debug)]
 pub struct SelectedInspectorObject {
     current_selected_object_system: String,
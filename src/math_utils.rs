@@ -1,4 +1,4 @@
-use glam::Vec3;
+use glam::{Mat4, Vec3, Vec4};
 use std::f32::consts::PI;
 
 pub fn rad_to_deg(rad: f32) -> f32 {
@@ -24,3 +24,199 @@ pub fn rad_vec_to_deg(rad_vec: Vec3) -> Vec3 {
 
     Vec3::new(x, y, z)
 }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Aabb with zero size at 0, 0, 0 if there's no points.
+    pub fn from_points(points: impl Iterator<Item = Vec3>) -> Aabb {
+        let mut aabb: Option<Aabb> = None;
+        for point in points {
+            aabb = match aabb {
+                Some(aabb) => Some(Aabb {
+                    min: aabb.min.min(point),
+                    max: aabb.max.max(point),
+                }),
+                None => Some(Aabb {
+                    min: point,
+                    max: point,
+                }),
+            };
+        }
+
+        aabb.unwrap_or(Aabb {
+            min: Vec3::ZERO,
+            max: Vec3::ZERO,
+        })
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) / 2.0
+    }
+
+    /// 0 if the point is inside.
+    pub fn distance(&self, point: Vec3) -> f32 {
+        point.clamp(self.min, self.max).distance(point)
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        [
+            Vec3::new(self.min.x, self.min.y, self.min.z),
+            Vec3::new(self.max.x, self.min.y, self.min.z),
+            Vec3::new(self.min.x, self.max.y, self.min.z),
+            Vec3::new(self.max.x, self.max.y, self.min.z),
+            Vec3::new(self.min.x, self.min.y, self.max.z),
+            Vec3::new(self.max.x, self.min.y, self.max.z),
+            Vec3::new(self.min.x, self.max.y, self.max.z),
+            Vec3::new(self.max.x, self.max.y, self.max.z),
+        ]
+    }
+
+    /// Aabb that contains this aabb transformed with `mat`.
+    pub fn transformed(&self, mat: &Mat4) -> Aabb {
+        Aabb::from_points(self.corners().iter().map(|corner| mat.transform_point3(*corner)))
+    }
+
+    pub fn scaled(&self, scale: f32) -> Aabb {
+        let center = self.center();
+        Aabb {
+            min: center + (self.min - center) * scale,
+            max: center + (self.max - center) * scale,
+        }
+    }
+}
+
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    // https://www.gamedevs.org/uploads/fast-extraction-viewing-frustum-planes-from-world-view-projection-matrix.pdf
+    pub fn from_view_proj(view_proj: &Mat4) -> Frustum {
+        let row_x = view_proj.row(0);
+        let row_y = view_proj.row(1);
+        let row_z = view_proj.row(2);
+        let row_w = view_proj.row(3);
+
+        Frustum {
+            planes: [
+                row_w + row_x,
+                row_w - row_x,
+                row_w + row_y,
+                row_w - row_y,
+                row_w + row_z,
+                row_w - row_z,
+            ],
+        }
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        for plane in &self.planes {
+            let normal = plane.truncate();
+            // the corner that is the furthest along plane's normal
+            let positive_corner = Vec3::select(normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            if normal.dot(positive_corner) + plane.w < 0.0 {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Camera at the origin looking down -z, 90 degrees fov, near 0.1, far 100.
+    fn frustum() -> Frustum {
+        let proj = Mat4::perspective_rh_gl(90.0_f32.to_radians(), 1.0, 0.1, 100.0);
+        let view = Mat4::look_at_rh(Vec3::ZERO, Vec3::NEG_Z, Vec3::Y);
+        Frustum::from_view_proj(&(proj * view))
+    }
+
+    fn cube(center: Vec3, half_size: f32) -> Aabb {
+        Aabb {
+            min: center - Vec3::splat(half_size),
+            max: center + Vec3::splat(half_size),
+        }
+    }
+
+    #[test]
+    fn accepts_boxes_inside() {
+        let frustum = frustum();
+        assert!(frustum.intersects_aabb(&cube(Vec3::new(0.0, 0.0, -10.0), 1.0)));
+        assert!(frustum.intersects_aabb(&cube(Vec3::new(5.0, -5.0, -20.0), 0.5)));
+    }
+
+    #[test]
+    fn accepts_boxes_crossing_planes() {
+        let frustum = frustum();
+        // crosses the right plane
+        assert!(frustum.intersects_aabb(&cube(Vec3::new(10.0, 0.0, -10.0), 1.0)));
+        // crosses the far plane
+        assert!(frustum.intersects_aabb(&cube(Vec3::new(0.0, 0.0, -100.0), 1.0)));
+        // camera inside the box
+        assert!(frustum.intersects_aabb(&cube(Vec3::ZERO, 1.0)));
+    }
+
+    #[test]
+    fn rejects_boxes_outside() {
+        let frustum = frustum();
+        // behind the camera
+        assert!(!frustum.intersects_aabb(&cube(Vec3::new(0.0, 0.0, 10.0), 1.0)));
+        // past the far plane
+        assert!(!frustum.intersects_aabb(&cube(Vec3::new(0.0, 0.0, -200.0), 1.0)));
+        // left, above and below
+        assert!(!frustum.intersects_aabb(&cube(Vec3::new(-30.0, 0.0, -10.0), 1.0)));
+        assert!(!frustum.intersects_aabb(&cube(Vec3::new(0.0, 30.0, -10.0), 1.0)));
+        assert!(!frustum.intersects_aabb(&cube(Vec3::new(0.0, -30.0, -10.0), 1.0)));
+    }
+
+    #[test]
+    fn box_from_points_contains_them() {
+        let points = [Vec3::new(1.0, -2.0, 3.0), Vec3::new(-1.0, 4.0, 0.0), Vec3::new(0.5, 0.0, -3.0)];
+        let aabb = Aabb::from_points(points.into_iter());
+        assert_eq!(aabb.min, Vec3::new(-1.0, -2.0, -3.0));
+        assert_eq!(aabb.max, Vec3::new(1.0, 4.0, 3.0));
+        assert_eq!(Aabb::from_points(std::iter::empty()), cube(Vec3::ZERO, 0.0));
+    }
+
+    #[test]
+    fn union_and_scale_of_boxes() {
+        let union = cube(Vec3::ZERO, 1.0).union(&cube(Vec3::new(4.0, 0.0, 0.0), 1.0));
+        assert_eq!(union.min, Vec3::new(-1.0, -1.0, -1.0));
+        assert_eq!(union.max, Vec3::new(5.0, 1.0, 1.0));
+        assert_eq!(cube(Vec3::new(2.0, 0.0, 0.0), 1.0).scaled(2.0), cube(Vec3::new(2.0, 0.0, 0.0), 2.0));
+    }
+
+    #[test]
+    fn scaled_box_stays_visible_near_the_frustum() {
+        // a box just past the left plane is culled, the bounds of an animated mesh are scaled so it's kept
+        let frustum = frustum();
+        let aabb = cube(Vec3::new(-14.0, 0.0, -10.0), 1.0);
+        assert!(!frustum.intersects_aabb(&aabb));
+        assert!(frustum.intersects_aabb(&aabb.scaled(3.0)));
+    }
+
+    #[test]
+    fn transformed_box_contains_rotated_corners() {
+        let aabb = cube(Vec3::ZERO, 1.0);
+        let rotated = aabb.transformed(&Mat4::from_rotation_y(std::f32::consts::FRAC_PI_4));
+        let half_diagonal = 2.0_f32.sqrt();
+        assert!((rotated.max.x - half_diagonal).abs() < 1e-5);
+        assert!((rotated.min.z + half_diagonal).abs() < 1e-5);
+        assert!((rotated.max.y - 1.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,987 @@
+use std::collections::HashMap;
+use egui_glium::egui_winit::egui::{DragValue, Slider, Ui};
+use glam::{Mat4, Quat, Vec3, Vec4};
+use crate::assets::model_asset::{self, Animation, AnimationChannelType, ModelAsset};
+
+/// Layer that always exists, `play_animation` and friends use it.
+pub const BASE_LAYER: &str = "base";
+
+#[derive(Debug)]
+pub struct NodeTransform {
+    pub local_position: Vec3,
+    pub local_rotation: Quat,
+    pub local_scale: Vec3,
+    pub global_transform: Option<Mat4>,
+    pub parent_global_transform: Option<Mat4>,
+    pub node_id: usize,
+    /// weights of the node mesh's morph targets, animated by morph target weights channels
+    pub morph_weights: Vec<f32>,
+}
+
+#[derive(Debug, Clone)]
+struct NodePose {
+    position: Vec3,
+    rotation: Quat,
+    scale: Vec3,
+    morph_weights: Vec<f32>,
+}
+
+/// Weighted sum of poses, rotations are added as 4d vectors and normalized at the end.
+#[derive(Debug, Clone, Default)]
+struct PoseSum {
+    position: Vec3,
+    rotation: Vec4,
+    scale: Vec3,
+    morph_weights: Vec<f32>,
+}
+
+type Pose = HashMap<usize, NodePose>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationBlendMode {
+    /// Replaces the pose below, weighted by the layer weight.
+    Override,
+    /// Adds the difference between the clip and its first frame to the pose below.
+    Additive,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct WeightFade {
+    from: f32,
+    to: f32,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl WeightFade {
+    fn new(from: f32, to: f32, duration: f32) -> WeightFade {
+        WeightFade { from, to, elapsed: 0.0, duration }
+    }
+
+    /// Current weight and whether the fade is over.
+    fn advance(&mut self, delta: f32) -> (f32, bool) {
+        self.elapsed += delta;
+        let progress = match self.duration > 0.0 {
+            true => (self.elapsed / self.duration).min(1.0),
+            false => 1.0,
+        };
+
+        (self.from + (self.to - self.from) * progress, progress >= 1.0)
+    }
+}
+
+/// Event key that a track passed during the last update.
+#[derive(Debug, Clone)]
+pub struct AnimationEvent {
+    pub name: String,
+    pub animation: String,
+    pub layer: String,
+    /// time of the event key in the animation
+    pub time: f32,
+}
+
+pub type AnimationEventCallback = Box<dyn FnMut(&AnimationEvent)>;
+
+struct AnimationEventCallbacks(Vec<AnimationEventCallback>);
+
+impl std::fmt::Debug for AnimationEventCallbacks {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "{} animation event callbacks", self.0.len())
+    }
+}
+
+#[derive(Debug)]
+pub struct AnimationTrack {
+    pub animation: Animation,
+    pub looping: bool,
+    /// multiplied by the model's animation speed, negative plays backwards
+    pub speed: f32,
+    time: f32,
+    /// events at the current time weren't fired yet
+    fresh: bool,
+    finished: bool,
+    weight: f32,
+    fade: Option<WeightFade>,
+    /// removed once it fades out
+    stopping: bool,
+    /// (from, to) times covered by the last advance, two ranges when a looping track wrapped around
+    last_segments: Vec<(f32, f32)>,
+}
+
+impl AnimationTrack {
+    fn new(animation: Animation, looping: bool, weight: f32, fade_duration: f32) -> AnimationTrack {
+        let mut track = AnimationTrack {
+            animation,
+            looping,
+            speed: 1.0,
+            time: 0.0,
+            fresh: true,
+            finished: false,
+            weight: 0.0,
+            fade: None,
+            stopping: false,
+            last_segments: vec![],
+        };
+        track.fade_to(weight, fade_duration);
+
+        track
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// 0 - 1 of the animation duration.
+    pub fn normalized_time(&self) -> f32 {
+        match self.animation.duration > 0.0 {
+            true => self.time / self.animation.duration,
+            false => 0.0,
+        }
+    }
+
+    fn seek(&mut self, time: f32) {
+        self.time = time.clamp(0.0, self.animation.duration);
+        self.fresh = true;
+        self.finished = false;
+    }
+
+    /// Moves the time and collects the event keys it passed.
+    fn advance(&mut self, delta: f32, events: &mut Vec<(String, f32)>) {
+        let delta = delta * self.speed;
+        let duration = self.animation.duration;
+        let previous = self.time;
+        let mut time = previous + delta;
+        let include_start = std::mem::replace(&mut self.fresh, false);
+
+        let looping = self.looping && duration > 0.0;
+        if !looping {
+            time = time.clamp(0.0, duration);
+        }
+
+        let mut fire = |from: f32, to: f32, include_from: bool, include_to: bool| {
+            for event in &self.animation.events {
+                let after_from = event.time > from || (include_from && event.time == from);
+                let before_to = event.time < to || (include_to && event.time == to);
+                if after_from && before_to {
+                    events.push((event.name.clone(), event.time));
+                }
+            }
+        };
+
+        self.last_segments.clear();
+        if delta > 0.0 || (include_start && delta == 0.0) {
+            if looping && time >= duration {
+                fire(previous, duration, include_start, true);
+                time = time.rem_euclid(duration);
+                fire(0.0, time, true, true);
+                self.last_segments.extend([(previous, duration), (0.0, time)]);
+            } else {
+                fire(previous, time, include_start, true);
+                self.last_segments.push((previous, time));
+            }
+        } else if delta < 0.0 {
+            if looping && time < 0.0 {
+                fire(0.0, previous, true, include_start);
+                time = time.rem_euclid(duration);
+                fire(time, duration, true, true);
+                self.last_segments.extend([(previous, 0.0), (duration, time)]);
+            } else {
+                fire(time, previous, true, include_start);
+                self.last_segments.push((previous, time));
+            }
+        }
+
+        self.time = time;
+        if !looping && ((delta > 0.0 && time >= duration) || (delta < 0.0 && time <= 0.0)) {
+            self.finished = true;
+        }
+    }
+
+    fn fade_to(&mut self, weight: f32, duration: f32) {
+        match duration > 0.0 {
+            true => self.fade = Some(WeightFade::new(self.weight, weight, duration)),
+            false => {
+                self.weight = weight;
+                self.fade = None;
+            }
+        }
+    }
+
+    fn fade_out(&mut self, duration: f32) {
+        self.fade_to(0.0, duration);
+        self.stopping = true;
+    }
+}
+
+#[derive(Debug)]
+pub struct AnimationLayer {
+    pub name: String,
+    pub blend_mode: AnimationBlendMode,
+    /// indices of the nodes this layer is allowed to move, None means all of them
+    pub mask: Option<Vec<usize>>,
+    pub tracks: Vec<AnimationTrack>,
+    weight: f32,
+    fade: Option<WeightFade>,
+}
+
+impl AnimationLayer {
+    fn new(name: &str, blend_mode: AnimationBlendMode, mask: Option<Vec<usize>>) -> AnimationLayer {
+        AnimationLayer {
+            name: name.into(),
+            blend_mode,
+            mask,
+            tracks: vec![],
+            weight: 1.0,
+            fade: None,
+        }
+    }
+
+    fn is_masked(&self, node_id: usize) -> bool {
+        match &self.mask {
+            Some(mask) => !mask.contains(&node_id),
+            None => false,
+        }
+    }
+
+    fn update_weights(&mut self, delta: f32) {
+        if let Some(fade) = &mut self.fade {
+            let (weight, finished) = fade.advance(delta);
+            self.weight = weight;
+            if finished {
+                self.fade = None;
+            }
+        }
+
+        self.tracks.retain_mut(|track| {
+            if let Some(fade) = &mut track.fade {
+                let (weight, finished) = fade.advance(delta);
+                track.weight = weight;
+                if finished {
+                    track.fade = None;
+                }
+            }
+
+            !(track.stopping && track.fade.is_none())
+        });
+    }
+
+    /// Movement of the root node over the time the tracks advanced last, weighted like the tracks.
+    fn root_motion_delta(&self, settings: RootMotionSettings, rest_pose: &Pose) -> RootMotionDelta {
+        let mut result = RootMotionDelta::default();
+        let rest = match rest_pose.get(&settings.node_index) {
+            Some(rest) => rest,
+            None => return result,
+        };
+        let weights_sum: f32 = self.tracks.iter().map(|track| track.weight).sum();
+        if weights_sum <= 0.0 {
+            return result;
+        }
+
+        let amount = weights_sum.min(1.0) * self.weight;
+        for track in &self.tracks {
+            let factor = track.weight / weights_sum * amount;
+            for (from, to) in &track.last_segments {
+                let from = sample_node(&track.animation, settings.node_index, *from, rest);
+                let to = sample_node(&track.animation, settings.node_index, *to, rest);
+                result.translation += (to.position - from.position) * Vec3::new(1.0, 0.0, 1.0) * factor;
+                if settings.rotation {
+                    let turn = (yaw(to.rotation) - yaw(from.rotation) + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+                    result.yaw += turn * factor;
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Blends all tracks on top of `input`.
+    fn blend(&self, input: &Pose, rest_pose: &Pose) -> Pose {
+        let weights_sum: f32 = self.tracks.iter().map(|track| track.weight).sum();
+        let mut result = input.clone();
+        if weights_sum <= 0.0 {
+            return result;
+        }
+
+        let mut accumulated: HashMap<usize, PoseSum> = HashMap::new();
+        for track in &self.tracks {
+            let time = track.time;
+            let factor = track.weight / weights_sum;
+
+            // every track covers every node, so the factors always add up to 1
+            let sampled = match self.blend_mode {
+                AnimationBlendMode::Override => {
+                    let mut sampled = input.clone();
+                    sampled.extend(sample_animation(&track.animation, time, input));
+                    sampled
+                }
+                AnimationBlendMode::Additive => {
+                    let mut delta: Pose = input
+                        .iter()
+                        .map(|(node_id, pose)| (*node_id, NodePose {
+                            position: Vec3::ZERO,
+                            rotation: Quat::IDENTITY,
+                            scale: Vec3::ONE,
+                            morph_weights: vec![0.0; pose.morph_weights.len()],
+                        }))
+                        .collect();
+                    let reference = sample_animation(&track.animation, 0.0, rest_pose);
+                    for (node_id, pose) in sample_animation(&track.animation, time, rest_pose) {
+                        let reference = &reference[&node_id];
+                        delta.insert(node_id, NodePose {
+                            position: pose.position - reference.position,
+                            rotation: reference.rotation.inverse() * pose.rotation,
+                            scale: pose.scale / reference.scale,
+                            morph_weights: pose.morph_weights.iter().zip(&reference.morph_weights).map(|(weight, reference)| weight - reference).collect(),
+                        });
+                    }
+                    delta
+                }
+            };
+
+            for (node_id, pose) in sampled {
+                let entry = accumulated.entry(node_id).or_default();
+                // q and -q are the same rotation, keep them in one hemisphere so they don't cancel out
+                let rotation = Vec4::from(pose.rotation);
+                let rotation = match entry.rotation.dot(rotation) < 0.0 {
+                    true => -rotation,
+                    false => rotation,
+                };
+                entry.position += pose.position * factor;
+                entry.rotation += rotation * factor;
+                entry.scale += pose.scale * factor;
+                entry.morph_weights.resize(pose.morph_weights.len(), 0.0);
+                for (sum, weight) in entry.morph_weights.iter_mut().zip(&pose.morph_weights) {
+                    *sum += weight * factor;
+                }
+            }
+        }
+
+        let amount = weights_sum.min(1.0) * self.weight;
+        for (node_id, blended) in accumulated {
+            if self.is_masked(node_id) {
+                continue;
+            }
+            let blended_rotation = match blended.rotation.length_squared() > f32::EPSILON {
+                true => Quat::from_vec4(blended.rotation.normalize()),
+                false => Quat::IDENTITY,
+            };
+
+            if let Some(pose) = result.get_mut(&node_id) {
+                match self.blend_mode {
+                    AnimationBlendMode::Override => {
+                        pose.position = pose.position.lerp(blended.position, amount);
+                        pose.rotation = pose.rotation.slerp(blended_rotation, amount);
+                        pose.scale = pose.scale.lerp(blended.scale, amount);
+                        for (weight, blended_weight) in pose.morph_weights.iter_mut().zip(&blended.morph_weights) {
+                            *weight += (blended_weight - *weight) * amount;
+                        }
+                    }
+                    AnimationBlendMode::Additive => {
+                        pose.position += blended.position * amount;
+                        pose.rotation = (pose.rotation * Quat::IDENTITY.slerp(blended_rotation, amount)).normalize();
+                        pose.scale *= Vec3::ONE.lerp(blended.scale, amount);
+                        for (weight, blended_weight) in pose.morph_weights.iter_mut().zip(&blended.morph_weights) {
+                            *weight += blended_weight * amount;
+                        }
+                    }
+                }
+            }
+        }
+
+        result
+    }
+}
+
+/// Takes the horizontal movement (and turning) of a root joint out of the pose,
+/// so the object or a character controller can be moved by it instead.
+#[derive(Debug, Clone, Copy)]
+pub struct RootMotionSettings {
+    pub node_index: usize,
+    /// also take the rotation around the up axis
+    pub rotation: bool,
+}
+
+/// Root motion of one update, in the space of the root joint's parent.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RootMotionDelta {
+    pub translation: Vec3,
+    /// radians around the up axis
+    pub yaw: f32,
+}
+
+/// All the animations a model is playing, in layers that are blended from first to last.
+#[derive(Debug)]
+pub struct CurrentAnimationSettings {
+    pub layers: Vec<AnimationLayer>,
+    /// used for tracks started with `play_animation`
+    pub looping: bool,
+    /// multiplies the speed of every track, negative plays backwards
+    pub speed: f32,
+    pub paused: bool,
+    /// events fired during the last update
+    pub fired_events: Vec<AnimationEvent>,
+    pub root_motion: Option<RootMotionSettings>,
+    /// root motion of the base layer during the last update
+    pub root_motion_delta: RootMotionDelta,
+    event_callbacks: AnimationEventCallbacks,
+    rest_pose: Pose,
+    /// pose of the base layer when it stopped playing, so the model doesn't snap back
+    held_pose: Pose,
+    active: bool,
+}
+
+impl CurrentAnimationSettings {
+    pub fn new(nodes_transforms: &Vec<NodeTransform>) -> CurrentAnimationSettings {
+        let mut rest_pose: Pose = HashMap::new();
+        for node in nodes_transforms {
+            rest_pose.insert(node.node_id, NodePose {
+                position: node.local_position,
+                rotation: node.local_rotation,
+                scale: node.local_scale,
+                morph_weights: node.morph_weights.clone(),
+            });
+        }
+
+        CurrentAnimationSettings {
+            layers: vec![AnimationLayer::new(BASE_LAYER, AnimationBlendMode::Override, None)],
+            looping: false,
+            speed: 1.0,
+            paused: false,
+            fired_events: vec![],
+            root_motion: None,
+            root_motion_delta: RootMotionDelta::default(),
+            event_callbacks: AnimationEventCallbacks(vec![]),
+            held_pose: rest_pose.clone(),
+            rest_pose,
+            active: false,
+        }
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&AnimationLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    fn layer_mut(&mut self, name: &str) -> Result<&mut AnimationLayer, AnimationError> {
+        self.layers
+            .iter_mut()
+            .find(|layer| layer.name == name)
+            .ok_or(AnimationError::LayerNotFound)
+    }
+
+    /// Adds a layer on top of the others, replaces it if it already exists.
+    pub fn add_layer(&mut self, name: &str, blend_mode: AnimationBlendMode, mask: Option<Vec<usize>>) {
+        match self.layer_mut(name) {
+            Ok(layer) => {
+                layer.blend_mode = blend_mode;
+                layer.mask = mask;
+            }
+            Err(_) => self.layers.push(AnimationLayer::new(name, blend_mode, mask)),
+        }
+    }
+
+    pub fn remove_layer(&mut self, name: &str) -> Result<(), AnimationError> {
+        if name == BASE_LAYER {
+            return Err(AnimationError::BaseLayerRemoval);
+        }
+        self.layer_mut(name)?;
+        self.layers.retain(|layer| layer.name != name);
+        self.active = true;
+
+        Ok(())
+    }
+
+    pub fn set_layer_weight(&mut self, name: &str, weight: f32, fade_duration: f32) -> Result<(), AnimationError> {
+        let layer = self.layer_mut(name)?;
+        let weight = weight.clamp(0.0, 1.0);
+        match fade_duration > 0.0 {
+            true => layer.fade = Some(WeightFade::new(layer.weight, weight, fade_duration)),
+            false => {
+                layer.weight = weight;
+                layer.fade = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fades out everything else on the layer and fades the animation in.
+    /// With a zero fade duration it's an instant switch.
+    pub fn play(&mut self, layer_name: &str, animation: Animation, looping: bool, fade_duration: f32) -> Result<(), AnimationError> {
+        let layer = self.layer_mut(layer_name)?;
+        match fade_duration > 0.0 {
+            true => layer.tracks.iter_mut().for_each(|track| track.fade_out(fade_duration)),
+            false => layer.tracks.clear(),
+        }
+        let mut track = AnimationTrack::new(animation, looping, 1.0, fade_duration);
+        if self.speed < 0.0 {
+            track.seek(track.animation.duration);
+        }
+        self.layer_mut(layer_name)?.tracks.push(track);
+
+        Ok(())
+    }
+
+    /// Plays the animation along with the ones already on the layer,
+    /// or changes its weight if it's already playing.
+    pub fn blend(&mut self, layer: &str, animation: Animation, weight: f32, looping: bool, fade_duration: f32) -> Result<(), AnimationError> {
+        let reversed = self.speed < 0.0;
+        let layer = self.layer_mut(layer)?;
+        let weight = weight.max(0.0);
+        let playing = layer
+            .tracks
+            .iter_mut()
+            .find(|track| track.animation.name == animation.name && !track.stopping);
+
+        match playing {
+            Some(track) => track.fade_to(weight, fade_duration),
+            None => {
+                let mut track = AnimationTrack::new(animation, looping, weight, fade_duration);
+                if reversed {
+                    track.seek(track.animation.duration);
+                }
+                layer.tracks.push(track);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn set_weight(&mut self, layer: &str, anim_name: &str, weight: f32, fade_duration: f32) -> Result<(), AnimationError> {
+        let layer = self.layer_mut(layer)?;
+        let mut found = false;
+        for track in &mut layer.tracks {
+            if track.animation.name == anim_name && !track.stopping {
+                track.fade_to(weight.max(0.0), fade_duration);
+                found = true;
+            }
+        }
+
+        match found {
+            true => Ok(()),
+            false => Err(AnimationError::TrackNotFound),
+        }
+    }
+
+    /// Track of the animation that isn't fading out or, if `anim_name` is None, the most weighted one.
+    pub fn track(&self, layer: &str, anim_name: Option<&str>) -> Option<&AnimationTrack> {
+        let mut tracks = self.layer(layer)?.tracks.iter().filter(|track| !track.stopping);
+        match anim_name {
+            Some(anim_name) => tracks.rfind(|track| track.animation.name == anim_name),
+            None => tracks.max_by(|a, b| a.weight.total_cmp(&b.weight)),
+        }
+    }
+
+    /// Sets the speed of one animation, the model's `speed` still multiplies it.
+    pub fn set_track_speed(&mut self, layer: &str, anim_name: &str, speed: f32) -> Result<(), AnimationError> {
+        let layer = self.layer_mut(layer)?;
+        let mut found = false;
+        for track in layer.tracks.iter_mut().filter(|track| track.animation.name == anim_name) {
+            track.speed = speed;
+            found = true;
+        }
+
+        match found {
+            true => Ok(()),
+            false => Err(AnimationError::TrackNotFound),
+        }
+    }
+
+    /// Jumps to `time` seconds of one animation or, if `anim_name` is None, of every animation on the layer.
+    /// Events between the old and the new time aren't fired.
+    pub fn seek(&mut self, layer: &str, anim_name: Option<&str>, time: f32) -> Result<(), AnimationError> {
+        let layer = self.layer_mut(layer)?;
+        let mut found = false;
+        for track in &mut layer.tracks {
+            if anim_name.is_none() || anim_name == Some(track.animation.name.as_str()) {
+                track.seek(time);
+                found = true;
+            }
+        }
+        self.active = true;
+
+        match found {
+            true => Ok(()),
+            false => Err(AnimationError::TrackNotFound),
+        }
+    }
+
+    /// Adds the event key to the animation if it's playing, the asset has to be changed for future tracks.
+    pub fn add_event(&mut self, anim_name: &str, time: f32, event_name: &str) {
+        for layer in &mut self.layers {
+            for track in layer.tracks.iter_mut().filter(|track| track.animation.name == anim_name) {
+                track.animation.add_event(time, event_name);
+            }
+        }
+    }
+
+    /// Called for every fired event after the pose is updated.
+    pub fn add_event_callback(&mut self, callback: AnimationEventCallback) {
+        self.event_callbacks.0.push(callback);
+    }
+
+    pub fn clear_event_callbacks(&mut self) {
+        self.event_callbacks.0.clear();
+    }
+
+    /// Animations of the layer that aren't fading out.
+    pub fn playing_animations(&self, layer: &str) -> Vec<&str> {
+        match self.layer(layer) {
+            Some(layer) => layer
+                .tracks
+                .iter()
+                .filter(|track| !track.stopping)
+                .map(|track| track.animation.name.as_str())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Stops one animation or, if `anim_name` is None, all of the layer's animations.
+    pub fn stop(&mut self, layer: &str, anim_name: Option<&str>, fade_duration: f32) -> Result<(), AnimationError> {
+        let layer = self.layer_mut(layer)?;
+        let matches = |track: &AnimationTrack| anim_name.is_none() || anim_name == Some(track.animation.name.as_str());
+        match fade_duration > 0.0 {
+            true => layer.tracks.iter_mut().filter(|track| matches(track)).for_each(|track| track.fade_out(fade_duration)),
+            false => layer.tracks.retain(|track| !matches(track)),
+        }
+
+        Ok(())
+    }
+
+    pub fn stop_all(&mut self) {
+        self.layers.iter_mut().for_each(|layer| layer.tracks.clear());
+    }
+
+    pub fn set_looping(&mut self, should_loop: bool) {
+        self.looping = should_loop;
+        if let Ok(layer) = self.layer_mut(BASE_LAYER) {
+            layer.tracks.iter_mut().for_each(|track| track.looping = should_loop);
+        }
+    }
+
+    /// The most weighted animation of the base layer that isn't fading out.
+    pub fn current_animation(&self) -> Option<&str> {
+        let layer = self.layer(BASE_LAYER)?;
+        layer
+            .tracks
+            .iter()
+            .filter(|track| !track.stopping)
+            .max_by(|a, b| a.weight.total_cmp(&b.weight))
+            .or(layer.tracks.last())
+            .map(|track| track.animation.name.as_str())
+    }
+
+    /// Delta time scaled by the speed, 0 when paused. Negative when playing backwards.
+    pub fn playback_delta(&self, delta: f32) -> f32 {
+        match self.paused {
+            true => 0.0,
+            false => delta * self.speed,
+        }
+    }
+
+    /// Advances the tracks by `delta` seconds, samples every layer, writes the result to the nodes
+    /// and calls the event callbacks.
+    pub fn update(&mut self, nodes_transforms: &mut Vec<NodeTransform>, delta: f32) {
+        self.fired_events.clear();
+        self.root_motion_delta = RootMotionDelta::default();
+        let playing = self.layers.iter().any(|layer| !layer.tracks.is_empty());
+        if !playing && !self.active {
+            return;
+        }
+        self.active = playing;
+
+        let fade_delta = match self.paused {
+            true => 0.0,
+            false => delta,
+        };
+        let playback_delta = self.playback_delta(delta);
+        let mut pose = self.held_pose.clone();
+        for (layer_index, layer) in self.layers.iter_mut().enumerate() {
+            layer.update_weights(fade_delta);
+            if layer.tracks.is_empty() {
+                continue;
+            }
+
+            for track in &mut layer.tracks {
+                let mut events = vec![];
+                track.advance(playback_delta, &mut events);
+                for (name, time) in events {
+                    self.fired_events.push(AnimationEvent {
+                        name,
+                        animation: track.animation.name.clone(),
+                        layer: layer.name.clone(),
+                        time,
+                    });
+                }
+            }
+
+            let input = match layer_index {
+                0 => &self.rest_pose,
+                _ => &pose,
+            };
+            let blended = layer.blend(input, &self.rest_pose);
+            if layer_index == 0 {
+                self.held_pose = blended.clone();
+                if let Some(root_motion) = self.root_motion {
+                    self.root_motion_delta = layer.root_motion_delta(root_motion, &self.rest_pose);
+                }
+            }
+            pose = blended;
+            layer.tracks.retain(|track| !track.finished);
+        }
+
+        if let Some(root_motion) = self.root_motion {
+            if let (Some(root_pose), Some(rest)) = (pose.get_mut(&root_motion.node_index), self.rest_pose.get(&root_motion.node_index)) {
+                root_pose.position.x = rest.position.x;
+                root_pose.position.z = rest.position.z;
+                if root_motion.rotation {
+                    root_pose.rotation = Quat::from_rotation_y(yaw(rest.rotation) - yaw(root_pose.rotation)) * root_pose.rotation;
+                }
+            }
+        }
+
+        for node in nodes_transforms {
+            if let Some(node_pose) = pose.get(&node.node_id) {
+                node.local_position = node_pose.position;
+                node.local_rotation = node_pose.rotation;
+                node.local_scale = node_pose.scale;
+                node.morph_weights.clone_from(&node_pose.morph_weights);
+            }
+        }
+
+        for event in &self.fired_events {
+            for callback in &mut self.event_callbacks.0 {
+                callback(event);
+            }
+        }
+    }
+
+    pub fn inspector_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("speed");
+            ui.add(DragValue::new(&mut self.speed).speed(0.05));
+            ui.checkbox(&mut self.paused, "paused");
+        });
+        for layer in &mut self.layers {
+            ui.collapsing(format!("layer '{}' ({:?}, weight {:.2})", layer.name, layer.blend_mode, layer.weight), |ui| {
+                if let Some(mask) = &layer.mask {
+                    ui.label(format!("mask: {} nodes", mask.len()));
+                }
+                for track in &mut layer.tracks {
+                    ui.label(format!(
+                        "'{}' weight {:.2}, speed {:.2}{}",
+                        track.animation.name,
+                        track.weight,
+                        track.speed,
+                        if track.looping { ", looping" } else { "" }
+                    ));
+                    let mut time = track.time;
+                    let duration = track.animation.duration;
+                    if ui.add(Slider::new(&mut time, 0.0..=duration).suffix(" s")).changed() {
+                        track.seek(time);
+                    }
+                }
+                if ui.button("stop").clicked() {
+                    layer.tracks.clear();
+                }
+            });
+        }
+    }
+}
+
+/// Node indices of the named node and everything below it, for layer masks.
+pub fn joint_mask(asset: &ModelAsset, joint_name: &str) -> Option<Vec<usize>> {
+    let root = asset.nodes.iter().find(|node| node.name == joint_name)?;
+    let mut mask = vec![];
+    let mut stack = vec![root.node_index];
+    while let Some(node_index) = stack.pop() {
+        mask.push(node_index);
+        if let Some(node) = asset.nodes.iter().find(|node| node.node_index == node_index) {
+            stack.extend(node.children_id.iter().copied());
+        }
+    }
+
+    Some(mask)
+}
+
+/// Morph target weights of a model asset's object: weights of its node with `overrides` (by target name) on top.
+pub fn morph_weights(object: &model_asset::Object, node: &NodeTransform, overrides: &HashMap<String, f32>) -> Vec<f32> {
+    let mut weights = node.morph_weights.clone();
+    weights.resize(object.morph_targets.len(), 0.0);
+    for (weight, target) in weights.iter_mut().zip(&object.morph_targets) {
+        if let Some(overridden) = overrides.get(&target.name) {
+            *weight = *overridden;
+        }
+    }
+
+    weights
+}
+
+/// Rotation around the y axis, from where the rotated z axis points.
+fn yaw(rotation: Quat) -> f32 {
+    let forward = rotation * Vec3::Z;
+    forward.x.atan2(forward.z)
+}
+
+fn sample_node(animation: &Animation, node_index: usize, time: f32, base: &NodePose) -> NodePose {
+    let mut node_pose = base.clone();
+    for channel in animation.channels.iter().filter(|channel| channel.node_index == node_index) {
+        match channel.channel_type {
+            AnimationChannelType::Translation => {
+                node_pose.position = channel.sample_vec3(time).unwrap_or(node_pose.position);
+            }
+            AnimationChannelType::Rotation => {
+                node_pose.rotation = channel.sample_quat(time).unwrap_or(node_pose.rotation);
+            }
+            _ => (),
+        }
+    }
+
+    node_pose
+}
+
+/// Pose of the animated nodes only, nodes missing in `base` are skipped.
+fn sample_animation(animation: &Animation, time: f32, base: &Pose) -> Pose {
+    let mut pose: Pose = HashMap::new();
+    for channel in &animation.channels {
+        let mut node_pose = match pose.get(&channel.node_index).or(base.get(&channel.node_index)) {
+            Some(node_pose) => node_pose.clone(),
+            None => continue,
+        };
+        match channel.channel_type {
+            AnimationChannelType::Translation => {
+                node_pose.position = channel.sample_vec3(time).unwrap_or(node_pose.position);
+            }
+            AnimationChannelType::Rotation => {
+                node_pose.rotation = channel.sample_quat(time).unwrap_or(node_pose.rotation);
+            }
+            AnimationChannelType::Scale => {
+                node_pose.scale = channel.sample_vec3(time).unwrap_or(node_pose.scale);
+            }
+            AnimationChannelType::MorphTargetWeights => {
+                if let Some(weights) = channel.sample_weights(time) {
+                    for (weight, sampled) in node_pose.morph_weights.iter_mut().zip(weights) {
+                        *weight = sampled;
+                    }
+                }
+            }
+        }
+        pose.insert(channel.node_index, node_pose);
+    }
+
+    pose
+}
+
+#[derive(Debug)]
+pub enum AnimationError {
+    LayerNotFound,
+    TrackNotFound,
+    BaseLayerRemoval,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn walk_animation() -> Animation {
+        let mut animation = Animation {
+            name: "walk".into(),
+            channels: vec![],
+            duration: 1.0,
+            events: vec![],
+        };
+        animation.add_event(0.75, "right_step");
+        animation.add_event(0.25, "left_step");
+        animation
+    }
+
+    fn update_events(settings: &mut CurrentAnimationSettings, delta: f32) -> Vec<(String, f32)> {
+        settings.update(&mut vec![], delta);
+        settings.fired_events.iter().map(|event| (event.name.clone(), event.time)).collect()
+    }
+
+    fn event(name: &str, time: f32) -> (String, f32) {
+        (name.into(), time)
+    }
+
+    #[test]
+    fn events_fire_once_when_passed() {
+        let mut settings = CurrentAnimationSettings::new(&vec![]);
+        settings.play(BASE_LAYER, walk_animation(), true, 0.0).unwrap();
+        assert_eq!(update_events(&mut settings, 0.125), vec![]);
+        assert_eq!(update_events(&mut settings, 0.25), vec![event("left_step", 0.25)]);
+        assert_eq!(update_events(&mut settings, 0.125), vec![]);
+    }
+
+    #[test]
+    fn events_fire_across_a_loop_wrap() {
+        let mut settings = CurrentAnimationSettings::new(&vec![]);
+        settings.play(BASE_LAYER, walk_animation(), true, 0.0).unwrap();
+        update_events(&mut settings, 0.5);
+        assert_eq!(
+            update_events(&mut settings, 0.875),
+            vec![event("right_step", 0.75), event("left_step", 0.25)]
+        );
+        let time = settings.track(BASE_LAYER, Some("walk")).unwrap().time();
+        assert_eq!(time, 0.375);
+    }
+
+    #[test]
+    fn events_fire_across_a_reversed_loop_wrap() {
+        let mut settings = CurrentAnimationSettings::new(&vec![]);
+        settings.speed = -1.0;
+        settings.play(BASE_LAYER, walk_animation(), true, 0.0).unwrap();
+        assert_eq!(update_events(&mut settings, 0.5), vec![event("right_step", 0.75)]);
+        assert_eq!(
+            update_events(&mut settings, 0.875),
+            vec![event("left_step", 0.25), event("right_step", 0.75)]
+        );
+    }
+
+    #[test]
+    fn event_at_the_start_fires_on_every_loop() {
+        let mut animation = walk_animation();
+        animation.add_event(0.0, "start");
+        let mut settings = CurrentAnimationSettings::new(&vec![]);
+        settings.play(BASE_LAYER, animation, true, 0.0).unwrap();
+        assert_eq!(update_events(&mut settings, 0.125), vec![event("start", 0.0)]);
+        assert_eq!(
+            update_events(&mut settings, 1.0),
+            vec![event("left_step", 0.25), event("right_step", 0.75), event("start", 0.0)]
+        );
+    }
+
+    #[test]
+    fn seek_skips_events_in_between() {
+        let mut settings = CurrentAnimationSettings::new(&vec![]);
+        settings.play(BASE_LAYER, walk_animation(), true, 0.0).unwrap();
+        update_events(&mut settings, 0.125);
+        settings.seek(BASE_LAYER, Some("walk"), 0.5).unwrap();
+        assert_eq!(update_events(&mut settings, 0.125), vec![]);
+    }
+
+    #[test]
+    fn seek_to_an_event_fires_it() {
+        let mut settings = CurrentAnimationSettings::new(&vec![]);
+        settings.play(BASE_LAYER, walk_animation(), true, 0.0).unwrap();
+        update_events(&mut settings, 0.5);
+        settings.seek(BASE_LAYER, None, 0.25).unwrap();
+        assert_eq!(update_events(&mut settings, 0.125), vec![event("left_step", 0.25)]);
+        assert!(matches!(
+            settings.seek(BASE_LAYER, Some("run"), 0.0),
+            Err(AnimationError::TrackNotFound)
+        ));
+    }
+
+    #[test]
+    fn one_shot_animation_fires_its_events_and_ends() {
+        let mut settings = CurrentAnimationSettings::new(&vec![]);
+        settings.play(BASE_LAYER, walk_animation(), false, 0.0).unwrap();
+        assert_eq!(
+            update_events(&mut settings, 2.0),
+            vec![event("left_step", 0.25), event("right_step", 0.75)]
+        );
+        assert!(settings.playing_animations(BASE_LAYER).is_empty());
+        assert_eq!(update_events(&mut settings, 2.0), vec![]);
+    }
+}
@@ -0,0 +1,724 @@
+use std::collections::HashMap;
+
+use egui_glium::egui_winit::egui::{DragValue, Ui};
+use glam::Vec2;
+
+use super::animation::{CurrentAnimationSettings, BASE_LAYER};
+use crate::assets::{
+    animator_asset::{
+        AnimatorAsset, AnimatorAssetError, AnimatorCondition, AnimatorParameterDefinition, AnimatorState,
+        AnimatorTransition, BlendSpace, BlendSpaceClip, ConditionOp, ConditionValue, ANY_STATE,
+    },
+    model_asset::ModelAsset,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimatorParameter {
+    Float(f32),
+    Bool(bool),
+    /// true while it's set
+    Trigger(bool),
+}
+
+/// Runs an `AnimatorAsset` state machine on the base animation layer of a model.
+#[derive(Debug)]
+pub struct Animator {
+    pub asset: AnimatorAsset,
+    parameters: HashMap<String, AnimatorParameter>,
+    current_state: usize,
+    /// seconds of clip time spent in the current state
+    state_time: f32,
+    /// state weights when the current transition started
+    fade_from: Vec<(usize, f32)>,
+    fade_time: f32,
+    fade_duration: f32,
+    entered_state: bool,
+    started: bool,
+}
+
+impl Animator {
+    pub fn new(asset: AnimatorAsset) -> Animator {
+        let parameters = asset
+            .parameters
+            .iter()
+            .map(|(name, definition)| {
+                let parameter = match definition {
+                    AnimatorParameterDefinition::Float { default } => AnimatorParameter::Float(*default),
+                    AnimatorParameterDefinition::Bool { default } => AnimatorParameter::Bool(*default),
+                    AnimatorParameterDefinition::Trigger => AnimatorParameter::Trigger(false),
+                };
+                (name.clone(), parameter)
+            })
+            .collect();
+
+        Animator {
+            current_state: asset.default_state_index(),
+            asset,
+            parameters,
+            state_time: 0.0,
+            fade_from: vec![],
+            fade_time: 0.0,
+            fade_duration: 0.0,
+            entered_state: false,
+            started: false,
+        }
+    }
+
+    pub fn from_file(path: &str) -> Result<Animator, AnimatorAssetError> {
+        Ok(Animator::new(AnimatorAsset::from_file(path)?))
+    }
+
+    pub fn current_state(&self) -> &str {
+        &self.asset.states[self.current_state].name
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<AnimatorParameter> {
+        self.parameters.get(name).copied()
+    }
+
+    pub fn set_float(&mut self, name: &str, value: f32) -> Result<(), AnimatorError> {
+        match self.parameters.get_mut(name) {
+            Some(AnimatorParameter::Float(parameter)) => {
+                *parameter = value;
+                Ok(())
+            }
+            Some(_) => Err(AnimatorError::ParameterTypeMismatch),
+            None => Err(AnimatorError::ParameterNotFound),
+        }
+    }
+
+    pub fn set_bool(&mut self, name: &str, value: bool) -> Result<(), AnimatorError> {
+        match self.parameters.get_mut(name) {
+            Some(AnimatorParameter::Bool(parameter)) => {
+                *parameter = value;
+                Ok(())
+            }
+            Some(_) => Err(AnimatorError::ParameterTypeMismatch),
+            None => Err(AnimatorError::ParameterNotFound),
+        }
+    }
+
+    /// Stays set until a transition that checks it happens or it's reset.
+    pub fn set_trigger(&mut self, name: &str) -> Result<(), AnimatorError> {
+        match self.parameters.get_mut(name) {
+            Some(AnimatorParameter::Trigger(parameter)) => {
+                *parameter = true;
+                Ok(())
+            }
+            Some(_) => Err(AnimatorError::ParameterTypeMismatch),
+            None => Err(AnimatorError::ParameterNotFound),
+        }
+    }
+
+    pub fn reset_trigger(&mut self, name: &str) -> Result<(), AnimatorError> {
+        match self.parameters.get_mut(name) {
+            Some(AnimatorParameter::Trigger(parameter)) => {
+                *parameter = false;
+                Ok(())
+            }
+            Some(_) => Err(AnimatorError::ParameterTypeMismatch),
+            None => Err(AnimatorError::ParameterNotFound),
+        }
+    }
+
+    /// Goes to the state without checking transitions.
+    pub fn play_state(&mut self, name: &str, duration: f32) -> Result<(), AnimatorError> {
+        let state = self.asset.state_index(name).ok_or(AnimatorError::StateNotFound)?;
+        self.enter_state(state, duration);
+
+        Ok(())
+    }
+
+    /// Clips used by the states that the model doesn't have.
+    pub fn missing_clips(&self, model_asset: &ModelAsset) -> Vec<String> {
+        let mut missing = vec![];
+        for state in &self.asset.states {
+            for (clip, _) in self.clip_weights(state) {
+                if !model_asset.contains_animation(clip) && !missing.iter().any(|name| name == clip) {
+                    missing.push(clip.to_string());
+                }
+            }
+        }
+
+        missing
+    }
+
+    pub fn update(&mut self, model_asset: &ModelAsset, animation_settings: &mut CurrentAnimationSettings, delta: f32) {
+        if !self.started {
+            self.started = true;
+            self.enter_state(self.current_state, 0.0);
+        }
+        self.state_time += animation_settings.playback_delta(delta).abs();
+        if !animation_settings.paused {
+            self.fade_time += delta;
+        }
+
+        let transition = self
+            .asset
+            .transitions
+            .iter()
+            .find(|transition| self.transition_ready(transition, model_asset))
+            .cloned();
+        if let Some(transition) = transition {
+            for condition in &transition.conditions {
+                if let Some(AnimatorParameter::Trigger(set)) = self.parameters.get_mut(&condition.parameter) {
+                    *set = false;
+                }
+            }
+            if let Some(state) = self.asset.state_index(&transition.to) {
+                self.enter_state(state, transition.duration);
+            }
+        }
+
+        let mut clips: HashMap<&str, (f32, bool)> = HashMap::new();
+        for (state_index, state_weight) in self.state_weights() {
+            let state = &self.asset.states[state_index];
+            for (clip, clip_weight) in self.clip_weights(state) {
+                let entry = clips.entry(clip).or_insert((0.0, state.looping));
+                entry.0 += state_weight * clip_weight;
+            }
+        }
+
+        let playing: Vec<String> = animation_settings
+            .playing_animations(BASE_LAYER)
+            .into_iter()
+            .map(|name| name.to_string())
+            .collect();
+        for (clip, (weight, looping)) in &clips {
+            if playing.iter().any(|name| name == clip) {
+                let _ = animation_settings.set_weight(BASE_LAYER, clip, *weight, 0.0);
+            } else if self.entered_state {
+                // tracks are only added when a state is entered, so one shot clips don't restart after they end
+                if let Some(animation) = model_asset.find_animation(clip) {
+                    let _ = animation_settings.blend(BASE_LAYER, animation, *weight, *looping, 0.0);
+                }
+            }
+        }
+        for name in playing {
+            if !clips.contains_key(name.as_str()) {
+                let _ = animation_settings.stop(BASE_LAYER, Some(&name), 0.0);
+            }
+        }
+
+        self.entered_state = false;
+        if self.fade_time >= self.fade_duration {
+            self.fade_from.clear();
+        }
+    }
+
+    fn enter_state(&mut self, state: usize, duration: f32) {
+        self.fade_from = match duration > 0.0 && self.started {
+            true => self.state_weights(),
+            false => vec![],
+        };
+        self.current_state = state;
+        self.state_time = 0.0;
+        self.fade_time = 0.0;
+        self.fade_duration = duration.max(0.0);
+        self.entered_state = true;
+    }
+
+    fn transition_ready(&self, transition: &AnimatorTransition, model_asset: &ModelAsset) -> bool {
+        let current = self.current_state();
+        let from_matches = transition.from == current || (transition.from == ANY_STATE && transition.to != current);
+        if !from_matches {
+            return false;
+        }
+
+        let exit_time = match transition.conditions.is_empty() {
+            true => Some(transition.exit_time.unwrap_or(1.0)),
+            false => transition.exit_time,
+        };
+        if let Some(exit_time) = exit_time {
+            if self.normalized_state_time(model_asset) < exit_time {
+                return false;
+            }
+        }
+
+        transition.conditions.iter().all(|condition| self.condition_met(condition))
+    }
+
+    fn condition_met(&self, condition: &AnimatorCondition) -> bool {
+        match (self.parameters.get(&condition.parameter), condition.value) {
+            (Some(AnimatorParameter::Float(value)), Some(ConditionValue::Float(target))) => match condition.op {
+                ConditionOp::Greater => *value > target,
+                ConditionOp::Less => *value < target,
+                ConditionOp::GreaterOrEqual => *value >= target,
+                ConditionOp::LessOrEqual => *value <= target,
+                ConditionOp::Equal => *value == target,
+                ConditionOp::NotEqual => *value != target,
+            },
+            (Some(AnimatorParameter::Bool(value)), target) => {
+                let target = match target {
+                    Some(ConditionValue::Bool(target)) => target,
+                    _ => true,
+                };
+                match condition.op {
+                    ConditionOp::NotEqual => *value != target,
+                    _ => *value == target,
+                }
+            }
+            (Some(AnimatorParameter::Trigger(set)), _) => *set,
+            _ => false,
+        }
+    }
+
+    /// Time in the current state divided by the length of its longest clip.
+    fn normalized_state_time(&self, model_asset: &ModelAsset) -> f32 {
+        let state = &self.asset.states[self.current_state];
+        let duration = self
+            .clip_weights(state)
+            .iter()
+            .filter_map(|(clip, _)| model_asset.animations.iter().find(|animation| animation.name == *clip))
+            .map(|animation| animation.duration)
+            .fold(0.0, f32::max);
+
+        match duration > 0.0 {
+            true => self.state_time / duration,
+            false => 1.0,
+        }
+    }
+
+    fn state_weights(&self) -> Vec<(usize, f32)> {
+        let progress = match self.fade_duration > 0.0 {
+            true => (self.fade_time / self.fade_duration).min(1.0),
+            false => 1.0,
+        };
+
+        let mut weights: Vec<(usize, f32)> = self
+            .fade_from
+            .iter()
+            .map(|(state, weight)| (*state, weight * (1.0 - progress)))
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect();
+        match weights.iter_mut().find(|(state, _)| *state == self.current_state) {
+            Some((_, weight)) => *weight += progress,
+            None => weights.push((self.current_state, progress)),
+        }
+
+        weights
+    }
+
+    fn clip_weights<'a>(&self, state: &'a AnimatorState) -> Vec<(&'a str, f32)> {
+        if let Some(clip) = &state.clip {
+            return vec![(clip.as_str(), 1.0)];
+        }
+
+        match &state.blend_space {
+            Some(BlendSpace::OneDimensional { parameter, clips }) => {
+                let weights = blend_space_1d(clips, self.float(parameter));
+                clips.iter().map(|clip| clip.clip.as_str()).zip(weights).collect()
+            }
+            Some(BlendSpace::TwoDimensional { parameters, clips }) => {
+                let point = Vec2::new(self.float(&parameters[0]), self.float(&parameters[1]));
+                let weights = blend_space_2d(clips, point);
+                clips.iter().map(|clip| clip.clip.as_str()).zip(weights).collect()
+            }
+            None => vec![],
+        }
+    }
+
+    fn float(&self, name: &str) -> f32 {
+        match self.parameters.get(name) {
+            Some(AnimatorParameter::Float(value)) => *value,
+            _ => 0.0,
+        }
+    }
+
+    pub fn inspector_ui(&mut self, ui: &mut Ui) {
+        ui.label(format!("state: {}", self.current_state()));
+        let mut names: Vec<String> = self.parameters.keys().cloned().collect();
+        names.sort();
+        for name in names {
+            ui.horizontal(|ui| {
+                ui.label(&name);
+                match self.parameters.get_mut(&name) {
+                    Some(AnimatorParameter::Float(value)) => {
+                        ui.add(DragValue::new(value).speed(0.05));
+                    }
+                    Some(AnimatorParameter::Bool(value)) => {
+                        ui.checkbox(value, "");
+                    }
+                    Some(AnimatorParameter::Trigger(set)) => {
+                        let label = if *set { "set" } else { "trigger" };
+                        *set |= ui.button(label).clicked();
+                    }
+                    None => (),
+                }
+            });
+        }
+    }
+}
+
+/// Mixes the two clips around `value`, clamped to the first and the last one.
+fn blend_space_1d(clips: &[BlendSpaceClip<f32>], value: f32) -> Vec<f32> {
+    let mut weights = vec![0.0; clips.len()];
+    let mut lower: Option<usize> = None;
+    let mut upper: Option<usize> = None;
+    for (index, clip) in clips.iter().enumerate() {
+        if clip.position <= value && lower.is_none_or(|lower| clip.position > clips[lower].position) {
+            lower = Some(index);
+        }
+        if clip.position >= value && upper.is_none_or(|upper| clip.position < clips[upper].position) {
+            upper = Some(index);
+        }
+    }
+
+    match (lower, upper) {
+        (Some(lower), Some(upper)) if clips[upper].position - clips[lower].position > f32::EPSILON => {
+            let t = (value - clips[lower].position) / (clips[upper].position - clips[lower].position);
+            weights[lower] = 1.0 - t;
+            weights[upper] = t;
+        }
+        (Some(index), _) | (None, Some(index)) => weights[index] = 1.0,
+        (None, None) => (),
+    }
+
+    weights
+}
+
+/// Gradient band interpolation, every clip gets 1 at its own position and 0 at the others.
+fn blend_space_2d(clips: &[BlendSpaceClip<[f32; 2]>], point: Vec2) -> Vec<f32> {
+    let positions: Vec<Vec2> = clips.iter().map(|clip| Vec2::from(clip.position)).collect();
+    let mut weights: Vec<f32> = positions
+        .iter()
+        .enumerate()
+        .map(|(i, position_i)| {
+            let mut weight: f32 = 1.0;
+            for (j, position_j) in positions.iter().enumerate() {
+                let edge = *position_j - *position_i;
+                let length_squared = edge.length_squared();
+                if i == j || length_squared <= f32::EPSILON {
+                    continue;
+                }
+                weight = weight.min(1.0 - (point - *position_i).dot(edge) / length_squared);
+            }
+            weight.max(0.0)
+        })
+        .collect();
+
+    let sum: f32 = weights.iter().sum();
+    if sum > 0.0 {
+        weights.iter_mut().for_each(|weight| *weight /= sum);
+    } else if let Some(nearest) = positions
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.distance_squared(point).total_cmp(&b.distance_squared(point)))
+        .map(|(index, _)| index)
+    {
+        weights[nearest] = 1.0;
+    }
+
+    weights
+}
+
+#[derive(Debug)]
+pub enum AnimatorError {
+    ParameterNotFound,
+    ParameterTypeMismatch,
+    StateNotFound,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assets::model_asset::Animation,
+        objects::animation::CurrentAnimationSettings,
+    };
+
+    const ANIMATOR: &str = r#"
+default_state = "idle"
+
+[parameters]
+speed = { type = "float" }
+grounded = { type = "bool", default = true }
+jump = { type = "trigger" }
+
+[[states]]
+name = "idle"
+clip = "Idle"
+
+[[states]]
+name = "walk"
+clip = "Walk"
+
+[[states]]
+name = "jump"
+clip = "Jump"
+looping = false
+
+[[states]]
+name = "land"
+clip = "Land"
+looping = false
+
+[[transitions]]
+from = "idle"
+to = "walk"
+conditions = [{ parameter = "speed", op = ">", value = 0.1 }]
+
+[[transitions]]
+from = "any"
+to = "jump"
+duration = 0.0
+conditions = [{ parameter = "jump" }]
+
+[[transitions]]
+from = "jump"
+to = "idle"
+exit_time = 0.9
+conditions = [{ parameter = "grounded", value = true }]
+
+[[transitions]]
+from = "land"
+to = "idle"
+"#;
+
+    fn animator() -> Animator {
+        Animator::new(toml::from_str(ANIMATOR).unwrap())
+    }
+
+    fn model_asset() -> ModelAsset {
+        let animation = |name: &str, duration: f32| Animation {
+            name: name.into(),
+            channels: vec![],
+            duration,
+            events: vec![],
+        };
+        ModelAsset {
+            path: "animator test".into(),
+            objects: vec![],
+            joints: vec![],
+            nodes: vec![],
+            root_nodes: vec![],
+            animations: vec![animation("Idle", 1.0), animation("Walk", 1.0), animation("Jump", 2.0), animation("Land", 1.0)],
+            textures: vec![],
+            joints_mats: [[[0.0; 4]; 4]; 128],
+            joints_inverse_bind_mats: [[[0.0; 4]; 4]; 128],
+        }
+    }
+
+    fn condition(parameter: &str, op: ConditionOp, value: Option<ConditionValue>) -> AnimatorCondition {
+        AnimatorCondition {
+            parameter: parameter.into(),
+            op,
+            value,
+        }
+    }
+
+    fn transition(animator: &Animator, from: &str, to: &str) -> AnimatorTransition {
+        animator
+            .asset
+            .transitions
+            .iter()
+            .find(|transition| transition.from == from && transition.to == to)
+            .cloned()
+            .unwrap()
+    }
+
+    #[test]
+    fn parameters_keep_their_types() {
+        let mut animator = animator();
+        assert!(animator.set_float("speed", 2.0).is_ok());
+        assert_eq!(animator.parameter("speed"), Some(AnimatorParameter::Float(2.0)));
+        assert!(matches!(animator.set_bool("speed", true), Err(AnimatorError::ParameterTypeMismatch)));
+        assert!(matches!(animator.set_trigger("grounded"), Err(AnimatorError::ParameterTypeMismatch)));
+        assert!(matches!(animator.set_float("height", 1.0), Err(AnimatorError::ParameterNotFound)));
+        assert_eq!(animator.parameter("grounded"), Some(AnimatorParameter::Bool(true)));
+    }
+
+    #[test]
+    fn float_conditions_compare_to_the_value() {
+        let mut animator = animator();
+        animator.set_float("speed", 2.0).unwrap();
+        let value = Some(ConditionValue::Float(1.0));
+        let results: Vec<bool> = [
+            ConditionOp::Greater,
+            ConditionOp::Less,
+            ConditionOp::GreaterOrEqual,
+            ConditionOp::LessOrEqual,
+            ConditionOp::Equal,
+            ConditionOp::NotEqual,
+        ]
+        .iter()
+        .map(|op| animator.condition_met(&condition("speed", *op, value)))
+        .collect();
+        assert_eq!(results, vec![true, false, true, false, false, true]);
+        // a float isn't compared to a bool
+        assert!(!animator.condition_met(&condition("speed", ConditionOp::Equal, Some(ConditionValue::Bool(true)))));
+    }
+
+    #[test]
+    fn bool_conditions_default_to_true() {
+        let mut animator = animator();
+        assert!(animator.condition_met(&condition("grounded", ConditionOp::Equal, None)));
+        assert!(!animator.condition_met(&condition("grounded", ConditionOp::NotEqual, Some(ConditionValue::Bool(true)))));
+        animator.set_bool("grounded", false).unwrap();
+        assert!(!animator.condition_met(&condition("grounded", ConditionOp::Equal, None)));
+        assert!(animator.condition_met(&condition("grounded", ConditionOp::Equal, Some(ConditionValue::Bool(false)))));
+        assert!(!animator.condition_met(&condition("missing", ConditionOp::Equal, None)));
+    }
+
+    #[test]
+    fn trigger_conditions_are_met_while_set() {
+        let mut animator = animator();
+        let jump = condition("jump", ConditionOp::Equal, None);
+        assert!(!animator.condition_met(&jump));
+        animator.set_trigger("jump").unwrap();
+        assert!(animator.condition_met(&jump));
+        animator.reset_trigger("jump").unwrap();
+        assert!(!animator.condition_met(&jump));
+    }
+
+    #[test]
+    fn transitions_start_from_their_state() {
+        let mut animator = animator();
+        let model_asset = model_asset();
+        let idle_to_walk = transition(&animator, "idle", "walk");
+        assert!(!animator.transition_ready(&idle_to_walk, &model_asset));
+        animator.set_float("speed", 1.0).unwrap();
+        assert!(animator.transition_ready(&idle_to_walk, &model_asset));
+
+        animator.play_state("walk", 0.0).unwrap();
+        assert!(!animator.transition_ready(&idle_to_walk, &model_asset));
+    }
+
+    #[test]
+    fn any_state_transitions_skip_their_own_state() {
+        let mut animator = animator();
+        let model_asset = model_asset();
+        let any_to_jump = transition(&animator, "any", "jump");
+        animator.set_trigger("jump").unwrap();
+        assert!(animator.transition_ready(&any_to_jump, &model_asset));
+        animator.play_state("jump", 0.0).unwrap();
+        assert!(!animator.transition_ready(&any_to_jump, &model_asset));
+    }
+
+    #[test]
+    fn exit_time_waits_for_part_of_the_clip() {
+        let mut animator = animator();
+        let model_asset = model_asset();
+        let jump_to_idle = transition(&animator, "jump", "idle");
+        animator.play_state("jump", 0.0).unwrap();
+        // 2 seconds clip, exit time 0.9
+        animator.state_time = 1.0;
+        assert!(!animator.transition_ready(&jump_to_idle, &model_asset));
+        animator.state_time = 1.9;
+        assert!(animator.transition_ready(&jump_to_idle, &model_asset));
+        animator.set_bool("grounded", false).unwrap();
+        assert!(!animator.transition_ready(&jump_to_idle, &model_asset));
+    }
+
+    #[test]
+    fn transitions_without_conditions_wait_for_the_clip_to_end() {
+        let mut animator = animator();
+        let model_asset = model_asset();
+        let land_to_idle = transition(&animator, "land", "idle");
+        animator.play_state("land", 0.0).unwrap();
+        animator.state_time = 0.5;
+        assert!(!animator.transition_ready(&land_to_idle, &model_asset));
+        animator.state_time = 1.0;
+        assert!(animator.transition_ready(&land_to_idle, &model_asset));
+    }
+
+    #[test]
+    fn transition_resets_its_trigger() {
+        let mut animator = animator();
+        let model_asset = model_asset();
+        let mut settings = CurrentAnimationSettings::new(&vec![]);
+        animator.update(&model_asset, &mut settings, 0.0);
+        assert_eq!(animator.current_state(), "idle");
+        assert_eq!(settings.playing_animations(BASE_LAYER), vec!["Idle"]);
+
+        animator.set_trigger("jump").unwrap();
+        animator.update(&model_asset, &mut settings, 0.1);
+        assert_eq!(animator.current_state(), "jump");
+        assert_eq!(animator.parameter("jump"), Some(AnimatorParameter::Trigger(false)));
+        assert_eq!(settings.playing_animations(BASE_LAYER), vec!["Jump"]);
+
+        // the any state transition doesn't go from jump to jump, so the trigger stays set
+        animator.set_trigger("jump").unwrap();
+        animator.update(&model_asset, &mut settings, 0.1);
+        assert_eq!(animator.current_state(), "jump");
+        assert_eq!(animator.parameter("jump"), Some(AnimatorParameter::Trigger(true)));
+    }
+
+    fn clips_1d(positions: &[f32]) -> Vec<BlendSpaceClip<f32>> {
+        positions
+            .iter()
+            .map(|position| BlendSpaceClip {
+                clip: format!("clip {}", position),
+                position: *position,
+            })
+            .collect()
+    }
+
+    fn clips_2d(positions: &[[f32; 2]]) -> Vec<BlendSpaceClip<[f32; 2]>> {
+        positions
+            .iter()
+            .map(|position| BlendSpaceClip {
+                clip: format!("clip {:?}", position),
+                position: *position,
+            })
+            .collect()
+    }
+
+    fn assert_sum_is_one(weights: &[f32]) {
+        let sum: f32 = weights.iter().sum();
+        assert!((sum - 1.0).abs() < 1e-5, "weights {:?} sum to {}", weights, sum);
+        assert!(weights.iter().all(|weight| *weight >= 0.0), "negative weight in {:?}", weights);
+    }
+
+    #[test]
+    fn blend_space_1d_mixes_neighbours() {
+        let clips = clips_1d(&[0.0, 1.0, 3.0]);
+        let weights = blend_space_1d(&clips, 2.0);
+        assert_sum_is_one(&weights);
+        assert_eq!(weights, vec![0.0, 0.5, 0.5]);
+
+        let weights = blend_space_1d(&clips, 1.0);
+        assert_sum_is_one(&weights);
+        assert_eq!(weights, vec![0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn blend_space_1d_clamps_outside() {
+        let clips = clips_1d(&[1.0, 0.0, 3.0]);
+        assert_eq!(blend_space_1d(&clips, -5.0), vec![0.0, 1.0, 0.0]);
+        assert_eq!(blend_space_1d(&clips, 10.0), vec![0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn blend_space_1d_same_positions() {
+        let clips = clips_1d(&[1.0, 1.0]);
+        assert_sum_is_one(&blend_space_1d(&clips, 1.0));
+        assert!(blend_space_1d(&[], 1.0).is_empty());
+    }
+
+    #[test]
+    fn blend_space_2d_weights_sum_to_one() {
+        let clips = clips_2d(&[[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]]);
+        for point in [Vec2::new(0.5, 0.5), Vec2::new(0.2, 0.7), Vec2::new(0.9, 0.1)] {
+            assert_sum_is_one(&blend_space_2d(&clips, point));
+        }
+    }
+
+    #[test]
+    fn blend_space_2d_clip_position_is_that_clip() {
+        let clips = clips_2d(&[[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]]);
+        let weights = blend_space_2d(&clips, Vec2::new(1.0, 0.0));
+        assert!((weights[1] - 1.0).abs() < 1e-5, "{:?}", weights);
+    }
+
+    #[test]
+    fn blend_space_2d_outside_of_the_space() {
+        let clips = clips_2d(&[[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]]);
+        for point in [Vec2::new(5.0, 5.0), Vec2::new(-3.0, 0.5), Vec2::new(0.5, -10.0)] {
+            assert_sum_is_one(&blend_space_2d(&clips, point));
+        }
+        // far past a corner only that corner's clip plays
+        let weights = blend_space_2d(&clips, Vec2::new(5.0, 5.0));
+        assert!((weights[3] - 1.0).abs() < 1e-5, "{:?}", weights);
+    }
+}
@@ -0,0 +1,156 @@
+use egui_glium::egui_winit::egui;
+
+use super::{gen_object_id, Object, ObjectGroup, Transform};
+use crate::{
+    framework::Framework,
+    managers::{
+        physics::ObjectBodyParameters,
+        render::{self, CameraData, CameraTarget, Projection, Viewport},
+    },
+};
+
+/// Camera that's drawn on top of (or instead of) the default camera.
+/// If any camera targets the screen, the default camera isn't drawn.
+#[derive(Debug)]
+pub struct Camera {
+    name: String,
+    transform: Transform,
+    parent_transform: Option<Transform>,
+    children: Vec<Box<dyn Object>>,
+    body: Option<ObjectBodyParameters>,
+    id: u128,
+    groups: Vec<ObjectGroup>,
+    pub projection: Projection,
+    pub near: f32,
+    pub far: f32,
+    pub target: CameraTarget,
+    pub order: i32,
+    pub enabled: bool,
+}
+
+impl Camera {
+    pub fn new(name: &str, projection: Projection, target: CameraTarget) -> Self {
+        Camera {
+            name: name.to_string(),
+            transform: Transform::default(),
+            parent_transform: None,
+            children: vec![],
+            body: None,
+            id: gen_object_id(),
+            groups: vec![],
+            projection,
+            near: 0.1,
+            far: 500.0,
+            target,
+            order: 0,
+            enabled: true,
+        }
+    }
+}
+
+impl Object for Camera {
+    fn start(&mut self) {}
+
+    fn update(&mut self, _: &mut Framework) {
+        if !self.enabled {
+            return;
+        }
+
+        let global_transform = self.global_transform();
+        render::add_camera(CameraData {
+            name: self.name.clone(),
+            position: global_transform.position,
+            rotation: global_transform.rotation,
+            projection: self.projection,
+            near: self.near,
+            far: self.far.max(self.near + 0.001),
+            target: self.target,
+            order: self.order,
+        });
+    }
+
+    fn children_list(&self) -> &Vec<Box<dyn Object>> {
+        &self.children
+    }
+
+    fn children_list_mut(&mut self) -> &mut Vec<Box<dyn Object>> {
+        &mut self.children
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn object_type(&self) -> &str {
+        "Camera"
+    }
+
+    fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    fn local_transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn set_local_transform(&mut self, transform: Transform) {
+        self.transform = transform
+    }
+
+    fn parent_transform(&self) -> Option<Transform> {
+        self.parent_transform
+    }
+
+    fn set_parent_transform(&mut self, transform: Transform) {
+        self.parent_transform = Some(transform);
+    }
+
+    fn set_body_parameters(&mut self, rigid_body: Option<ObjectBodyParameters>) {
+        self.body = rigid_body
+    }
+
+    fn body_parameters(&self) -> Option<ObjectBodyParameters> {
+        self.body
+    }
+
+    fn object_id(&self) -> &u128 {
+        &self.id
+    }
+
+    fn inspector_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Camera parameters");
+
+        ui.checkbox(&mut self.enabled, "enabled");
+        match &mut self.projection {
+            Projection::Perspective { fov } => {
+                ui.add(egui::Slider::new(fov, 1.0..=170.0).text("fov"));
+            }
+            Projection::Orthographic { height } => {
+                ui.add(egui::Slider::new(height, 0.1..=500.0).text("height"));
+            }
+        }
+        ui.add(egui::Slider::new(&mut self.near, 0.001..=10.0).text("near"));
+        ui.add(egui::Slider::new(&mut self.far, 1.0..=5000.0).text("far"));
+
+        match &mut self.target {
+            CameraTarget::Screen(viewport) => {
+                ui.label("viewport:");
+                ui.add(egui::Slider::new(&mut viewport.x, 0.0..=1.0).text("x"));
+                ui.add(egui::Slider::new(&mut viewport.y, 0.0..=1.0).text("y"));
+                ui.add(egui::Slider::new(&mut viewport.width, 0.0..=1.0).text("width"));
+                ui.add(egui::Slider::new(&mut viewport.height, 0.0..=1.0).text("height"));
+                ui.add(egui::DragValue::new(&mut self.order).prefix("order: "));
+            }
+            CameraTarget::Texture { width, height } => {
+                ui.label(format!("render target: {}x{}", width, height));
+            }
+        }
+        if ui.button("full screen").clicked() {
+            self.target = CameraTarget::Screen(Viewport::FULL);
+        }
+    }
+
+    fn groups_list(&mut self) -> &mut Vec<super::ObjectGroup> {
+        &mut self.groups
+    }
+}
@@ -237,6 +237,15 @@ impl CharacterController {
         }
     }
 
+    /// Moves by a world space translation (e.g. `ModelObject::take_root_motion`) and turns by `yaw` degrees.
+    pub fn apply_root_motion(&mut self, translation: Vec3, yaw: f32) {
+        // move_controller takes the direction with x and z flipped
+        self.move_controller(Vec3::new(-translation.x, translation.y, -translation.z));
+        let mut rotation = self.local_transform().rotation;
+        rotation.y += yaw;
+        self.set_rotation(rotation, false);
+    }
+
     pub fn walk_to(&mut self, target: Vec3, speed: f32) {
         let movement = CharacterControllerMovement { target, speed };
         //dbg!(&movement);
@@ -1,9 +1,9 @@
 use super::{gen_object_id, Object, ObjectGroup, Transform};
 use crate::{framework::Framework, managers::{
     physics::ObjectBodyParameters,
-    render::{self, Cascades, ShadowTextures},
-}, math_utils::deg_vec_to_rad};
-use glam::{Mat4, Quat};
+    render::{self, Cascades, InstanceData, RenderQueue, ShadowTextures},
+}};
+use glam::Mat4;
 use glium::{
     framebuffer::SimpleFrameBuffer, glutin::surface::WindowSurface, Display
 };
@@ -17,7 +17,9 @@ pub struct InstancedModelObject {
     body: Option<ObjectBodyParameters>,
     id: u128,
     groups: Vec<ObjectGroup>,
-    instance: String
+    instance: String,
+    /// model matrix is replaced with the object's transform every frame
+    pub instance_data: InstanceData,
 }
 
 impl InstancedModelObject {
@@ -34,43 +36,21 @@ impl InstancedModelObject {
             groups: vec![],
             body: None,
             id: gen_object_id(),
-            instance: instance.into()
+            instance: instance.into(),
+            instance_data: InstanceData::default(),
         }
     }
 }
 
-impl InstancedModelObject {
-    fn setup_mat(&self) -> Mat4 {
-        let global_transform = &self.global_transform();
-
-        let rotation_vector = deg_vec_to_rad(global_transform.rotation);
-        let mut translation_vector = global_transform.position;
-        translation_vector.z = -translation_vector.z;
-        let scale_vector = global_transform.scale;
-
-        let rotation_quat = Quat::from_euler(
-            glam::EulerRot::XYZ,
-            rotation_vector.x, rotation_vector.y, rotation_vector.z
-        );
-
-        let transform =
-            Mat4::from_scale_rotation_translation(scale_vector, rotation_quat, translation_vector);
-        transform
-
-        //let view = render::get_view_matrix();
-        //let proj = render::get_projection_matrix();
-
-        //let mvp = proj * view * transform;
-
-
-    }
-}
-
 impl Object for InstancedModelObject {
     fn start(&mut self) {}
 
     fn update(&mut self, _: &mut Framework) {
-        render::add_instance_position(&self.instance, self.setup_mat());
+        let data = InstanceData {
+            model: InstanceData::from_transform(&self.global_transform()).model,
+            ..self.instance_data
+        };
+        render::add_instance(&self.instance, data);
     }
 
     fn children_list(&self) -> &Vec<Box<dyn Object>> {
@@ -130,7 +110,7 @@ impl Object for InstancedModelObject {
         &mut self.groups
     }
 
-    fn render(&mut self, _: &Display<WindowSurface>, _: &mut glium::Frame, _: &Cascades, _: &ShadowTextures) {}
+    fn render(&mut self, _: &Display<WindowSurface>, _: &mut SimpleFrameBuffer, _: &Cascades, _: &ShadowTextures, _: &mut RenderQueue) {}
 
     fn shadow_render(&mut self, _: &Mat4, _: &Display<WindowSurface>, _: &mut SimpleFrameBuffer) {}
 }
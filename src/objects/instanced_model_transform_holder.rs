@@ -1,9 +1,9 @@
 use super::{gen_object_id, Object, ObjectGroup, Transform};
 use crate::{framework::Framework, managers::{
     physics::ObjectBodyParameters,
-    render::{self, Cascades, ShadowTextures},
-}, math_utils::deg_vec_to_rad};
-use glam::{Mat4, Quat};
+    render::{self, Cascades, InstanceData, RenderQueue, ShadowTextures},
+}};
+use glam::Mat4;
 use glium::{
     framebuffer::SimpleFrameBuffer, glutin::surface::WindowSurface, Display
 };
@@ -18,7 +18,7 @@ pub struct InstancedModelTransformHolder {
     id: u128,
     groups: Vec<ObjectGroup>,
     instance: String,
-    mats: Vec<Mat4>
+    instances: Vec<InstanceData>
 }
 
 impl InstancedModelTransformHolder {
@@ -37,34 +37,18 @@ impl InstancedModelTransformHolder {
             body: None,
             id: gen_object_id(),
             instance: instance.into(),
-            mats: Self::transforms_to_mats(transforms),
+            instances: Self::transforms_to_instances(transforms),
         }
     }
 }
 
 impl InstancedModelTransformHolder {
-    fn setup_mat(transform: &Transform) -> Mat4 {
-        let rotation_vector = deg_vec_to_rad(transform.rotation);
-        let mut translation_vector = transform.position;
-        translation_vector.z = -translation_vector.z;
-        let scale_vector = transform.scale;
-
-        let rotation_quat = Quat::from_euler(
-            glam::EulerRot::XYZ,
-            rotation_vector.x, rotation_vector.y, rotation_vector.z
-        );
-
-        let transform =
-            Mat4::from_scale_rotation_translation(scale_vector, rotation_quat, translation_vector);
-        transform
-    }
-
     pub fn set_transforms(&mut self, transforms: Vec<Transform>) {
-        self.mats = Self::transforms_to_mats(transforms);
+        self.instances = Self::transforms_to_instances(transforms);
     }
 
-    fn transforms_to_mats(transforms: Vec<Transform>) -> Vec<Mat4> { 
-        transforms.iter().map(|tr| Self::setup_mat(tr)).collect::<Vec<Mat4>>()
+    fn transforms_to_instances(transforms: Vec<Transform>) -> Vec<InstanceData> {
+        transforms.iter().map(InstanceData::from_transform).collect::<Vec<InstanceData>>()
     }
 }
 
@@ -72,7 +56,7 @@ impl Object for InstancedModelTransformHolder {
     fn start(&mut self) {}
 
     fn update(&mut self, _: &mut Framework) {
-        render::add_instance_positions_vec(&self.instance, &self.mats);
+        render::add_instances_vec(&self.instance, &self.instances);
     }
 
     fn children_list(&self) -> &Vec<Box<dyn Object>> {
@@ -132,7 +116,7 @@ impl Object for InstancedModelTransformHolder {
         &mut self.groups
     }
 
-    fn render(&mut self, _: &Display<WindowSurface>, _: &mut glium::Frame, _: &Cascades, _: &ShadowTextures) {}
+    fn render(&mut self, _: &Display<WindowSurface>, _: &mut SimpleFrameBuffer, _: &Cascades, _: &ShadowTextures, _: &mut RenderQueue) {}
 
     fn shadow_render(&mut self, _: &Mat4, _: &Display<WindowSurface>, _: &mut SimpleFrameBuffer) {}
 }
@@ -1,23 +1,24 @@
-use super::{gen_object_id, Object, ObjectGroup, Transform};
+use super::{animation::{self, AnimationBlendMode, CurrentAnimationSettings, NodeTransform, BASE_LAYER}, gen_object_id, model_object::ModelObjectError, Object, ObjectGroup, Transform};
 use crate::{
     assets::{
-        model_asset::{self, Animation, AnimationChannel, AnimationChannelType, ModelAsset},
+        model_asset::{self, AlphaMode, ModelAsset},
         shader_asset::ShaderAsset,
         texture_asset::TextureAsset,
-    }, framework::Framework, managers::{
-        debugger::{self, error, warn},
+    }, framework::{self, Framework}, managers::{
+        debugger::{error, warn},
         physics::ObjectBodyParameters,
-        render::{self, get_projection_matrix, get_view_matrix, Cascades, ShadowTextures, Vertex},
-    }
+        render::{self, get_projection_matrix, get_view_matrix, Cascades, InstanceData, LightsBlock, LodSettings, MaterialTextures, ModelUniformBuffers, Lightmap, MorphTargetTextures, MorphUniforms, RenderQueue, RenderQueueItem, ShadowTextures, Vertex},
+        sky,
+    }, math_utils::{Aabb, Frustum}
 };
-use egui_glium::egui_winit::egui::ComboBox;
-use glam::{Mat4, Quat, Vec3};
+use egui_glium::egui_winit::egui::{ComboBox, Slider};
+use glam::{Mat4, Vec3};
 use glium::{
     framebuffer::SimpleFrameBuffer, glutin::surface::WindowSurface, implement_vertex, uniform, uniforms::{
         MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction, UniformBuffer,
-    }, Display, IndexBuffer, Program, Surface, VertexBuffer
+    }, texture::Texture2d, vertex::VertexBufferSlice, Display, IndexBuffer, Program, Surface, VertexBuffer
 };
-use std::time::Instant;
+use std::{collections::HashMap, ops::Range};
 
 #[derive(Debug)]
 pub struct MasterInstancedModelObject {
@@ -31,12 +32,40 @@ pub struct MasterInstancedModelObject {
     pub model_asset: ModelAsset,
     pub nodes_transforms: Vec<NodeTransform>,
     pub animation_settings: CurrentAnimationSettings,
+    /// morph target weights set by name, they're used instead of the animated ones
+    morph_weights: HashMap<String, f32>,
     pub shader_asset: ShaderAsset,
     pub texture_asset: Option<TextureAsset>,
+    pub lod_settings: LodSettings,
+    /// instances further than this from the camera aren't drawn and don't cast shadows
+    pub cull_distance: Option<f32>,
+    /// instances that stay until they're removed, slot index is InstanceId's index
+    persistent_instances: Vec<InstanceSlot>,
+    free_instance_slots: Vec<usize>,
+    /// persistent instances by slot, free slots are zeroed, drawn as is when nothing is culled
+    persistent_buffer: Option<VertexBuffer<Instance>>,
+    /// slots changed since persistent_buffer was written
+    persistent_dirty: Option<Range<usize>>,
+    /// from the last camera render
+    visible_instances: usize,
     texture: Option<glium::texture::Texture2d>,
+    material_textures: Option<MaterialTextures>,
+    morph_target_textures: Option<MorphTargetTextures>,
+    lightmap: Option<Lightmap>,
+    /// first and last sun cascades from the last render, for the old shadow uniforms
+    shadow_view_projs: [[[f32; 4]; 4]; 2],
     vertex_buffer: Vec<VertexBuffer<Vertex>>,
-    programs: Vec<Program>,
-    shadow_programs: Vec<Program>,
+    index_buffers: Vec<IndexBuffer<u32>>,
+    uniform_buffers: Option<ModelUniformBuffers>,
+    /// instances the cameras draw, rebuilt every camera render
+    batch: InstanceBatch,
+    /// instances that cast shadows, built once per frame for every shadow map
+    shadow_batch: InstanceBatch,
+    shadow_batch_frame: Option<u64>,
+    programs: Vec<&'static Program>,
+    shadow_programs: Vec<&'static Program>,
+    /// lights reaching instances of each lod_ranges bucket
+    lod_lights: Vec<UniformBuffer<LightsBlock>>,
     started: bool,
     error: bool,
     inspector_anim_name: String,
@@ -54,20 +83,19 @@ impl MasterInstancedModelObject {
             let node_local_transform_mat = Mat4::from_cols_array_2d(&node.transform);
             let node_scale_rotation_translation =
                 node_local_transform_mat.to_scale_rotation_translation();
-            let node_rotation = node_scale_rotation_translation
-                .1
-                .to_euler(glam::EulerRot::XYZ);
-
             nodes_transforms.push(NodeTransform {
                 local_position: node_scale_rotation_translation.2,
-                local_rotation: node_rotation.into(),
+                local_rotation: node_scale_rotation_translation.1,
                 local_scale: node_scale_rotation_translation.0,
                 global_transform: None,
                 node_id: node.node_index,
                 parent_global_transform: None,
+                morph_weights: node.morph_weights.clone(),
             });
         }
 
+        let animation_settings = CurrentAnimationSettings::new(&nodes_transforms);
+
         MasterInstancedModelObject {
             transform: Transform::default(),
             nodes_transforms,
@@ -78,17 +106,31 @@ impl MasterInstancedModelObject {
             groups: vec![],
             texture_asset,
             shader_asset,
+            lod_settings: LodSettings::default(),
+            cull_distance: None,
+            persistent_instances: vec![],
+            free_instance_slots: vec![],
+            persistent_buffer: None,
+            persistent_dirty: None,
+            visible_instances: 0,
             texture: None,
+            material_textures: None,
+            morph_target_textures: None,
+            lightmap: None,
+            shadow_view_projs: [Mat4::IDENTITY.to_cols_array_2d(); 2],
             vertex_buffer: vec![],
+            index_buffers: vec![],
+            uniform_buffers: None,
+            batch: InstanceBatch::default(),
+            shadow_batch: InstanceBatch::default(),
+            shadow_batch_frame: None,
             programs: vec![],
             shadow_programs: vec![],
+            lod_lights: vec![],
             started: false,
             error: false,
-            animation_settings: CurrentAnimationSettings {
-                animation: None,
-                looping: false,
-                timer: None,
-            },
+            animation_settings,
+            morph_weights: HashMap::new(),
             body: None,
             id: gen_object_id(),
             inspector_anim_name: "None".into(),
@@ -100,7 +142,8 @@ impl Object for MasterInstancedModelObject {
     fn start(&mut self) {}
 
     fn update(&mut self, _: &mut Framework) {
-        self.update_animation();
+        let delta = framework::get_delta_time().as_secs_f32();
+        self.animation_settings.update(&mut self.nodes_transforms, delta);
         for node in &self.model_asset.root_nodes {
             set_nodes_global_transform(
                 &node,
@@ -164,6 +207,35 @@ impl Object for MasterInstancedModelObject {
         ui.label(&format!("error: {}", self.error));
         ui.label(&format!("model asset: {}", self.model_asset.path));
         ui.label(&format!("texture asset: {}", self.texture_asset.is_some()));
+        ui.collapsing("materials", |ui| {
+            for object in &self.model_asset.objects {
+                let material = &object.material;
+                ui.label(format!(
+                    "node {}: '{}', alpha mode: {:?}, double sided: {}",
+                    object.node_index, material.name, material.alpha_mode, material.double_sided
+                ));
+            }
+        });
+        let persistent_count = self.persistent_instances.len() - self.free_instance_slots.len();
+        ui.label(format!("persistent instances: {}, visible: {}", persistent_count, self.visible_instances));
+        let mut cull = self.cull_distance.is_some();
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut cull, "cull distance");
+            let mut distance = self.cull_distance.unwrap_or(100.0);
+            if cull {
+                ui.add(egui_glium::egui_winit::egui::DragValue::new(&mut distance).speed(1.0));
+            }
+            self.cull_distance = if cull { Some(distance.max(0.0)) } else { None };
+        });
+        let lods_count = self.model_asset.lods_count();
+        if lods_count > 1 {
+            ui.collapsing(format!("LODs ({})", lods_count), |ui| {
+                for lod in 0..lods_count {
+                    ui.label(format!("LOD {}: {} instances", lod, self.batch.lod_ranges.get(lod + 1).map_or(0, |range| range.len())));
+                }
+                self.lod_settings.inspector_ui(ui);
+            });
+        }
 
         let anim_name = self.inspector_anim_name.clone();
         ComboBox::from_label("animation")
@@ -180,16 +252,33 @@ impl Object for MasterInstancedModelObject {
             if ui.button("play animation").clicked() {
                 let _ = self.play_animation(&anim_name);
             }
-            ui.checkbox(&mut self.animation_settings.looping, "loop");
-            if ui.button("stop").clicked() {
-                self.animation_settings.animation = None;
-                self.animation_settings.timer = None;
+            if ui.button("cross-fade").clicked() {
+                let _ = self.cross_fade(&anim_name, 0.3, BASE_LAYER, self.is_looping());
             }
-            if let Some(timer) = &self.animation_settings.timer {
-                ui.label("time:");
-                ui.label(format!("{} s", timer.elapsed().as_secs_f32()));
+            let mut looping = self.is_looping();
+            ui.checkbox(&mut looping, "loop");
+            self.set_looping(looping);
+            if ui.button("stop").clicked() {
+                self.animation_settings.stop_all();
             }
         });
+        let morph_target_names: Vec<String> = self.model_asset.morph_target_names().into_iter().map(String::from).collect();
+        if !morph_target_names.is_empty() {
+            ui.collapsing("morph targets", |ui| {
+                for name in morph_target_names {
+                    ui.horizontal(|ui| {
+                        let mut weight = self.morph_weight(&name).unwrap_or(0.0);
+                        if ui.add(Slider::new(&mut weight, 0.0..=1.0).text(&name)).changed() {
+                            let _ = self.set_morph_weight(&name, weight);
+                        }
+                        if self.morph_weights.contains_key(&name) && ui.button("animate").clicked() {
+                            self.clear_morph_weight(&name);
+                        }
+                    });
+                }
+            });
+        }
+        self.animation_settings.inspector_ui(ui);
     }
 
     fn groups_list(&mut self) -> &mut Vec<ObjectGroup> {
@@ -199,9 +288,10 @@ impl Object for MasterInstancedModelObject {
     fn render(
         &mut self,
         display: &Display<WindowSurface>,
-        target: &mut glium::Frame,
+        _target: &mut SimpleFrameBuffer,
         cascades: &Cascades,
-        shadow_texture: &ShadowTextures,
+        _shadow_texture: &ShadowTextures,
+        queue: &mut RenderQueue,
     ) {
         if self.error {
             return;
@@ -210,131 +300,197 @@ impl Object for MasterInstancedModelObject {
             self.start_mesh(display);
         }
 
-        let closest_shadow_view_proj_cols = cascades.closest_view_proj.to_cols_array_2d();
-        let furthest_shadow_view_proj_cols = cascades.furthest_view_proj.to_cols_array_2d();
 
-        let matrices = match render::get_instance_positions(&self.name) {
-            Some(matrices) => matrices,
-            None => {
-                return
-            },
-        };
-        let mut per_instance_data = Vec::new();
-
-
-        for matrix in matrices {
-            per_instance_data.push(Instance {
-                model: matrix.to_cols_array_2d(),
-            });
+        let bounding_box = self.model_asset.nodes_bounding_box();
+        let buckets = self.lod_buckets(render::is_visible, true);
+        self.visible_instances = buckets[0].len();
+        if buckets[0].is_empty() {
+            self.batch.lod_ranges.clear();
+            return;
         }
-        let per_instance_buffer = glium::vertex::VertexBuffer::dynamic(display, &per_instance_data).unwrap();
-
-
-
-        //dbg!(&self.model_asset.objects);
-        for i in 0..self.model_asset.objects.len() {
-            let object = &self.model_asset.objects[i];
-
-            let indices = IndexBuffer::new(
-                display,
-                glium::index::PrimitiveType::TrianglesList,
-                &object.indices,
-            );
-
-            let mut transform: Option<&NodeTransform> = None;
-            for tr in &self.nodes_transforms {
-                if tr.node_id == self.model_asset.objects[i].node_index {
-                    transform = Some(tr);
-                    break;
+        let instances_center = buckets[0]
+            .iter()
+            .map(|instance| Mat4::from_cols_array_2d(&instance.model).transform_point3(bounding_box.center()))
+            .sum::<Vec3>()
+            / buckets[0].len() as f32;
+        // every bucket gets lights that reach any of its instances
+        let lod_lights: Vec<LightsBlock> = buckets
+            .iter()
+            .map(|bucket| {
+                let bucket_box = bucket
+                    .iter()
+                    .map(|instance| bounding_box.transformed(&Mat4::from_cols_array_2d(&instance.model)))
+                    .reduce(|aabb, instance_box| aabb.union(&instance_box));
+                match bucket_box {
+                    Some(bucket_box) => render::get_lights_block(&bucket_box),
+                    None => render::get_lights_block(&Aabb::from_points(std::iter::empty())),
                 }
-            }
-
-            match transform {
-                Some(_) => (),
-                None => {
-                    error("no node transform found!");
-                    return;
-                }
-            }
-
-            //let setup_mat_result = self.setup_mat(transform.unwrap());
-            //let mvp: Mat4 = setup_mat_result.mvp;
-            //let model: Mat4 = setup_mat_result.model;
+            })
+            .collect();
+        self.write_lod_buckets(display, buckets, false);
+        if !self.write_lod_lights(display, &lod_lights) {
+            return;
+        }
 
-            let texture_option = self.texture.as_ref();
+        let camera_position = render::get_camera_render_position();
+        let uniform_buffers = self
+            .uniform_buffers
+            .as_ref()
+            .expect("uniform buffers weren't created(why)");
+        uniform_buffers.cascades.write(&cascades.as_block());
+        self.shadow_view_projs = cascades.closest_and_furthest_view_projs();
+        uniform_buffers.joints.write(&self.get_joints_transforms());
 
-            let empty_texture = glium::texture::Texture2d::empty(display, 1, 1).unwrap();
-            let texture: &glium::texture::Texture2d;
-            match texture_option {
-                Some(tx) => texture = tx,
-                None => texture = &empty_texture,
+        let camera_distance = instances_center.distance(camera_position);
+        for i in 0..self.model_asset.objects.len() {
+            if self.instances_range(&self.batch, i).is_empty() {
+                continue;
             }
 
-            let joints = UniformBuffer::new(display, self.get_joints_transforms()).unwrap();
-            let inverse_bind_mats =
-                UniformBuffer::new(display, self.model_asset.joints_inverse_bind_mats).unwrap();
-
-            let camera_position: [f32; 3] = render::get_camera_position().into();
+            let transparent = self.model_asset.objects[i].material.alpha_mode == AlphaMode::Blend;
+            queue.add(RenderQueueItem::new(
+                self.id,
+                i,
+                self.programs[i],
+                self.base_color_texture(display, i),
+                &self.vertex_buffer[i],
+                transparent,
+                camera_distance,
+            ));
+        }
+    }
 
+    fn render_queued(
+        &mut self,
+        display: &Display<WindowSurface>,
+        target: &mut SimpleFrameBuffer,
+        shadow_texture: &ShadowTextures,
+        i: usize,
+    ) {
+        if let Some(lightmap) = &mut self.lightmap {
+            lightmap.prepare(display);
+        }
 
-            let sampler_behaviour = glium::uniforms::SamplerBehavior {
-                minify_filter: MinifySamplerFilter::Nearest,
-                magnify_filter: MagnifySamplerFilter::Nearest,
-                wrap_function: (
-                    SamplerWrapFunction::Repeat,
-                    SamplerWrapFunction::Repeat,
-                    SamplerWrapFunction::Repeat,
-                ),
-                ..Default::default()
-            };
+        let object = &self.model_asset.objects[i];
+        let per_instance_buffer = match self.instances_slice(&self.batch, i) {
+            Some(slice) => slice,
+            None => return,
+        };
+        let uniform_buffers = self
+            .uniform_buffers
+            .as_ref()
+            .expect("uniform buffers weren't created(why)");
+
+        let material = &object.material;
+        let material_textures = self
+            .material_textures
+            .as_ref()
+            .expect("material textures weren't created(why)");
+
+        let morph_target_textures = self
+            .morph_target_textures
+            .as_ref()
+            .expect("morph target textures weren't created(why)");
+        let morph = match (morph_target_textures.has_targets(i), self.node_transform(i)) {
+            (true, Some(transform)) => MorphUniforms::from_weights(&animation::morph_weights(object, transform, &self.morph_weights)),
+            _ => MorphUniforms::default(),
+        };
 
-            let uniforms = uniform! {
-                view: get_view_matrix().to_cols_array_2d(),
-                proj: get_projection_matrix().to_cols_array_2d(),
-                jointsMats: &joints,
-                jointsInverseBindMats: &inverse_bind_mats,
-                mesh: object.transform,
-                tex: Sampler(texture, sampler_behaviour),
-                lightPos: render::get_light_direction().to_array(),
-                closestShadowTexture: &shadow_texture.closest,
-                furthestShadowTexture: &shadow_texture.furthest,
-                closestShadowViewProj: [
-                    closest_shadow_view_proj_cols[0],
-                    closest_shadow_view_proj_cols[1],
-                    closest_shadow_view_proj_cols[2],
-                    closest_shadow_view_proj_cols[3],
-                ],
-                furthestShadowViewProj: [
-                    furthest_shadow_view_proj_cols[0],
-                    furthest_shadow_view_proj_cols[1],
-                    furthest_shadow_view_proj_cols[2],
-                    furthest_shadow_view_proj_cols[3],
-                ],
-                cameraPosition: camera_position,
-            };
+        let texture = self.base_color_texture(display, i);
+        let camera_position: [f32; 3] = render::get_camera_render_position().into();
+        let fog = sky::get_fog();
+
+        let sampler_behaviour = glium::uniforms::SamplerBehavior {
+            minify_filter: MinifySamplerFilter::Nearest,
+            magnify_filter: MagnifySamplerFilter::Nearest,
+            wrap_function: (
+                SamplerWrapFunction::Repeat,
+                SamplerWrapFunction::Repeat,
+                SamplerWrapFunction::Repeat,
+            ),
+            ..Default::default()
+        };
+        let lightmap_sampler_behaviour = glium::uniforms::SamplerBehavior {
+            minify_filter: MinifySamplerFilter::Linear,
+            magnify_filter: MagnifySamplerFilter::Linear,
+            ..Default::default()
+        };
 
-            let draw_params = glium::DrawParameters {
-                depth: glium::Depth {
-                    test: glium::draw_parameters::DepthTest::IfLess,
-                    write: true,
-                    ..Default::default()
-                },
-                blend: glium::draw_parameters::Blend::alpha_blending(),
-                backface_culling: glium::draw_parameters::BackfaceCullingMode::CullClockwise,
-                polygon_mode: glium::draw_parameters::PolygonMode::Fill,
-                ..Default::default()
-            };
+        let uniforms = uniform! {
+            view: get_view_matrix().to_cols_array_2d(),
+            proj: get_projection_matrix().to_cols_array_2d(),
+            jointsMats: &uniform_buffers.joints,
+            jointsInverseBindMats: &uniform_buffers.inverse_bind_mats,
+            mesh: object.transform,
+            tex: Sampler(texture, sampler_behaviour),
+            baseColorTexCoord: render::material_tex_coord(&material.base_color_texture),
+            baseColorFactor: material.base_color_factor,
+            metallicFactor: material.metallic_factor,
+            roughnessFactor: material.roughness_factor,
+            metallicRoughnessTexture: Sampler(material_textures.get(&material.metallic_roughness_texture), sampler_behaviour),
+            metallicRoughnessTexCoord: render::material_tex_coord(&material.metallic_roughness_texture),
+            normalTexture: Sampler(material_textures.get(&material.normal_texture), sampler_behaviour),
+            hasNormalTexture: material.normal_texture.is_some(),
+            normalTexCoord: render::material_tex_coord(&material.normal_texture),
+            normalScale: material.normal_scale,
+            occlusionTexture: Sampler(material_textures.get(&material.occlusion_texture), sampler_behaviour),
+            occlusionStrength: material.occlusion_strength,
+            occlusionTexCoord: render::material_tex_coord(&material.occlusion_texture),
+            emissiveTexture: Sampler(material_textures.get(&material.emissive_texture), sampler_behaviour),
+            emissiveFactor: material.emissive_factor,
+            emissiveTexCoord: render::material_tex_coord(&material.emissive_texture),
+            lightmapTexture: Sampler(self.lightmap.as_ref().map_or(render::get_white_texture(), |lightmap| lightmap.texture()), lightmap_sampler_behaviour),
+            hasLightmap: self.lightmap.is_some(),
+            lightmapIntensity: self.lightmap.as_ref().map_or(0.0, |lightmap| lightmap.intensity),
+            alphaMode: material.alpha_mode.as_shader_int(),
+            alphaCutoff: material.alpha_cutoff,
+            lightPos: render::get_light_direction().to_array(),
+            sunDirection: render::get_sun_direction().to_array(),
+            sunColor: render::get_sun_color().to_array(),
+            ambientColor: render::get_ambient_color().to_array(),
+            cascades: &uniform_buffers.cascades,
+            cascadeShadowTexture0: shadow_texture.cascade(0),
+            cascadeShadowTexture1: shadow_texture.cascade(1),
+            cascadeShadowTexture2: shadow_texture.cascade(2),
+            cascadeShadowTexture3: shadow_texture.cascade(3),
+            closestShadowTexture: shadow_texture.cascade(0),
+            furthestShadowTexture: shadow_texture.furthest(),
+            closestShadowViewProj: self.shadow_view_projs[0],
+            furthestShadowViewProj: self.shadow_view_projs[1],
+            cameraPosition: camera_position,
+            lights: &self.lod_lights[self.lod_bucket(i)],
+            pointShadowMap0: &shadow_texture.point[0],
+            pointShadowMap1: &shadow_texture.point[1],
+            spotShadowMap0: &shadow_texture.spot[0],
+            spotShadowMap1: &shadow_texture.spot[1],
+            spotShadowViewProj0: render::get_spot_shadow_view_proj(0).to_cols_array_2d(),
+            spotShadowViewProj1: render::get_spot_shadow_view_proj(1).to_cols_array_2d(),
+            lightShadowNear: render::LIGHT_SHADOW_NEAR,
+            fogMode: fog.mode.as_shader_int(),
+            fogColor: fog.color.to_array(),
+            fogStart: fog.start,
+            fogEnd: fog.end,
+            fogDensity: fog.density,
+            morphTargets: morph_target_textures.get(i),
+            morphTargetsCount: morph.count,
+            morphVertexCount: object.vertices.len() as i32,
+            morphTargetIndices0: morph.indices[0],
+            morphTargetIndices1: morph.indices[1],
+            morphWeights0: morph.weights[0],
+            morphWeights1: morph.weights[1],
+        };
 
-            target
-                .draw(
-                    (&self.vertex_buffer[i], per_instance_buffer.per_instance().unwrap()),
-                    &indices.unwrap(),
-                    &self.programs[i],
-                    &uniforms,
-                    &draw_params,
-                )
-                .unwrap();
-        }
+        let draw_params = render::material_draw_parameters(material);
+
+        target
+            .draw(
+                (&self.vertex_buffer[i], per_instance_buffer.per_instance().unwrap()),
+                &self.index_buffers[i],
+                self.programs[i],
+                &uniforms,
+                &draw_params,
+            )
+            .unwrap();
     }
 
     fn shadow_render(
@@ -351,30 +507,28 @@ impl Object for MasterInstancedModelObject {
             return;
         }
 
-        let matrices = match render::get_instance_positions(&self.name) {
-            Some(matrices) => matrices,
-            None => {
-                return
-            },
-        };
-        let mut per_instance_data = Vec::new();
-
+        // one buffer for every shadow map of a frame, the GPU clips instances outside of each one
+        let frame_index = render::get_frame_index();
+        if self.shadow_batch_frame != Some(frame_index) {
+            self.shadow_batch_frame = Some(frame_index);
+            // fading in LODs don't cast shadows
+            let buckets = self.lod_buckets(|_| true, false);
+            self.write_lod_buckets(display, buckets, true);
+        }
 
-        for matrix in matrices {
-            per_instance_data.push(Instance {
-                model: matrix.to_cols_array_2d(),
-            });
+        let frustum = Frustum::from_view_proj(view_proj);
+        if !render::is_shadow_visible(&self.shadow_batch.bounds, &frustum) {
+            return;
         }
-        let per_instance_buffer = glium::vertex::VertexBuffer::dynamic(display, &per_instance_data).unwrap();
 
         for i in 0..self.model_asset.objects.len() {
-            let object = &self.model_asset.objects[i];
-
-            let indices = IndexBuffer::new(
-                display,
-                glium::index::PrimitiveType::TrianglesList,
-                &object.indices,
-            );
+            if self.instances_range(&self.shadow_batch, i).is_empty() {
+                continue;
+            }
+            let per_instance_buffer = match self.instances_slice(&self.shadow_batch, i) {
+                Some(slice) => slice,
+                None => continue,
+            };
 
             let mut transform: Option<&NodeTransform> = None;
             for tr in &self.nodes_transforms {
@@ -418,8 +572,8 @@ impl Object for MasterInstancedModelObject {
             target
                 .draw(
                     (&self.vertex_buffer[i], per_instance_buffer.per_instance().unwrap()),
-                    &indices.unwrap(),
-                    &self.shadow_programs[i],
+                    &self.index_buffers[i],
+                    self.shadow_programs[i],
                     &uniforms,
                     &draw_params,
                 )
@@ -429,12 +583,102 @@ impl Object for MasterInstancedModelObject {
 }
 
 impl MasterInstancedModelObject {
+    pub fn set_lod_settings(&mut self, settings: LodSettings) {
+        self.lod_settings = settings;
+    }
+
+    /// How much skinned meshes' bind pose bounds are grown for culling, the default is 1.5.
+    pub fn set_skinned_bounds_scale(&mut self, scale: f32) {
+        self.model_asset.set_skinned_bounds_scale(scale);
+    }
+
+    /// Baked lighting sampled with the second texture coords set, None removes it.
+    pub fn set_lightmap(&mut self, texture: Option<TextureAsset>, intensity: f32) {
+        self.lightmap = texture.map(|texture| Lightmap::new(texture, intensity));
+    }
+
+    /// Instance that's drawn every frame until it's removed, returns its id.
+    /// Instances added with `render::add_instance` only last one frame.
+    pub fn add_instance(&mut self, data: InstanceData) -> InstanceId {
+        let index = match self.free_instance_slots.pop() {
+            Some(index) => {
+                self.persistent_instances[index].data = Some(data);
+                index
+            }
+            None => {
+                self.persistent_instances.push(InstanceSlot {
+                    data: Some(data),
+                    generation: 0,
+                });
+                self.persistent_instances.len() - 1
+            }
+        };
+        self.mark_persistent_dirty(index);
+
+        InstanceId {
+            index: index as u32,
+            generation: self.persistent_instances[index].generation,
+        }
+    }
+
+    pub fn update_instance(&mut self, id: InstanceId, data: InstanceData) {
+        match self.persistent_instance_mut(id) {
+            Some(instance) => *instance = data,
+            None => {
+                error(&format!("MasterInstancedModelObject error:
+update_instance failed, there's no instance with id {}", id.to_bits()));
+                return;
+            }
+        }
+        self.mark_persistent_dirty(id.index as usize);
+    }
+
+    /// Ids of removed instances stay invalid after their slot is reused.
+    pub fn remove_instance(&mut self, id: InstanceId) {
+        if self.persistent_instance_mut(id).is_none() {
+            error(&format!("MasterInstancedModelObject error:
+remove_instance failed, there's no instance with id {}", id.to_bits()));
+            return;
+        }
+
+        let slot = &mut self.persistent_instances[id.index as usize];
+        slot.data = None;
+        slot.generation = (slot.generation + 1) % INSTANCE_GENERATIONS;
+        self.free_instance_slots.push(id.index as usize);
+        self.mark_persistent_dirty(id.index as usize);
+    }
+
+    /// Removes every persistent instance, their ids stay invalid.
+    pub fn clear_instances(&mut self) {
+        for (index, slot) in self.persistent_instances.iter_mut().enumerate() {
+            if slot.data.take().is_some() {
+                slot.generation = (slot.generation + 1) % INSTANCE_GENERATIONS;
+                self.free_instance_slots.push(index);
+            }
+        }
+        self.persistent_dirty = Some(0..self.persistent_instances.len());
+    }
+
+    fn persistent_instance_mut(&mut self, id: InstanceId) -> Option<&mut InstanceData> {
+        match self.persistent_instances.get_mut(id.index as usize) {
+            Some(slot) if slot.generation == id.generation => slot.data.as_mut(),
+            _ => None,
+        }
+    }
+
+    fn mark_persistent_dirty(&mut self, index: usize) {
+        self.persistent_dirty = Some(match self.persistent_dirty.take() {
+            Some(dirty) => dirty.start.min(index)..dirty.end.max(index + 1),
+            None => index..index + 1,
+        });
+    }
+
     pub fn get_asset(&self) -> &ModelAsset {
         &self.model_asset
     }
 
     pub fn set_looping(&mut self, should_loop: bool) {
-        self.animation_settings.looping = should_loop;
+        self.animation_settings.set_looping(should_loop);
     }
 
     pub fn is_looping(&self) -> bool {
@@ -442,27 +686,118 @@ impl MasterInstancedModelObject {
     }
 
     pub fn current_animation(&self) -> Option<&str> {
-        match &self.animation_settings.animation {
-            Some(animation) => Some(&animation.name),
+        self.animation_settings.current_animation()
+    }
+
+    pub fn play_animation(&mut self, anim_name: &str) -> Result<(), ModelObjectError> {
+        self.cross_fade(anim_name, 0.0, BASE_LAYER, self.is_looping())
+    }
+
+    /// Fades the animation in over `duration` seconds while everything else on the layer fades out.
+    pub fn cross_fade(&mut self, anim_name: &str, duration: f32, layer: &str, looping: bool) -> Result<(), ModelObjectError> {
+        let animation = self
+            .model_asset
+            .find_animation(anim_name)
+            .ok_or(ModelObjectError::AnimationNotFound)?;
+        self.animation_settings.play(layer, animation, looping, duration)?;
+
+        Ok(())
+    }
+
+    /// Plays the animation together with the ones already on the layer, weights are normalized.
+    /// Changes the weight if it's already playing.
+    pub fn blend_animation(&mut self, anim_name: &str, weight: f32, fade_duration: f32, layer: &str, looping: bool) -> Result<(), ModelObjectError> {
+        let animation = self
+            .model_asset
+            .find_animation(anim_name)
+            .ok_or(ModelObjectError::AnimationNotFound)?;
+        self.animation_settings.blend(layer, animation, weight, looping, fade_duration)?;
+
+        Ok(())
+    }
+
+    pub fn set_animation_weight(&mut self, anim_name: &str, weight: f32, fade_duration: f32, layer: &str) -> Result<(), ModelObjectError> {
+        self.animation_settings.set_weight(layer, anim_name, weight, fade_duration)?;
+        Ok(())
+    }
+
+    /// Stops the animation or, if `anim_name` is None, everything on the layer.
+    pub fn stop_animation(&mut self, anim_name: Option<&str>, fade_duration: f32, layer: &str) -> Result<(), ModelObjectError> {
+        self.animation_settings.stop(layer, anim_name, fade_duration)?;
+        Ok(())
+    }
+
+    /// Adds a layer above the existing ones.
+    /// With `mask_joint` the layer only moves that joint and its children.
+    pub fn add_animation_layer(&mut self, name: &str, blend_mode: AnimationBlendMode, mask_joint: Option<&str>) -> Result<(), ModelObjectError> {
+        let mask = match mask_joint {
+            Some(joint_name) => Some(
+                super::animation::joint_mask(&self.model_asset, joint_name)
+                    .ok_or(ModelObjectError::JointNotFound)?,
+            ),
             None => None,
+        };
+        self.animation_settings.add_layer(name, blend_mode, mask);
+
+        Ok(())
+    }
+
+    pub fn remove_animation_layer(&mut self, name: &str) -> Result<(), ModelObjectError> {
+        self.animation_settings.remove_layer(name)?;
+        Ok(())
+    }
+
+    /// Event at `time` seconds of the animation, delivered to the event callbacks when a track passes it.
+    pub fn add_animation_event(&mut self, anim_name: &str, time: f32, event_name: &str) -> Result<(), ModelObjectError> {
+        if !self.model_asset.add_animation_event(anim_name, time, event_name) {
+            return Err(ModelObjectError::AnimationNotFound);
         }
+        self.animation_settings.add_event(anim_name, time, event_name);
+
+        Ok(())
     }
 
-    pub fn play_animation(&mut self, anim_name: &str) -> Result<(), ModelObjectError> {
-        let anim_option = self.model_asset.find_animation(anim_name);
+    pub fn set_animation_layer_weight(&mut self, name: &str, weight: f32, fade_duration: f32) -> Result<(), ModelObjectError> {
+        self.animation_settings.set_layer_weight(name, weight, fade_duration)?;
+        Ok(())
+    }
 
-        match anim_option {
-            Some(animation) => {
-                self.animation_settings = CurrentAnimationSettings {
-                    animation: Some(animation),
-                    looping: self.animation_settings.looping,
-                    timer: Some(Instant::now()),
-                };
+    /// Sets the weight of every morph target with this name for all instances, animations don't change it until `clear_morph_weight`.
+    pub fn set_morph_weight(&mut self, name: &str, weight: f32) -> Result<(), ModelObjectError> {
+        if !self.model_asset.morph_target_names().contains(&name) {
+            return Err(ModelObjectError::MorphTargetNotFound);
+        }
+        self.morph_weights.insert(name.into(), weight);
+
+        Ok(())
+    }
+
+    /// Gives the morph target back to animations.
+    pub fn clear_morph_weight(&mut self, name: &str) {
+        self.morph_weights.remove(name);
+    }
 
-                Ok(())
+    /// Current weight of the first morph target with this name.
+    pub fn morph_weight(&self, name: &str) -> Option<f32> {
+        if let Some(weight) = self.morph_weights.get(name) {
+            return Some(*weight);
+        }
+        for i in 0..self.model_asset.objects.len() {
+            let object = &self.model_asset.objects[i];
+            if let Some(target_index) = object.morph_targets.iter().position(|target| target.name == name) {
+                let weights = &self.node_transform(i)?.morph_weights;
+                return Some(weights.get(target_index).copied().unwrap_or(0.0));
             }
-            None => Err(ModelObjectError::AnimationNotFound),
         }
+
+        None
+    }
+
+    fn node_transform(&self, object_index: usize) -> Option<&NodeTransform> {
+        let node_index = self.model_asset.objects[object_index].node_index;
+        self.nodes_transforms
+            .iter()
+            .find(|transform| transform.node_id == node_index)
     }
 
     fn get_joints_transforms(&self) -> [[[f32; 4]; 4]; 128] {
@@ -508,38 +843,183 @@ impl MasterInstancedModelObject {
         joints_array
     }
 
-    fn update_animation(&mut self) {
-        let anim_settings = &mut self.animation_settings;
-        let animation_option = &mut anim_settings.animation;
-
-        match animation_option {
-            Some(ref mut animation) => {
-                let timer = &anim_settings.timer;
-                let time_elapsed = timer.expect("no timer(why)").elapsed().as_secs_f32();
-                set_objects_anim_node_transform(
-                    &mut animation.channels,
-                    &mut self.nodes_transforms,
-                    time_elapsed,
-                );
-
-                if time_elapsed >= animation.duration {
-                    if anim_settings.looping {
-                        set_objects_anim_node_transform(
-                            &mut animation.channels,
-                            &mut self.nodes_transforms,
-                            time_elapsed,
-                        );
-                        anim_settings.timer = Some(Instant::now());
-                    } else {
-                        anim_settings.animation = None;
-                        anim_settings.timer = None;
-                        //return;
-                        ()
-                    }
+    fn base_color_texture(&self, display: &Display<WindowSurface>, object_index: usize) -> &Texture2d {
+        let material = &self.model_asset.objects[object_index].material;
+        let material_textures = self
+            .material_textures
+            .as_ref()
+            .expect("material textures weren't created(why)");
+
+        match &self.texture {
+            Some(tx) => tx,
+            None => match self.model_asset.has_materials() {
+                true => material_textures.get(&material.base_color_texture),
+                false => render::get_default_texture(display)
+                    .unwrap_or(material_textures.get(&material.base_color_texture)),
+            },
+        }
+    }
+
+    /// Visible instances of this frame and persistent ones grouped like lod_ranges,
+    /// an instance in a cross-fade band goes to both LODs.
+    fn lod_buckets(&self, is_visible: impl Fn(&Aabb) -> bool, cross_fade: bool) -> Vec<Vec<Instance>> {
+        let lods_count = self.model_asset.lods_count();
+        let bounding_box = self.model_asset.nodes_bounding_box();
+        let camera_position = render::get_camera_render_position();
+        let mut buckets: Vec<Vec<Instance>> = vec![Vec::new(); lods_count + 1];
+
+        let frame_instances = render::get_instances(&self.name).into_iter().flatten();
+        let persistent_instances = self.persistent_instances.iter().filter_map(|slot| slot.data.as_ref());
+        for data in frame_instances.chain(persistent_instances) {
+            let instance_box = bounding_box.transformed(&data.model);
+            if self.cull_distance.is_some_and(|distance| instance_box.center().distance(camera_position) > distance) {
+                continue;
+            }
+            if !is_visible(&instance_box) {
+                continue;
+            }
+
+            buckets[0].push(Instance::new(data, 0.0));
+            if lods_count < 2 {
+                continue;
+            }
+
+            let selection = self.lod_settings.select(&instance_box, lods_count);
+            match (selection.next, cross_fade) {
+                (Some(next), true) => {
+                    buckets[selection.lod + 1].push(Instance::new(data, selection.fade));
+                    buckets[next + 1].push(Instance::new(data, selection.fade - 1.0));
                 }
+                _ => buckets[selection.lod + 1].push(Instance::new(data, 0.0)),
             }
-            None => (),
         }
+
+        buckets
+    }
+
+    /// Buckets go to the camera's batch or to the shadow one.
+    fn write_lod_buckets(&mut self, display: &Display<WindowSurface>, buckets: Vec<Vec<Instance>>, shadow: bool) {
+        let mut batch = match shadow {
+            true => std::mem::take(&mut self.shadow_batch),
+            false => std::mem::take(&mut self.batch),
+        };
+        if shadow {
+            let bounding_box = self.model_asset.nodes_bounding_box();
+            batch.bounds = Aabb::from_points(buckets[0].iter().flat_map(|instance| {
+                let instance_box = bounding_box.transformed(&Mat4::from_cols_array_2d(&instance.model));
+                [instance_box.min, instance_box.max]
+            }));
+        }
+
+        // nothing culled, no frame instances and no LODs to sort them into: persistent slots are drawn as they are
+        let no_frame_instances = render::get_instances(&self.name).is_none_or(|instances| instances.is_empty());
+        let persistent_count = self.persistent_instances.len() - self.free_instance_slots.len();
+        batch.draws_persistent_buffer = no_frame_instances
+            && buckets.len() <= 2
+            && buckets[0].len() == persistent_count
+            && self.write_persistent_buffer(display);
+        if batch.draws_persistent_buffer {
+            batch.lod_ranges = vec![0..self.persistent_instances.len(); buckets.len()];
+        } else {
+            // without LOD-less meshes the first bucket isn't needed
+            let skip_all = buckets.len() > 2 && self.model_asset.objects.iter().all(|object| object.lod.is_some());
+
+            batch.lod_ranges.clear();
+            let mut per_instance_data = Vec::new();
+            for (i, bucket) in buckets.into_iter().enumerate() {
+                if i == 0 && skip_all {
+                    batch.lod_ranges.push(0..0);
+                    continue;
+                }
+                let start = per_instance_data.len();
+                per_instance_data.extend(bucket);
+                batch.lod_ranges.push(start..per_instance_data.len());
+            }
+
+            // nothing is drawn this time if the buffer couldn't be created
+            if !write_instance_buffer(display, &mut batch.buffer, &per_instance_data) {
+                batch.lod_ranges.clear();
+            }
+        }
+
+        match shadow {
+            true => self.shadow_batch = batch,
+            false => self.batch = batch,
+        }
+    }
+
+    fn lod_bucket(&self, object_index: usize) -> usize {
+        match self.model_asset.objects[object_index].lod {
+            Some(lod) => lod + 1,
+            None => 0,
+        }
+    }
+
+    fn instances_range(&self, batch: &InstanceBatch, object_index: usize) -> Range<usize> {
+        batch.lod_ranges.get(self.lod_bucket(object_index)).cloned().unwrap_or(0..0)
+    }
+
+    fn instances_slice<'a>(&'a self, batch: &'a InstanceBatch, object_index: usize) -> Option<VertexBufferSlice<'a, Instance>> {
+        let buffer = match batch.draws_persistent_buffer {
+            true => &self.persistent_buffer,
+            false => &batch.buffer,
+        };
+        buffer.as_ref()?.slice(self.instances_range(batch, object_index))
+    }
+
+    /// Writes only slots changed since the last write, false if the buffer couldn't be created.
+    fn write_persistent_buffer(&mut self, display: &Display<WindowSurface>) -> bool {
+        let slots_count = self.persistent_instances.len();
+        let too_small = match &self.persistent_buffer {
+            Some(buffer) => buffer.len() < slots_count,
+            None => true,
+        };
+        if too_small {
+            match VertexBuffer::empty_dynamic(display, slots_count.next_power_of_two()) {
+                Ok(buffer) => self.persistent_buffer = Some(buffer),
+                Err(err) => {
+                    error(&format!("MasterInstancedModelObject error:\npersistent instance buffer creation error!\nErr: {}", err));
+                    self.persistent_buffer = None;
+                    return false;
+                }
+            }
+            self.persistent_dirty = Some(0..slots_count);
+        }
+
+        if let Some(dirty) = self.persistent_dirty.take() {
+            let dirty = dirty.start.min(slots_count)..dirty.end.min(slots_count);
+            let instances: Vec<Instance> = self.persistent_instances[dirty.clone()]
+                .iter()
+                .map(|slot| match &slot.data {
+                    Some(data) => Instance::new(data, 0.0),
+                    None => Instance::hidden(),
+                })
+                .collect();
+            if !instances.is_empty() {
+                self.persistent_buffer.as_ref().unwrap().slice(dirty).unwrap().write(&instances);
+            }
+        }
+
+        true
+    }
+
+    /// Buffers are reused between frames, false if a new one couldn't be created.
+    fn write_lod_lights(&mut self, display: &Display<WindowSurface>, lod_lights: &[LightsBlock]) -> bool {
+        while self.lod_lights.len() < lod_lights.len() {
+            match UniformBuffer::empty_dynamic(display) {
+                Ok(buffer) => self.lod_lights.push(buffer),
+                Err(err) => {
+                    error(&format!("master instanced model object error:\nlights buffer creation error!\nErr: {}", err));
+                    self.error = true;
+                    return false;
+                }
+            }
+        }
+        for (buffer, block) in self.lod_lights.iter().zip(lod_lights) {
+            buffer.write(block);
+        }
+
+        true
     }
 
     fn start_mesh(&mut self, display: &Display<WindowSurface>) {
@@ -566,6 +1046,35 @@ impl MasterInstancedModelObject {
                     return;
                 }
             }
+
+            let index_buffer = IndexBuffer::new(
+                display,
+                glium::index::PrimitiveType::TrianglesList,
+                &i.indices,
+            );
+            match index_buffer {
+                Ok(buff) => self.index_buffers.push(buff),
+                Err(err) => {
+                    error(&format!(
+                        "Mesh object error:\nindex buffer creation error!\nErr: {}",
+                        err
+                    ));
+                    self.error = true;
+                    return;
+                }
+            }
+        }
+
+        match ModelUniformBuffers::new(display, &self.model_asset) {
+            Ok(buffers) => self.uniform_buffers = Some(buffers),
+            Err(err) => {
+                error(&format!(
+                    "MasterInstancedModelObject error:\nuniform buffers creation error!\nErr: {}",
+                    err
+                ));
+                self.error = true;
+                return;
+            }
         }
 
         let vertex_shader_source = &self.shader_asset.vertex_shader_source;
@@ -575,19 +1084,9 @@ impl MasterInstancedModelObject {
         let fragment_shadow_shader_src = &shadow_shader.fragment_shader_source;
 
         for _ in &self.model_asset.objects {
-            let program = Program::from_source(
-                display,
-                &vertex_shader_source,
-                &fragment_shader_source,
-                None,
-            );
-
-            let shadow_program = Program::from_source(
-                display,
-                &vertex_shadow_shader_src,
-                &fragment_shadow_shader_src,
-                None,
-            );
+            let program = render::get_program(display, vertex_shader_source, fragment_shader_source);
+            let shadow_program =
+                render::get_program(display, vertex_shadow_shader_src, fragment_shadow_shader_src);
 
             match shadow_program {
                 Ok(prog) => self.shadow_programs.push(prog),
@@ -614,13 +1113,14 @@ impl MasterInstancedModelObject {
             }
         }
 
+        self.material_textures = Some(MaterialTextures::new(display, &self.model_asset));
+        self.morph_target_textures = Some(MorphTargetTextures::new(display, &self.model_asset));
+
+        // a texture passed manually overrides base color textures of the materials,
+        // the default texture is used only when the model doesn't have materials at all
         if self.texture_asset.is_some() {
             let asset = self.texture_asset.as_ref().unwrap();
-            let image = glium::texture::RawImage2d::from_raw_rgba(
-                asset.image_raw.clone(),
-                asset.image_dimensions,
-            );
-            let texture = glium::texture::texture2d::Texture2d::new(display, image);
+            let texture = render::create_srgb_texture(display, &asset.image_raw, asset.image_dimensions, false);
 
             match texture {
                 Ok(tx) => self.texture = Some(tx),
@@ -632,82 +1132,12 @@ impl MasterInstancedModelObject {
                     self.texture = None;
                 }
             }
-        } else {
-            let asset_result = TextureAsset::default_texture();
-            match asset_result {
-                Ok(asset) => {
-                    let image = glium::texture::RawImage2d::from_raw_rgba_reversed(
-                        &asset.image_raw,
-                        asset.image_dimensions,
-                    );
-                    let texture = glium::texture::texture2d::Texture2d::new(display, image);
-
-                    match texture {
-                        Ok(tx) => self.texture = Some(tx),
-                        Err(err) => {
-                            error(&format!(
-                                "MasterInstancedModelObject error:\ntexture creating error!\nErr: {}",
-                                err
-                            ));
-                            self.texture = None;
-                        }
-                    }
-                }
-                Err(err) => {
-                    debugger::error(&format!(
-                        "model object({}) - failed to open a default texture.\nerror: {:?}",
-                        self.name, err
-                    ));
-                    self.texture = None;
-                }
-            }
-        };
+        }
 
         self.started = true;
     }
 }
 
-fn set_objects_anim_node_transform(
-    channels: &mut Vec<AnimationChannel>,
-    nodes_transforms: &mut Vec<NodeTransform>,
-    time_elapsed: f32,
-) {
-    for channel in channels {
-        match channel.channel_type {
-            AnimationChannelType::Translation => {
-                for node in &mut *nodes_transforms {
-                    if node.node_id == channel.node_index {
-                        let x_pos = channel.x_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        let y_pos = channel.y_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        let z_pos = channel.z_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        node.local_position = Vec3::new(x_pos, y_pos, z_pos);
-                    }
-                }
-            }
-            AnimationChannelType::Rotation => {
-                for node in &mut *nodes_transforms {
-                    if node.node_id == channel.node_index {
-                        let x_rot = channel.x_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        let y_rot = channel.y_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        let z_rot = channel.z_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        node.local_rotation = Vec3::new(x_rot, y_rot, z_rot);
-                    }
-                }
-            }
-            AnimationChannelType::Scale => {
-                for node in &mut *nodes_transforms {
-                    if node.node_id == channel.node_index {
-                        let x_scale = channel.x_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        let y_scale = channel.y_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        let z_scale = channel.z_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        node.local_scale = Vec3::new(x_scale, y_scale, z_scale);
-                    }
-                }
-            }
-        }
-    }
-}
-
 fn set_nodes_global_transform(
     node: &model_asset::Node,
     nodes_list: &Vec<model_asset::Node>,
@@ -729,16 +1159,9 @@ fn set_nodes_global_transform(
     }
     let node_transform = node_transform.expect("node transform was None(why)");
 
-    let local_rotation = node_transform.local_rotation;
-    let local_rotation_quat = Quat::from_euler(
-        glam::EulerRot::XYZ,
-        local_rotation.x,
-        local_rotation.y,
-        local_rotation.z,
-    );
     let local_transform = Mat4::from_scale_rotation_translation(
         node_transform.local_scale,
-        local_rotation_quat,
+        node_transform.local_rotation,
         node_transform.local_position,
     );
 
@@ -772,31 +1195,125 @@ fn set_nodes_global_transform(
     }
 }
 
-#[derive(Debug)]
-pub struct CurrentAnimationSettings {
-    pub animation: Option<Animation>,
-    pub looping: bool,
-    pub timer: Option<Instant>,
+/// Ids stay exact in lua numbers (doubles), so generations wrap at 2^21.
+const INSTANCE_GENERATIONS: u32 = 1 << 21;
+
+/// Persistent instance handle, the generation changes when the instance is removed,
+/// so old ids don't reach an instance that got the same slot later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstanceId {
+    pub index: u32,
+    pub generation: u32,
 }
 
-#[derive(Debug)]
-pub struct NodeTransform {
-    pub local_position: Vec3,
-    pub local_rotation: Vec3,
-    pub local_scale: Vec3,
-    pub global_transform: Option<Mat4>,
-    pub parent_global_transform: Option<Mat4>,
-    pub node_id: usize,
+impl InstanceId {
+    pub fn to_bits(self) -> u64 {
+        (self.generation as u64) << 32 | self.index as u64
+    }
+
+    pub fn from_bits(bits: u64) -> InstanceId {
+        InstanceId {
+            index: bits as u32,
+            generation: (bits >> 32) as u32,
+        }
+    }
 }
 
-#[derive(Debug)]
-pub enum ModelObjectError {
-    AnimationNotFound,
+#[derive(Debug, Clone)]
+struct InstanceSlot {
+    /// None is a free slot
+    data: Option<InstanceData>,
+    generation: u32,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 pub struct Instance {
     model: [[f32; 4]; 4],
+    /// same as `lodFade` uniform of ModelObject
+    lod_fade: f32,
+    tint: [f32; 4],
+    wind_phase: f32,
+    texture_index: i32,
+    custom: [f32; 4],
+}
+
+impl Instance {
+    /// Zero model matrix collapses every vertex to one point, so nothing is drawn.
+    fn hidden() -> Instance {
+        Instance {
+            model: [[0.0; 4]; 4],
+            lod_fade: 0.0,
+            tint: [0.0; 4],
+            wind_phase: 0.0,
+            texture_index: 0,
+            custom: [0.0; 4],
+        }
+    }
+
+    fn new(data: &InstanceData, lod_fade: f32) -> Instance {
+        Instance {
+            model: data.model.to_cols_array_2d(),
+            lod_fade,
+            tint: data.tint.to_array(),
+            wind_phase: data.wind_phase,
+            texture_index: data.texture_index,
+            custom: data.custom.to_array(),
+        }
+    }
 }
 
-implement_vertex!(Instance, model);
+implement_vertex!(Instance, model, lod_fade, tint, wind_phase, texture_index, custom);
+
+/// Instances of one kind of pass grouped by LOD.
+#[derive(Debug)]
+struct InstanceBatch {
+    buffer: Option<VertexBuffer<Instance>>,
+    /// buffer ranges, 0 - all instances, lod + 1 - instances of that LOD
+    lod_ranges: Vec<Range<usize>>,
+    /// persistent_buffer is drawn instead of buffer
+    draws_persistent_buffer: bool,
+    /// render space bounds of all instances, only shadow batches have them
+    bounds: Aabb,
+}
+
+impl Default for InstanceBatch {
+    fn default() -> Self {
+        InstanceBatch {
+            buffer: None,
+            lod_ranges: vec![],
+            draws_persistent_buffer: false,
+            bounds: Aabb::from_points(std::iter::empty()),
+        }
+    }
+}
+
+/// Instance buffer is reused between frames and only grows, false if a new one couldn't be created.
+fn write_instance_buffer(
+    display: &Display<WindowSurface>,
+    buffer: &mut Option<VertexBuffer<Instance>>,
+    per_instance_data: &[Instance],
+) -> bool {
+    let too_small = match buffer {
+        Some(buffer) => buffer.len() < per_instance_data.len(),
+        None => true,
+    };
+    if too_small {
+        match VertexBuffer::empty_dynamic(display, per_instance_data.len().next_power_of_two()) {
+            Ok(new_buffer) => *buffer = Some(new_buffer),
+            Err(err) => {
+                error(&format!("MasterInstancedModelObject error:\ninstance buffer creation error!\nErr: {}", err));
+                *buffer = None;
+                return false;
+            }
+        }
+    }
+
+    buffer
+        .as_ref()
+        .unwrap()
+        .slice(0..per_instance_data.len())
+        .unwrap()
+        .write(per_instance_data);
+
+    true
+}
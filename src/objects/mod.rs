@@ -3,15 +3,18 @@ use crate::{
     managers::{
         self,
         physics::{self, BodyType, CollisionGroups, ObjectBodyParameters, RenderColliderType},
-        render::{self, Cascades, ShadowTextures},
+        render::{self, Cascades, RenderQueue, ShadowTextures},
     },
 };
 use downcast_rs::{impl_downcast, Downcast};
 use egui_glium::egui_winit::egui::Ui;
 use glam::{Mat4, Vec3};
-use glium::{framebuffer::SimpleFrameBuffer, glutin::surface::WindowSurface, Display, Frame};
+use glium::{framebuffer::SimpleFrameBuffer, glutin::surface::WindowSurface, Display};
 use serde::{Deserialize, Serialize};
 
+pub mod animation;
+pub mod animator;
+pub mod camera;
 pub mod camera_position;
 pub mod character_controller;
 pub mod empty_object;
@@ -21,8 +24,13 @@ pub mod master_instanced_model_object;
 pub mod instanced_model_transform_holder;
 pub mod nav_obstacle;
 pub mod navmesh;
+pub mod particle_emitter;
+pub mod point_light;
 pub mod ray;
 pub mod sound_emitter;
+pub mod spot_light;
+pub mod sprite;
+pub mod terrain;
 pub mod trigger;
 
 static mut LAST_OBJECT_ID: u128 = 0;
@@ -68,9 +76,20 @@ pub trait Object: std::fmt::Debug + Downcast {
     fn render(
         &mut self,
         _display: &Display<WindowSurface>,
-        _target: &mut Frame,
+        _target: &mut SimpleFrameBuffer,
         _cascades: &Cascades,
         _shadow_textures: &ShadowTextures,
+        _queue: &mut RenderQueue,
+    ) {
+    }
+
+    /// Draws a mesh that this object added to render queue in render().
+    fn render_queued(
+        &mut self,
+        _display: &Display<WindowSurface>,
+        _target: &mut SimpleFrameBuffer,
+        _shadow_textures: &ShadowTextures,
+        _mesh_index: usize,
     ) {
     }
 
@@ -153,13 +172,14 @@ pub trait Object: std::fmt::Debug + Downcast {
     fn render_children(
         &mut self,
         display: &Display<WindowSurface>,
-        target: &mut Frame,
+        target: &mut SimpleFrameBuffer,
         cascades: &Cascades,
         shadow_texture: &ShadowTextures,
+        queue: &mut RenderQueue,
     ) {
         self.children_list_mut().iter_mut().for_each(|child| {
-            child.render(display, target, cascades, shadow_texture);
-            child.render_children(display, target, cascades, shadow_texture);
+            child.render(display, target, cascades, shadow_texture, queue);
+            child.render_children(display, target, cascades, shadow_texture, queue);
         });
     }
 
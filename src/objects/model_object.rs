@@ -1,23 +1,42 @@
-use super::{gen_object_id, Object, ObjectGroup, Transform};
+use std::collections::HashMap;
+
+use super::{animation::{self, AnimationBlendMode, AnimationError, CurrentAnimationSettings, NodeTransform, RootMotionSettings, BASE_LAYER}, animator::Animator, gen_object_id, Object, ObjectGroup, Transform};
 use crate::{
     assets::{
-        model_asset::{self, Animation, AnimationChannel, AnimationChannelType, ModelAsset},
+        model_asset::{self, AlphaMode, ModelAsset},
         shader_asset::ShaderAsset,
         texture_asset::TextureAsset,
-    }, framework::Framework, managers::{
-        debugger::{self, error, warn},
+    }, framework::{self, Framework}, managers::{
+        debugger::{error, warn},
         physics::ObjectBodyParameters,
-        render::{self, Cascades, ShadowTextures, Vertex},
-    }, math_utils::deg_to_rad
+        render::{self, Cascades, LodSelection, LodSettings, MaterialTextures, ModelUniformBuffers, Lightmap, MorphTargetTextures, MorphUniforms, RenderQueue, RenderQueueItem, ShadowTextures, Vertex},
+        sky,
+    }, math_utils::{deg_to_rad, deg_vec_to_rad, rad_to_deg, rad_vec_to_deg, Aabb, Frustum}
 };
-use egui_glium::egui_winit::egui::ComboBox;
+use egui_glium::egui_winit::egui::{ComboBox, Slider};
 use glam::{Mat4, Quat, Vec3};
 use glium::{
     framebuffer::SimpleFrameBuffer, glutin::surface::WindowSurface, uniform, uniforms::{
-        MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction, UniformBuffer,
-    }, Display, IndexBuffer, Program, Surface, VertexBuffer
+        MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction,
+    }, texture::Texture2d, Display, IndexBuffer, Program, Surface, VertexBuffer
 };
-use std::time::Instant;
+
+/// Root motion in world space, collected since it was last taken.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RootMotion {
+    pub translation: Vec3,
+    /// degrees around the y axis
+    pub yaw: f32,
+}
+
+/// Child object placed at a node of the model every frame.
+#[derive(Debug, Clone)]
+struct JointAttachment {
+    child_name: String,
+    node_index: usize,
+    /// in the joint's local space
+    offset: Mat4,
+}
 
 pub struct ModelObject {
     name: String,
@@ -30,12 +49,34 @@ pub struct ModelObject {
     pub model_asset: ModelAsset,
     pub nodes_transforms: Vec<NodeTransform>,
     pub animation_settings: CurrentAnimationSettings,
+    /// state machine that drives the base animation layer
+    animator: Option<Animator>,
+    /// morph target weights set by name, they're used instead of the animated ones
+    morph_weights: HashMap<String, f32>,
+    /// children that follow joints instead of the object's transform
+    joint_attachments: Vec<JointAttachment>,
+    /// first skinned mesh, joints are placed with its model matrix
+    skinned_object: Option<usize>,
+    /// moves the object by the root motion, otherwise it's kept for `take_root_motion`
+    root_motion_to_object: bool,
+    root_motion: RootMotion,
     pub shader_asset: ShaderAsset,
     pub texture_asset: Option<TextureAsset>,
+    pub lod_settings: LodSettings,
+    lod_selection: LodSelection,
     texture: Option<glium::texture::Texture2d>,
+    /// camera whose render target is used instead of the base color texture
+    camera_texture: Option<String>,
+    material_textures: Option<MaterialTextures>,
+    morph_target_textures: Option<MorphTargetTextures>,
+    lightmap: Option<Lightmap>,
+    /// first and last sun cascades from the last render, for the old shadow uniforms
+    shadow_view_projs: [[[f32; 4]; 4]; 2],
     vertex_buffer: Vec<VertexBuffer<Vertex>>,
-    programs: Vec<Program>,
-    shadow_programs: Vec<Program>,
+    index_buffers: Vec<IndexBuffer<u32>>,
+    uniform_buffers: Option<ModelUniformBuffers>,
+    programs: Vec<&'static Program>,
+    shadow_programs: Vec<&'static Program>,
     started: bool,
     error: bool,
     inspector_anim_name: String,
@@ -53,20 +94,23 @@ impl ModelObject {
             let node_local_transform_mat = Mat4::from_cols_array_2d(&node.transform);
             let node_scale_rotation_translation =
                 node_local_transform_mat.to_scale_rotation_translation();
-            let node_rotation = node_scale_rotation_translation
-                .1
-                .to_euler(glam::EulerRot::XYZ);
-
             nodes_transforms.push(NodeTransform {
                 local_position: node_scale_rotation_translation.2,
-                local_rotation: node_rotation.into(),
+                local_rotation: node_scale_rotation_translation.1,
                 local_scale: node_scale_rotation_translation.0,
                 global_transform: None,
                 node_id: node.node_index,
                 parent_global_transform: None,
+                morph_weights: node.morph_weights.clone(),
             });
         }
 
+        let animation_settings = CurrentAnimationSettings::new(&nodes_transforms);
+        let skinned_object = asset
+            .objects
+            .iter()
+            .position(|object| object.vertices.iter().any(|vertex| vertex.weights != [0.0; 4]));
+
         ModelObject {
             transform: Transform::default(),
             nodes_transforms,
@@ -77,17 +121,28 @@ impl ModelObject {
             groups: vec![],
             texture_asset,
             shader_asset,
+            lod_settings: LodSettings::default(),
+            lod_selection: LodSelection::default(),
             texture: None,
+            camera_texture: None,
+            material_textures: None,
+            morph_target_textures: None,
+            lightmap: None,
+            shadow_view_projs: [Mat4::IDENTITY.to_cols_array_2d(); 2],
             vertex_buffer: vec![],
+            index_buffers: vec![],
+            uniform_buffers: None,
             programs: vec![],
             shadow_programs: vec![],
             started: false,
             error: false,
-            animation_settings: CurrentAnimationSettings {
-                animation: None,
-                looping: false,
-                timer: None,
-            },
+            animation_settings,
+            animator: None,
+            morph_weights: HashMap::new(),
+            joint_attachments: vec![],
+            skinned_object,
+            root_motion_to_object: false,
+            root_motion: RootMotion::default(),
             body: None,
             id: gen_object_id(),
             inspector_anim_name: "None".into(),
@@ -113,7 +168,11 @@ impl Object for ModelObject {
     fn start(&mut self) {}
 
     fn update(&mut self, _: &mut Framework) {
-        self.update_animation();
+        let delta = framework::get_delta_time().as_secs_f32();
+        if let Some(animator) = &mut self.animator {
+            animator.update(&self.model_asset, &mut self.animation_settings, delta);
+        }
+        self.animation_settings.update(&mut self.nodes_transforms, delta);
         for node in &self.model_asset.root_nodes {
             set_nodes_global_transform(
                 &node,
@@ -122,6 +181,36 @@ impl Object for ModelObject {
                 &mut self.nodes_transforms,
             );
         }
+        self.collect_root_motion();
+    }
+
+    fn update_children(&mut self, framework: &mut Framework) {
+        let global_transform = self.global_transform();
+        let attached: Vec<(String, Option<Transform>)> = self
+            .joint_attachments
+            .iter()
+            .map(|attachment| (attachment.child_name.clone(), self.attachment_transform(attachment)))
+            .collect();
+
+        for child in &mut self.children {
+            match attached.iter().find(|(name, _)| name == child.name()) {
+                Some((_, transform)) => {
+                    // the joint is already in world space, so the object's transform isn't added to it
+                    child.set_parent_transform(Transform {
+                        position: Vec3::ZERO,
+                        rotation: Vec3::ZERO,
+                        scale: Vec3::ZERO,
+                    });
+                    if let Some(transform) = transform {
+                        child.set_position(transform.position, true);
+                        child.set_rotation(transform.rotation, true);
+                    }
+                }
+                None => child.set_parent_transform(global_transform),
+            }
+            child.update(framework);
+            child.update_children(framework);
+        }
     }
 
     fn children_list(&self) -> &Vec<Box<dyn Object>> {
@@ -177,6 +266,21 @@ impl Object for ModelObject {
         ui.label(&format!("error: {}", self.error));
         ui.label(&format!("model asset: {}", self.model_asset.path));
         ui.label(&format!("texture asset: {}", self.texture_asset.is_some()));
+        ui.collapsing("materials", |ui| {
+            for object in &self.model_asset.objects {
+                let material = &object.material;
+                ui.label(format!(
+                    "node {}: '{}', alpha mode: {:?}, double sided: {}",
+                    object.node_index, material.name, material.alpha_mode, material.double_sided
+                ));
+            }
+        });
+        let lods_count = self.model_asset.lods_count();
+        if lods_count > 1 {
+            ui.collapsing(format!("LODs ({}), current: {}", lods_count, self.lod_selection.lod), |ui| {
+                self.lod_settings.inspector_ui(ui);
+            });
+        }
 
         let anim_name = self.inspector_anim_name.clone();
         ComboBox::from_label("animation")
@@ -193,16 +297,36 @@ impl Object for ModelObject {
             if ui.button("play animation").clicked() {
                 let _ = self.play_animation(&anim_name);
             }
-            ui.checkbox(&mut self.animation_settings.looping, "loop");
-            if ui.button("stop").clicked() {
-                self.animation_settings.animation = None;
-                self.animation_settings.timer = None;
+            if ui.button("cross-fade").clicked() {
+                let _ = self.cross_fade(&anim_name, 0.3, BASE_LAYER, self.is_looping());
             }
-            if let Some(timer) = &self.animation_settings.timer {
-                ui.label("time:");
-                ui.label(format!("{} s", timer.elapsed().as_secs_f32()));
+            let mut looping = self.is_looping();
+            ui.checkbox(&mut looping, "loop");
+            self.set_looping(looping);
+            if ui.button("stop").clicked() {
+                self.animation_settings.stop_all();
             }
         });
+        if let Some(animator) = &mut self.animator {
+            ui.collapsing("animator", |ui| animator.inspector_ui(ui));
+        }
+        let morph_target_names: Vec<String> = self.model_asset.morph_target_names().into_iter().map(String::from).collect();
+        if !morph_target_names.is_empty() {
+            ui.collapsing("morph targets", |ui| {
+                for name in morph_target_names {
+                    ui.horizontal(|ui| {
+                        let mut weight = self.morph_weight(&name).unwrap_or(0.0);
+                        if ui.add(Slider::new(&mut weight, 0.0..=1.0).text(&name)).changed() {
+                            let _ = self.set_morph_weight(&name, weight);
+                        }
+                        if self.morph_weights.contains_key(&name) && ui.button("animate").clicked() {
+                            self.clear_morph_weight(&name);
+                        }
+                    });
+                }
+            });
+        }
+        self.animation_settings.inspector_ui(ui);
     }
 
     fn groups_list(&mut self) -> &mut Vec<ObjectGroup> {
@@ -212,134 +336,221 @@ impl Object for ModelObject {
     fn render(
         &mut self,
         display: &Display<WindowSurface>,
-        target: &mut glium::Frame,
+        _target: &mut SimpleFrameBuffer,
         cascades: &Cascades,
-        shadow_texture: &ShadowTextures,
+        _shadow_texture: &ShadowTextures,
+        queue: &mut RenderQueue,
     ) {
-        if self.error {
-            return;
-        }
         if !self.started {
             self.start_mesh(display);
         }
+        if self.error {
+            return;
+        }
 
-        let closest_shadow_view_proj_cols = cascades.closest_view_proj.to_cols_array_2d();
-        let furthest_shadow_view_proj_cols = cascades.furthest_view_proj.to_cols_array_2d();
+        let uniform_buffers = self
+            .uniform_buffers
+            .as_ref()
+            .expect("uniform buffers weren't created(why)");
+        uniform_buffers.cascades.write(&cascades.as_block());
+        self.shadow_view_projs = cascades.closest_and_furthest_view_projs();
+        uniform_buffers.joints.write(&self.get_joints_transforms());
+
+        let camera_position = render::get_camera_render_position();
+        self.lod_selection = self.select_lod();
 
         for i in 0..self.model_asset.objects.len() {
             let object = &self.model_asset.objects[i];
-
-            let indices = IndexBuffer::new(
-                display,
-                glium::index::PrimitiveType::TrianglesList,
-                &object.indices,
-            );
-
-            let mut transform: Option<&NodeTransform> = None;
-            for tr in &self.nodes_transforms {
-                if tr.node_id == self.model_asset.objects[i].node_index {
-                    transform = Some(tr);
-                    break;
-                }
+            if self.lod_selection.mesh_fade(object.lod).is_none() {
+                continue;
             }
 
-            match transform {
-                Some(_) => (),
+            let transform = match self.node_transform(i) {
+                Some(transform) => transform,
                 None => {
                     error("no node transform found!");
                     return;
                 }
+            };
+
+            let model: Mat4 = self.setup_mat(transform).model;
+            let bounding_box = object.bounding_box.transformed(&model);
+            if !render::is_visible(&bounding_box) {
+                continue;
             }
 
-            let setup_mat_result = self.setup_mat(transform.unwrap());
-            let mvp: Mat4 = setup_mat_result.mvp;
-            let model: Mat4 = setup_mat_result.model;
+            let transparent = object.material.alpha_mode == AlphaMode::Blend;
+            let camera_distance = bounding_box.center().distance(camera_position);
+            queue.add(RenderQueueItem::new(
+                self.id,
+                i,
+                self.programs[i],
+                self.base_color_texture(display, i),
+                &self.vertex_buffer[i],
+                transparent,
+                camera_distance,
+            ));
+        }
+    }
 
-            let texture_option = self.texture.as_ref();
+    fn render_queued(
+        &mut self,
+        display: &Display<WindowSurface>,
+        target: &mut SimpleFrameBuffer,
+        shadow_texture: &ShadowTextures,
+        i: usize,
+    ) {
+        if let Some(lightmap) = &mut self.lightmap {
+            lightmap.prepare(display);
+        }
 
-            let empty_texture = glium::texture::Texture2d::empty(display, 1, 1).unwrap();
-            let texture: &glium::texture::Texture2d;
-            match texture_option {
-                Some(tx) => texture = tx,
-                None => texture = &empty_texture,
+        let object = &self.model_asset.objects[i];
+        let transform = match self.node_transform(i) {
+            Some(transform) => transform,
+            None => {
+                error("no node transform found!");
+                return;
             }
-            let mvp_cols = mvp.to_cols_array_2d();
-            let model_cols = model.to_cols_array_2d();
+        };
 
-            let joints = UniformBuffer::new(display, self.get_joints_transforms()).unwrap();
-            let inverse_bind_mats =
-                UniformBuffer::new(display, self.model_asset.joints_inverse_bind_mats).unwrap();
-            let camera_position: [f32; 3] = render::get_camera_position().into();
-
-            let sampler_behaviour = glium::uniforms::SamplerBehavior {
-                minify_filter: MinifySamplerFilter::Nearest,
-                magnify_filter: MagnifySamplerFilter::Nearest,
-                wrap_function: (
-                    SamplerWrapFunction::Repeat,
-                    SamplerWrapFunction::Repeat,
-                    SamplerWrapFunction::Repeat,
-                ),
-                ..Default::default()
-            };
+        let setup_mat_result = self.setup_mat(transform);
+        let mvp: Mat4 = setup_mat_result.mvp;
+        let model: Mat4 = setup_mat_result.model;
+
+        let uniform_buffers = self
+            .uniform_buffers
+            .as_ref()
+            .expect("uniform buffers weren't created(why)");
+        let material = &object.material;
+        let material_textures = self
+            .material_textures
+            .as_ref()
+            .expect("material textures weren't created(why)");
+
+        let morph_target_textures = self
+            .morph_target_textures
+            .as_ref()
+            .expect("morph target textures weren't created(why)");
+        let morph = match morph_target_textures.has_targets(i) {
+            true => MorphUniforms::from_weights(&animation::morph_weights(object, transform, &self.morph_weights)),
+            false => MorphUniforms::default(),
+        };
 
-            let uniforms = uniform! {
-                is_instanced: false,
-                jointsMats: &joints,
-                jointsInverseBindMats: &inverse_bind_mats,
-                mesh: object.transform,
-                mvp: [
-                    mvp_cols[0],
-                    mvp_cols[1],
-                    mvp_cols[2],
-                    mvp_cols[3],
-                ],
-                model: [
-                    model_cols[0],
-                    model_cols[1],
-                    model_cols[2],
-                    model_cols[3],
-                ],
-                tex: Sampler(texture, sampler_behaviour),
-                lightPos: render::get_light_direction().to_array(),
-                closestShadowTexture: &shadow_texture.closest,
-                furthestShadowTexture: &shadow_texture.furthest,
-                closestShadowViewProj: [
-                    closest_shadow_view_proj_cols[0],
-                    closest_shadow_view_proj_cols[1],
-                    closest_shadow_view_proj_cols[2],
-                    closest_shadow_view_proj_cols[3],
-                ],
-                furthestShadowViewProj: [
-                    furthest_shadow_view_proj_cols[0],
-                    furthest_shadow_view_proj_cols[1],
-                    furthest_shadow_view_proj_cols[2],
-                    furthest_shadow_view_proj_cols[3],
-                ],
-                cameraPosition: camera_position,
-            };
+        let texture = self.base_color_texture(display, i);
+        let mvp_cols = mvp.to_cols_array_2d();
+        let model_cols = model.to_cols_array_2d();
+
+        let camera_position: [f32; 3] = render::get_camera_render_position().into();
+        let fog = sky::get_fog();
+        let lod_fade = self.lod_selection.mesh_fade(object.lod).unwrap_or(0.0);
+        uniform_buffers
+            .lights
+            .write(&render::get_lights_block(&object.bounding_box.transformed(&model)));
+
+        let sampler_behaviour = glium::uniforms::SamplerBehavior {
+            minify_filter: MinifySamplerFilter::Nearest,
+            magnify_filter: MagnifySamplerFilter::Nearest,
+            wrap_function: (
+                SamplerWrapFunction::Repeat,
+                SamplerWrapFunction::Repeat,
+                SamplerWrapFunction::Repeat,
+            ),
+            ..Default::default()
+        };
+        let lightmap_sampler_behaviour = glium::uniforms::SamplerBehavior {
+            minify_filter: MinifySamplerFilter::Linear,
+            magnify_filter: MagnifySamplerFilter::Linear,
+            ..Default::default()
+        };
 
-            let draw_params = glium::DrawParameters {
-                depth: glium::Depth {
-                    test: glium::draw_parameters::DepthTest::IfLess,
-                    write: true,
-                    ..Default::default()
-                },
-                blend: glium::draw_parameters::Blend::alpha_blending(),
-                backface_culling: glium::draw_parameters::BackfaceCullingMode::CullClockwise,
-                polygon_mode: glium::draw_parameters::PolygonMode::Fill,
-                ..Default::default()
-            };
+        let uniforms = uniform! {
+            is_instanced: false,
+            jointsMats: &uniform_buffers.joints,
+            jointsInverseBindMats: &uniform_buffers.inverse_bind_mats,
+            mesh: object.transform,
+            view: render::get_view_matrix().to_cols_array_2d(),
+            mvp: [
+                mvp_cols[0],
+                mvp_cols[1],
+                mvp_cols[2],
+                mvp_cols[3],
+            ],
+            model: [
+                model_cols[0],
+                model_cols[1],
+                model_cols[2],
+                model_cols[3],
+            ],
+            tex: Sampler(texture, sampler_behaviour),
+            baseColorTexCoord: render::material_tex_coord(&material.base_color_texture),
+            baseColorFactor: material.base_color_factor,
+            metallicFactor: material.metallic_factor,
+            roughnessFactor: material.roughness_factor,
+            metallicRoughnessTexture: Sampler(material_textures.get(&material.metallic_roughness_texture), sampler_behaviour),
+            metallicRoughnessTexCoord: render::material_tex_coord(&material.metallic_roughness_texture),
+            normalTexture: Sampler(material_textures.get(&material.normal_texture), sampler_behaviour),
+            hasNormalTexture: material.normal_texture.is_some(),
+            normalTexCoord: render::material_tex_coord(&material.normal_texture),
+            normalScale: material.normal_scale,
+            occlusionTexture: Sampler(material_textures.get(&material.occlusion_texture), sampler_behaviour),
+            occlusionStrength: material.occlusion_strength,
+            occlusionTexCoord: render::material_tex_coord(&material.occlusion_texture),
+            emissiveTexture: Sampler(material_textures.get(&material.emissive_texture), sampler_behaviour),
+            emissiveFactor: material.emissive_factor,
+            emissiveTexCoord: render::material_tex_coord(&material.emissive_texture),
+            lightmapTexture: Sampler(self.lightmap.as_ref().map_or(render::get_white_texture(), |lightmap| lightmap.texture()), lightmap_sampler_behaviour),
+            hasLightmap: self.lightmap.is_some(),
+            lightmapIntensity: self.lightmap.as_ref().map_or(0.0, |lightmap| lightmap.intensity),
+            alphaMode: material.alpha_mode.as_shader_int(),
+            alphaCutoff: material.alpha_cutoff,
+            lightPos: render::get_light_direction().to_array(),
+            sunDirection: render::get_sun_direction().to_array(),
+            sunColor: render::get_sun_color().to_array(),
+            ambientColor: render::get_ambient_color().to_array(),
+            cascades: &uniform_buffers.cascades,
+            cascadeShadowTexture0: shadow_texture.cascade(0),
+            cascadeShadowTexture1: shadow_texture.cascade(1),
+            cascadeShadowTexture2: shadow_texture.cascade(2),
+            cascadeShadowTexture3: shadow_texture.cascade(3),
+            closestShadowTexture: shadow_texture.cascade(0),
+            furthestShadowTexture: shadow_texture.furthest(),
+            closestShadowViewProj: self.shadow_view_projs[0],
+            furthestShadowViewProj: self.shadow_view_projs[1],
+            cameraPosition: camera_position,
+            lights: &uniform_buffers.lights,
+            pointShadowMap0: &shadow_texture.point[0],
+            pointShadowMap1: &shadow_texture.point[1],
+            spotShadowMap0: &shadow_texture.spot[0],
+            spotShadowMap1: &shadow_texture.spot[1],
+            spotShadowViewProj0: render::get_spot_shadow_view_proj(0).to_cols_array_2d(),
+            spotShadowViewProj1: render::get_spot_shadow_view_proj(1).to_cols_array_2d(),
+            lightShadowNear: render::LIGHT_SHADOW_NEAR,
+            fogMode: fog.mode.as_shader_int(),
+            fogColor: fog.color.to_array(),
+            fogStart: fog.start,
+            fogEnd: fog.end,
+            fogDensity: fog.density,
+            lodFade: lod_fade,
+            morphTargets: morph_target_textures.get(i),
+            morphTargetsCount: morph.count,
+            morphVertexCount: object.vertices.len() as i32,
+            morphTargetIndices0: morph.indices[0],
+            morphTargetIndices1: morph.indices[1],
+            morphWeights0: morph.weights[0],
+            morphWeights1: morph.weights[1],
+        };
 
-            target
-                .draw(
-                    &self.vertex_buffer[i],
-                    &indices.unwrap(),
-                    &self.programs[i],
-                    &uniforms,
-                    &draw_params,
-                )
-                .unwrap();
-        }
+        let draw_params = render::material_draw_parameters(material);
+
+        target
+            .draw(
+                &self.vertex_buffer[i],
+                &self.index_buffers[i],
+                self.programs[i],
+                &uniforms,
+                &draw_params,
+            )
+            .unwrap();
     }
 
     fn shadow_render(
@@ -356,34 +567,30 @@ impl Object for ModelObject {
             return;
         }
 
+        let frustum = Frustum::from_view_proj(view_proj);
+
         for i in 0..self.model_asset.objects.len() {
             let object = &self.model_asset.objects[i];
-
-            let indices = IndexBuffer::new(
-                display,
-                glium::index::PrimitiveType::TrianglesList,
-                &object.indices,
-            );
-
-            let mut transform: Option<&NodeTransform> = None;
-            for tr in &self.nodes_transforms {
-                if tr.node_id == self.model_asset.objects[i].node_index {
-                    transform = Some(tr);
-                    break;
-                }
+            // LOD from the last camera render, fading in LOD doesn't cast shadows
+            if object.lod.is_some_and(|lod| lod != self.lod_selection.lod) {
+                continue;
             }
 
-            match transform {
-                Some(_) => (),
+            let transform = match self.node_transform(i) {
+                Some(transform) => transform,
                 None => {
                     error("no node transform found!");
                     return;
                 }
-            }
+            };
 
-            let setup_mat_result = self.setup_mat(transform.unwrap());
+            let setup_mat_result = self.setup_mat(transform);
             let model: Mat4 = setup_mat_result.model;
 
+            if !render::is_shadow_visible(&object.bounding_box.transformed(&model), &frustum) {
+                continue;
+            }
+
             let model_cols = model.to_cols_array_2d();
             let view_proj_cols = view_proj.to_cols_array_2d();
 
@@ -416,8 +623,8 @@ impl Object for ModelObject {
             target
                 .draw(
                     &self.vertex_buffer[i],
-                    &indices.unwrap(),
-                    &self.shadow_programs[i],
+                    &self.index_buffers[i],
+                    self.shadow_programs[i],
                     &uniforms,
                     &draw_params,
                 )
@@ -427,12 +634,51 @@ impl Object for ModelObject {
 }
 
 impl ModelObject {
+    pub fn set_lod_settings(&mut self, settings: LodSettings) {
+        self.lod_settings = settings;
+    }
+
+    /// How much skinned meshes' bind pose bounds are grown for culling, the default is 1.5.
+    pub fn set_skinned_bounds_scale(&mut self, scale: f32) {
+        self.model_asset.set_skinned_bounds_scale(scale);
+    }
+
+    /// Baked lighting sampled with the second texture coords set, None removes it.
+    pub fn set_lightmap(&mut self, texture: Option<TextureAsset>, intensity: f32) {
+        self.lightmap = texture.map(|texture| Lightmap::new(texture, intensity));
+    }
+
+    /// Shows what a render target camera sees, for monitors and mirrors.
+    pub fn set_camera_texture(&mut self, camera_name: Option<&str>) {
+        self.camera_texture = camera_name.map(|name| name.to_string());
+    }
+
     pub fn get_asset(&self) -> &ModelAsset {
         &self.model_asset
     }
 
+    /// The animator takes over the base layer, other layers can still be used for scripted animations.
+    pub fn set_animator(&mut self, animator: Option<Animator>) {
+        if let Some(animator) = &animator {
+            for clip in animator.missing_clips(&self.model_asset) {
+                warn(&format!("model object warning
+animator of object '{}' uses animation '{}' which the model doesn't have", self.name, clip));
+            }
+            let _ = self.animation_settings.stop(BASE_LAYER, None, 0.0);
+        }
+        self.animator = animator;
+    }
+
+    pub fn animator(&self) -> Option<&Animator> {
+        self.animator.as_ref()
+    }
+
+    pub fn animator_mut(&mut self) -> Option<&mut Animator> {
+        self.animator.as_mut()
+    }
+
     pub fn set_looping(&mut self, should_loop: bool) {
-        self.animation_settings.looping = should_loop;
+        self.animation_settings.set_looping(should_loop);
     }
 
     pub fn is_looping(&self) -> bool {
@@ -440,27 +686,245 @@ impl ModelObject {
     }
 
     pub fn current_animation(&self) -> Option<&str> {
-        match &self.animation_settings.animation {
-            Some(animation) => Some(&animation.name),
+        self.animation_settings.current_animation()
+    }
+
+    pub fn play_animation(&mut self, anim_name: &str) -> Result<(), ModelObjectError> {
+        self.cross_fade(anim_name, 0.0, BASE_LAYER, self.is_looping())
+    }
+
+    /// Fades the animation in over `duration` seconds while everything else on the layer fades out.
+    pub fn cross_fade(&mut self, anim_name: &str, duration: f32, layer: &str, looping: bool) -> Result<(), ModelObjectError> {
+        let animation = self
+            .model_asset
+            .find_animation(anim_name)
+            .ok_or(ModelObjectError::AnimationNotFound)?;
+        self.animation_settings.play(layer, animation, looping, duration)?;
+
+        Ok(())
+    }
+
+    /// Plays the animation together with the ones already on the layer, weights are normalized.
+    /// Changes the weight if it's already playing.
+    pub fn blend_animation(&mut self, anim_name: &str, weight: f32, fade_duration: f32, layer: &str, looping: bool) -> Result<(), ModelObjectError> {
+        let animation = self
+            .model_asset
+            .find_animation(anim_name)
+            .ok_or(ModelObjectError::AnimationNotFound)?;
+        self.animation_settings.blend(layer, animation, weight, looping, fade_duration)?;
+
+        Ok(())
+    }
+
+    pub fn set_animation_weight(&mut self, anim_name: &str, weight: f32, fade_duration: f32, layer: &str) -> Result<(), ModelObjectError> {
+        self.animation_settings.set_weight(layer, anim_name, weight, fade_duration)?;
+        Ok(())
+    }
+
+    /// Stops the animation or, if `anim_name` is None, everything on the layer.
+    pub fn stop_animation(&mut self, anim_name: Option<&str>, fade_duration: f32, layer: &str) -> Result<(), ModelObjectError> {
+        self.animation_settings.stop(layer, anim_name, fade_duration)?;
+        Ok(())
+    }
+
+    /// Adds a layer above the existing ones.
+    /// With `mask_joint` the layer only moves that joint and its children.
+    pub fn add_animation_layer(&mut self, name: &str, blend_mode: AnimationBlendMode, mask_joint: Option<&str>) -> Result<(), ModelObjectError> {
+        let mask = match mask_joint {
+            Some(joint_name) => Some(
+                super::animation::joint_mask(&self.model_asset, joint_name)
+                    .ok_or(ModelObjectError::JointNotFound)?,
+            ),
             None => None,
+        };
+        self.animation_settings.add_layer(name, blend_mode, mask);
+
+        Ok(())
+    }
+
+    pub fn remove_animation_layer(&mut self, name: &str) -> Result<(), ModelObjectError> {
+        self.animation_settings.remove_layer(name)?;
+        Ok(())
+    }
+
+    /// Event at `time` seconds of the animation, delivered to the event callbacks when a track passes it.
+    pub fn add_animation_event(&mut self, anim_name: &str, time: f32, event_name: &str) -> Result<(), ModelObjectError> {
+        if !self.model_asset.add_animation_event(anim_name, time, event_name) {
+            return Err(ModelObjectError::AnimationNotFound);
         }
+        self.animation_settings.add_event(anim_name, time, event_name);
+
+        Ok(())
     }
 
-    pub fn play_animation(&mut self, anim_name: &str) -> Result<(), ModelObjectError> {
-        let anim_option = self.model_asset.find_animation(anim_name);
-
-        match anim_option {
-            Some(animation) => {
-                self.animation_settings = CurrentAnimationSettings {
-                    animation: Some(animation),
-                    looping: self.animation_settings.looping,
-                    timer: Some(Instant::now()),
-                };
+    pub fn set_animation_layer_weight(&mut self, name: &str, weight: f32, fade_duration: f32) -> Result<(), ModelObjectError> {
+        self.animation_settings.set_layer_weight(name, weight, fade_duration)?;
+        Ok(())
+    }
+
+    /// Takes the horizontal movement of a root joint out of the animations (the first joint of the skin if `joint_name` is None).
+    /// With `apply_to_object` the object moves by it, otherwise it's collected for `take_root_motion`.
+    pub fn set_root_motion(&mut self, joint_name: Option<&str>, rotation: bool, apply_to_object: bool) -> Result<(), ModelObjectError> {
+        let node_index = match joint_name {
+            Some(joint_name) => self.model_asset.nodes.iter().find(|node| node.name == joint_name).map(|node| node.node_index),
+            None => self.model_asset.joints.first().map(|joint| joint.node_index),
+        };
+        let node_index = node_index.ok_or(ModelObjectError::JointNotFound)?;
+
+        self.animation_settings.root_motion = Some(RootMotionSettings { node_index, rotation });
+        self.root_motion_to_object = apply_to_object;
+        self.root_motion = RootMotion::default();
+
+        Ok(())
+    }
+
+    pub fn disable_root_motion(&mut self) {
+        self.animation_settings.root_motion = None;
+        self.root_motion = RootMotion::default();
+    }
+
+    /// Root motion collected since the last call, pass it to `CharacterController::apply_root_motion`.
+    pub fn take_root_motion(&mut self) -> RootMotion {
+        std::mem::take(&mut self.root_motion)
+    }
+
+    /// Moves the root motion of the last update from the root's parent space to world space.
+    fn collect_root_motion(&mut self) {
+        let settings = match self.animation_settings.root_motion {
+            Some(settings) => settings,
+            None => return,
+        };
+        let delta = self.animation_settings.root_motion_delta;
+        let parent_global = self
+            .nodes_transforms
+            .iter()
+            .find(|transform| transform.node_id == settings.node_index)
+            .and_then(|transform| transform.parent_global_transform)
+            .unwrap_or(Mat4::IDENTITY);
+
+        let rotation = deg_vec_to_rad(self.transform.rotation);
+        let rotation = Quat::from_euler(glam::EulerRot::XYZ, rotation.x, rotation.y, rotation.z);
+        let translation = rotation * (parent_global.transform_vector3(delta.translation) * self.transform.scale);
+        // render space has z flipped
+        let translation = Vec3::new(translation.x, translation.y, -translation.z);
+        let yaw = rad_to_deg(delta.yaw);
+
+        match self.root_motion_to_object {
+            true => {
+                self.set_position(self.transform.position + translation, true);
+                let mut rotation = self.transform.rotation;
+                rotation.y += yaw;
+                self.set_rotation(rotation, true);
+            }
+            false => {
+                self.root_motion.translation += translation;
+                self.root_motion.yaw += yaw;
+            }
+        }
+    }
+
+    /// Makes a child follow the animated joint (or any other node) every frame.
+    /// `offset` is in the joint's space, the child's scale stays its own.
+    pub fn attach_to_joint(&mut self, child_name: &str, joint_name: &str, offset: Transform) -> Result<(), ModelObjectError> {
+        if !self.children.iter().any(|child| child.name() == child_name) {
+            return Err(ModelObjectError::ChildNotFound);
+        }
+        let node_index = self
+            .model_asset
+            .nodes
+            .iter()
+            .find(|node| node.name == joint_name)
+            .ok_or(ModelObjectError::JointNotFound)?
+            .node_index;
+
+        let rotation = deg_vec_to_rad(offset.rotation);
+        let attachment = JointAttachment {
+            child_name: child_name.into(),
+            node_index,
+            offset: Mat4::from_rotation_translation(
+                Quat::from_euler(glam::EulerRot::XYZ, rotation.x, rotation.y, rotation.z),
+                offset.position,
+            ),
+        };
+        self.joint_attachments.retain(|attachment| attachment.child_name != child_name);
+        self.joint_attachments.push(attachment);
+
+        Ok(())
+    }
 
-                Ok(())
+    /// The child goes back to following the object's transform, staying where it is now.
+    pub fn detach_from_joint(&mut self, child_name: &str) {
+        self.joint_attachments.retain(|attachment| attachment.child_name != child_name);
+        let global_transform = self.global_transform();
+        if let Some(child) = self.children.iter_mut().find(|child| child.name() == child_name) {
+            let transform = child.local_transform();
+            child.set_position(transform.position - global_transform.position, true);
+            child.set_rotation(transform.rotation - global_transform.rotation, true);
+            child.set_parent_transform(global_transform);
+        }
+    }
+
+    /// World transform of a joint or any other named node, scale isn't included.
+    pub fn joint_transform(&self, joint_name: &str) -> Option<Transform> {
+        let node = self.model_asset.nodes.iter().find(|node| node.name == joint_name)?;
+        let world = self.node_world_matrix(node.node_index)?;
+        Some(transform_from_render_matrix(&world))
+    }
+
+    fn attachment_transform(&self, attachment: &JointAttachment) -> Option<Transform> {
+        let world = self.node_world_matrix(attachment.node_index)?;
+        Some(transform_from_render_matrix(&(world * attachment.offset)))
+    }
+
+    /// Joints of the skin are moved by the skinned mesh's model matrix, other nodes are placed like meshes.
+    fn node_world_matrix(&self, node_index: usize) -> Option<Mat4> {
+        let node_transform = self
+            .nodes_transforms
+            .iter()
+            .find(|transform| transform.node_id == node_index)?;
+        let is_joint = self.model_asset.joints.iter().any(|joint| joint.node_index == node_index);
+
+        match (is_joint, self.skinned_object) {
+            (true, Some(object_index)) => {
+                let mesh_transform = self.node_transform(object_index)?;
+                Some(self.setup_mat(mesh_transform).model * node_transform.global_transform?)
+            }
+            _ => {
+                node_transform.global_transform?;
+                Some(self.setup_mat(node_transform).model)
+            }
+        }
+    }
+
+    /// Sets the weight of every morph target with this name, animations don't change it until `clear_morph_weight`.
+    pub fn set_morph_weight(&mut self, name: &str, weight: f32) -> Result<(), ModelObjectError> {
+        if !self.model_asset.morph_target_names().contains(&name) {
+            return Err(ModelObjectError::MorphTargetNotFound);
+        }
+        self.morph_weights.insert(name.into(), weight);
+
+        Ok(())
+    }
+
+    /// Gives the morph target back to animations.
+    pub fn clear_morph_weight(&mut self, name: &str) {
+        self.morph_weights.remove(name);
+    }
+
+    /// Current weight of the first morph target with this name.
+    pub fn morph_weight(&self, name: &str) -> Option<f32> {
+        if let Some(weight) = self.morph_weights.get(name) {
+            return Some(*weight);
+        }
+        for i in 0..self.model_asset.objects.len() {
+            let object = &self.model_asset.objects[i];
+            if let Some(target_index) = object.morph_targets.iter().position(|target| target.name == name) {
+                let weights = &self.node_transform(i)?.morph_weights;
+                return Some(weights.get(target_index).copied().unwrap_or(0.0));
             }
-            None => Err(ModelObjectError::AnimationNotFound),
         }
+
+        None
     }
 
     fn get_joints_transforms(&self) -> [[[f32; 4]; 4]; 128] {
@@ -506,37 +970,56 @@ impl ModelObject {
         joints_array
     }
 
-    fn update_animation(&mut self) {
-        let anim_settings = &mut self.animation_settings;
-        let animation_option = &mut anim_settings.animation;
-
-        match animation_option {
-            Some(ref mut animation) => {
-                let timer = &anim_settings.timer;
-                let time_elapsed = timer.expect("no timer(why)").elapsed().as_secs_f32();
-                set_objects_anim_node_transform(
-                    &mut animation.channels,
-                    &mut self.nodes_transforms,
-                    time_elapsed,
-                );
-
-                if time_elapsed >= animation.duration {
-                    if anim_settings.looping {
-                        set_objects_anim_node_transform(
-                            &mut animation.channels,
-                            &mut self.nodes_transforms,
-                            time_elapsed,
-                        );
-                        anim_settings.timer = Some(Instant::now());
-                    } else {
-                        anim_settings.animation = None;
-                        anim_settings.timer = None;
-                        //return;
-                        ()
-                    }
-                }
+    /// LOD of the whole model, picked with the union of its meshes' bounding boxes.
+    fn select_lod(&self) -> LodSelection {
+        let lods_count = self.model_asset.lods_count();
+        if lods_count < 2 {
+            return LodSelection::default();
+        }
+
+        let mut bounding_box: Option<Aabb> = None;
+        for i in 0..self.model_asset.objects.len() {
+            if let Some(transform) = self.node_transform(i) {
+                let model = self.setup_mat(transform).model;
+                let object_box = self.model_asset.objects[i].bounding_box.transformed(&model);
+                bounding_box = Some(match bounding_box {
+                    Some(bounding_box) => bounding_box.union(&object_box),
+                    None => object_box,
+                });
             }
-            None => (),
+        }
+
+        match bounding_box {
+            Some(bounding_box) => self.lod_settings.select(&bounding_box, lods_count),
+            None => LodSelection::default(),
+        }
+    }
+
+    fn node_transform(&self, object_index: usize) -> Option<&NodeTransform> {
+        let node_index = self.model_asset.objects[object_index].node_index;
+        self.nodes_transforms
+            .iter()
+            .find(|transform| transform.node_id == node_index)
+    }
+
+    fn base_color_texture(&self, display: &Display<WindowSurface>, object_index: usize) -> &Texture2d {
+        let material = &self.model_asset.objects[object_index].material;
+        let material_textures = self
+            .material_textures
+            .as_ref()
+            .expect("material textures weren't created(why)");
+
+        if let Some(camera_name) = &self.camera_texture {
+            return render::get_camera_texture(camera_name).unwrap_or(render::get_white_texture());
+        }
+
+        match &self.texture {
+            Some(tx) => tx,
+            None => match self.model_asset.has_materials() {
+                true => material_textures.get(&material.base_color_texture),
+                false => render::get_default_texture(display)
+                    .unwrap_or(material_textures.get(&material.base_color_texture)),
+            },
         }
     }
 
@@ -628,6 +1111,35 @@ impl ModelObject {
                     return;
                 }
             }
+
+            let index_buffer = IndexBuffer::new(
+                display,
+                glium::index::PrimitiveType::TrianglesList,
+                &i.indices,
+            );
+            match index_buffer {
+                Ok(buff) => self.index_buffers.push(buff),
+                Err(err) => {
+                    error(&format!(
+                        "ModelObject error:\nindex buffer creation error!\nErr: {}",
+                        err
+                    ));
+                    self.error = true;
+                    return;
+                }
+            }
+        }
+
+        match ModelUniformBuffers::new(display, &self.model_asset) {
+            Ok(buffers) => self.uniform_buffers = Some(buffers),
+            Err(err) => {
+                error(&format!(
+                    "ModelObject error:\nuniform buffers creation error!\nErr: {}",
+                    err
+                ));
+                self.error = true;
+                return;
+            }
         }
 
         let vertex_shader_source = &self.shader_asset.vertex_shader_source;
@@ -637,19 +1149,9 @@ impl ModelObject {
         let fragment_shadow_shader_src = &shadow_shader.fragment_shader_source;
 
         for _ in &self.model_asset.objects {
-            let program = Program::from_source(
-                display,
-                &vertex_shader_source,
-                &fragment_shader_source,
-                None,
-            );
-
-            let shadow_program = Program::from_source(
-                display,
-                &vertex_shadow_shader_src,
-                &fragment_shadow_shader_src,
-                None,
-            );
+            let program = render::get_program(display, vertex_shader_source, fragment_shader_source);
+            let shadow_program =
+                render::get_program(display, vertex_shadow_shader_src, fragment_shadow_shader_src);
 
             match program {
                 Ok(prog) => self.programs.push(prog),
@@ -676,13 +1178,14 @@ impl ModelObject {
             }
         }
 
+        self.material_textures = Some(MaterialTextures::new(display, &self.model_asset));
+        self.morph_target_textures = Some(MorphTargetTextures::new(display, &self.model_asset));
+
+        // a texture passed manually overrides base color textures of the materials,
+        // the default texture is used only when the model doesn't have materials at all (see render())
         if self.texture_asset.is_some() {
             let asset = self.texture_asset.as_ref().unwrap();
-            let image = glium::texture::RawImage2d::from_raw_rgba(
-                asset.image_raw.clone(),
-                asset.image_dimensions,
-            );
-            let texture = glium::texture::texture2d::Texture2d::new(display, image);
+            let texture = render::create_srgb_texture(display, &asset.image_raw, asset.image_dimensions, false);
 
             match texture {
                 Ok(tx) => self.texture = Some(tx),
@@ -694,79 +1197,20 @@ impl ModelObject {
                     self.texture = None;
                 }
             }
-        } else {
-            let asset_result = TextureAsset::default_texture();
-            match asset_result {
-                Ok(asset) => {
-                    let image = glium::texture::RawImage2d::from_raw_rgba_reversed(
-                        &asset.image_raw,
-                        asset.image_dimensions,
-                    );
-                    let texture = glium::texture::texture2d::Texture2d::new(display, image);
-
-                    match texture {
-                        Ok(tx) => self.texture = Some(tx),
-                        Err(err) => {
-                            error(&format!(
-                                "ModelObject error:\ntexture creating error!\nErr: {}",
-                                err
-                            ));
-                            self.texture = None;
-                        }
-                    }
-                }
-                Err(err) => {
-                    debugger::error(&format!(
-                        "model object({}) - failed to open a default texture.\nerror: {:?}",
-                        self.name, err
-                    ));
-                    self.texture = None;
-                }
-            }
-        };
+        }
 
         self.started = true;
     }
 }
 
-fn set_objects_anim_node_transform(
-    channels: &mut Vec<AnimationChannel>,
-    nodes_transforms: &mut Vec<NodeTransform>,
-    time_elapsed: f32,
-) {
-    for channel in channels {
-        match channel.channel_type {
-            AnimationChannelType::Translation => {
-                for node in &mut *nodes_transforms {
-                    if node.node_id == channel.node_index {
-                        let x_pos = channel.x_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        let y_pos = channel.y_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        let z_pos = channel.z_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        node.local_position = Vec3::new(x_pos, y_pos, z_pos);
-                    }
-                }
-            }
-            AnimationChannelType::Rotation => {
-                for node in &mut *nodes_transforms {
-                    if node.node_id == channel.node_index {
-                        let x_rot = channel.x_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        let y_rot = channel.y_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        let z_rot = channel.z_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        node.local_rotation = Vec3::new(x_rot, y_rot, z_rot);
-                    }
-                }
-            }
-            AnimationChannelType::Scale => {
-                for node in &mut *nodes_transforms {
-                    if node.node_id == channel.node_index {
-                        let x_scale = channel.x_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        let y_scale = channel.y_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        let z_scale = channel.z_axis_spline.clamped_sample(time_elapsed).unwrap();
-                        node.local_scale = Vec3::new(x_scale, y_scale, z_scale);
-                    }
-                }
-            }
-        }
+/// Back from render space (z is flipped) to position and rotation in degrees.
+fn transform_from_render_matrix(mat: &Mat4) -> Transform {
+    let (_, rotation, translation) = mat.to_scale_rotation_translation();
+    let (x, y, z) = rotation.to_euler(glam::EulerRot::XYZ);
+    Transform {
+        position: Vec3::new(translation.x, translation.y, -translation.z),
+        rotation: rad_vec_to_deg(Vec3::new(x, y, z)),
+        scale: Vec3::ONE,
     }
 }
 
@@ -791,16 +1235,9 @@ fn set_nodes_global_transform(
     }
     let node_transform = node_transform.expect("node transform was None(why)");
 
-    let local_rotation = node_transform.local_rotation;
-    let local_rotation_quat = Quat::from_euler(
-        glam::EulerRot::XYZ,
-        local_rotation.x,
-        local_rotation.y,
-        local_rotation.z,
-    );
     let local_transform = Mat4::from_scale_rotation_translation(
         node_transform.local_scale,
-        local_rotation_quat,
+        node_transform.local_rotation,
         node_transform.local_position,
     );
 
@@ -834,30 +1271,29 @@ fn set_nodes_global_transform(
     }
 }
 
-#[derive(Debug)]
-pub struct CurrentAnimationSettings {
-    pub animation: Option<Animation>,
-    pub looping: bool,
-    pub timer: Option<Instant>,
-}
-
 #[derive(Debug)]
 struct SetupMatrixResult {
     pub mvp: Mat4,
     pub model: Mat4,
 }
 
-#[derive(Debug)]
-pub struct NodeTransform {
-    pub local_position: Vec3,
-    pub local_rotation: Vec3,
-    pub local_scale: Vec3,
-    pub global_transform: Option<Mat4>,
-    pub parent_global_transform: Option<Mat4>,
-    pub node_id: usize,
-}
-
 #[derive(Debug)]
 pub enum ModelObjectError {
     AnimationNotFound,
+    JointNotFound,
+    AnimationLayerNotFound,
+    AnimationTrackNotFound,
+    BaseLayerRemoval,
+    MorphTargetNotFound,
+    ChildNotFound,
+}
+
+impl From<AnimationError> for ModelObjectError {
+    fn from(err: AnimationError) -> Self {
+        match err {
+            AnimationError::LayerNotFound => ModelObjectError::AnimationLayerNotFound,
+            AnimationError::TrackNotFound => ModelObjectError::AnimationTrackNotFound,
+            AnimationError::BaseLayerRemoval => ModelObjectError::BaseLayerRemoval,
+        }
+    }
 }
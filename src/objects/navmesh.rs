@@ -1,5 +1,5 @@
 use glam::Vec2;
-use glium::{glutin::surface::WindowSurface, Display};
+use glium::{framebuffer::SimpleFrameBuffer, glutin::surface::WindowSurface, Display};
 //use recast_rs::{util, Heightfield, CompactHeightfield, NoRegions, PolyMesh, ContourBuildFlags, ContourSet};
 use super::{gen_object_id, Object, ObjectGroup, Transform};
 use crate::{framework::Framework, managers::{
@@ -122,9 +122,10 @@ impl Object for NavigationGround {
     fn render(
         &mut self,
         _display: &Display<WindowSurface>,
-        _target: &mut glium::Frame,
+        _target: &mut SimpleFrameBuffer,
         _cascades: &crate::managers::render::Cascades,
         _shadow_textures: &crate::managers::render::ShadowTextures,
+        _queue: &mut crate::managers::render::RenderQueue,
     ) {
     }
 
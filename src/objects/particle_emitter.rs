@@ -0,0 +1,975 @@
+use std::{borrow::Cow, collections::BTreeMap, path::Path};
+
+use egui_glium::egui_winit::egui;
+use glam::{Mat4, Quat, Vec3, Vec4};
+use glium::{
+    draw_parameters::{Blend, BlendingFunction, LinearBlendingFactor},
+    framebuffer::SimpleFrameBuffer,
+    glutin::surface::WindowSurface,
+    implement_vertex,
+    index::{NoIndices, PrimitiveType},
+    texture::{ClientFormat, MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat},
+    uniform,
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction},
+    vertex::TransformFeedbackSession,
+    Display, Program, Surface, VertexBuffer,
+};
+use rand::{rngs::ThreadRng, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use splines::{Interpolation, Key, Spline};
+
+use super::{gen_object_id, Object, ObjectGroup, Transform};
+use crate::{
+    assets::texture_asset::TextureAsset,
+    framework::{self, Framework},
+    managers::{
+        assets::get_full_asset_path,
+        debugger::error,
+        physics::ObjectBodyParameters,
+        render::{self, Cascades, RenderQueue, RenderQueueItem, ShadowTextures},
+    },
+    math_utils::{deg_to_rad, Aabb},
+};
+
+const PARTICLES_VERTEX_SHADER: &str = include_str!("../assets/particles.vert");
+const PARTICLES_FRAGMENT_SHADER: &str = include_str!("../assets/particles.frag");
+const PARTICLES_SIMULATE_VERTEX_SHADER: &str = include_str!("../assets/particles_simulate.vert");
+// nothing is rasterized while simulating, but a program needs a fragment shader
+const PARTICLES_SIMULATE_FRAGMENT_SHADER: &str = "#version 330\nout vec4 color;\nvoid main() { color = vec4(0.0); }\n";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EmissionShape {
+    /// particles fly in every direction
+    Point,
+    /// particles start inside the sphere and fly away from its center
+    Sphere { radius: f32 },
+    /// particles start inside the box and fly up
+    Box { half_extents: Vec3 },
+    /// particles start on a disc and fly up, at most `angle` degrees away from up
+    Cone { angle: f32, radius: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParticleBlend {
+    Alpha,
+    /// for fire and sparks, alpha scales the added colour
+    Additive,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Burst {
+    /// seconds from the start of a cycle
+    pub time: f32,
+    pub count: u32,
+}
+
+/// Curve keys' time is 0 - 1 of a particle's lifetime.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ColorKey {
+    pub time: f32,
+    /// linear rgba, values above 1 glow with bloom
+    pub color: Vec4,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SizeKey {
+    pub time: f32,
+    pub size: f32,
+}
+
+/// Emitter definition, can be loaded from a toml or json file.
+/// Ranges are [min, max], missing fields get default values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParticleEmitterSettings {
+    pub shape: EmissionShape,
+    /// particles per second
+    pub rate: f32,
+    pub bursts: Vec<Burst>,
+    /// seconds of one cycle, emitter stops after it if it isn't looping
+    pub duration: f32,
+    pub looping: bool,
+    pub max_particles: usize,
+    pub lifetime: [f32; 2],
+    pub speed: [f32; 2],
+    /// added to every particle's start velocity, in emitter's space
+    pub velocity: Vec3,
+    /// world space acceleration
+    pub gravity: Vec3,
+    /// velocity fraction lost per second
+    pub drag: f32,
+    /// degrees per second
+    pub rotation_speed: [f32; 2],
+    /// if false, particles move with the emitter
+    pub world_space: bool,
+    pub color_over_life: Vec<ColorKey>,
+    pub size_over_life: Vec<SizeKey>,
+    pub texture: Option<String>,
+    pub blend: ParticleBlend,
+    /// texture sheet columns and rows, frames go left to right, top to bottom
+    pub sheet: [u32; 2],
+    /// sheet frames per second, 0 plays all frames once over the lifetime
+    pub sheet_fps: f32,
+    /// particles fade out when they get closer than this to the geometry behind them, 0 disables it
+    pub soft_distance: f32,
+}
+
+impl Default for ParticleEmitterSettings {
+    fn default() -> Self {
+        ParticleEmitterSettings {
+            shape: EmissionShape::Cone {
+                angle: 25.0,
+                radius: 0.1,
+            },
+            rate: 10.0,
+            bursts: vec![],
+            duration: 5.0,
+            looping: true,
+            max_particles: 1000,
+            lifetime: [1.0, 2.0],
+            speed: [1.0, 2.0],
+            velocity: Vec3::ZERO,
+            gravity: Vec3::ZERO,
+            drag: 0.0,
+            rotation_speed: [0.0, 0.0],
+            world_space: true,
+            color_over_life: vec![
+                ColorKey {
+                    time: 0.0,
+                    color: Vec4::ONE,
+                },
+                ColorKey {
+                    time: 1.0,
+                    color: Vec4::new(1.0, 1.0, 1.0, 0.0),
+                },
+            ],
+            size_over_life: vec![SizeKey {
+                time: 0.0,
+                size: 0.2,
+            }],
+            texture: None,
+            blend: ParticleBlend::Alpha,
+            sheet: [1, 1],
+            sheet_fps: 0.0,
+            soft_distance: 0.5,
+        }
+    }
+}
+
+impl ParticleEmitterSettings {
+    /// Format is picked by the extension, anything that isn't .json is read as toml.
+    pub fn from_file(path: &str) -> Result<ParticleEmitterSettings, ParticleEmitterError> {
+        let full_path = get_full_asset_path(path);
+        let source = match std::fs::read_to_string(&full_path) {
+            Ok(source) => source,
+            Err(err) => {
+                error(&format!("particle emitter definition loading error!\npath: {}\nerr: {}", full_path, err));
+                return Err(ParticleEmitterError::ReadError);
+            }
+        };
+
+        let settings = match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+            Some("json") => serde_json::from_str(&source).map_err(|err| err.to_string()),
+            _ => toml::from_str(&source).map_err(|err| err.to_string()),
+        };
+        match settings {
+            Ok(settings) => Ok(settings),
+            Err(err) => {
+                error(&format!("particle emitter definition parsing error!\npath: {}\nerr: {}", full_path, err));
+                Err(ParticleEmitterError::ParseError)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ParticleEmitterError {
+    ReadError,
+    ParseError,
+}
+
+/// Particle state on the GPU, the simulation reads one buffer of them and writes the next one.
+/// Field order is the order of transform feedback varyings.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct ParticleState {
+    particle_position: [f32; 3],
+    particle_velocity: [f32; 3],
+    particle_age: f32,
+    particle_lifetime: f32,
+    /// radians
+    particle_rotation: f32,
+    particle_rotation_speed: f32,
+}
+implement_vertex!(
+    ParticleState,
+    particle_position,
+    particle_velocity,
+    particle_age,
+    particle_lifetime,
+    particle_rotation,
+    particle_rotation_speed
+);
+
+const SIMULATE_VARYINGS: [&str; 6] = [
+    "next_position",
+    "next_velocity",
+    "next_age",
+    "next_lifetime",
+    "next_rotation",
+    "next_rotation_speed",
+];
+
+/// Texels per curve in the baked curves texture.
+const CURVE_SAMPLES: usize = 64;
+
+#[derive(Copy, Clone, Debug)]
+struct ParticleCorner {
+    corner: [f32; 2],
+}
+implement_vertex!(ParticleCorner, corner);
+
+/// What the CPU knows about a particle slot, enough for spawning and culling.
+#[derive(Debug, Clone, Copy)]
+struct ParticleSlot {
+    /// emitter's clock time, the slot is free after it
+    death_time: f32,
+    /// emitter's world position when the particle was spawned
+    origin: Vec3,
+}
+
+/// Particle waiting to be written into its slot on the next render.
+#[derive(Debug, Clone, Copy)]
+struct PendingSpawn {
+    spawn_time: f32,
+    state: ParticleState,
+}
+
+/// GPU simulated particles drawn as camera facing quads with one instanced draw call.
+#[derive(Debug)]
+pub struct ParticleEmitter {
+    name: String,
+    transform: Transform,
+    parent_transform: Option<Transform>,
+    children: Vec<Box<dyn Object>>,
+    body: Option<ObjectBodyParameters>,
+    id: u128,
+    groups: Vec<ObjectGroup>,
+    settings: ParticleEmitterSettings,
+    /// stops spawning new particles, alive ones finish their lives
+    pub emitting: bool,
+    /// seconds since the emitter was created
+    clock: f32,
+    slots: Vec<ParticleSlot>,
+    /// by slot index, so a slot reused before the next render is written once
+    pending_spawns: BTreeMap<usize, PendingSpawn>,
+    /// time to simulate on the next render, the first camera of a frame runs the simulation
+    pending_time: f32,
+    /// state buffers are created again with every slot dead, after a restart or `max_particles` change
+    clear_pending: bool,
+    /// slots past this one are free, only these are drawn
+    slots_in_use: usize,
+    bounds: Aabb,
+    cycle_time: f32,
+    rate_remainder: f32,
+    /// r, g, b, a
+    color_curves: [Spline<f32, f32>; 4],
+    size_curve: Spline<f32, f32>,
+    curves_texture: Option<Texture2d>,
+    curves_dirty: bool,
+    texture: Option<Texture2d>,
+    loaded_texture_path: Option<String>,
+    program: Option<&'static Program>,
+    simulate_program: Option<&'static Program>,
+    quad: Option<VertexBuffer<ParticleCorner>>,
+    state: Option<VertexBuffer<ParticleState>>,
+    next_state: Option<VertexBuffer<ParticleState>>,
+    error: bool,
+}
+
+impl ParticleEmitter {
+    pub fn new(name: &str, settings: ParticleEmitterSettings) -> Self {
+        let mut emitter = ParticleEmitter {
+            name: name.to_string(),
+            transform: Transform::default(),
+            parent_transform: None,
+            children: vec![],
+            body: None,
+            id: gen_object_id(),
+            groups: vec![],
+            settings: ParticleEmitterSettings::default(),
+            emitting: true,
+            clock: 0.0,
+            slots: vec![],
+            pending_spawns: BTreeMap::new(),
+            pending_time: 0.0,
+            clear_pending: true,
+            slots_in_use: 0,
+            bounds: Aabb::from_points(std::iter::empty()),
+            cycle_time: 0.0,
+            rate_remainder: 0.0,
+            color_curves: [Spline::from_vec(vec![]), Spline::from_vec(vec![]), Spline::from_vec(vec![]), Spline::from_vec(vec![])],
+            size_curve: Spline::from_vec(vec![]),
+            curves_texture: None,
+            curves_dirty: true,
+            texture: None,
+            loaded_texture_path: None,
+            program: None,
+            simulate_program: None,
+            quad: None,
+            state: None,
+            next_state: None,
+            error: false,
+        };
+        emitter.set_settings(settings);
+
+        emitter
+    }
+
+    pub fn from_file(name: &str, definition_path: &str) -> Result<Self, ParticleEmitterError> {
+        Ok(ParticleEmitter::new(name, ParticleEmitterSettings::from_file(definition_path)?))
+    }
+
+    /// Alive particles keep going with the new settings, unless `max_particles` changes, that removes them.
+    pub fn set_settings(&mut self, settings: ParticleEmitterSettings) {
+        let mut color_keys = settings.color_over_life.clone();
+        color_keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        self.color_curves = [0, 1, 2, 3].map(|channel| {
+            curve(color_keys.iter().map(|key| (key.time, key.color[channel])))
+        });
+
+        let mut size_keys = settings.size_over_life.clone();
+        size_keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        self.size_curve = curve(size_keys.iter().map(|key| (key.time, key.size)));
+        self.curves_dirty = true;
+
+        if settings.max_particles != self.slots.len() {
+            self.slots = vec![self.free_slot(); settings.max_particles];
+            self.pending_spawns.clear();
+            // buffers of the new size are created on the next render
+            self.clear_pending = true;
+        }
+
+        self.settings = settings;
+    }
+
+    /// Alive particles count.
+    pub fn particles_count(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.death_time > self.clock).count()
+    }
+
+    /// Spawns `count` particles right away.
+    pub fn burst(&mut self, count: u32) {
+        let emitter_matrix = self.emitter_matrix();
+        let emitter_rotation = Quat::from_mat4(&emitter_matrix);
+        let origin = match self.settings.world_space {
+            true => emitter_matrix.w_axis.truncate(),
+            false => Vec3::ZERO,
+        };
+        let mut rng = thread_rng();
+
+        let mut spawned = 0;
+        for index in 0..self.slots.len() {
+            if spawned >= count {
+                break;
+            }
+            if self.slots[index].death_time > self.clock {
+                continue;
+            }
+            spawned += 1;
+
+            let (position, direction) = sample_shape(&self.settings.shape, &mut rng);
+            let velocity = direction * random_in(&mut rng, self.settings.speed) + self.settings.velocity;
+            let (position, velocity) = match self.settings.world_space {
+                true => (emitter_matrix.transform_point3(position), emitter_rotation * velocity),
+                false => (position, velocity),
+            };
+            let lifetime = random_in(&mut rng, self.settings.lifetime).max(0.001);
+
+            self.slots[index] = ParticleSlot {
+                death_time: self.clock + lifetime,
+                origin,
+            };
+            self.pending_spawns.insert(
+                index,
+                PendingSpawn {
+                    spawn_time: self.clock,
+                    state: ParticleState {
+                        particle_position: position.to_array(),
+                        particle_velocity: velocity.to_array(),
+                        particle_age: 0.0,
+                        particle_lifetime: lifetime,
+                        particle_rotation: rng.gen_range(0.0..std::f32::consts::TAU),
+                        particle_rotation_speed: deg_to_rad(random_in(&mut rng, self.settings.rotation_speed)),
+                    },
+                },
+            );
+        }
+    }
+
+    /// Removes all particles and starts emitting from the beginning of a cycle.
+    pub fn restart(&mut self) {
+        let free_slot = self.free_slot();
+        self.slots.fill(free_slot);
+        self.pending_spawns.clear();
+        self.clear_pending = true;
+        self.cycle_time = 0.0;
+        self.rate_remainder = 0.0;
+        self.emitting = true;
+    }
+
+    fn free_slot(&self) -> ParticleSlot {
+        ParticleSlot {
+            death_time: self.clock,
+            origin: Vec3::ZERO,
+        }
+    }
+
+    /// World space, positions aren't flipped to render space.
+    fn emitter_matrix(&self) -> Mat4 {
+        let transform = self.global_transform();
+        let rotation = Quat::from_euler(
+            glam::EulerRot::XYZ,
+            deg_to_rad(transform.rotation.x),
+            deg_to_rad(transform.rotation.y),
+            deg_to_rad(transform.rotation.z),
+        );
+
+        Mat4::from_rotation_translation(rotation, transform.position)
+    }
+
+    fn emit(&mut self, delta_time: f32) {
+        let previous_time = self.cycle_time;
+        self.cycle_time += delta_time;
+
+        let mut burst_count = 0;
+        let duration = self.settings.duration;
+        let cycle_ended = duration > 0.0 && self.cycle_time >= duration;
+        for burst in &self.settings.bursts {
+            let in_this_cycle = burst.time >= previous_time && burst.time < self.cycle_time;
+            let in_next_cycle =
+                cycle_ended && self.settings.looping && burst.time < self.cycle_time - duration;
+            if in_this_cycle || in_next_cycle {
+                burst_count += burst.count;
+            }
+        }
+
+        self.rate_remainder += self.settings.rate.max(0.0) * delta_time;
+        let rate_count = self.rate_remainder.floor();
+        self.rate_remainder -= rate_count;
+
+        if cycle_ended {
+            match self.settings.looping {
+                true => self.cycle_time -= duration,
+                false => self.emitting = false,
+            }
+        }
+
+        self.burst(burst_count + rate_count as u32);
+    }
+
+    /// How far a particle can get from its origin, slots' origins grown by it bound the particles.
+    fn particle_reach(&self) -> f32 {
+        let settings = &self.settings;
+        let shape_extent = match settings.shape {
+            EmissionShape::Point => 0.0,
+            EmissionShape::Sphere { radius } => radius.abs(),
+            EmissionShape::Box { half_extents } => half_extents.length(),
+            EmissionShape::Cone { radius, .. } => radius.abs(),
+        };
+        let lifetime = settings.lifetime[0].max(settings.lifetime[1]).max(0.001);
+        let speed = settings.speed[0].abs().max(settings.speed[1].abs()) + settings.velocity.length();
+        let max_size = settings.size_over_life.iter().fold(0.2f32, |size, key| size.max(key.size.abs()));
+
+        // drag only slows particles down, so it's left out
+        shape_extent + speed * lifetime + 0.5 * settings.gravity.length() * lifetime * lifetime + max_size
+    }
+
+    /// Counts slots in use and bounds them in render space.
+    fn update_bounds(&mut self) {
+        self.slots_in_use = self
+            .slots
+            .iter()
+            .rposition(|slot| slot.death_time > self.clock)
+            .map_or(0, |index| index + 1);
+
+        let origins = match self.settings.world_space {
+            true => Aabb::from_points(
+                self.slots[..self.slots_in_use]
+                    .iter()
+                    .filter(|slot| slot.death_time > self.clock)
+                    .map(|slot| slot.origin),
+            ),
+            false => Aabb::from_points(std::iter::once(self.emitter_matrix().w_axis.truncate())),
+        };
+        let reach = Vec3::splat(self.particle_reach());
+        let (min, max) = (origins.min - reach, origins.max + reach);
+        self.bounds = Aabb {
+            min: Vec3::new(min.x, min.y, -max.z),
+            max: Vec3::new(max.x, max.y, -min.z),
+        };
+    }
+
+    fn start_render(&mut self, display: &Display<WindowSurface>) {
+        match render::get_program(display, PARTICLES_VERTEX_SHADER, PARTICLES_FRAGMENT_SHADER) {
+            Ok(program) => self.program = Some(program),
+            Err(err) => {
+                error(&format!("ParticleEmitter error:\nprogram creation error!\nErr: {}", err));
+                self.error = true;
+                return;
+            }
+        }
+
+        match render::get_transform_feedback_program(
+            display,
+            PARTICLES_SIMULATE_VERTEX_SHADER,
+            PARTICLES_SIMULATE_FRAGMENT_SHADER,
+            &SIMULATE_VARYINGS,
+        ) {
+            Ok(program) => self.simulate_program = Some(program),
+            Err(err) => {
+                error(&format!("ParticleEmitter error:\nsimulation program creation error!\nErr: {}", err));
+                self.error = true;
+                return;
+            }
+        }
+
+        let corners = [[-0.5, -0.5], [0.5, -0.5], [-0.5, 0.5], [0.5, 0.5]].map(|corner| ParticleCorner { corner });
+        match VertexBuffer::new(display, &corners) {
+            Ok(quad) => self.quad = Some(quad),
+            Err(err) => {
+                error(&format!("ParticleEmitter error:\nvertex buffer creation error!\nErr: {}", err));
+                self.error = true;
+            }
+        }
+    }
+
+    /// Two state buffers of `max_particles`, all slots start dead.
+    fn create_state_buffers(&mut self, display: &Display<WindowSurface>) -> bool {
+        let dead = vec![ParticleState::default(); self.slots.len()];
+        match (VertexBuffer::dynamic(display, &dead), VertexBuffer::dynamic(display, &dead)) {
+            (Ok(state), Ok(next_state)) => {
+                self.state = Some(state);
+                self.next_state = Some(next_state);
+                self.clear_pending = false;
+                true
+            }
+            (Err(err), _) | (_, Err(err)) => {
+                error(&format!("ParticleEmitter error:\nstate buffer creation error!\nErr: {}", err));
+                false
+            }
+        }
+    }
+
+    fn update_texture(&mut self, display: &Display<WindowSurface>) {
+        if self.loaded_texture_path == self.settings.texture {
+            return;
+        }
+        self.loaded_texture_path = self.settings.texture.clone();
+        self.texture = None;
+
+        let path = match &self.settings.texture {
+            Some(path) => path,
+            None => return,
+        };
+        let asset = match TextureAsset::from_file(path) {
+            Ok(asset) => asset,
+            Err(err) => {
+                error(&format!("ParticleEmitter error:\nfailed to load texture {}\nErr: {:?}", path, err));
+                return;
+            }
+        };
+        match render::create_srgb_texture(display, &asset.image_raw, asset.image_dimensions, false) {
+            Ok(texture) => self.texture = Some(texture),
+            Err(err) => error(&format!("ParticleEmitter error:\ntexture creating error!\nErr: {}", err)),
+        }
+    }
+
+    /// Bakes colour and size curves into two rows of a float texture for the vertex shader.
+    fn update_curves_texture(&mut self, display: &Display<WindowSurface>) {
+        if !self.curves_dirty {
+            return;
+        }
+        self.curves_dirty = false;
+
+        let mut texels = Vec::with_capacity(CURVE_SAMPLES * 2 * 4);
+        for sample in 0..CURVE_SAMPLES {
+            let life = sample as f32 / (CURVE_SAMPLES - 1) as f32;
+            texels.extend(self.color_curves.each_ref().map(|curve| curve.clamped_sample(life).unwrap_or(1.0)));
+        }
+        for sample in 0..CURVE_SAMPLES {
+            let life = sample as f32 / (CURVE_SAMPLES - 1) as f32;
+            texels.extend([self.size_curve.clamped_sample(life).unwrap_or(0.2), 0.0, 0.0, 0.0]);
+        }
+
+        let image = RawImage2d {
+            data: Cow::Owned(texels),
+            width: CURVE_SAMPLES as u32,
+            height: 2,
+            format: ClientFormat::F32F32F32F32,
+        };
+        match Texture2d::with_format(display, image, UncompressedFloatFormat::F32F32F32F32, MipmapsOption::NoMipmap) {
+            Ok(texture) => self.curves_texture = Some(texture),
+            Err(err) => {
+                error(&format!("ParticleEmitter error:\ncurves texture creation error!\nErr: {}", err));
+                self.error = true;
+            }
+        }
+    }
+
+    /// Runs the pending simulation time on the GPU and writes new particles into their slots.
+    fn simulate(&mut self, display: &Display<WindowSurface>, target: &mut SimpleFrameBuffer) {
+        if self.clear_pending && !self.create_state_buffers(display) {
+            self.error = true;
+            return;
+        }
+
+        let delta_time = std::mem::take(&mut self.pending_time);
+        if delta_time > 0.0 {
+            // local particles live in emitter's space, so gravity is rotated into it
+            let gravity = match self.settings.world_space {
+                true => self.settings.gravity,
+                false => Quat::from_mat4(&self.emitter_matrix()).inverse() * self.settings.gravity,
+            };
+            let uniforms = uniform! {
+                deltaTime: delta_time,
+                gravity: gravity.to_array(),
+                dragFactor: (1.0 - self.settings.drag * delta_time).max(0.0),
+            };
+
+            let program = self.simulate_program.unwrap();
+            let (state, next_state) = (self.state.as_ref().unwrap(), self.next_state.as_mut().unwrap());
+            let session = match TransformFeedbackSession::new(display, program, next_state) {
+                Ok(session) => session,
+                Err(err) => {
+                    error(&format!("ParticleEmitter error:\ntransform feedback error!\nErr: {}", err));
+                    self.error = true;
+                    return;
+                }
+            };
+            let draw_params = glium::DrawParameters {
+                transform_feedback: Some(&session),
+                draw_primitives: false,
+                ..Default::default()
+            };
+            if let Err(err) = target.draw(state, NoIndices(PrimitiveType::Points), program, &uniforms, &draw_params) {
+                error(&format!("ParticleEmitter error:\nsimulation draw error!\nErr: {}", err));
+                self.error = true;
+                return;
+            }
+            drop(session);
+            std::mem::swap(&mut self.state, &mut self.next_state);
+        }
+
+        // runs of neighbour slots are written with one upload
+        let state = self.state.as_ref().unwrap();
+        let pending_spawns = std::mem::take(&mut self.pending_spawns);
+        let mut run: Vec<ParticleState> = vec![];
+        let mut run_start = 0;
+        for (index, spawn) in pending_spawns {
+            if run_start + run.len() != index {
+                if let Some(slice) = state.slice(run_start..run_start + run.len()) {
+                    slice.write(&run);
+                }
+                run.clear();
+                run_start = index;
+            }
+
+            // spawned a few updates ago if the emitter wasn't rendered since
+            let mut particle = spawn.state;
+            let age = self.clock - spawn.spawn_time;
+            let velocity = Vec3::from(particle.particle_velocity);
+            particle.particle_position = (Vec3::from(particle.particle_position) + velocity * age).to_array();
+            particle.particle_age = age;
+            run.push(particle);
+        }
+        if let Some(slice) = state.slice(run_start..run_start + run.len()) {
+            slice.write(&run);
+        }
+
+        self.update_bounds();
+    }
+}
+
+impl Object for ParticleEmitter {
+    fn start(&mut self) {}
+
+    fn update(&mut self, _: &mut Framework) {
+        let delta_time = framework::get_delta_time().as_secs_f32();
+        self.clock += delta_time;
+        self.pending_time += delta_time;
+        if self.emitting {
+            self.emit(delta_time);
+        }
+    }
+
+    fn children_list(&self) -> &Vec<Box<dyn Object>> {
+        &self.children
+    }
+
+    fn children_list_mut(&mut self) -> &mut Vec<Box<dyn Object>> {
+        &mut self.children
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn object_type(&self) -> &str {
+        "ParticleEmitter"
+    }
+
+    fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    fn local_transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn set_local_transform(&mut self, transform: Transform) {
+        self.transform = transform
+    }
+
+    fn parent_transform(&self) -> Option<Transform> {
+        self.parent_transform
+    }
+
+    fn set_parent_transform(&mut self, transform: Transform) {
+        self.parent_transform = Some(transform);
+    }
+
+    fn set_body_parameters(&mut self, rigid_body: Option<ObjectBodyParameters>) {
+        self.body = rigid_body
+    }
+
+    fn body_parameters(&self) -> Option<ObjectBodyParameters> {
+        self.body
+    }
+
+    fn object_id(&self) -> &u128 {
+        &self.id
+    }
+
+    fn inspector_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("ParticleEmitter parameters");
+        ui.label(format!("error: {}", self.error));
+        ui.label(format!("particles: {}/{}", self.particles_count(), self.settings.max_particles));
+        ui.checkbox(&mut self.emitting, "emitting");
+        ui.checkbox(&mut self.settings.looping, "looping");
+        ui.checkbox(&mut self.settings.world_space, "world space");
+        ui.add(egui::Slider::new(&mut self.settings.rate, 0.0..=1000.0).text("rate"));
+        ui.add(egui::Slider::new(&mut self.settings.duration, 0.0..=60.0).text("duration"));
+        ui.add(egui::Slider::new(&mut self.settings.drag, 0.0..=10.0).text("drag"));
+        ui.add(egui::Slider::new(&mut self.settings.soft_distance, 0.0..=5.0).text("soft distance"));
+        ui.horizontal(|ui| {
+            ui.label("gravity:");
+            ui.add(egui::DragValue::new(&mut self.settings.gravity.x).speed(0.1));
+            ui.add(egui::DragValue::new(&mut self.settings.gravity.y).speed(0.1));
+            ui.add(egui::DragValue::new(&mut self.settings.gravity.z).speed(0.1));
+        });
+        ui.horizontal(|ui| {
+            if ui.button("burst 10").clicked() {
+                self.burst(10);
+            }
+            if ui.button("restart").clicked() {
+                self.restart();
+            }
+        });
+    }
+
+    fn groups_list(&mut self) -> &mut Vec<ObjectGroup> {
+        &mut self.groups
+    }
+
+    fn render(
+        &mut self,
+        display: &Display<WindowSurface>,
+        target: &mut SimpleFrameBuffer,
+        _cascades: &Cascades,
+        _shadow_textures: &ShadowTextures,
+        queue: &mut RenderQueue,
+    ) {
+        if self.error || self.slots.is_empty() {
+            return;
+        }
+        if self.program.is_none() {
+            self.start_render(display);
+            if self.error {
+                return;
+            }
+        }
+        self.update_texture(display);
+        self.update_curves_texture(display);
+
+        // once per frame, the first camera that renders the emitter runs it
+        if self.pending_time > 0.0 || self.clear_pending || !self.pending_spawns.is_empty() {
+            self.simulate(display, target);
+        }
+        if self.error || self.slots_in_use == 0 || !render::is_visible(&self.bounds) {
+            return;
+        }
+
+        let camera_distance = self.bounds.center().distance(render::get_camera_render_position());
+        queue.add(RenderQueueItem::new(
+            self.id,
+            0,
+            self.program.unwrap(),
+            self.texture.as_ref().unwrap_or(render::get_white_texture()),
+            self.quad.as_ref().unwrap(),
+            true,
+            camera_distance,
+        ));
+    }
+
+    fn render_queued(
+        &mut self,
+        _display: &Display<WindowSurface>,
+        target: &mut SimpleFrameBuffer,
+        _shadow_textures: &ShadowTextures,
+        _mesh_index: usize,
+    ) {
+        let instances = match self.state.as_ref().and_then(|state| state.slice(0..self.slots_in_use)) {
+            Some(instances) => instances,
+            None => return,
+        };
+        let curves = match &self.curves_texture {
+            Some(curves) => curves,
+            None => return,
+        };
+        let texture = self.texture.as_ref().unwrap_or(render::get_white_texture());
+        let (width, height) = target.get_dimensions();
+
+        // local particles are in emitter's space
+        let to_world = match self.settings.world_space {
+            true => Mat4::IDENTITY,
+            false => self.emitter_matrix(),
+        };
+        let model = Mat4::from_scale(Vec3::new(1.0, 1.0, -1.0)) * to_world;
+
+        let uniforms = uniform! {
+            model: model.to_cols_array_2d(),
+            view: render::get_view_matrix().to_cols_array_2d(),
+            proj: render::get_projection_matrix().to_cols_array_2d(),
+            inverseProj: render::get_projection_matrix().inverse().to_cols_array_2d(),
+            curves: curves
+                .sampled()
+                .minify_filter(MinifySamplerFilter::Nearest)
+                .magnify_filter(MagnifySamplerFilter::Nearest),
+            sheetSize: [self.settings.sheet[0].max(1) as f32, self.settings.sheet[1].max(1) as f32],
+            sheetFps: self.settings.sheet_fps,
+            tex: texture
+                .sampled()
+                .minify_filter(MinifySamplerFilter::Linear)
+                .magnify_filter(MagnifySamplerFilter::Linear)
+                .wrap_function(SamplerWrapFunction::Clamp),
+            resolution: [width as f32, height as f32],
+        };
+
+        let draw_params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: glium::draw_parameters::DepthTest::IfLess,
+                write: false,
+                ..Default::default()
+            },
+            blend: match self.settings.blend {
+                ParticleBlend::Alpha => Blend::alpha_blending(),
+                ParticleBlend::Additive => Blend {
+                    color: BlendingFunction::Addition {
+                        source: LinearBlendingFactor::SourceAlpha,
+                        destination: LinearBlendingFactor::One,
+                    },
+                    alpha: BlendingFunction::Addition {
+                        source: LinearBlendingFactor::Zero,
+                        destination: LinearBlendingFactor::One,
+                    },
+                    constant_value: (0.0, 0.0, 0.0, 0.0),
+                },
+            },
+            ..Default::default()
+        };
+
+        let vertices = (self.quad.as_ref().unwrap(), instances.per_instance().unwrap());
+        let indices = NoIndices(PrimitiveType::TriangleStrip);
+        let program = self.program.unwrap();
+
+        // soft particles need scene depth, the white texture stands in for it when they're disabled
+        let scene_depth = match self.settings.soft_distance > 0.0 {
+            true => render::get_scene_depth(),
+            false => None,
+        };
+        let result = match scene_depth {
+            Some(depth) => {
+                let uniforms = uniforms.add("softDistance", self.settings.soft_distance).add(
+                    "depthTexture",
+                    depth
+                        .sampled()
+                        .minify_filter(MinifySamplerFilter::Nearest)
+                        .magnify_filter(MagnifySamplerFilter::Nearest),
+                );
+                target.draw(vertices, indices, program, &uniforms, &draw_params)
+            }
+            None => {
+                let uniforms = uniforms
+                    .add("softDistance", 0.0f32)
+                    .add("depthTexture", render::get_white_texture());
+                target.draw(vertices, indices, program, &uniforms, &draw_params)
+            }
+        };
+        if let Err(err) = result {
+            error(&format!("ParticleEmitter error:\ndraw error!\nErr: {}", err));
+        }
+    }
+}
+
+fn curve(keys: impl Iterator<Item = (f32, f32)>) -> Spline<f32, f32> {
+    Spline::from_vec(
+        keys.map(|(time, value)| Key::new(time, value, Interpolation::Linear))
+            .collect(),
+    )
+}
+
+fn random_in(rng: &mut ThreadRng, range: [f32; 2]) -> f32 {
+    let (min, max) = (range[0].min(range[1]), range[0].max(range[1]));
+    rng.gen_range(min..=max)
+}
+
+fn random_direction(rng: &mut ThreadRng) -> Vec3 {
+    let y: f32 = rng.gen_range(-1.0..=1.0);
+    let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+    let radius = (1.0 - y * y).sqrt();
+    Vec3::new(radius * angle.cos(), y, radius * angle.sin())
+}
+
+/// Start position and direction in emitter's space.
+fn sample_shape(shape: &EmissionShape, rng: &mut ThreadRng) -> (Vec3, Vec3) {
+    match *shape {
+        EmissionShape::Point => (Vec3::ZERO, random_direction(rng)),
+        EmissionShape::Sphere { radius } => {
+            let direction = random_direction(rng);
+            (direction * radius * rng.gen::<f32>().cbrt(), direction)
+        }
+        EmissionShape::Box { half_extents } => {
+            let position = Vec3::new(
+                rng.gen_range(-1.0..=1.0),
+                rng.gen_range(-1.0..=1.0),
+                rng.gen_range(-1.0..=1.0),
+            ) * half_extents;
+            (position, Vec3::Y)
+        }
+        EmissionShape::Cone { angle, radius } => {
+            let around = rng.gen_range(0.0..std::f32::consts::TAU);
+            let (sin_around, cos_around) = around.sin_cos();
+            let disc_distance = radius * rng.gen::<f32>().sqrt();
+
+            let cos_tilt = 1.0 - rng.gen::<f32>() * (1.0 - deg_to_rad(angle.clamp(0.0, 180.0)).cos());
+            let sin_tilt = (1.0 - cos_tilt * cos_tilt).max(0.0).sqrt();
+            (
+                Vec3::new(cos_around * disc_distance, 0.0, sin_around * disc_distance),
+                Vec3::new(cos_around * sin_tilt, cos_tilt, sin_around * sin_tilt),
+            )
+        }
+    }
+}
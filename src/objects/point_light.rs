@@ -0,0 +1,133 @@
+use egui_glium::egui_winit::egui;
+use glam::Vec3;
+
+use super::{gen_object_id, Object, ObjectGroup, Transform};
+use crate::{
+    framework::Framework,
+    managers::{
+        physics::ObjectBodyParameters,
+        render::{self, PointLightData},
+    },
+};
+
+#[derive(Debug)]
+pub struct PointLight {
+    name: String,
+    transform: Transform,
+    parent_transform: Option<Transform>,
+    children: Vec<Box<dyn Object>>,
+    body: Option<ObjectBodyParameters>,
+    id: u128,
+    groups: Vec<ObjectGroup>,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub range: f32,
+    pub cast_shadows: bool,
+}
+
+impl PointLight {
+    pub fn new(name: &str, color: Vec3, intensity: f32, range: f32) -> Self {
+        PointLight {
+            name: name.to_string(),
+            transform: Transform::default(),
+            parent_transform: None,
+            children: vec![],
+            body: None,
+            id: gen_object_id(),
+            groups: vec![],
+            color,
+            intensity,
+            range: range.max(render::MIN_LIGHT_RANGE),
+            cast_shadows: false,
+        }
+    }
+
+    /// Range is clamped to MIN_LIGHT_RANGE.
+    pub fn set_range(&mut self, range: f32) {
+        self.range = range.max(render::MIN_LIGHT_RANGE);
+    }
+}
+
+impl Object for PointLight {
+    fn start(&mut self) {}
+
+    fn update(&mut self, _: &mut Framework) {
+        let mut position = self.global_transform().position;
+        position.z = -position.z;
+
+        render::add_point_light(PointLightData {
+            position,
+            color: self.color,
+            intensity: self.intensity,
+            range: self.range.max(render::MIN_LIGHT_RANGE),
+            cast_shadows: self.cast_shadows,
+        });
+    }
+
+    fn children_list(&self) -> &Vec<Box<dyn Object>> {
+        &self.children
+    }
+
+    fn children_list_mut(&mut self) -> &mut Vec<Box<dyn Object>> {
+        &mut self.children
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn object_type(&self) -> &str {
+        "PointLight"
+    }
+
+    fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    fn local_transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn set_local_transform(&mut self, transform: Transform) {
+        self.transform = transform
+    }
+
+    fn parent_transform(&self) -> Option<Transform> {
+        self.parent_transform
+    }
+
+    fn set_parent_transform(&mut self, transform: Transform) {
+        self.parent_transform = Some(transform);
+    }
+
+    fn set_body_parameters(&mut self, rigid_body: Option<ObjectBodyParameters>) {
+        self.body = rigid_body
+    }
+
+    fn body_parameters(&self) -> Option<ObjectBodyParameters> {
+        self.body
+    }
+
+    fn object_id(&self) -> &u128 {
+        &self.id
+    }
+
+    fn inspector_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("PointLight parameters");
+
+        let mut color = self.color.to_array();
+        ui.horizontal(|ui| {
+            ui.label("color:");
+            ui.color_edit_button_rgb(&mut color);
+        });
+        self.color = color.into();
+
+        ui.add(egui::Slider::new(&mut self.intensity, 0.0..=100.0).text("intensity"));
+        ui.add(egui::Slider::new(&mut self.range, render::MIN_LIGHT_RANGE..=200.0).text("range"));
+        ui.checkbox(&mut self.cast_shadows, "cast shadows");
+    }
+
+    fn groups_list(&mut self) -> &mut Vec<super::ObjectGroup> {
+        &mut self.groups
+    }
+}
@@ -0,0 +1,167 @@
+use egui_glium::egui_winit::egui;
+use glam::{Quat, Vec3};
+
+use super::{gen_object_id, Object, ObjectGroup, Transform};
+use crate::{
+    framework::Framework,
+    managers::{
+        physics::ObjectBodyParameters,
+        render::{self, SpotLightData},
+    },
+    math_utils::deg_to_rad,
+};
+
+/// Spot light shines down (negative y) until rotated.
+#[derive(Debug)]
+pub struct SpotLight {
+    name: String,
+    transform: Transform,
+    parent_transform: Option<Transform>,
+    children: Vec<Box<dyn Object>>,
+    body: Option<ObjectBodyParameters>,
+    id: u128,
+    groups: Vec<ObjectGroup>,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub range: f32,
+    /// degrees
+    pub inner_angle: f32,
+    /// degrees
+    pub outer_angle: f32,
+    pub cast_shadows: bool,
+}
+
+impl SpotLight {
+    pub fn new(
+        name: &str,
+        color: Vec3,
+        intensity: f32,
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> Self {
+        SpotLight {
+            name: name.to_string(),
+            transform: Transform::default(),
+            parent_transform: None,
+            children: vec![],
+            body: None,
+            id: gen_object_id(),
+            groups: vec![],
+            color,
+            intensity,
+            range: range.max(render::MIN_LIGHT_RANGE),
+            inner_angle,
+            outer_angle,
+            cast_shadows: false,
+        }
+    }
+
+    /// Range is clamped to MIN_LIGHT_RANGE.
+    pub fn set_range(&mut self, range: f32) {
+        self.range = range.max(render::MIN_LIGHT_RANGE);
+    }
+
+    pub fn direction(&self) -> Vec3 {
+        let rotation = self.global_transform().rotation;
+        let rotation_quat = Quat::from_euler(
+            glam::EulerRot::XYZ,
+            deg_to_rad(rotation.x),
+            deg_to_rad(rotation.y),
+            deg_to_rad(rotation.z),
+        );
+
+        rotation_quat * Vec3::NEG_Y
+    }
+}
+
+impl Object for SpotLight {
+    fn start(&mut self) {}
+
+    fn update(&mut self, _: &mut Framework) {
+        let mut position = self.global_transform().position;
+        position.z = -position.z;
+        let mut direction = self.direction();
+        direction.z = -direction.z;
+
+        render::add_spot_light(SpotLightData {
+            position,
+            direction,
+            color: self.color,
+            intensity: self.intensity,
+            range: self.range.max(render::MIN_LIGHT_RANGE),
+            inner_angle: self.inner_angle.min(self.outer_angle),
+            outer_angle: self.outer_angle,
+            cast_shadows: self.cast_shadows,
+        });
+    }
+
+    fn children_list(&self) -> &Vec<Box<dyn Object>> {
+        &self.children
+    }
+
+    fn children_list_mut(&mut self) -> &mut Vec<Box<dyn Object>> {
+        &mut self.children
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn object_type(&self) -> &str {
+        "SpotLight"
+    }
+
+    fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    fn local_transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn set_local_transform(&mut self, transform: Transform) {
+        self.transform = transform
+    }
+
+    fn parent_transform(&self) -> Option<Transform> {
+        self.parent_transform
+    }
+
+    fn set_parent_transform(&mut self, transform: Transform) {
+        self.parent_transform = Some(transform);
+    }
+
+    fn set_body_parameters(&mut self, rigid_body: Option<ObjectBodyParameters>) {
+        self.body = rigid_body
+    }
+
+    fn body_parameters(&self) -> Option<ObjectBodyParameters> {
+        self.body
+    }
+
+    fn object_id(&self) -> &u128 {
+        &self.id
+    }
+
+    fn inspector_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("SpotLight parameters");
+
+        let mut color = self.color.to_array();
+        ui.horizontal(|ui| {
+            ui.label("color:");
+            ui.color_edit_button_rgb(&mut color);
+        });
+        self.color = color.into();
+
+        ui.add(egui::Slider::new(&mut self.intensity, 0.0..=100.0).text("intensity"));
+        ui.add(egui::Slider::new(&mut self.range, render::MIN_LIGHT_RANGE..=200.0).text("range"));
+        ui.add(egui::Slider::new(&mut self.inner_angle, 0.0..=89.0).text("inner angle"));
+        ui.add(egui::Slider::new(&mut self.outer_angle, 0.0..=89.0).text("outer angle"));
+        ui.checkbox(&mut self.cast_shadows, "cast shadows");
+    }
+
+    fn groups_list(&mut self) -> &mut Vec<super::ObjectGroup> {
+        &mut self.groups
+    }
+}
@@ -0,0 +1,464 @@
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use egui_glium::egui_winit::egui;
+use glam::{Vec2, Vec3, Vec4};
+use glium::{
+    draw_parameters::{Blend, DepthTest},
+    framebuffer::SimpleFrameBuffer,
+    glutin::surface::WindowSurface,
+    implement_vertex,
+    index::{NoIndices, PrimitiveType},
+    texture::Texture2d,
+    uniform,
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction},
+    Display, Program, Surface, VertexBuffer,
+};
+
+use super::{gen_object_id, Object, ObjectGroup, Transform};
+use crate::{
+    assets::texture_asset::TextureAsset,
+    framework::{self, Framework},
+    managers::{
+        debugger::error,
+        physics::ObjectBodyParameters,
+        render::{self, Cascades, RenderQueue, RenderQueueItem, ShadowTextures},
+    },
+    math_utils::Aabb,
+};
+
+const SPRITE_VERTEX_SHADER: &str = include_str!("../assets/sprite.vert");
+const SPRITE_FRAGMENT_SHADER: &str = include_str!("../assets/sprite.frag");
+
+/// Text is rasterized at this size, the label is scaled to its height in the world.
+const TEXT_PIXEL_HEIGHT: f32 = 64.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpriteSize {
+    /// size is in world units, the sprite gets smaller with distance
+    World,
+    /// size is in pixels, the sprite always faces the camera
+    Screen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpriteFacing {
+    Camera,
+    /// rotates only around the axis (world space) to face the camera, for trees and beams
+    Axis(Vec3),
+}
+
+#[derive(Copy, Clone, Debug)]
+struct SpriteCorner {
+    corner: [f32; 2],
+}
+implement_vertex!(SpriteCorner, corner);
+
+/// Camera facing textured quad centered on its position, or a text label.
+#[derive(Debug)]
+pub struct Sprite {
+    name: String,
+    transform: Transform,
+    parent_transform: Option<Transform>,
+    children: Vec<Box<dyn Object>>,
+    body: Option<ObjectBodyParameters>,
+    id: u128,
+    groups: Vec<ObjectGroup>,
+    texture_path: Option<String>,
+    /// Some makes it a text label, texture is the rasterized text then
+    text: Option<String>,
+    /// width / height of the whole rasterized text
+    text_aspect: f32,
+    /// x, y, width, height in 0 - 1 of the texture, y goes down from the top
+    region: Vec4,
+    /// linear rgba, multiplied with the texture
+    pub color: Vec4,
+    /// width and height, text labels only use height
+    pub size: Vec2,
+    pub size_mode: SpriteSize,
+    pub facing: SpriteFacing,
+    /// if false, the sprite is drawn over everything
+    pub depth_test: bool,
+    texture: Option<Texture2d>,
+    texture_changed: bool,
+    program: Option<&'static Program>,
+    quad: Option<VertexBuffer<SpriteCorner>>,
+    error: bool,
+}
+
+impl Sprite {
+    pub fn new(name: &str, texture_path: Option<&str>, size: Vec2) -> Self {
+        Sprite {
+            name: name.to_string(),
+            transform: Transform::default(),
+            parent_transform: None,
+            children: vec![],
+            body: None,
+            id: gen_object_id(),
+            groups: vec![],
+            texture_path: texture_path.map(|path| path.to_string()),
+            text: None,
+            text_aspect: 1.0,
+            region: Vec4::new(0.0, 0.0, 1.0, 1.0),
+            color: Vec4::ONE,
+            size,
+            size_mode: SpriteSize::World,
+            facing: SpriteFacing::Camera,
+            depth_test: true,
+            texture: None,
+            texture_changed: true,
+            program: None,
+            quad: None,
+            error: false,
+        }
+    }
+
+    /// Text in JetBrains Mono, `height` is the height of one line. `\n` starts a new line.
+    pub fn text_label(name: &str, text: &str, height: f32) -> Self {
+        let mut sprite = Sprite::new(name, None, Vec2::new(height, height));
+        sprite.set_text(text);
+
+        sprite
+    }
+
+    /// None draws a white quad tinted with `color`.
+    pub fn set_texture(&mut self, texture_path: Option<String>) {
+        self.texture_path = texture_path;
+        self.text = None;
+        self.texture_changed = true;
+    }
+
+    /// Atlas region in 0 - 1: x, y, width, height. y goes down from the top of the texture.
+    pub fn set_region(&mut self, region: Vec4) {
+        self.region = region;
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        if self.text.as_deref() == Some(text) {
+            return;
+        }
+        self.text = Some(text.to_string());
+        self.region = Vec4::new(0.0, 0.0, 1.0, 1.0);
+        self.texture_changed = true;
+    }
+
+    fn quad_size(&self) -> Vec2 {
+        match &self.text {
+            Some(text) => {
+                let lines = text.lines().count().max(1) as f32;
+                Vec2::new(self.size.y * lines * self.text_aspect, self.size.y * lines)
+            }
+            None => self.size,
+        }
+    }
+
+    fn start_render(&mut self, display: &Display<WindowSurface>) {
+        match render::get_program(display, SPRITE_VERTEX_SHADER, SPRITE_FRAGMENT_SHADER) {
+            Ok(program) => self.program = Some(program),
+            Err(err) => {
+                error(&format!("Sprite error:\nprogram creation error!\nErr: {}", err));
+                self.error = true;
+                return;
+            }
+        }
+
+        let corners = [[-0.5, -0.5], [0.5, -0.5], [-0.5, 0.5], [0.5, 0.5]].map(|corner| SpriteCorner { corner });
+        match VertexBuffer::new(display, &corners) {
+            Ok(quad) => self.quad = Some(quad),
+            Err(err) => {
+                error(&format!("Sprite error:\nvertex buffer creation error!\nErr: {}", err));
+                self.error = true;
+            }
+        }
+    }
+
+    fn update_texture(&mut self, display: &Display<WindowSurface>) {
+        if !self.texture_changed {
+            return;
+        }
+        self.texture_changed = false;
+        self.texture = None;
+
+        let (pixels, dimensions) = match (&self.text, &self.texture_path) {
+            (Some(text), _) => match rasterize_text(text) {
+                Some((pixels, dimensions)) => {
+                    self.text_aspect = dimensions.0 as f32 / dimensions.1 as f32;
+                    (pixels, dimensions)
+                }
+                None => return,
+            },
+            (None, Some(path)) => match TextureAsset::from_file(path) {
+                Ok(asset) => (asset.image_raw, asset.image_dimensions),
+                Err(err) => {
+                    error(&format!("Sprite error:\nfailed to load texture {}\nErr: {:?}", path, err));
+                    return;
+                }
+            },
+            (None, None) => return,
+        };
+        match render::create_srgb_texture(display, &pixels, dimensions, false) {
+            Ok(texture) => self.texture = Some(texture),
+            Err(err) => error(&format!("Sprite error:\ntexture creating error!\nErr: {}", err)),
+        }
+    }
+}
+
+impl Object for Sprite {
+    fn start(&mut self) {}
+
+    fn update(&mut self, _: &mut Framework) {}
+
+    fn children_list(&self) -> &Vec<Box<dyn Object>> {
+        &self.children
+    }
+
+    fn children_list_mut(&mut self) -> &mut Vec<Box<dyn Object>> {
+        &mut self.children
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn object_type(&self) -> &str {
+        "Sprite"
+    }
+
+    fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    fn local_transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn set_local_transform(&mut self, transform: Transform) {
+        self.transform = transform
+    }
+
+    fn parent_transform(&self) -> Option<Transform> {
+        self.parent_transform
+    }
+
+    fn set_parent_transform(&mut self, transform: Transform) {
+        self.parent_transform = Some(transform);
+    }
+
+    fn set_body_parameters(&mut self, rigid_body: Option<ObjectBodyParameters>) {
+        self.body = rigid_body
+    }
+
+    fn body_parameters(&self) -> Option<ObjectBodyParameters> {
+        self.body
+    }
+
+    fn object_id(&self) -> &u128 {
+        &self.id
+    }
+
+    fn inspector_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Sprite parameters");
+        ui.label(format!("error: {}", self.error));
+        if let Some(text) = &self.text {
+            ui.label(format!("text: {}", text));
+        }
+        ui.checkbox(&mut self.depth_test, "depth test");
+        ui.horizontal(|ui| {
+            ui.label("size:");
+            ui.add(egui::DragValue::new(&mut self.size.x).speed(0.01));
+            ui.add(egui::DragValue::new(&mut self.size.y).speed(0.01));
+        });
+        ui.horizontal(|ui| {
+            ui.label("color:");
+            let mut color = self.color.to_array();
+            ui.color_edit_button_rgba_unmultiplied(&mut color);
+            self.color = Vec4::from(color);
+        });
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.size_mode, SpriteSize::World, "world size");
+            ui.radio_value(&mut self.size_mode, SpriteSize::Screen, "screen size");
+        });
+    }
+
+    fn groups_list(&mut self) -> &mut Vec<ObjectGroup> {
+        &mut self.groups
+    }
+
+    fn render(
+        &mut self,
+        display: &Display<WindowSurface>,
+        _target: &mut SimpleFrameBuffer,
+        _cascades: &Cascades,
+        _shadow_textures: &ShadowTextures,
+        queue: &mut RenderQueue,
+    ) {
+        if self.error {
+            return;
+        }
+        if self.program.is_none() {
+            self.start_render(display);
+            if self.error {
+                return;
+            }
+        }
+        self.update_texture(display);
+
+        let position = self.global_transform().position;
+        let center = Vec3::new(position.x, position.y, -position.z);
+        // screen sized sprites only need their center in front of the camera
+        let radius = match self.size_mode {
+            SpriteSize::World => self.quad_size().max_element() / 2.0,
+            SpriteSize::Screen => 0.0,
+        };
+        let bounds = Aabb {
+            min: center - Vec3::splat(radius),
+            max: center + Vec3::splat(radius),
+        };
+        if !render::is_visible(&bounds) {
+            return;
+        }
+
+        let camera_distance = center.distance(render::get_camera_render_position());
+        queue.add(RenderQueueItem::new(
+            self.id,
+            0,
+            self.program.unwrap(),
+            self.texture.as_ref().unwrap_or(render::get_white_texture()),
+            self.quad.as_ref().unwrap(),
+            true,
+            camera_distance,
+        ));
+    }
+
+    fn render_queued(
+        &mut self,
+        _display: &Display<WindowSurface>,
+        target: &mut SimpleFrameBuffer,
+        _shadow_textures: &ShadowTextures,
+        _mesh_index: usize,
+    ) {
+        let position = self.global_transform().position;
+        let (axis, facing) = match self.facing {
+            SpriteFacing::Camera => (Vec3::Y, 0),
+            SpriteFacing::Axis(axis) => (Vec3::new(axis.x, axis.y, -axis.z), 1),
+        };
+        let size_mode = match self.size_mode {
+            SpriteSize::World => 0,
+            SpriteSize::Screen => 1,
+        };
+        let texture = self.texture.as_ref().unwrap_or(render::get_white_texture());
+        let (width, height) = target.get_dimensions();
+
+        let uniforms = uniform! {
+            view: render::get_view_matrix().to_cols_array_2d(),
+            proj: render::get_projection_matrix().to_cols_array_2d(),
+            center: [position.x, position.y, -position.z],
+            size: self.quad_size().to_array(),
+            sizeMode: size_mode,
+            facing: facing,
+            axis: axis.to_array(),
+            cameraPosition: render::get_camera_render_position().to_array(),
+            resolution: [width as f32, height as f32],
+            region: self.region.to_array(),
+            tex: texture
+                .sampled()
+                .minify_filter(MinifySamplerFilter::Linear)
+                .magnify_filter(MagnifySamplerFilter::Linear)
+                .wrap_function(SamplerWrapFunction::Clamp),
+            tint: self.color.to_array(),
+        };
+
+        let draw_params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: match self.depth_test {
+                    true => DepthTest::IfLess,
+                    false => DepthTest::Overwrite,
+                },
+                write: false,
+                ..Default::default()
+            },
+            blend: Blend::alpha_blending(),
+            ..Default::default()
+        };
+
+        let result = target.draw(
+            self.quad.as_ref().unwrap(),
+            NoIndices(PrimitiveType::TriangleStrip),
+            self.program.unwrap(),
+            &uniforms,
+            &draw_params,
+        );
+        if let Err(err) = result {
+            error(&format!("Sprite error:\ndraw error!\nErr: {}", err));
+        }
+    }
+}
+
+/// White text with coverage in alpha, rows go from the top.
+fn rasterize_text(text: &str) -> Option<(Vec<u8>, (u32, u32))> {
+    let font = match FontRef::try_from_slice(framework::get_font_data()) {
+        Ok(font) => font,
+        Err(err) => {
+            error(&format!("Sprite error:\nfailed to read font!\nErr: {}", err));
+            return None;
+        }
+    };
+    let font = font.as_scaled(PxScale::from(TEXT_PIXEL_HEIGHT));
+    let line_height = font.height().ceil();
+
+    let lines: Vec<&str> = match text.is_empty() {
+        true => vec![" "],
+        false => text.lines().collect(),
+    };
+    let line_width = |line: &str| -> f32 {
+        let mut previous = None;
+        let mut width = 0.0;
+        for character in line.chars() {
+            let glyph = font.glyph_id(character);
+            if let Some(previous) = previous {
+                width += font.kern(previous, glyph);
+            }
+            width += font.h_advance(glyph);
+            previous = Some(glyph);
+        }
+        width
+    };
+
+    let width = lines.iter().map(|line| line_width(line)).fold(1.0, f32::max).ceil() as u32;
+    let height = (line_height * lines.len() as f32) as u32;
+    // transparent texels are white too, so filtering doesn't darken the edges
+    let mut pixels = [255, 255, 255, 0].repeat((width * height) as usize);
+
+    for (line_index, line) in lines.iter().enumerate() {
+        // lines are centered like the whole label
+        let mut x = (width as f32 - line_width(line)) / 2.0;
+        let baseline = line_index as f32 * line_height + font.ascent();
+        let mut previous = None;
+
+        for character in line.chars() {
+            let glyph_id = font.glyph_id(character);
+            if let Some(previous) = previous {
+                x += font.kern(previous, glyph_id);
+            }
+            let glyph = glyph_id.with_scale_and_position(font.scale(), point(x, baseline));
+            x += font.h_advance(glyph_id);
+            previous = Some(glyph_id);
+
+            let outlined = match font.outline_glyph(glyph) {
+                Some(outlined) => outlined,
+                None => continue,
+            };
+            let bounds = outlined.px_bounds();
+            outlined.draw(|glyph_x, glyph_y, coverage| {
+                let pixel_x = bounds.min.x as i32 + glyph_x as i32;
+                let pixel_y = bounds.min.y as i32 + glyph_y as i32;
+                if pixel_x < 0 || pixel_y < 0 || pixel_x >= width as i32 || pixel_y >= height as i32 {
+                    return;
+                }
+                let index = ((pixel_y as u32 * width + pixel_x as u32) * 4) as usize;
+                let alpha = (coverage.clamp(0.0, 1.0) * 255.0) as u8;
+                pixels[index + 3] = pixels[index + 3].max(alpha);
+            });
+        }
+    }
+
+    Some((pixels, (width, height)))
+}
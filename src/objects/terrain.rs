@@ -0,0 +1,788 @@
+use egui_glium::egui_winit::egui;
+use glam::{Mat4, Vec2, Vec3};
+use glium::{
+    framebuffer::SimpleFrameBuffer,
+    glutin::surface::WindowSurface,
+    index::PrimitiveType,
+    texture::{RawImage2d, Texture2d},
+    uniform,
+    uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction, UniformBuffer},
+    Display, IndexBuffer, Program, Surface, VertexBuffer,
+};
+
+use super::{gen_object_id, Object, ObjectGroup, Transform};
+use crate::{
+    assets::{
+        shader_asset::{self, ShaderAsset},
+        texture_asset::{self, HeightmapError, TextureAsset},
+    },
+    framework::Framework,
+    managers::{
+        debugger::error,
+        physics::{BodyColliderType, BodyType, ObjectBodyParameters},
+        render::{self, Cascades, CascadesBlock, LightsBlock, LodSettings, RenderQueue, RenderQueueItem, ShadowTextures, Vertex},
+        sky,
+    },
+    math_utils::{Aabb, Frustum},
+};
+
+const TERRAIN_VERTEX_SHADER: &str = include_str!("../assets/terrain.vert");
+const TERRAIN_FRAGMENT_SHADER: &str = include_str!("../assets/terrain.frag");
+
+/// Cells along one side of a chunk.
+const CHUNK_CELLS: usize = 32;
+/// Chunk LOD k skips 2^k - 1 of every 2^k rows and columns.
+const CHUNK_LODS: usize = 4;
+
+/// One of 4 textures blended by the splat map's r, g, b and a channels.
+#[derive(Debug, Clone)]
+pub struct TerrainLayer {
+    pub texture: Option<String>,
+    /// multiplied with the texture, linear
+    pub color: Vec3,
+    /// world units per texture repeat
+    pub tiling: f32,
+}
+
+#[derive(Debug)]
+pub enum TerrainError {
+    HeightmapLoadError,
+    HeightmapTooSmall,
+}
+
+#[derive(Debug)]
+struct TerrainChunk {
+    /// render space, relative to the terrain's position
+    bounding_box: Aabb,
+    /// one mesh per LOD
+    meshes: Vec<(VertexBuffer<Vertex>, IndexBuffer<u32>)>,
+    /// LOD from the last camera render, shadows use it too
+    lod: usize,
+}
+
+/// Heightmap terrain split into chunks with their own LODs, with a heightfield collider.
+/// Terrain is centered on its position, rotation and scale are ignored.
+#[derive(Debug)]
+pub struct Terrain {
+    name: String,
+    transform: Transform,
+    parent_transform: Option<Transform>,
+    children: Vec<Box<dyn Object>>,
+    body: Option<ObjectBodyParameters>,
+    id: u128,
+    groups: Vec<ObjectGroup>,
+    /// world units, row by row, rows go along z
+    heights: Vec<f32>,
+    rows: usize,
+    columns: usize,
+    /// x and z size
+    size: Vec2,
+    pub lod_settings: LodSettings,
+    layers: [TerrainLayer; 4],
+    /// None means it's generated from slope and height
+    splat_map_path: Option<String>,
+    splat_map: Option<Texture2d>,
+    layer_textures: [Option<Texture2d>; 4],
+    textures_changed: bool,
+    chunks: Vec<TerrainChunk>,
+    program: Option<&'static Program>,
+    shadow_program: Option<&'static Program>,
+    cascades_buffer: Option<UniformBuffer<CascadesBlock>>,
+    /// written per chunk with the lights that reach it
+    lights_buffer: Option<UniformBuffer<LightsBlock>>,
+    started: bool,
+    error: bool,
+}
+
+impl Terrain {
+    /// `heights` are in world units, row by row. rows go along z.
+    pub fn new(name: &str, heights: Vec<f32>, rows: usize, columns: usize, size: Vec2) -> Self {
+        Terrain {
+            name: name.to_string(),
+            transform: Transform::default(),
+            parent_transform: None,
+            children: vec![],
+            body: None,
+            id: gen_object_id(),
+            groups: vec![],
+            heights,
+            rows,
+            columns,
+            size,
+            lod_settings: LodSettings {
+                thresholds: vec![40.0, 80.0, 160.0],
+                ..Default::default()
+            },
+            layers: [
+                TerrainLayer { texture: None, color: Vec3::new(0.22, 0.4, 0.12), tiling: 4.0 },
+                TerrainLayer { texture: None, color: Vec3::new(0.35, 0.32, 0.3), tiling: 8.0 },
+                TerrainLayer { texture: None, color: Vec3::new(0.6, 0.55, 0.4), tiling: 4.0 },
+                TerrainLayer { texture: None, color: Vec3::new(0.9, 0.9, 0.95), tiling: 8.0 },
+            ],
+            splat_map_path: None,
+            splat_map: None,
+            layer_textures: [None, None, None, None],
+            textures_changed: true,
+            chunks: vec![],
+            program: None,
+            shadow_program: None,
+            cascades_buffer: None,
+            lights_buffer: None,
+            started: false,
+            error: false,
+        }
+    }
+
+    /// Grayscale heightmap, black is 0 and white is `height`. Pixel columns go along x, rows along z.
+    pub fn from_heightmap(name: &str, heightmap_path: &str, size: Vec2, height: f32) -> Result<Self, TerrainError> {
+        let (heights, rows, columns) = match texture_asset::load_heightmap(heightmap_path) {
+            Ok(heightmap) => heightmap,
+            Err(HeightmapError::LoadError) => return Err(TerrainError::HeightmapLoadError),
+            Err(HeightmapError::TooSmall) => return Err(TerrainError::HeightmapTooSmall),
+        };
+
+        let heights = heights.into_iter().map(|pixel_height| pixel_height * height).collect();
+        Ok(Terrain::new(name, heights, rows, columns, size))
+    }
+
+    /// Rolling hills from value noise, `resolution` is vertices along one side.
+    pub fn generated(name: &str, resolution: usize, size: Vec2, height: f32, seed: u32) -> Self {
+        let resolution = resolution.max(2);
+        let mut heights = Vec::with_capacity(resolution * resolution);
+        for row in 0..resolution {
+            for column in 0..resolution {
+                let point = Vec2::new(column as f32, row as f32) / (resolution - 1) as f32 * 4.0;
+                heights.push(fractal_noise(seed, point) * height);
+            }
+        }
+
+        Terrain::new(name, heights, resolution, resolution, size)
+    }
+
+    /// World space height, None outside of the terrain.
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        let position = self.global_transform().position;
+        let cell = self.cell_size();
+        let grid_x = (x - position.x + self.size.x / 2.0) / cell.x;
+        let grid_z = (z - position.z + self.size.y / 2.0) / cell.y;
+        let (last_column, last_row) = ((self.columns - 1) as f32, (self.rows - 1) as f32);
+        if !(0.0..=last_column).contains(&grid_x) || !(0.0..=last_row).contains(&grid_z) {
+            return None;
+        }
+
+        let column = (grid_x.floor() as usize).min(self.columns - 2);
+        let row = (grid_z.floor() as usize).min(self.rows - 2);
+        let (fraction_x, fraction_z) = (grid_x - column as f32, grid_z - row as f32);
+        let h00 = self.grid_height(row, column);
+        let h01 = self.grid_height(row, column + 1);
+        let h10 = self.grid_height(row + 1, column);
+        let h11 = self.grid_height(row + 1, column + 1);
+
+        // cells are split along (row, column) - (row + 1, column + 1), same as the collider
+        let height = match fraction_z >= fraction_x {
+            true => h00 + fraction_z * (h10 - h00) + fraction_x * (h11 - h10),
+            false => h00 + fraction_x * (h01 - h00) + fraction_z * (h11 - h01),
+        };
+
+        Some(position.y + height)
+    }
+
+    /// World space normal, None outside of the terrain.
+    pub fn normal_at(&self, x: f32, z: f32) -> Option<Vec3> {
+        let cell = self.cell_size();
+        let sample = |x: f32, z: f32| self.height_at(x, z);
+        let center = sample(x, z)?;
+
+        let left = sample(x - cell.x, z).unwrap_or(center);
+        let right = sample(x + cell.x, z).unwrap_or(center);
+        let back = sample(x, z - cell.y).unwrap_or(center);
+        let front = sample(x, z + cell.y).unwrap_or(center);
+
+        Some(Vec3::new(left - right, 2.0 * cell.x.min(cell.y), back - front).normalize())
+    }
+
+    /// Layer index is 0 - 3, texture paths are relative to assets folder.
+    pub fn set_layer(&mut self, index: usize, layer: TerrainLayer) {
+        match self.layers.get_mut(index) {
+            Some(old_layer) => {
+                *old_layer = layer;
+                self.textures_changed = true;
+            }
+            None => error(&format!("Terrain error:\nlayer index {} is out of 0 - 3 range", index)),
+        }
+    }
+
+    /// RGBA image with layers' weights, stretched over the whole terrain.
+    /// None brings back the splat map generated from slope and height.
+    pub fn set_splat_map(&mut self, path: Option<String>) {
+        self.splat_map_path = path;
+        self.textures_changed = true;
+    }
+
+    fn cell_size(&self) -> Vec2 {
+        Vec2::new(
+            self.size.x / (self.columns - 1) as f32,
+            self.size.y / (self.rows - 1) as f32,
+        )
+    }
+
+    fn grid_height(&self, row: usize, column: usize) -> f32 {
+        self.heights[row * self.columns + column]
+    }
+
+    fn grid_normal(&self, row: usize, column: usize) -> Vec3 {
+        let cell = self.cell_size();
+        let left = self.grid_height(row, column.saturating_sub(1));
+        let right = self.grid_height(row, (column + 1).min(self.columns - 1));
+        let back = self.grid_height(row.saturating_sub(1), column);
+        let front = self.grid_height((row + 1).min(self.rows - 1), column);
+
+        Vec3::new((left - right) * cell.y, 2.0 * cell.x * cell.y, (back - front) * cell.x).normalize()
+    }
+
+    /// Render space, relative to the terrain's position.
+    fn grid_position(&self, row: usize, column: usize) -> Vec3 {
+        let cell = self.cell_size();
+        Vec3::new(
+            column as f32 * cell.x - self.size.x / 2.0,
+            self.grid_height(row, column),
+            -(row as f32 * cell.y - self.size.y / 2.0),
+        )
+    }
+
+    fn model_matrix(&self) -> Mat4 {
+        let position = self.global_transform().position;
+        Mat4::from_translation(Vec3::new(position.x, position.y, -position.z))
+    }
+
+    fn start_render(&mut self, display: &Display<WindowSurface>) {
+        self.started = true;
+
+        match render::get_program(display, TERRAIN_VERTEX_SHADER, &shader_asset::expand_includes(TERRAIN_FRAGMENT_SHADER)) {
+            Ok(program) => self.program = Some(program),
+            Err(err) => {
+                error(&format!("Terrain error:\nprogram creation error!\nErr: {}", err));
+                self.error = true;
+                return;
+            }
+        }
+
+        match ShaderAsset::load_shadow_shader() {
+            Ok(shadow_shader) => match render::get_program(
+                display,
+                &shadow_shader.vertex_shader_source,
+                &shadow_shader.fragment_shader_source,
+            ) {
+                Ok(program) => self.shadow_program = Some(program),
+                Err(err) => error(&format!("Terrain error:\nshadow program creation error!\nErr: {}", err)),
+            },
+            Err(err) => error(&format!("Terrain error:\nfailed to load shadow shader!\nErr: {:?}", err)),
+        }
+
+        let buffers = UniformBuffer::empty_dynamic(display).and_then(|cascades| Ok((cascades, UniformBuffer::empty_dynamic(display)?)));
+        match buffers {
+            Ok((cascades, lights)) => {
+                self.cascades_buffer = Some(cascades);
+                self.lights_buffer = Some(lights);
+            }
+            Err(err) => {
+                error(&format!("Terrain error:\nuniform buffer creation error!\nErr: {}", err));
+                self.error = true;
+                return;
+            }
+        }
+
+        self.chunks.clear();
+        for first_row in (0..self.rows - 1).step_by(CHUNK_CELLS) {
+            for first_column in (0..self.columns - 1).step_by(CHUNK_CELLS) {
+                let last_row = (first_row + CHUNK_CELLS).min(self.rows - 1);
+                let last_column = (first_column + CHUNK_CELLS).min(self.columns - 1);
+                match self.build_chunk(display, first_row..=last_row, first_column..=last_column) {
+                    Some(chunk) => self.chunks.push(chunk),
+                    None => {
+                        self.error = true;
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    fn build_chunk(
+        &self,
+        display: &Display<WindowSurface>,
+        rows: std::ops::RangeInclusive<usize>,
+        columns: std::ops::RangeInclusive<usize>,
+    ) -> Option<TerrainChunk> {
+        let bounding_box = Aabb::from_points(
+            rows.clone()
+                .flat_map(|row| columns.clone().map(move |column| (row, column)))
+                .map(|(row, column)| self.grid_position(row, column)),
+        );
+        // skirts go down from chunk's edges, so gaps between LODs of neighbours aren't see-through
+        let skirt_depth = (bounding_box.max.y - bounding_box.min.y).max(self.cell_size().max_element() * 2.0);
+
+        let mut meshes = Vec::with_capacity(CHUNK_LODS);
+        for lod in 0..CHUNK_LODS {
+            let step = 1 << lod;
+            let chunk_rows = grid_lines(*rows.start(), *rows.end(), step);
+            let chunk_columns = grid_lines(*columns.start(), *columns.end(), step);
+
+            let mut vertices = Vec::new();
+            for &row in &chunk_rows {
+                for &column in &chunk_columns {
+                    vertices.push(self.grid_vertex(row, column, 0.0));
+                }
+            }
+
+            let width = chunk_columns.len() as u32;
+            let mut indices: Vec<u32> = Vec::new();
+            for row in 0..chunk_rows.len() as u32 - 1 {
+                for column in 0..width - 1 {
+                    let p00 = row * width + column;
+                    let p01 = p00 + 1;
+                    let p10 = p00 + width;
+                    let p11 = p10 + 1;
+                    indices.extend_from_slice(&[p00, p11, p10, p00, p01, p11]);
+                }
+            }
+
+            let edges: [(Vec<(usize, usize)>, Vec3); 4] = [
+                (chunk_columns.iter().map(|&c| (chunk_rows[0], c)).collect(), Vec3::Z),
+                (chunk_columns.iter().map(|&c| (*chunk_rows.last().unwrap(), c)).collect(), -Vec3::Z),
+                (chunk_rows.iter().map(|&r| (r, chunk_columns[0])).collect(), -Vec3::X),
+                (chunk_rows.iter().map(|&r| (r, *chunk_columns.last().unwrap())).collect(), Vec3::X),
+            ];
+            for (edge, outward) in edges {
+                for pair in edge.windows(2) {
+                    let top = [self.grid_vertex(pair[0].0, pair[0].1, 0.0), self.grid_vertex(pair[1].0, pair[1].1, 0.0)];
+                    let bottom = [
+                        self.grid_vertex(pair[0].0, pair[0].1, skirt_depth),
+                        self.grid_vertex(pair[1].0, pair[1].1, skirt_depth),
+                    ];
+                    let first = vertices.len() as u32;
+                    vertices.extend_from_slice(&[top[0], top[1], bottom[0], bottom[1]]);
+
+                    // counter clockwise when looking at the skirt from outside
+                    let normal = (Vec3::from(bottom[0].position) - Vec3::from(top[0].position))
+                        .cross(Vec3::from(top[1].position) - Vec3::from(top[0].position));
+                    match normal.dot(outward) > 0.0 {
+                        true => indices.extend_from_slice(&[first, first + 2, first + 1, first + 1, first + 2, first + 3]),
+                        false => indices.extend_from_slice(&[first, first + 1, first + 2, first + 1, first + 3, first + 2]),
+                    }
+                }
+            }
+
+            let vertex_buffer = VertexBuffer::new(display, &vertices);
+            let index_buffer = IndexBuffer::new(display, PrimitiveType::TrianglesList, &indices);
+            match (vertex_buffer, index_buffer) {
+                (Ok(vertex_buffer), Ok(index_buffer)) => meshes.push((vertex_buffer, index_buffer)),
+                (Err(err), _) => {
+                    error(&format!("Terrain error:\nvertex buffer creation error!\nErr: {}", err));
+                    return None;
+                }
+                (_, Err(err)) => {
+                    error(&format!("Terrain error:\nindex buffer creation error!\nErr: {}", err));
+                    return None;
+                }
+            }
+        }
+
+        Some(TerrainChunk {
+            bounding_box: Aabb {
+                min: bounding_box.min - Vec3::Y * skirt_depth,
+                max: bounding_box.max,
+            },
+            meshes,
+            lod: 0,
+        })
+    }
+
+    fn grid_vertex(&self, row: usize, column: usize, depth: f32) -> Vertex {
+        let normal = self.grid_normal(row, column);
+        Vertex {
+            position: (self.grid_position(row, column) - Vec3::Y * depth).to_array(),
+            normal: [normal.x, normal.y, -normal.z],
+            tex_coords: [
+                (column as f32 + 0.5) / self.columns as f32,
+                (row as f32 + 0.5) / self.rows as f32,
+            ],
+            joints: [0.0; 4],
+            weights: [0.0; 4],
+            color: [1.0; 4],
+            tangent: [1.0, 0.0, 0.0, 1.0],
+            tex_coords_1: [0.0; 2],
+        }
+    }
+
+    fn update_textures(&mut self, display: &Display<WindowSurface>) {
+        if !self.textures_changed {
+            return;
+        }
+        self.textures_changed = false;
+
+        for (index, layer) in self.layers.iter().enumerate() {
+            self.layer_textures[index] = layer
+                .texture
+                .as_ref()
+                .and_then(|path| load_texture(display, path, true));
+        }
+
+        self.splat_map = match &self.splat_map_path {
+            Some(path) => load_texture(display, path, false),
+            None => None,
+        };
+        if self.splat_map.is_none() {
+            let image = RawImage2d::from_raw_rgba(self.generate_splat_map(), (self.columns as u32, self.rows as u32));
+            match Texture2d::new(display, image) {
+                Ok(texture) => self.splat_map = Some(texture),
+                Err(err) => error(&format!("Terrain error:\nsplat map creation error!\nErr: {}", err)),
+            }
+        }
+    }
+
+    /// Layer 0 on flat ground, 1 on slopes, 2 low and 3 high.
+    fn generate_splat_map(&self) -> Vec<u8> {
+        let (min_height, max_height) = self
+            .heights
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), height| (min.min(*height), max.max(*height)));
+        let height_range = (max_height - min_height).max(0.0001);
+
+        let mut splat = Vec::with_capacity(self.rows * self.columns * 4);
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let slope = 1.0 - self.grid_normal(row, column).y;
+                let height = (self.grid_height(row, column) - min_height) / height_range;
+
+                let rock = smoothstep(0.2, 0.4, slope);
+                let high = smoothstep(0.75, 0.9, height) * (1.0 - rock);
+                let low = (1.0 - smoothstep(0.05, 0.15, height)) * (1.0 - rock);
+                let flat = (1.0 - rock - high - low).max(0.0);
+
+                splat.extend([flat, rock, low, high].map(|weight| (weight.clamp(0.0, 1.0) * 255.0) as u8));
+            }
+        }
+
+        splat
+    }
+}
+
+impl Object for Terrain {
+    fn start(&mut self) {
+        if self.rows < 2 || self.columns < 2 || self.heights.len() != self.rows * self.columns {
+            error(&format!("Terrain error:\nterrain {} has wrong heights size", self.name));
+            self.error = true;
+            return;
+        }
+
+        let collider = BodyColliderType::Heightfield(
+            self.heights.clone(),
+            self.rows,
+            self.columns,
+            Vec3::new(self.size.x, 1.0, self.size.y),
+        );
+        self.build_object_rigid_body(Some(BodyType::Fixed(Some(collider))), None, 1.0, None, None);
+    }
+
+    fn update(&mut self, _: &mut Framework) {}
+
+    fn children_list(&self) -> &Vec<Box<dyn Object>> {
+        &self.children
+    }
+
+    fn children_list_mut(&mut self) -> &mut Vec<Box<dyn Object>> {
+        &mut self.children
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn object_type(&self) -> &str {
+        "Terrain"
+    }
+
+    fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    fn local_transform(&self) -> Transform {
+        self.transform
+    }
+
+    fn set_local_transform(&mut self, transform: Transform) {
+        self.transform = transform
+    }
+
+    fn parent_transform(&self) -> Option<Transform> {
+        self.parent_transform
+    }
+
+    fn set_parent_transform(&mut self, transform: Transform) {
+        self.parent_transform = Some(transform);
+    }
+
+    fn set_body_parameters(&mut self, rigid_body: Option<ObjectBodyParameters>) {
+        self.body = rigid_body
+    }
+
+    fn body_parameters(&self) -> Option<ObjectBodyParameters> {
+        self.body
+    }
+
+    fn object_id(&self) -> &u128 {
+        &self.id
+    }
+
+    fn inspector_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Terrain parameters");
+        ui.label(format!("error: {}", self.error));
+        ui.label(format!("grid: {}x{}, chunks: {}", self.columns, self.rows, self.chunks.len()));
+        ui.label(format!("size: {}x{}", self.size.x, self.size.y));
+        for (index, layer) in self.layers.iter_mut().enumerate() {
+            ui.add(egui::Slider::new(&mut layer.tiling, 0.1..=100.0).text(format!("layer {} tiling", index)));
+        }
+        self.lod_settings.inspector_ui(ui);
+    }
+
+    fn groups_list(&mut self) -> &mut Vec<ObjectGroup> {
+        &mut self.groups
+    }
+
+    fn render(
+        &mut self,
+        display: &Display<WindowSurface>,
+        _target: &mut SimpleFrameBuffer,
+        cascades: &Cascades,
+        _shadow_textures: &ShadowTextures,
+        queue: &mut RenderQueue,
+    ) {
+        if !self.started {
+            self.start_render(display);
+        }
+        if self.error {
+            return;
+        }
+        self.update_textures(display);
+        self.cascades_buffer.as_ref().unwrap().write(&cascades.as_block());
+
+        let model = self.model_matrix();
+        let camera_position = render::get_camera_render_position();
+        let program = self.program.unwrap();
+
+        for i in 0..self.chunks.len() {
+            let bounding_box = self.chunks[i].bounding_box.transformed(&model);
+            if !render::is_visible(&bounding_box) {
+                continue;
+            }
+
+            let lod = self.lod_settings.select(&bounding_box, CHUNK_LODS).lod.min(CHUNK_LODS - 1);
+            self.chunks[i].lod = lod;
+            let camera_distance = bounding_box.center().distance(camera_position);
+            queue.add(RenderQueueItem::new(
+                self.id,
+                i,
+                program,
+                self.splat_map.as_ref().unwrap_or(render::get_white_texture()),
+                &self.chunks[i].meshes[lod].0,
+                false,
+                camera_distance,
+            ));
+        }
+    }
+
+    fn render_queued(
+        &mut self,
+        _display: &Display<WindowSurface>,
+        target: &mut SimpleFrameBuffer,
+        shadow_textures: &ShadowTextures,
+        i: usize,
+    ) {
+        let chunk = &self.chunks[i];
+        let (vertex_buffer, index_buffer) = &chunk.meshes[chunk.lod];
+        let layer_texture = |index: usize| layer_sampler(self.layer_textures[index].as_ref().unwrap_or(render::get_white_texture()));
+        let splat_map = self.splat_map.as_ref().unwrap_or(render::get_white_texture());
+        let fog = sky::get_fog();
+        let model = self.model_matrix();
+        let lights_buffer = self.lights_buffer.as_ref().unwrap();
+        lights_buffer.write(&render::get_lights_block(&chunk.bounding_box.transformed(&model)));
+
+        let uniforms = uniform! {
+            model: model.to_cols_array_2d(),
+            view: render::get_view_matrix().to_cols_array_2d(),
+            proj: render::get_projection_matrix().to_cols_array_2d(),
+            splatMap: splat_map
+                .sampled()
+                .minify_filter(MinifySamplerFilter::Linear)
+                .magnify_filter(MagnifySamplerFilter::Linear)
+                .wrap_function(SamplerWrapFunction::Clamp),
+            layer0: layer_texture(0),
+            layer1: layer_texture(1),
+            layer2: layer_texture(2),
+            layer3: layer_texture(3),
+            layerColor0: self.layers[0].color.to_array(),
+            layerColor1: self.layers[1].color.to_array(),
+            layerColor2: self.layers[2].color.to_array(),
+            layerColor3: self.layers[3].color.to_array(),
+            layerTiling: self.layers.each_ref().map(|layer| layer.tiling.max(0.001)),
+            sunDirection: render::get_sun_direction().to_array(),
+            sunColor: render::get_sun_color().to_array(),
+            ambientColor: render::get_ambient_color().to_array(),
+            cascades: self.cascades_buffer.as_ref().unwrap(),
+            lights: lights_buffer,
+            pointShadowMap0: &shadow_textures.point[0],
+            pointShadowMap1: &shadow_textures.point[1],
+            spotShadowMap0: &shadow_textures.spot[0],
+            spotShadowMap1: &shadow_textures.spot[1],
+            spotShadowViewProj0: render::get_spot_shadow_view_proj(0).to_cols_array_2d(),
+            spotShadowViewProj1: render::get_spot_shadow_view_proj(1).to_cols_array_2d(),
+            lightShadowNear: render::LIGHT_SHADOW_NEAR,
+            cascadeShadowTexture0: shadow_textures.cascade(0),
+            cascadeShadowTexture1: shadow_textures.cascade(1),
+            cascadeShadowTexture2: shadow_textures.cascade(2),
+            cascadeShadowTexture3: shadow_textures.cascade(3),
+            cameraPosition: render::get_camera_render_position().to_array(),
+            fogMode: fog.mode.as_shader_int(),
+            fogColor: fog.color.to_array(),
+            fogStart: fog.start,
+            fogEnd: fog.end,
+            fogDensity: fog.density,
+        };
+
+        let draw_params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: glium::draw_parameters::DepthTest::IfLess,
+                write: true,
+                ..Default::default()
+            },
+            backface_culling: glium::draw_parameters::BackfaceCullingMode::CullClockwise,
+            ..Default::default()
+        };
+
+        if let Err(err) = target.draw(vertex_buffer, index_buffer, self.program.unwrap(), &uniforms, &draw_params) {
+            error(&format!("Terrain error:\ndraw error!\nErr: {}", err));
+        }
+    }
+
+    fn shadow_render(
+        &mut self,
+        view_proj: &Mat4,
+        display: &Display<WindowSurface>,
+        target: &mut SimpleFrameBuffer,
+    ) {
+        if !self.started {
+            self.start_render(display);
+        }
+        let shadow_program = match (self.error, self.shadow_program) {
+            (false, Some(program)) => program,
+            _ => return,
+        };
+
+        let frustum = Frustum::from_view_proj(view_proj);
+        let model = self.model_matrix();
+        let uniforms = uniform! {
+            model: model.to_cols_array_2d(),
+            view_proj: view_proj.to_cols_array_2d(),
+            lightPos: render::get_light_direction().to_array(),
+        };
+        let draw_params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: glium::draw_parameters::DepthTest::IfLessOrEqual,
+                write: true,
+                ..Default::default()
+            },
+            backface_culling: glium::draw_parameters::BackfaceCullingMode::CullCounterClockwise,
+            ..Default::default()
+        };
+
+        for chunk in &self.chunks {
+            if !render::is_shadow_visible(&chunk.bounding_box.transformed(&model), &frustum) {
+                continue;
+            }
+
+            let (vertex_buffer, index_buffer) = &chunk.meshes[chunk.lod];
+            if let Err(err) = target.draw(vertex_buffer, index_buffer, shadow_program, &uniforms, &draw_params) {
+                error(&format!("Terrain error:\nshadow draw error!\nErr: {}", err));
+                return;
+            }
+        }
+    }
+}
+
+fn layer_sampler(texture: &Texture2d) -> Sampler<'_, Texture2d> {
+    texture
+        .sampled()
+        .minify_filter(MinifySamplerFilter::LinearMipmapLinear)
+        .magnify_filter(MagnifySamplerFilter::Linear)
+        .wrap_function(SamplerWrapFunction::Repeat)
+}
+
+/// Layer textures are srgb colours, splat maps are weights and are uploaded as is.
+fn load_texture(display: &Display<WindowSurface>, path: &str, srgb: bool) -> Option<Texture2d> {
+    let asset = match TextureAsset::from_file(path) {
+        Ok(asset) => asset,
+        Err(err) => {
+            error(&format!("Terrain error:\nfailed to load texture {}\nErr: {:?}", path, err));
+            return None;
+        }
+    };
+    let texture = match srgb {
+        true => render::create_srgb_texture(display, &asset.image_raw, asset.image_dimensions, false),
+        false => Texture2d::new(display, RawImage2d::from_raw_rgba(asset.image_raw, asset.image_dimensions)),
+    };
+    match texture {
+        Ok(texture) => Some(texture),
+        Err(err) => {
+            error(&format!("Terrain error:\ntexture creating error!\nErr: {}", err));
+            None
+        }
+    }
+}
+
+/// Every `step`th line from `first` to `last`, `last` is always included.
+fn grid_lines(first: usize, last: usize, step: usize) -> Vec<usize> {
+    let mut lines: Vec<usize> = (first..last).step_by(step).collect();
+    lines.push(last);
+    lines
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lattice_value(seed: u32, x: i32, y: i32) -> f32 {
+    let mut hash = seed
+        .wrapping_mul(0x27d4_eb2d)
+        .wrapping_add((x as u32).wrapping_mul(0x8da6_b343))
+        .wrapping_add((y as u32).wrapping_mul(0xd816_3841));
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x2c1b_3c6d);
+    hash ^= hash >> 12;
+    hash as f32 / u32::MAX as f32
+}
+
+fn value_noise(seed: u32, point: Vec2) -> f32 {
+    let cell = point.floor();
+    let fraction = point - cell;
+    let (x, y) = (cell.x as i32, cell.y as i32);
+    let (sx, sy) = (smoothstep(0.0, 1.0, fraction.x), smoothstep(0.0, 1.0, fraction.y));
+
+    let bottom = lattice_value(seed, x, y) + (lattice_value(seed, x + 1, y) - lattice_value(seed, x, y)) * sx;
+    let top = lattice_value(seed, x, y + 1) + (lattice_value(seed, x + 1, y + 1) - lattice_value(seed, x, y + 1)) * sx;
+    bottom + (top - bottom) * sy
+}
+
+/// 0 - 1, 5 octaves of value noise.
+fn fractal_noise(seed: u32, point: Vec2) -> f32 {
+    let (mut value, mut amplitude, mut frequency, mut total) = (0.0, 0.5, 1.0, 0.0);
+    for octave in 0..5 {
+        value += value_noise(seed.wrapping_add(octave), point * frequency) * amplitude;
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+
+    value / total
+}
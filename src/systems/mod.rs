@@ -5,12 +5,12 @@ pub mod player_manager;
 
 use crate::{
     framework::Framework, managers::{
-        debugger, networking::{self, Message, MessageReliability, NetworkError}, physics, render::{Cascades, ShadowTextures}, systems::{register_object_id_name, register_object_id_system, CallList, SystemValue}
+        debugger, networking::{self, Message, MessageReliability, NetworkError}, physics, render::{Cascades, RenderQueue, ShadowTextures}, systems::{register_object_id_name, register_object_id_system, CallList, SystemValue}
     }, objects::Object
 };
 use egui_glium::egui_winit::egui::Context;
 use glam::Mat4;
-use glium::{framebuffer::SimpleFrameBuffer, glutin::surface::WindowSurface, Display, Frame};
+use glium::{framebuffer::SimpleFrameBuffer, glutin::surface::WindowSurface, Display};
 
 pub trait System {
     fn client_start(&mut self, framework: &mut Framework);
@@ -84,16 +84,17 @@ pub trait System {
     fn render_objects(
         &mut self,
         display: &Display<WindowSurface>,
-        target: &mut Frame,
+        target: &mut SimpleFrameBuffer,
         cascades: &Cascades,
         shadow_textures: &ShadowTextures,
+        queue: &mut RenderQueue,
     ) {
         self.objects_list_mut()
             .into_iter()
-            .for_each(|object| object.render(display, target, cascades, shadow_textures));
+            .for_each(|object| object.render(display, target, cascades, shadow_textures, queue));
         self.objects_list_mut()
             .into_iter()
-            .for_each(|object| object.render_children(display, target, cascades, shadow_textures));
+            .for_each(|object| object.render_children(display, target, cascades, shadow_textures, queue));
         self.objects_list_mut()
             .into_iter()
             .for_each(|object| object.debug_render());